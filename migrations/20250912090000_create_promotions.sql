@@ -0,0 +1,25 @@
+-- Store-wide promotions applied automatically at checkout
+CREATE TABLE promotions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name TEXT NOT NULL,
+    description TEXT,
+    discount_percent NUMERIC(5,2) NOT NULL CHECK (discount_percent > 0 AND discount_percent <= 100),
+    min_order_total NUMERIC(10,2) NOT NULL DEFAULT 0 CHECK (min_order_total >= 0),
+    starts_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    ends_at TIMESTAMP WITH TIME ZONE,
+    active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    CHECK (ends_at IS NULL OR ends_at > starts_at)
+);
+
+-- Promotions that were applied to an order, kept for the order breakdown
+CREATE TABLE order_promotions (
+    order_id UUID NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    promotion_id UUID NOT NULL REFERENCES promotions(id),
+    name TEXT NOT NULL,
+    discount_amount NUMERIC(10,2) NOT NULL,
+    PRIMARY KEY (order_id, promotion_id)
+);
+
+CREATE INDEX idx_promotions_active_window ON promotions(active, starts_at, ends_at);
+CREATE INDEX idx_order_promotions_order_id ON order_promotions(order_id);
@@ -0,0 +1,4 @@
+-- Reservations held for an order during the payment window
+ALTER TABLE stock_reservations ADD COLUMN order_id UUID REFERENCES orders(id) ON DELETE CASCADE;
+
+CREATE INDEX idx_stock_reservations_order_id ON stock_reservations(order_id);
@@ -0,0 +1,4 @@
+-- Soft-delete products so historical order items keep resolving
+ALTER TABLE products ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX idx_products_not_deleted ON products(created_at DESC) WHERE deleted_at IS NULL;
@@ -0,0 +1,4 @@
+-- Human-readable stock keeping unit for warehouse integrations
+ALTER TABLE products ADD COLUMN sku TEXT;
+
+ALTER TABLE products ADD CONSTRAINT products_sku_key UNIQUE (sku);
@@ -0,0 +1,2 @@
+-- Emails are compared case-insensitively; enforce it at the database level too
+CREATE UNIQUE INDEX users_email_lower_key ON users (LOWER(email));
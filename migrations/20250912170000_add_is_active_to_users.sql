@@ -0,0 +1,2 @@
+-- Let admins disable accounts without deleting them (orders keep referencing the user)
+ALTER TABLE users ADD COLUMN is_active BOOLEAN NOT NULL DEFAULT true;
@@ -0,0 +1,4 @@
+-- Subcategories: a category may sit under one parent category
+ALTER TABLE categories ADD COLUMN parent_id UUID REFERENCES categories(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_categories_parent_id ON categories(parent_id);
@@ -0,0 +1,2 @@
+-- Set once the stock of a cancelled/refunded order has been returned, so it is never returned twice
+ALTER TABLE orders ADD COLUMN restocked_at TIMESTAMPTZ;
@@ -0,0 +1,2 @@
+-- Free-text instructions the shopper leaves at checkout (e.g. delivery notes)
+ALTER TABLE orders ADD COLUMN notes TEXT;
@@ -0,0 +1,9 @@
+-- Price breakdown behind orders.total: total = subtotal - promotion discount + tax + shipping
+ALTER TABLE orders
+    ADD COLUMN subtotal NUMERIC(10,2) NOT NULL DEFAULT 0,
+    ADD COLUMN tax NUMERIC(10,2) NOT NULL DEFAULT 0,
+    ADD COLUMN shipping NUMERIC(10,2) NOT NULL DEFAULT 0;
+
+-- Orders placed before the breakdown existed carried neither tax nor shipping
+UPDATE orders o
+SET subtotal = o.total + COALESCE((SELECT SUM(discount_amount) FROM order_promotions op WHERE op.order_id = o.id), 0);
@@ -0,0 +1,17 @@
+-- Discount codes shoppers enter at checkout; exactly one of percent_off / amount_off is set
+CREATE TABLE coupons (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    code TEXT NOT NULL UNIQUE,
+    percent_off NUMERIC(5,2) CHECK (percent_off > 0 AND percent_off <= 100),
+    amount_off NUMERIC(10,2) CHECK (amount_off > 0),
+    expires_at TIMESTAMP WITH TIME ZONE,
+    usage_limit INTEGER CHECK (usage_limit > 0),
+    times_used INTEGER NOT NULL DEFAULT 0 CHECK (times_used >= 0),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    CHECK ((percent_off IS NULL) <> (amount_off IS NULL))
+);
+
+-- The coupon redeemed on an order and the discount it gave
+ALTER TABLE orders
+    ADD COLUMN coupon_id UUID REFERENCES coupons(id) ON DELETE SET NULL,
+    ADD COLUMN coupon_discount NUMERIC(10,2) NOT NULL DEFAULT 0;
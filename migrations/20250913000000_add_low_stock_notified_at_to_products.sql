@@ -0,0 +1,3 @@
+-- When the low-stock webhook last fired for the product, so repeated dips inside the
+-- debounce window are reported only once
+ALTER TABLE products ADD COLUMN low_stock_notified_at TIMESTAMPTZ;
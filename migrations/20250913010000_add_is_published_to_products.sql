@@ -0,0 +1,2 @@
+-- Unpublished products stay in stock and in order history but are hidden from the public catalogue
+ALTER TABLE products ADD COLUMN is_published BOOLEAN NOT NULL DEFAULT true;
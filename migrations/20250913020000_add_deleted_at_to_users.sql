@@ -0,0 +1,2 @@
+-- Self-deleted accounts are anonymized rather than removed so their orders stay on the books
+ALTER TABLE users ADD COLUMN deleted_at TIMESTAMPTZ;
@@ -0,0 +1,3 @@
+-- Carrier and tracking number recorded when an order ships
+ALTER TABLE orders ADD COLUMN tracking_number TEXT;
+ALTER TABLE orders ADD COLUMN carrier TEXT;
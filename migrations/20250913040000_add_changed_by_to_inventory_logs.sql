@@ -0,0 +1,3 @@
+-- User whose request caused the change; NULL for system changes such as checkout and expiry cleanup.
+-- No foreign key: the audit trail must outlive the account it names.
+ALTER TABLE inventory_logs ADD COLUMN changed_by UUID;
@@ -0,0 +1,12 @@
+-- One row per order status change, so customers can see when each step happened.
+-- changed_by has no foreign key, like inventory_logs: the trail must outlive the account it names.
+CREATE TABLE order_status_history (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    order_id UUID NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    from_status TEXT NOT NULL,
+    to_status TEXT NOT NULL,
+    changed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    changed_by UUID
+);
+
+CREATE INDEX idx_order_status_history_order_id ON order_status_history(order_id, changed_at);
@@ -0,0 +1,3 @@
+-- "Oils" and "oils" are the same category. Existing case-only duplicates must be renamed
+-- or merged before this index can be built.
+CREATE UNIQUE INDEX categories_name_lower_key ON categories (lower(name));
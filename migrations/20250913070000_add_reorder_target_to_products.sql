@@ -0,0 +1,2 @@
+-- Stock level purchasing wants to get back to when reordering; NULL means twice the low-stock threshold.
+ALTER TABLE products ADD COLUMN reorder_target INT CHECK (reorder_target >= 0);
@@ -3,6 +3,7 @@ use argon2::{password_hash::{SaltString, rand_core::OsRng}, Argon2, PasswordHash
 use sqlx::postgres::PgPoolOptions;
 use hemp_backend::repository::UserRepository;
 use hemp_backend::model::user::User;
+use hemp_backend::services::auth_service::normalize_email;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -26,6 +27,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
+    let email = normalize_email(&email);
+
     let database_url = env::var("DATABASE_URL")
         .or_else(|_| env::var("TEST_DATABASE_URL"))
         .map_err(|_| {
@@ -62,7 +65,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Some(existing) => {
             // Update existing user to admin role and set new password hash
             let updated: User = sqlx::query_as::<_, User>(
-                "UPDATE users SET role = $1, password_hash = $2 WHERE email = $3 RETURNING id, email, password_hash, role, created_at"
+                "UPDATE users SET role = $1, password_hash = $2 WHERE LOWER(email) = $3 RETURNING id, email, password_hash, role, email_verified, is_active, created_at"
             )
             .bind("admin")
             .bind(&password_hash)
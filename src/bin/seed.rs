@@ -0,0 +1,50 @@
+use std::env;
+use sqlx::postgres::PgPoolOptions;
+use hemp_backend::seed::{seed, SeedConfig};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Load .env if present
+    let _ = dotenvy::dotenv();
+
+    // Sample data must never land in a real database by accident
+    if env::var("SEED_DEMO_DATA").ok().as_deref() != Some("1") {
+        eprintln!("ERROR: refusing to seed without SEED_DEMO_DATA=1.");
+        eprintln!("Example: SEED_DEMO_DATA=1 DATABASE_URL=postgres://... cargo run --bin seed");
+        std::process::exit(2);
+    }
+
+    let config = SeedConfig::from_env().unwrap_or_else(|e| {
+        eprintln!("ERROR: {}", e);
+        std::process::exit(2);
+    });
+
+    let database_url = env::var("DATABASE_URL")
+        .or_else(|_| env::var("TEST_DATABASE_URL"))
+        .map_err(|_| {
+            eprintln!("ERROR: DATABASE_URL or TEST_DATABASE_URL must be set.");
+            std::io::Error::other("missing DATABASE_URL")
+        })?;
+
+    let pool = PgPoolOptions::new()
+        .max_connections(5)
+        .connect(&database_url)
+        .await?;
+
+    if env::var("RUN_MIGRATIONS").ok().unwrap_or_else(|| "0".into()) == "1" {
+        eprintln!("Running migrations...");
+        sqlx::migrate!().run(&pool).await?;
+    }
+
+    let summary = seed(&pool, &config).await?;
+    if summary.skipped {
+        println!("Demo user '{}' already exists; nothing seeded.", summary.demo_user_email);
+    } else {
+        println!(
+            "Seeded {} categories, {} products and demo user '{}' (password from SEED_DEMO_PASSWORD or the default).",
+            summary.categories, summary.products, summary.demo_user_email
+        );
+    }
+
+    Ok(())
+}
@@ -1,17 +1,32 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use validator::Validate;
-use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+use utoipa::{IntoParams, ToSchema};
 use crate::model::user::User;
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct SignupDto {
     #[validate(email(message = "Invalid email format"))]
     pub email: String,
-    #[validate(length(min = 6, max = 100, message = "Password must be between 6 and 100 characters"))]
+    #[validate(
+        length(min = 8, max = 100, message = "Password must be between 8 and 100 characters"),
+        custom(function = "validate_password_strength")
+    )]
     pub password: String,
 }
 
+/// Requires at least one letter and one digit.
+fn validate_password_strength(password: &str) -> Result<(), ValidationError> {
+    let has_letter = password.chars().any(|c| c.is_alphabetic());
+    let has_digit = password.chars().any(|c| c.is_ascii_digit());
+    if has_letter && has_digit {
+        Ok(())
+    } else {
+        Err(ValidationError::new("password_strength")
+            .with_message("Password must contain at least one letter and one digit".into()))
+    }
+}
+
 #[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct LoginDto {
     #[validate(email(message = "Invalid email format"))]
@@ -20,11 +35,49 @@ pub struct LoginDto {
     pub password: String,
 }
 
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ForgotPasswordDto {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ResetPasswordDto {
+    #[validate(length(min = 1, message = "Reset token is required"))]
+    pub token: String,
+    #[validate(
+        length(min = 8, max = 100, message = "Password must be between 8 and 100 characters"),
+        custom(function = "validate_password_strength")
+    )]
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct DeleteAccountDto {
+    /// The account's current password, as confirmation
+    #[validate(length(min = 1, message = "Password is required"))]
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct VerifyEmailQuery {
+    /// Token from the verification email
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateRoleDto {
+    /// Either `client` or `admin`
+    pub role: String,
+}
+
 #[derive(Debug, Serialize, ToSchema)]
 pub struct UserResponse {
     pub id: Uuid,
     pub email: String,
     pub role: String,
+    pub email_verified: bool,
+    pub is_active: bool,
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
@@ -34,6 +87,8 @@ impl From<User> for UserResponse {
             id: u.id,
             email: u.email,
             role: u.role,
+            email_verified: u.email_verified,
+            is_active: u.is_active,
             created_at: u.created_at,
         }
     }
@@ -45,4 +100,16 @@ pub struct Claims {
     pub email: String,
     pub role: String,
     pub exp: usize,
+    /// Unique token id, used to revoke the token on logout
+    pub jti: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+}
+
+impl Claims {
+    pub fn has_role(&self, role: &str) -> bool {
+        self.role == role
+    }
 }
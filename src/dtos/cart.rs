@@ -1,11 +1,57 @@
-use serde::{Deserialize};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use validator::Validate;
 use utoipa::ToSchema;
+use crate::dtos::promotion::AppliedPromotion;
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct AddToCartDto {
     pub product_id: Uuid,
-    #[validate(range(min = 1, max = 100, message = "Quantity must be between 1 and 100"))]
+    /// Units to add; the line's total is capped by `CART_MAX_ITEM_QUANTITY` (default 100)
+    #[serde(deserialize_with = "whole_quantity")]
+    #[validate(range(min = 1, message = "Quantity must be at least 1"))]
     pub quantity: i32,
 }
+
+/// Takes any JSON number so fractions and values past `i32` get a message about the quantity
+/// rather than serde's "invalid type" wording.
+fn whole_quantity<'de, D>(deserializer: D) -> Result<i32, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    let number = serde_json::Number::deserialize(deserializer)?;
+    let value = number.as_f64().unwrap_or(f64::NAN);
+    if value.fract() != 0.0 {
+        return Err(D::Error::custom("Quantity must be a whole number"));
+    }
+    if value < i32::MIN as f64 || value > i32::MAX as f64 {
+        return Err(D::Error::custom("Quantity is out of range"));
+    }
+    Ok(value as i32)
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CartQuoteItem {
+    pub product_id: Uuid,
+    pub product_name: String,
+    pub quantity: i32,
+    #[schema(value_type = String, example = "9.99")]
+    pub price: Decimal,
+    #[schema(value_type = String, example = "19.98")]
+    pub subtotal: Decimal,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CartQuoteResponse {
+    pub items: Vec<CartQuoteItem>,
+    #[schema(value_type = String, example = "125.00")]
+    pub subtotal: Decimal,
+    #[schema(value_type = String, example = "18.75")]
+    pub discount: Decimal,
+    pub applied_promotions: Vec<AppliedPromotion>,
+    #[schema(value_type = String, example = "106.25")]
+    pub total: Decimal,
+}
@@ -1,18 +1,39 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use crate::model::category::Category;
+use crate::model::category::{Category, CategoryWithCount};
 use utoipa::ToSchema;
+use validator::Validate;
 
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct NewCategoryDto {
     pub name: String,
     pub description: Option<String>,
+    pub parent_id: Option<Uuid>,
 }
 
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateCategoryDto {
     pub name: Option<String>,
-    pub description: Option<String>,
+    /// `null` clears the description
+    #[serde(default, deserialize_with = "super::present")]
+    #[schema(value_type = Option<String>)]
+    pub description: Option<Option<String>>,
+    /// `null` moves the category back to the root
+    #[serde(default, deserialize_with = "super::present")]
+    #[schema(value_type = Option<Uuid>)]
+    pub parent_id: Option<Option<Uuid>>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct BulkAssignDto {
+    #[validate(length(min = 1, max = 100, message = "Between 1 and 100 product ids are allowed"))]
+    pub product_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BulkAssignResponse {
+    /// Products newly added to the category; ones already in it or unknown are not counted
+    pub linked: u64,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -20,8 +41,12 @@ pub struct CategoryResponse {
     pub id: Uuid,
     pub name: String,
     pub description: Option<String>,
+    pub parent_id: Option<Uuid>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Number of live products in the category; only present on listings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub product_count: Option<i64>,
 }
 
 impl From<Category> for CategoryResponse {
@@ -30,8 +55,28 @@ impl From<Category> for CategoryResponse {
             id: c.id,
             name: c.name,
             description: c.description,
+            parent_id: c.parent_id,
             created_at: c.created_at,
             updated_at: c.updated_at,
+            product_count: None,
+        }
+    }
+}
+
+impl From<CategoryWithCount> for CategoryResponse {
+    fn from(c: CategoryWithCount) -> Self {
+        CategoryResponse {
+            product_count: Some(c.product_count),
+            ..CategoryResponse::from(c.category)
         }
     }
 }
+
+/// A category with its subcategories nested underneath, as returned by `/api/category/tree`.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CategoryTreeNode {
+    #[serde(flatten)]
+    pub category: CategoryResponse,
+    #[schema(no_recursion)]
+    pub children: Vec<CategoryTreeNode>,
+}
@@ -0,0 +1,22 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use validator::Validate;
+
+/// Creates a coupon or replaces all of an existing coupon's settings.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CouponDto {
+    #[validate(length(min = 1, max = 64, message = "Coupon code must be between 1 and 64 characters"))]
+    pub code: String,
+    /// Percentage taken off the order; set this or `amount_off`
+    #[schema(value_type = Option<String>, example = "10.00")]
+    pub percent_off: Option<Decimal>,
+    /// Fixed amount taken off the order; set this or `percent_off`
+    #[schema(value_type = Option<String>, example = "5.00")]
+    pub amount_off: Option<Decimal>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// How many orders may redeem the coupon; unlimited when omitted
+    #[validate(range(min = 1, message = "Usage limit must be at least 1"))]
+    pub usage_limit: Option<i32>,
+}
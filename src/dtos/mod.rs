@@ -3,9 +3,23 @@ pub mod category;
 pub mod product;
 pub mod cart;
 pub mod order;
+pub mod promotion;
+pub mod coupon;
 
 pub use order::*;
 pub use cart::*;
 pub use auth::*;
 pub use category::*;
-pub use product::{NewProductDto, ProductResponse, UpdateProductDto};
+pub use promotion::*;
+pub use coupon::*;
+pub use product::{NewProductDto, ProductDetailResponse, ProductImportReport, ProductImportRow, ProductResponse, UpdateProductDto};
+
+/// Only called for fields that are in the body, so a present `null` becomes `Some(None)`
+/// while a missing field falls back to the `None` default.
+pub(crate) fn present<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: serde::Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    <Option<T> as serde::Deserialize>::deserialize(deserializer).map(Some)
+}
@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use crate::dtos::promotion::AppliedPromotion;
+use validator::Validate;
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct OrderResponse {
@@ -13,15 +15,34 @@ pub struct OrderResponse {
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
-#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[derive(Debug, Deserialize, Serialize, Validate, ToSchema)]
 pub struct CreateOrderRequest {
+    /// Instructions for the order, e.g. delivery notes
     #[serde(default)]
+    #[validate(length(max = 1000))]
     pub notes: Option<String>,
+    /// Discount code to redeem on the order
+    #[serde(default)]
+    #[validate(length(min = 1, max = 64))]
+    pub coupon_code: Option<String>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct CreateOrderResponse {
     pub id: Uuid,
+    #[schema(value_type = String, example = "145.23")]
+    pub subtotal: Decimal,
+    /// Promotion and coupon discounts combined
+    #[schema(value_type = String, example = "21.78")]
+    pub discount: Decimal,
+    pub applied_promotions: Vec<AppliedPromotion>,
+    pub coupon_code: Option<String>,
+    #[schema(value_type = String, example = "5.00")]
+    pub coupon_discount: Decimal,
+    #[schema(value_type = String, example = "10.73")]
+    pub tax: Decimal,
+    #[schema(value_type = String, example = "4.99")]
+    pub shipping: Decimal,
     #[schema(value_type = String, example = "123.45")]
     pub total: Decimal,
     pub status: String,
@@ -46,10 +67,81 @@ pub struct OrderItemResponse {
 pub struct OrderDetailsResponse {
     pub id: Uuid,
     pub user_id: Uuid,
+    #[schema(value_type = String, example = "130.00")]
+    pub subtotal: Decimal,
+    #[schema(value_type = String, example = "22.27")]
+    pub discount: Decimal,
+    /// The promotions that made up the discount when the order was placed
+    pub applied_promotions: Vec<AppliedPromotion>,
+    #[schema(value_type = String, example = "10.73")]
+    pub tax: Decimal,
+    #[schema(value_type = String, example = "4.99")]
+    pub shipping: Decimal,
     #[schema(value_type = String, example = "123.45")]
     pub total: Decimal,
     pub status: String,
     pub payment_id: Option<Uuid>,
+    pub notes: Option<String>,
+    pub tracking_number: Option<String>,
+    pub carrier: Option<String>,
     pub items: Vec<OrderItemResponse>,
     pub created_at: DateTime<Utc>,
 }
+
+/// A receipt for an order: what was bought, what it cost and whether it has been paid.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct InvoiceResponse {
+    /// Stable reference derived from the order, e.g. `INV-20250912-1A2B3C4D`
+    pub invoice_number: String,
+    pub order_id: Uuid,
+    /// When the order was placed
+    pub issued_at: DateTime<Utc>,
+    pub customer_id: Uuid,
+    pub customer_email: String,
+    pub items: Vec<OrderItemResponse>,
+    #[schema(value_type = String, example = "130.00")]
+    pub subtotal: Decimal,
+    #[schema(value_type = String, example = "22.27")]
+    pub discount: Decimal,
+    #[schema(value_type = String, example = "10.73")]
+    pub tax: Decimal,
+    #[schema(value_type = String, example = "4.99")]
+    pub shipping: Decimal,
+    #[schema(value_type = String, example = "123.45")]
+    pub total: Decimal,
+    pub order_status: String,
+    /// Status of the order's payment, absent while no payment has been started
+    pub payment_status: Option<String>,
+    pub notes: Option<String>,
+}
+
+/// Moves several orders to the same status, e.g. marking a fulfillment batch shipped.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct BulkStatusRequest {
+    #[validate(length(min = 1, max = 100, message = "Between 1 and 100 order ids are allowed"))]
+    pub order_ids: Vec<Uuid>,
+    pub status: String,
+    /// Update no order unless every one can be moved; by default orders that can't are skipped
+    /// and reported
+    #[serde(default)]
+    pub all_or_nothing: bool,
+}
+
+/// What happened to one order of a bulk status update.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BulkStatusResult {
+    pub order_id: Uuid,
+    pub updated: bool,
+    /// Why the order was skipped
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BulkStatusResponse {
+    /// Number of orders moved to the new status
+    pub updated: usize,
+    /// Number of orders skipped
+    pub failed: usize,
+    /// One entry per requested order, in request order
+    pub results: Vec<BulkStatusResult>,
+}
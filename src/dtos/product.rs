@@ -3,12 +3,15 @@ use uuid::Uuid;
 use rust_decimal::Decimal;
 use validator::Validate;
 use utoipa::ToSchema;
-use crate::model::product::Product;
+use crate::dtos::category::CategoryResponse;
+use crate::model::product::{Product, ProductWithAvailableStock};
 
 #[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
 pub struct NewProductDto {
     #[validate(length(min = 1, max = 255, message = "Product name must be between 1 and 255 characters"))]
     pub name: String,
+    #[validate(length(min = 1, max = 64, message = "SKU must be between 1 and 64 characters"))]
+    pub sku: Option<String>,
     #[validate(length(max = 1000, message = "Description must not exceed 1000 characters"))]
     pub description: Option<String>,
     // #[validate(range(min = 0.01, message = "Price must be greater than 0"))] // Temporarily disabled
@@ -20,24 +23,42 @@ pub struct NewProductDto {
     pub image_url: Option<String>,
     #[validate(range(min = 0, message = "Low stock threshold cannot be negative"))]
     pub low_stock_threshold: Option<i32>,
+    #[validate(range(min = 0, message = "Reorder target cannot be negative"))]
+    pub reorder_target: Option<i32>,
     pub track_inventory: Option<bool>,
 }
 
+/// Fields left out of the body are kept. The nullable ones (`sku`, `description`, `image_url`,
+/// `low_stock_threshold`, `reorder_target`) can also be sent as `null` to clear them.
 #[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
 pub struct UpdateProductDto {
     #[validate(length(min = 1, max = 255, message = "Product name must be between 1 and 255 characters"))]
     pub name: Option<String>,
+    #[serde(default, deserialize_with = "super::present", skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    #[validate(length(min = 1, max = 64, message = "SKU must be between 1 and 64 characters"))]
+    pub sku: Option<Option<String>>,
+    #[serde(default, deserialize_with = "super::present", skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
     #[validate(length(max = 1000, message = "Description must not exceed 1000 characters"))]
-    pub description: Option<String>,
+    pub description: Option<Option<String>>,
     // #[validate(range(min = 0.01, message = "Price must be greater than 0"))] // Temporarily disabled
     #[schema(value_type = String, example = "123.45")]
     pub price: Option<Decimal>,
     #[validate(range(min = 0, message = "Stock cannot be negative"))]
     pub stock: Option<i32>,
+    #[serde(default, deserialize_with = "super::present", skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
     #[validate(url(message = "Invalid image URL format"))]
-    pub image_url: Option<String>,
+    pub image_url: Option<Option<String>>,
+    #[serde(default, deserialize_with = "super::present", skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<i32>)]
     #[validate(range(min = 0, message = "Low stock threshold cannot be negative"))]
-    pub low_stock_threshold: Option<i32>,
+    pub low_stock_threshold: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "super::present", skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<i32>)]
+    #[validate(range(min = 0, message = "Reorder target cannot be negative"))]
+    pub reorder_target: Option<Option<i32>>,
     pub track_inventory: Option<bool>,
 }
 
@@ -45,30 +66,79 @@ pub struct UpdateProductDto {
 pub struct ProductResponse {
     pub id: Uuid,
     pub name: String,
+    pub sku: Option<String>,
     pub description: Option<String>,
     #[schema(value_type = String, example = "123.45")]
     pub price: Decimal,
     pub stock: i32,
+    /// Stock minus active reservations; null when the product doesn't track inventory
+    pub available_stock: Option<i32>,
     pub image_url: Option<String>,
     pub low_stock_threshold: Option<i32>,
+    pub reorder_target: Option<i32>,
     pub track_inventory: bool,
+    pub is_published: bool,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
+/// Built straight from a `Product` (write responses) no reservations are looked up, so
+/// `available_stock` is the raw stock; product reads and listings go through
+/// `ProductWithAvailableStock`.
 impl From<Product> for ProductResponse {
     fn from(p: Product) -> Self {
         ProductResponse {
             id: p.id,
             name: p.name,
+            sku: p.sku,
             description: p.description,
             price: p.price,
             stock: p.stock,
+            available_stock: p.track_inventory.then_some(p.stock),
             image_url: p.image_url,
             low_stock_threshold: p.low_stock_threshold,
+            reorder_target: p.reorder_target,
             track_inventory: p.track_inventory,
+            is_published: p.is_published,
             created_at: p.created_at,
             updated_at: p.updated_at,
         }
     }
 }
+
+impl From<ProductWithAvailableStock> for ProductResponse {
+    fn from(p: ProductWithAvailableStock) -> Self {
+        let track_inventory = p.product.track_inventory;
+        ProductResponse {
+            available_stock: track_inventory.then_some(p.available_stock),
+            ..ProductResponse::from(p.product)
+        }
+    }
+}
+
+/// A single product as shown on its detail page: the product fields plus its categories.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProductDetailResponse {
+    #[serde(flatten)]
+    pub product: ProductResponse,
+    pub categories: Vec<CategoryResponse>,
+}
+
+/// Outcome of one data line of a CSV import.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProductImportRow {
+    /// Line in the file, counting the header as line 1
+    pub line: u64,
+    pub name: Option<String>,
+    /// Id of the created product, absent when the row failed
+    pub product_id: Option<Uuid>,
+    /// Why the row was rejected, absent when it was imported
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProductImportReport {
+    pub imported: usize,
+    pub failed: usize,
+    pub rows: Vec<ProductImportRow>,
+}
@@ -0,0 +1,30 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct NewPromotionDto {
+    #[validate(length(min = 1, max = 255, message = "Promotion name must be between 1 and 255 characters"))]
+    pub name: String,
+    #[validate(length(max = 1000, message = "Description must not exceed 1000 characters"))]
+    pub description: Option<String>,
+    #[schema(value_type = String, example = "15.00")]
+    pub discount_percent: Decimal,
+    #[schema(value_type = Option<String>, example = "100.00")]
+    pub min_order_total: Option<Decimal>,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AppliedPromotion {
+    pub promotion_id: Uuid,
+    pub name: String,
+    #[schema(value_type = String, example = "15.00")]
+    pub discount_percent: Decimal,
+    #[schema(value_type = String, example = "18.75")]
+    pub discount_amount: Decimal,
+}
@@ -4,6 +4,7 @@ use axum::{
     Json,
 };
 use serde_json::json;
+use std::collections::HashMap;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -22,9 +23,24 @@ pub enum AppError {
     
     #[error("Validation error: {0}")]
     Validation(String),
+
+    /// Failed validation rules keyed by field name, so clients can attach each message to its
+    /// form input.
+    #[error("Validation error: {}", describe_fields(.0))]
+    InvalidFields(HashMap<String, Vec<String>>),
     
     #[error("Not found: {0}")]
     NotFound(String),
+
+    #[error("Insufficient stock: {0}")]
+    InsufficientStock(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    /// The path exists but not for this method; the router adds the `Allow` header.
+    #[error("Method not allowed: {0}")]
+    MethodNotAllowed(String),
     
     #[error("Unauthorized")]
     Unauthorized,
@@ -36,6 +52,26 @@ pub enum AppError {
     Internal(String),
 }
 
+impl AppError {
+    /// Stable machine-readable code for clients to branch on instead of matching messages.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Database(_) => "database_error",
+            AppError::ImageUpload(_) => "image_upload_failed",
+            AppError::FileTooLarge { .. } => "file_too_large",
+            AppError::InvalidFileType { .. } => "invalid_file_type",
+            AppError::Validation(_) | AppError::InvalidFields(_) => "validation_error",
+            AppError::NotFound(_) => "not_found",
+            AppError::InsufficientStock(_) => "insufficient_stock",
+            AppError::Conflict(_) => "conflict",
+            AppError::MethodNotAllowed(_) => "method_not_allowed",
+            AppError::Unauthorized => "unauthorized",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::Internal(_) => "internal_error",
+        }
+    }
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let (status, error_message) = match self {
@@ -59,10 +95,26 @@ impl IntoResponse for AppError {
                 tracing::warn!("Validation error: {}", msg);
                 (StatusCode::BAD_REQUEST, "Validation failed")
             }
+            AppError::InvalidFields(ref fields) => {
+                tracing::warn!("Validation error: {}", describe_fields(fields));
+                (StatusCode::BAD_REQUEST, "Validation failed")
+            }
             AppError::NotFound(ref msg) => {
                 tracing::info!("Resource not found: {}", msg);
                 (StatusCode::NOT_FOUND, "Resource not found")
             }
+            AppError::InsufficientStock(ref msg) => {
+                tracing::warn!("Insufficient stock: {}", msg);
+                (StatusCode::CONFLICT, "Insufficient stock")
+            }
+            AppError::Conflict(ref msg) => {
+                tracing::warn!("Conflict: {}", msg);
+                (StatusCode::CONFLICT, "Conflict with the current state of the resource")
+            }
+            AppError::MethodNotAllowed(ref msg) => {
+                tracing::info!("Method not allowed: {}", msg);
+                (StatusCode::METHOD_NOT_ALLOWED, "Method not allowed")
+            }
             AppError::Unauthorized => {
                 tracing::warn!("Unauthorized access attempt");
                 (StatusCode::UNAUTHORIZED, "Unauthorized")
@@ -77,14 +129,34 @@ impl IntoResponse for AppError {
             }
         };
 
-        let details = self.to_string();
-        let body = Json(json!({
+        // Server-side failures were logged above; their detail (SQL, driver messages) stays out
+        // of the response
+        let details = match self {
+            AppError::Database(_) | AppError::Internal(_) => error_message.to_string(),
+            _ => self.to_string(),
+        };
+        let mut body = json!({
+            "code": self.code(),
             "error": error_message,
             "details": details
-        }));
+        });
+        if let AppError::InvalidFields(fields) = self {
+            body["fields"] = json!(fields);
+        }
 
-        (status, body).into_response()
+        (status, Json(body)).into_response()
     }
 }
 
+/// One `field: message` entry per failed rule, comma separated and ordered by field name.
+pub fn describe_fields(fields: &HashMap<String, Vec<String>>) -> String {
+    let mut names: Vec<&String> = fields.keys().collect();
+    names.sort();
+    names
+        .into_iter()
+        .flat_map(|field| fields[field].iter().map(move |message| format!("{}: {}", field, message)))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
 pub type AppResult<T> = Result<T, AppError>;
@@ -1,9 +1,12 @@
 pub mod dtos;
 pub mod errors;
+pub mod logging;
 pub mod middleware;
 pub mod model;
 pub mod openapi;
 pub mod repository;
 pub mod routes;
+pub mod seed;
 pub mod services;
+pub mod shutdown;
 pub mod state;
@@ -0,0 +1,58 @@
+use std::env;
+
+use tracing::Subscriber;
+use tracing_subscriber::{fmt::MakeWriter, layer::SubscriberExt, EnvFilter};
+
+/// How log lines are written: readable text for local development, or one JSON object per
+/// line for log aggregators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    #[default]
+    Pretty,
+    Json,
+}
+
+impl LogFormat {
+    /// Reads `LOG_FORMAT` (`pretty` or `json`); unset means pretty.
+    pub fn from_env() -> Result<Self, String> {
+        match env::var("LOG_FORMAT") {
+            Ok(value) => value.parse(),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "pretty" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            other => Err(format!("LOG_FORMAT must be 'pretty' or 'json', got '{}'", other)),
+        }
+    }
+}
+
+/// Builds the application's subscriber. JSON lines carry the timestamp and the fields of the
+/// enclosing spans, so the `request_id` set by the request-id middleware lands on every line
+/// logged while handling that request.
+pub fn subscriber<W>(format: LogFormat, filter: EnvFilter, writer: W) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let registry = tracing_subscriber::registry().with(filter);
+
+    match format {
+        LogFormat::Pretty => Box::new(registry.with(tracing_subscriber::fmt::layer().with_writer(writer))),
+        LogFormat::Json => Box::new(
+            registry.with(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(true)
+                    .with_writer(writer),
+            ),
+        ),
+    }
+}
@@ -1,23 +1,24 @@
-use sqlx::postgres::PgPoolOptions;
 use std::env;
 use tokio::net::TcpListener;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::util::SubscriberInitExt;
 use utoipa::OpenApi as _;
 use utoipa_swagger_ui::SwaggerUi;
 
 use crate::openapi::ApiDoc;
-use crate::state::AppState;
+use crate::state::{AppState, AuthConfig, BodyLimits, CartLimits, CloudinaryConfig, DbPoolConfig, EnvConfig, HttpClientConfig, InventoryConfig, JwtConfig, LowStockWebhookConfig, MailConfig, MetricsConfig, OrderConfig, OrderWebhookConfig, PromotionConfig, ReservationConfig, StripeConfig};
 
 mod dtos;
 mod errors;
+mod logging;
 mod middleware;
 mod model;
 mod openapi;
 mod repository;
 mod routes;
 mod services;
+mod shutdown;
 
 mod state;
 
@@ -26,14 +27,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     dotenvy::dotenv().ok();
 
     // Initialize tracing
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
-                "hemp_backend=debug,tower_http=debug,axum::rejection=trace".into()
-            }),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    let log_format = logging::LogFormat::from_env().unwrap_or_else(|e| panic!("Invalid logging configuration: {}", e));
+    logging::subscriber(
+        log_format,
+        tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+            "hemp_backend=debug,tower_http=debug,axum::rejection=trace".into()
+        }),
+        std::io::stdout,
+    )
+    .init();
 
     let server_port = env::var("PORT").unwrap_or_else(|_| "3000".to_string());
     let server_address = format!("0.0.0.0:{}", server_port);
@@ -45,20 +47,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         "change_me_in_production".to_string()
     });
 
-    // Ensure Stripe key is available if needed
-    let _stripe_secret_key = env::var("STRIPE_SECRET_KEY")
-        .expect("STRIPE_SECRET_KEY environment variable is required for payment processing");
+    let stripe_config = StripeConfig::from_env().unwrap_or_else(|e| panic!("Invalid Stripe configuration: {}", e));
 
-    // Cloudinary configuration
-    let cloudinary_cloud_name = env::var("CLOUDINARY_CLOUD_NAME")
-        .expect("CLOUDINARY_CLOUD_NAME environment variable is required for image uploads");
-    let cloudinary_api_key = env::var("CLOUDINARY_API_KEY")
-        .expect("CLOUDINARY_API_KEY environment variable is required for image uploads");
-    let cloudinary_api_secret = env::var("CLOUDINARY_API_SECRET")
-        .expect("CLOUDINARY_API_SECRET environment variable is required for image uploads");
+    let cloudinary_config = CloudinaryConfig::from_env().unwrap_or_else(|e| panic!("Invalid Cloudinary configuration: {}", e));
 
-    let pool = PgPoolOptions::new()
-        .max_connections(10)
+    let pool_config = DbPoolConfig::from_env().unwrap_or_else(|e| panic!("Invalid database pool configuration: {}", e));
+    tracing::info!(
+        "Database pool: max {} / min {} connections, {:?} acquire timeout",
+        pool_config.max_connections, pool_config.min_connections, pool_config.acquire_timeout
+    );
+
+    let pool = pool_config
+        .pool_options()
         .connect(&database_url)
         .await
         .expect("Cannot connect to database");
@@ -68,28 +68,86 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     sqlx::migrate!().run(&pool).await?;
     tracing::info!("Database migrations completed successfully");
 
+    let jwt_config = JwtConfig::from_env().unwrap_or_else(|e| panic!("Invalid JWT configuration: {}", e));
+    let auth_config = AuthConfig::from_env().unwrap_or_else(|e| panic!("Invalid auth configuration: {}", e));
+    let reservation_config = ReservationConfig::from_env()
+        .unwrap_or_else(|e| panic!("Invalid reservation configuration: {}", e));
+    let inventory_config = InventoryConfig::from_env()
+        .unwrap_or_else(|e| panic!("Invalid inventory configuration: {}", e));
+    let low_stock_webhook_config = LowStockWebhookConfig::from_env()
+        .unwrap_or_else(|e| panic!("Invalid low stock webhook configuration: {}", e));
+    let order_webhook_config = OrderWebhookConfig::from_env()
+        .unwrap_or_else(|e| panic!("Invalid order webhook configuration: {}", e));
+    let order_config = OrderConfig::from_env().unwrap_or_else(|e| panic!("Invalid order configuration: {}", e));
+    let cart_limits = CartLimits::from_env().unwrap_or_else(|e| panic!("Invalid cart limits configuration: {}", e));
+    let promotion_config = PromotionConfig::from_env().unwrap_or_else(|e| panic!("Invalid promotion configuration: {}", e));
+    let body_limits = BodyLimits::from_env().unwrap_or_else(|e| panic!("Invalid request size configuration: {}", e));
+    let http_config = HttpClientConfig::from_env().unwrap_or_else(|e| panic!("Invalid HTTP client configuration: {}", e));
+    let metrics_config = MetricsConfig::from_env().unwrap_or_else(|e| panic!("Invalid metrics configuration: {}", e));
+    let mail_config = MailConfig::from_env().unwrap_or_else(|e| panic!("Invalid mail configuration: {}", e));
+    if mail_config.api_key.is_none() {
+        tracing::warn!("MAIL_API_KEY not set; verification and password reset emails will not be sent");
+    }
+    let http = http_config.build_client();
+
     let state = AppState {
         db: pool,
         jwt_secret: std::sync::Arc::new(jwt_secret),
-        cloudinary_cloud_name: std::sync::Arc::new(cloudinary_cloud_name),
-        cloudinary_api_key: std::sync::Arc::new(cloudinary_api_key),
-        cloudinary_api_secret: std::sync::Arc::new(cloudinary_api_secret),
+        jwt_config: std::sync::Arc::new(jwt_config),
+        auth_config: std::sync::Arc::new(auth_config),
+        reservation_config: std::sync::Arc::new(reservation_config),
+        inventory_config: std::sync::Arc::new(inventory_config),
+        low_stock_webhook_config: std::sync::Arc::new(low_stock_webhook_config),
+        order_webhook_config: std::sync::Arc::new(order_webhook_config),
+        order_config: std::sync::Arc::new(order_config),
+        cart_limits: std::sync::Arc::new(cart_limits),
+        promotion_config: std::sync::Arc::new(promotion_config),
+        body_limits: std::sync::Arc::new(body_limits),
+        mailer: services::mailer::from_config(http.clone(), &mail_config),
+        http,
+        stripe_config: std::sync::Arc::new(stripe_config),
+        cloudinary_config: std::sync::Arc::new(cloudinary_config),
     };
 
     let cors = CorsLayer::new()
         .allow_methods(Any)
         .allow_headers(Any)
-        .allow_origin(Any); // Configure this properly for production
+        .allow_origin(Any) // Configure this properly for production
+        .expose_headers([
+            axum::http::HeaderName::from_static("x-total-count"),
+            axum::http::HeaderName::from_static("x-page-limit"),
+            axum::http::HeaderName::from_static("x-page-offset"),
+            axum::http::HeaderName::from_static(middleware::request_id::REQUEST_ID_HEADER),
+        ]);
 
     let listener = TcpListener::bind(&server_address).await?;
     tracing::info!("Server listening on {}", server_address);
 
-    let router = routes::build_route(state)
-        .merge(SwaggerUi::new("/docs").url("/api-docs/openapi.json", ApiDoc::openapi()))
+    let db = state.db.clone();
+    let mut router = routes::build_route(state.clone())
+        .merge(SwaggerUi::new("/docs").url("/api-docs/openapi.json", ApiDoc::openapi()));
+
+    if metrics_config.enabled {
+        let handle = middleware::metrics::install_recorder();
+        router = router
+            .merge(routes::metrics::build_route(handle).with_state(state))
+            .layer(axum::middleware::from_fn(middleware::metrics::track_metrics));
+        tracing::info!("Prometheus metrics exposed at /metrics");
+    }
+
+    let router = router
         .layer(TraceLayer::new_for_http())
+        // Outside the trace layer so its request/response events carry the id
+        .layer(axum::middleware::from_fn(middleware::request_id::request_id))
         .layer(cors);
 
-    axum::serve(listener, router).await?;
+    axum::serve(listener, router)
+        .with_graceful_shutdown(shutdown::shutdown_signal())
+        .await?;
+
+    tracing::info!("In-flight requests drained, closing database connections");
+    db.close().await;
+    tracing::info!("Shutdown complete");
 
     Ok(())
 }
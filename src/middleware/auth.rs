@@ -1,46 +1,193 @@
 use crate::dtos::Claims;
 use crate::errors::AppError;
+use crate::repository::UserRepository;
 use crate::state::AppState;
 use axum::{
-    extract::FromRequestParts,
-    http::{StatusCode, request::Parts},
+    Json,
+    extract::{FromRequestParts, OptionalFromRequestParts},
+    http::{StatusCode, header, request::Parts},
+    response::{IntoResponse, Response},
 };
-use jsonwebtoken::{DecodingKey, Validation, decode};
+use jsonwebtoken::{DecodingKey, Validation, decode, errors::ErrorKind};
+use serde_json::json;
 
+/// Why a request failed authentication. Each variant has its own `code` so clients can tell
+/// "log in again" (`token_expired`) from "send a token" (`missing_token`); 401s also carry a
+/// `WWW-Authenticate: Bearer` challenge as RFC 6750 asks. `AccountNotFound` is a token whose
+/// account was deleted (or never existed) and is answered with 404. `Unavailable` means the
+/// token could not be checked against the accounts table and is answered with 503.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthRejection {
+    MissingToken,
+    InvalidToken,
+    TokenExpired,
+    TokenRevoked,
+    AccountDeactivated,
+    AccountNotFound,
+    Unavailable,
+}
+
+impl AuthRejection {
+    pub fn code(&self) -> &'static str {
+        match self {
+            AuthRejection::MissingToken => "missing_token",
+            AuthRejection::InvalidToken => "invalid_token",
+            AuthRejection::TokenExpired => "token_expired",
+            AuthRejection::TokenRevoked => "token_revoked",
+            AuthRejection::AccountDeactivated => "account_deactivated",
+            AuthRejection::AccountNotFound => "account_not_found",
+            AuthRejection::Unavailable => "service_unavailable",
+        }
+    }
+
+    fn message(&self) -> &'static str {
+        match self {
+            AuthRejection::MissingToken => "Missing bearer token",
+            AuthRejection::InvalidToken => "Invalid token",
+            AuthRejection::TokenExpired => "Token has expired",
+            AuthRejection::TokenRevoked => "Token has been revoked",
+            AuthRejection::AccountDeactivated => "Account is deactivated",
+            AuthRejection::AccountNotFound => "User no longer exists",
+            AuthRejection::Unavailable => "Could not verify the token, try again later",
+        }
+    }
+
+    fn challenge(&self) -> Option<&'static str> {
+        match self {
+            AuthRejection::MissingToken => Some("Bearer"),
+            AuthRejection::InvalidToken => Some(r#"Bearer error="invalid_token""#),
+            AuthRejection::TokenExpired => {
+                Some(r#"Bearer error="invalid_token", error_description="The access token expired""#)
+            }
+            AuthRejection::TokenRevoked => {
+                Some(r#"Bearer error="invalid_token", error_description="The access token was revoked""#)
+            }
+            AuthRejection::AccountDeactivated | AuthRejection::AccountNotFound | AuthRejection::Unavailable => None,
+        }
+    }
+}
+
+impl IntoResponse for AuthRejection {
+    fn into_response(self) -> Response {
+        let (status, error) = match self {
+            AuthRejection::AccountDeactivated => (StatusCode::FORBIDDEN, "Forbidden"),
+            AuthRejection::AccountNotFound => (StatusCode::NOT_FOUND, "Not Found"),
+            AuthRejection::Unavailable => (StatusCode::SERVICE_UNAVAILABLE, "Service Unavailable"),
+            _ => (StatusCode::UNAUTHORIZED, "Unauthorized"),
+        };
+        let body = Json(json!({
+            "code": self.code(),
+            "error": error,
+            "details": self.message()
+        }));
+
+        let mut response = (status, body).into_response();
+        if let Some(challenge) = self.challenge() {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, header::HeaderValue::from_static(challenge));
+        }
+        response
+    }
+}
 
 pub struct AuthUser(pub Claims);
 
 impl FromRequestParts<AppState> for AuthUser
 {
-    type Rejection = (StatusCode, String);
+    type Rejection = AuthRejection;
 
     async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
         let auth_header = parts
             .headers
-            .get("Authorization")
-            .ok_or((StatusCode::UNAUTHORIZED, "missing auth".to_string()))?;
+            .get(header::AUTHORIZATION)
+            .ok_or(AuthRejection::MissingToken)?;
         let token = auth_header
             .to_str()
-            .map_err(|_| (StatusCode::UNAUTHORIZED, "invalid header".to_string()))?;
+            .map_err(|_| AuthRejection::InvalidToken)?;
         let token = token
             .strip_prefix("Bearer ")
-            .ok_or((StatusCode::UNAUTHORIZED, "invalid token".to_string()))?;
+            .ok_or(AuthRejection::InvalidToken)?;
+
+        let config = &state.jwt_config;
+        let mut validation = Validation::new(config.algorithm);
+        let mut required_claims = vec!["exp"];
+        if let Some(ref issuer) = config.issuer {
+            validation.set_issuer(&[issuer]);
+            required_claims.push("iss");
+        }
+        match config.audience {
+            Some(ref audience) => {
+                validation.set_audience(&[audience]);
+                required_claims.push("aud");
+            }
+            None => validation.validate_aud = false,
+        }
+        validation.set_required_spec_claims(&required_claims);
 
         let decoded = decode::<Claims>(
             token,
             &DecodingKey::from_secret(state.jwt_secret.as_bytes()),
-            &Validation::default(),
+            &validation,
         )
-        .map_err(|_| (StatusCode::UNAUTHORIZED, "invalid token".to_string()))?;
+        .map_err(|e| match e.kind() {
+            ErrorKind::ExpiredSignature => AuthRejection::TokenExpired,
+            _ => AuthRejection::InvalidToken,
+        })?;
+
+        // Reject tokens revoked by logout and tokens of deleted or deactivated accounts. If the
+        // lookup fails we can't tell either way, so the request is refused rather than let through.
+        let mut claims = decoded.claims;
+        match UserRepository::new(state.db.clone()).token_status(claims.jti, claims.sub).await {
+            Ok((true, _)) => return Err(AuthRejection::TokenRevoked),
+            Ok((false, None)) => return Err(AuthRejection::AccountNotFound),
+            Ok((false, Some((_, false)))) => return Err(AuthRejection::AccountDeactivated),
+            // The role is the account's current one, not the one the token was issued with, so
+            // a demoted admin loses admin access straight away
+            Ok((false, Some((role, true)))) => claims.role = role,
+            Err(e) => {
+                tracing::error!("Failed to check token status: {}", e);
+                return Err(AuthRejection::Unavailable);
+            }
+        }
+
+        Ok(AuthUser(claims))
+    }
+}
+
+/// `Option<AuthUser>` is for public routes that show admins more: a request without an
+/// `Authorization` header is anonymous, but a header that doesn't check out is still rejected.
+impl OptionalFromRequestParts<AppState> for AuthUser {
+    type Rejection = AuthRejection;
 
-        Ok(AuthUser(decoded.claims))
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Option<Self>, Self::Rejection> {
+        if !parts.headers.contains_key(header::AUTHORIZATION) {
+            return Ok(None);
+        }
+        <AuthUser as FromRequestParts<AppState>>::from_request_parts(parts, state).await.map(Some)
+    }
+}
+
+/// An authenticated admin. Non-admins are turned away with 403 during extraction, before the
+/// handler runs.
+pub struct AdminUser(pub Claims);
+
+impl FromRequestParts<AppState> for AdminUser {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let AuthUser(claims) = <AuthUser as FromRequestParts<AppState>>::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        require_admin(&claims).map_err(IntoResponse::into_response)?;
+        Ok(AdminUser(claims))
     }
 }
 
 pub fn require_admin(claims: &Claims) -> Result<(), AppError> {
-    if claims.role == "admin" {
+    if claims.has_role("admin") {
         Ok(())
     } else {
-        Err(AppError::Unauthorized)
+        Err(AppError::Forbidden("Admin access required".into()))
     }
 }
@@ -0,0 +1,36 @@
+use axum::{
+    body::HttpBody,
+    extract::{DefaultBodyLimit, Request, State},
+    middleware::{from_fn_with_state, Next},
+    response::{IntoResponse, Response},
+    Router,
+};
+use tower_http::limit::RequestBodyLimitLayer;
+
+use crate::errors::AppError;
+
+/// Caps request bodies on every route in `router` at `max_bytes`. Bodies that declare a larger
+/// length up front get the usual JSON error with a 413; streamed bodies are cut off once they
+/// pass the limit.
+pub fn limit_body<S>(router: Router<S>, max_bytes: usize) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router
+        // Replaces axum's per-extractor 2MB default, which would otherwise cap larger limits
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(max_bytes))
+        .layer(from_fn_with_state(max_bytes, reject_oversized))
+}
+
+async fn reject_oversized(State(max_bytes): State<usize>, req: Request, next: Next) -> Response {
+    // Exact when the client sent a Content-Length
+    match req.body().size_hint().exact() {
+        Some(actual_size) if actual_size > max_bytes as u64 => AppError::FileTooLarge {
+            max_size: max_bytes as u64,
+            actual_size,
+        }
+        .into_response(),
+        _ => next.run(req).await,
+    }
+}
@@ -5,9 +5,13 @@ use axum::{
 };
 use async_trait::async_trait;
 use serde::de::DeserializeOwned;
-use validator::Validate;
-use crate::errors::AppError;
+use std::collections::HashMap;
+use validator::{Validate, ValidationErrors};
+use crate::errors::{describe_fields, AppError};
 
+/// A JSON body that deserialized and passed its `Validate` rules. Bodies with the wrong shape
+/// (a string where a number belongs, a fraction for a whole-number field, a missing field) are
+/// reported the same way as rule failures: a 400 naming the offending field.
 pub struct ValidatedJson<T>(pub T);
 
 impl<T, S> FromRequest<S> for ValidatedJson<T>
@@ -19,30 +23,50 @@ where
 
     fn from_request(req: Request, state: &S) -> impl core::future::Future<Output = Result<Self, Self::Rejection>> + Send {
         async move {
-        let Json(value) = Json::<T>::from_request(req, state)
+        let Json(body) = Json::<serde_json::Value>::from_request(req, state)
             .await
             .map_err(IntoResponse::into_response)?;
 
-        value.validate().map_err(|errors| {
-            let error_message = errors
-                .field_errors()
-                .iter()
-                .flat_map(|(field, errors)| {
-                    errors.iter().map(move |error| {
-                        format!(
-                            "{}: {}",
-                            field,
-                            error.message.as_ref().unwrap_or(&"Invalid value".into())
-                        )
-                    })
-                })
-                .collect::<Vec<String>>()
-                .join(", ");
+        let value: T = serde_path_to_error::deserialize(body).map_err(|error| {
+            let field = match error.path().to_string() {
+                path if path == "." => "body".to_string(),
+                path => path,
+            };
+            let fields = HashMap::from([(field, vec![error.into_inner().to_string()])]);
+            AppError::InvalidFields(fields).into_response()
+        })?;
 
-            AppError::Validation(error_message).into_response()
+        value.validate().map_err(|errors| {
+            AppError::InvalidFields(field_messages(&errors)).into_response()
         })?;
 
         Ok(ValidatedJson(value))
         }
     }
 }
+
+/// The failed rules' messages grouped by field name.
+pub fn field_messages(errors: &ValidationErrors) -> HashMap<String, Vec<String>> {
+    errors
+        .field_errors()
+        .iter()
+        .map(|(field, errors)| {
+            let messages = errors
+                .iter()
+                .map(|error| {
+                    error
+                        .message
+                        .as_ref()
+                        .map(|message| message.to_string())
+                        .unwrap_or_else(|| "Invalid value".to_string())
+                })
+                .collect();
+            (field.to_string(), messages)
+        })
+        .collect()
+}
+
+/// One `field: message` entry per failed rule, comma separated.
+pub fn describe_validation_errors(errors: &ValidationErrors) -> String {
+    describe_fields(&field_messages(errors))
+}
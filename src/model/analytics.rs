@@ -0,0 +1,27 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct TopProduct {
+    pub product_id: Uuid,
+    pub name: String,
+    pub quantity_sold: i64,
+    #[schema(value_type = String, example = "249.50")]
+    pub revenue: Decimal,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SalesSummary {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    #[schema(value_type = String, example = "1834.20")]
+    pub total_revenue: Decimal,
+    pub order_count: i64,
+    #[schema(value_type = String, example = "61.14")]
+    pub average_order_value: Decimal,
+    /// Best sellers by units sold
+    pub top_products: Vec<TopProduct>,
+}
@@ -8,6 +8,15 @@ pub struct Category {
     pub id: Uuid,
     pub name: String,
     pub description: Option<String>,
+    pub parent_id: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
 }
+
+/// A category plus how many live products are assigned to it.
+#[derive(Debug, sqlx::FromRow)]
+pub struct CategoryWithCount {
+    #[sqlx(flatten)]
+    pub category: Category,
+    pub product_count: i64,
+}
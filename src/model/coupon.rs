@@ -0,0 +1,20 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct Coupon {
+    pub id: Uuid,
+    /// Stored upper-case; codes are matched case-insensitively
+    pub code: String,
+    #[schema(value_type = Option<String>, example = "10.00")]
+    pub percent_off: Option<Decimal>,
+    #[schema(value_type = Option<String>, example = "5.00")]
+    pub amount_off: Option<Decimal>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub usage_limit: Option<i32>,
+    pub times_used: i32,
+    pub created_at: DateTime<Utc>,
+}
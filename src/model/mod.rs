@@ -5,3 +5,6 @@ pub mod product;
 pub mod user;
 pub mod payment;
 pub mod stock;
+pub mod promotion;
+pub mod coupon;
+pub mod analytics;
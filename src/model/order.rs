@@ -8,13 +8,40 @@ use utoipa::ToSchema;
 pub struct Order {
     pub id: Uuid,
     pub user_id: Uuid,
+    /// Sum of the item prices before promotions, tax and shipping
+    #[schema(value_type = String, example = "130.00")]
+    pub subtotal: Decimal,
+    #[schema(value_type = String, example = "10.73")]
+    pub tax: Decimal,
+    #[schema(value_type = String, example = "4.99")]
+    pub shipping: Decimal,
     #[schema(value_type = String, example = "123.45")]
     pub total: Decimal,
-    pub status: String,
+    pub coupon_id: Option<Uuid>,
+    /// Part of the discount that came from the coupon
+    #[schema(value_type = String, example = "5.00")]
+    pub coupon_discount: Decimal,
+    /// Parsed on load, so a row holding an unknown status fails loudly instead of flowing through
+    #[sqlx(try_from = "String")]
+    pub status: OrderStatus,
     pub payment_id: Option<Uuid>,
+    pub notes: Option<String>,
+    /// Set when the order is marked shipped
+    pub tracking_number: Option<String>,
+    pub carrier: Option<String>,
     pub created_at: DateTime<Utc>,
 }
 
+/// What an order costs: `total = subtotal - discount + tax + shipping`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderTotals {
+    pub subtotal: Decimal,
+    pub discount: Decimal,
+    pub tax: Decimal,
+    pub shipping: Decimal,
+    pub total: Decimal,
+}
+
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
 pub struct OrderItem {
     pub id: Uuid,
@@ -32,12 +59,30 @@ pub struct OrderWithItems {
     pub items: Vec<OrderItem>,
 }
 
+/// One step in an order's status timeline.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct OrderStatusChange {
+    pub id: Uuid,
+    pub order_id: Uuid,
+    pub from_status: String,
+    pub to_status: String,
+    pub changed_at: DateTime<Utc>,
+    /// User whose request made the change; `null` for changes made by the system, such as
+    /// payment webhooks
+    pub changed_by: Option<Uuid>,
+}
+
 #[derive(Debug, Deserialize, utoipa::ToSchema)]
 pub struct UpdateStatusDto {
     pub status: String,
+    /// Only accepted together with `"status": "shipped"`
+    pub tracking_number: Option<String>,
+    /// Shipping carrier, e.g. `UPS`; needs a `tracking_number`
+    pub carrier: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
 pub enum OrderStatus {
     Cart,
     PendingPayment,
@@ -50,18 +95,65 @@ pub enum OrderStatus {
     Refunded,
 }
 
-impl ToString for OrderStatus {
-    fn to_string(&self) -> String {
-        match self {
-            OrderStatus::Cart => "cart".to_string(),
-            OrderStatus::PendingPayment => "pending_payment".to_string(),
-            OrderStatus::PaymentProcessing => "payment_processing".to_string(),
-            OrderStatus::Paid => "paid".to_string(),
-            OrderStatus::Processing => "processing".to_string(),
-            OrderStatus::Shipped => "shipped".to_string(),
-            OrderStatus::Delivered => "delivered".to_string(),
-            OrderStatus::Cancelled => "cancelled".to_string(),
-            OrderStatus::Refunded => "refunded".to_string(),
+impl std::fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            OrderStatus::Cart => "cart",
+            OrderStatus::PendingPayment => "pending_payment",
+            OrderStatus::PaymentProcessing => "payment_processing",
+            OrderStatus::Paid => "paid",
+            OrderStatus::Processing => "processing",
+            OrderStatus::Shipped => "shipped",
+            OrderStatus::Delivered => "delivered",
+            OrderStatus::Cancelled => "cancelled",
+            OrderStatus::Refunded => "refunded",
+        })
+    }
+}
+
+impl std::str::FromStr for OrderStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cart" => Ok(OrderStatus::Cart),
+            "pending_payment" => Ok(OrderStatus::PendingPayment),
+            "payment_processing" => Ok(OrderStatus::PaymentProcessing),
+            "paid" => Ok(OrderStatus::Paid),
+            "processing" => Ok(OrderStatus::Processing),
+            "shipped" => Ok(OrderStatus::Shipped),
+            "delivered" => Ok(OrderStatus::Delivered),
+            "cancelled" => Ok(OrderStatus::Cancelled),
+            "refunded" => Ok(OrderStatus::Refunded),
+            other => Err(format!("Unknown order status '{}'", other)),
         }
     }
 }
+
+impl TryFrom<String> for OrderStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl OrderStatus {
+    /// The order lifecycle as an admin may move it: cart → pending_payment, then paid →
+    /// processing → shipped → delivered. Moving into and out of payment_processing and into paid
+    /// is left to the payment flow, which holds and takes the stock. An order can be cancelled
+    /// until it is paid for and refunded after that; cancelled/refunded are final.
+    pub fn can_transition_to(&self, next: &OrderStatus) -> bool {
+        use OrderStatus::*;
+
+        matches!(
+            (self, next),
+            (Cart | PendingPayment | PaymentProcessing, Cancelled)
+                | (Cart, PendingPayment)
+                | (Paid, Processing)
+                | (Processing, Shipped)
+                | (Shipped, Delivered)
+                | (Paid | Processing | Shipped | Delivered, Refunded)
+        )
+    }
+}
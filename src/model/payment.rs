@@ -66,8 +66,10 @@ impl PaymentStatus {
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CreatePaymentIntentRequest {
-    #[schema(value_type = String, example = "123.45")]
-    pub amount: BigDecimal,
+    /// Optional; the order total is always what gets charged, and a value that differs from it
+    /// is rejected
+    #[schema(value_type = Option<String>, example = "123.45")]
+    pub amount: Option<BigDecimal>,
     pub currency: String,
     pub order_id: Uuid,
 }
@@ -8,15 +8,21 @@ use utoipa::ToSchema;
 pub struct Product {
     pub id: Uuid,
     pub name: String,
+    pub sku: Option<String>,
     pub description: Option<String>,
     #[schema(value_type = String, example = "19.99")]
     pub price: Decimal,
     pub stock: i32,
     pub low_stock_threshold: Option<i32>,
+    /// Stock level to reorder up to; the reorder report uses twice the threshold when unset
+    pub reorder_target: Option<i32>,
     pub track_inventory: bool,
+    /// Unpublished products are hidden from public listings but still resolvable by id
+    pub is_published: bool,
     pub image_url: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
@@ -0,0 +1,42 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct Promotion {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    #[schema(value_type = String, example = "15.00")]
+    pub discount_percent: Decimal,
+    #[schema(value_type = String, example = "100.00")]
+    pub min_order_total: Decimal,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// How multiple eligible promotions combine on a single order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PromotionStacking {
+    /// Only the promotion giving the largest discount applies
+    #[default]
+    BestOnly,
+    /// Every eligible promotion applies, each computed on the subtotal
+    Stack,
+}
+
+impl std::str::FromStr for PromotionStacking {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "best" => Ok(PromotionStacking::BestOnly),
+            "stack" => Ok(PromotionStacking::Stack),
+            other => Err(format!("Unknown promotion stacking '{}', expected best or stack", other)),
+        }
+    }
+}
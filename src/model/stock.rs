@@ -2,12 +2,14 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use utoipa::ToSchema;
+use validator::Validate;
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
 pub struct StockReservation {
     pub id: Uuid,
     pub product_id: Uuid,
     pub cart_id: Uuid,
+    pub order_id: Option<Uuid>, // set when held for an order awaiting payment
     pub quantity: i32,
     pub reserved_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
@@ -24,6 +26,7 @@ pub struct InventoryLog {
     pub new_stock: i32,
     pub reference_id: Option<Uuid>,
     pub notes: Option<String>,
+    pub changed_by: Option<Uuid>, // user behind the change; None for system changes
     pub created_at: DateTime<Utc>,
 }
 
@@ -61,17 +64,51 @@ impl InventoryChangeType {
     }
 }
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StockUpdateMode {
+    /// `quantity` is the new stock level
+    #[default]
+    Set,
+    /// `quantity` is added to (or, when negative, taken from) the current stock
+    Adjust,
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct StockUpdateRequest {
     pub quantity: i32,
+    #[serde(default)]
+    pub mode: StockUpdateMode,
     pub notes: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
 pub struct StockReservationRequest {
     pub product_id: Uuid,
+    #[validate(range(min = 1, message = "Quantity must be at least 1"))]
     pub quantity: i32,
-    pub expires_in_minutes: Option<i32>, // defaults to 30 minutes
+    #[validate(range(min = 1, message = "Expiry must be at least 1 minute"))]
+    pub expires_in_minutes: Option<i32>, // defaults to RESERVATION_DEFAULT_MINUTES
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct CartReservationRequest {
+    #[validate(range(min = 1, message = "Expiry must be at least 1 minute"))]
+    pub expires_in_minutes: Option<i32>, // defaults to RESERVATION_DEFAULT_MINUTES
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct BatchStockRequest {
+    #[validate(length(min = 1, max = 100, message = "Between 1 and 100 product ids are allowed"))]
+    pub product_ids: Vec<Uuid>,
+}
+
+/// Who is holding a product's stock right now.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ProductReservations {
+    pub product_id: Uuid,
+    pub total_reserved: i32,
+    pub reservations: Vec<StockReservation>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -84,6 +121,31 @@ pub struct LowStockAlert {
     pub is_critical: bool, // true if stock is 0 or negative
 }
 
+/// A product at or below its low-stock threshold and how many units would restock it.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ReorderSuggestion {
+    pub product_id: Uuid,
+    pub product_name: String,
+    pub current_stock: i32,
+    pub available_stock: i32,
+    pub threshold: i32,
+    /// The product's own reorder target, if set
+    pub reorder_target: Option<i32>,
+    /// Units to order to bring available stock up to the reorder target (twice the threshold without one)
+    pub suggested_quantity: i32,
+}
+
+/// One product's stock position, as exported to CSV.
+#[derive(Debug, Clone)]
+pub struct StockLevel {
+    pub product_id: Uuid,
+    pub product_name: String,
+    pub current_stock: i32,
+    pub reserved: i32,
+    pub available_stock: i32,
+    pub is_low_stock: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct InventoryReport {
     pub total_products: i32,
@@ -9,5 +9,7 @@ pub struct User {
     pub email: String,
     pub password_hash: String,
     pub role: String,
+    pub email_verified: bool,
+    pub is_active: bool,
     pub created_at: DateTime<Utc>,
 }
@@ -1,14 +1,15 @@
 use utoipa::{
-    openapi::security::{ApiKey, ApiKeyValue, SecurityScheme},
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
     Modify, OpenApi,
 };
 
 
 use crate::dtos::{
-    NewProductDto, UpdateProductDto, ProductResponse,
-    SignupDto, LoginDto, UserResponse,
-    AddToCartDto, OrderResponse, CategoryResponse, NewCategoryDto, UpdateCategoryDto,
-    CreateOrderRequest, CreateOrderResponse, OrderDetailsResponse, OrderItemResponse,
+    NewProductDto, UpdateProductDto, ProductResponse, ProductDetailResponse, ProductImportReport, ProductImportRow,
+    SignupDto, LoginDto, UserResponse, ForgotPasswordDto, ResetPasswordDto, UpdateRoleDto, DeleteAccountDto,
+    AddToCartDto, OrderResponse, CategoryResponse, CategoryTreeNode, NewCategoryDto, UpdateCategoryDto, BulkAssignDto, BulkAssignResponse,
+    BulkStatusRequest, BulkStatusResponse, BulkStatusResult, CreateOrderRequest, CreateOrderResponse, InvoiceResponse, OrderDetailsResponse, OrderItemResponse,
+    CartQuoteItem, CartQuoteResponse, NewPromotionDto, AppliedPromotion, CouponDto,
 };
 
 #[derive(OpenApi)]
@@ -18,59 +19,117 @@ use crate::dtos::{
         crate::routes::product::list_products,
         crate::routes::product::create_product,
         crate::routes::product::get_product,
+        crate::routes::product::get_product_by_sku,
         crate::routes::product::update_product,
         crate::routes::product::delete_product,
+        crate::routes::product::restore_product,
+        crate::routes::product::duplicate_product,
+        crate::routes::product::publish_product,
+        crate::routes::product::unpublish_product,
+        crate::routes::product::import_products,
         
         // Category routes
         crate::routes::category::list_categories,
         crate::routes::category::create_category,
         crate::routes::category::get_category,
+        crate::routes::category::category_tree,
+        crate::routes::category::list_children,
+        crate::routes::category::category_breadcrumb,
+        crate::routes::category::list_category_products,
         crate::routes::category::update_category,
         crate::routes::category::delete_category,
         crate::routes::category::assign_product,
+        crate::routes::category::assign_products,
+        crate::routes::category::unassign_product,
         
         // Auth routes
         crate::routes::auth::signup,
         crate::routes::auth::login,
+        crate::routes::auth::logout,
+        crate::routes::auth::delete_account,
+        crate::routes::auth::me,
+        crate::routes::auth::verify_email,
+        crate::routes::auth::forgot_password,
+        crate::routes::auth::reset_password,
+        crate::routes::auth::list_users,
+        crate::routes::auth::update_user_role,
+        crate::routes::auth::deactivate_user,
+        crate::routes::auth::activate_user,
         
         // Cart routes
         crate::routes::cart::add_to_cart,
+        crate::routes::cart::quote_cart,
         
         // Order routes
         crate::routes::order::create_order,
         crate::routes::order::get_order_details,
+        crate::routes::order::get_invoice,
+        crate::routes::order::get_status_history,
         crate::routes::order::my_orders,
         crate::routes::order::all_orders,
+        crate::routes::order::all_orders_with_items,
         crate::routes::order::update_status,
+        crate::routes::order::bulk_update_status,
         crate::routes::order::pay_order,
+        crate::routes::order::cancel_order,
 
         // Inventory routes
         crate::routes::inventory::get_available_stock,
         crate::routes::inventory::update_stock,
+        crate::routes::inventory::get_available_stock_batch,
         crate::routes::inventory::get_inventory_history,
+        crate::routes::inventory::list_product_reservations,
         crate::routes::inventory::create_reservation,
+        crate::routes::inventory::reserve_cart,
+        crate::routes::inventory::list_all_reservations,
         crate::routes::inventory::cancel_reservation,
         crate::routes::inventory::cleanup_expired_reservations,
         crate::routes::inventory::get_low_stock_alerts,
         crate::routes::inventory::get_inventory_report,
+        crate::routes::inventory::get_reorder_report,
+        crate::routes::inventory::export_inventory,
 
         // Payment routes
+        crate::routes::payment::list_payments,
         crate::routes::payment::create_payment_intent,
         crate::routes::payment::get_payment_by_order,
+        crate::routes::payment::get_payment_by_intent,
         crate::routes::payment::refund_payment,
         crate::routes::payment::handle_stripe_webhook,
 
+        // Promotion routes
+        crate::routes::promotion::list_promotions,
+        crate::routes::promotion::create_promotion,
+        crate::routes::promotion::deactivate_promotion,
+
+        // Coupon routes
+        crate::routes::coupon::list_coupons,
+        crate::routes::coupon::create_coupon,
+        crate::routes::coupon::get_coupon,
+        crate::routes::coupon::update_coupon,
+        crate::routes::coupon::delete_coupon,
+
+        // Analytics routes
+        crate::routes::analytics::sales_summary,
+
         // Image upload
         crate::routes::image::upload_image,
+        crate::routes::image::delete_image,
+
+        // Health
+        crate::routes::health::liveness,
+        crate::routes::health::readiness,
+        crate::routes::metrics::render_metrics,
     ),
     components(
         schemas(
             // DTOs
-            NewProductDto, UpdateProductDto, ProductResponse,
-            SignupDto, LoginDto, UserResponse,
+            NewProductDto, UpdateProductDto, ProductResponse, ProductDetailResponse, ProductImportReport, ProductImportRow,
+            SignupDto, LoginDto, UserResponse, ForgotPasswordDto, ResetPasswordDto, UpdateRoleDto, DeleteAccountDto,
             AddToCartDto, OrderResponse,
-            CreateOrderRequest, CreateOrderResponse, OrderDetailsResponse, OrderItemResponse,
-            CategoryResponse, NewCategoryDto, UpdateCategoryDto,
+            BulkStatusRequest, BulkStatusResponse, BulkStatusResult, CreateOrderRequest, CreateOrderResponse, InvoiceResponse, OrderDetailsResponse, OrderItemResponse,
+            CategoryResponse, CategoryTreeNode, NewCategoryDto, UpdateCategoryDto, BulkAssignDto, BulkAssignResponse,
+            CartQuoteItem, CartQuoteResponse, NewPromotionDto, AppliedPromotion, CouponDto,
 
             // Models
             crate::model::product::Product,
@@ -82,18 +141,29 @@ use crate::dtos::{
             crate::model::order::Order,
             crate::model::order::OrderItem,
             crate::model::order::OrderWithItems,
+            crate::model::order::OrderStatusChange,
             crate::model::order::UpdateStatusDto,
             crate::model::payment::Payment,
             crate::model::payment::PaymentWebhook,
             crate::model::payment::CreatePaymentIntentRequest,
             crate::model::payment::PaymentIntentResponse,
             crate::model::stock::StockReservation,
+            crate::model::stock::ProductReservations,
+            crate::model::stock::BatchStockRequest,
             crate::model::stock::InventoryLog,
             crate::model::stock::InventoryChangeType,
             crate::model::stock::StockUpdateRequest,
+            crate::model::stock::StockUpdateMode,
             crate::model::stock::StockReservationRequest,
+            crate::model::stock::CartReservationRequest,
             crate::model::stock::LowStockAlert,
             crate::model::stock::InventoryReport,
+            crate::model::stock::ReorderSuggestion,
+            crate::routes::pagination::PageMeta,
+            crate::model::promotion::Promotion,
+            crate::model::coupon::Coupon,
+            crate::model::analytics::SalesSummary,
+            crate::model::analytics::TopProduct,
         )
     ),
     tags(
@@ -104,7 +174,11 @@ use crate::dtos::{
         (name = "Cart", description = "Shopping cart endpoints"),
         (name = "Orders", description = "Order management endpoints"),
         (name = "Payments", description = "Payment processing endpoints"),
+        (name = "Promotions", description = "Store-wide promotion endpoints"),
+        (name = "Coupons", description = "Checkout discount code endpoints"),
+        (name = "Analytics", description = "Admin sales reporting endpoints"),
         (name = "Images", description = "Image upload endpoints"),
+        (name = "Health", description = "Liveness and readiness probes"),
     ),
     info(
         title = "Hemp E-commerce API",
@@ -134,7 +208,12 @@ impl Modify for SecurityAddon {
         if let Some(components) = openapi.components.as_mut() {
             components.add_security_scheme(
                 "bearer_auth",
-                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("Authorization"))),
+                SecurityScheme::Http(
+                    HttpBuilder::new()
+                        .scheme(HttpAuthScheme::Bearer)
+                        .bearer_format("JWT")
+                        .build(),
+                ),
             )
         }
     }
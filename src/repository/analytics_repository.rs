@@ -0,0 +1,83 @@
+use crate::model::analytics::TopProduct;
+use crate::model::order::OrderStatus;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+
+// Binds $1 = statuses counted as sales, $2 = from, $3 = to
+const SALES_CLAUSE: &str = r#"
+    o.status = ANY($1)
+    AND ($2::timestamptz IS NULL OR o.created_at >= $2)
+    AND ($3::timestamptz IS NULL OR o.created_at < $3)
+"#;
+
+/// Orders that were paid for and not cancelled or refunded.
+fn sale_statuses() -> Vec<String> {
+    [
+        OrderStatus::Paid,
+        OrderStatus::Processing,
+        OrderStatus::Shipped,
+        OrderStatus::Delivered,
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+#[derive(Clone)]
+pub struct AnalyticsRepository {
+    pub pool: PgPool,
+}
+
+impl AnalyticsRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Number of sales placed in `[from, to)` and their combined order totals.
+    pub async fn sales_totals(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<(i64, Decimal), sqlx::Error> {
+        sqlx::query_as::<_, (i64, Decimal)>(&format!(
+            "SELECT COUNT(*), COALESCE(SUM(o.total), 0) FROM orders o WHERE {}",
+            SALES_CLAUSE
+        ))
+        .bind(sale_statuses())
+        .bind(from)
+        .bind(to)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    /// The `limit` products with the most units sold in `[from, to)`.
+    pub async fn top_products(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<TopProduct>, sqlx::Error> {
+        sqlx::query_as::<_, TopProduct>(&format!(
+            r#"
+            SELECT oi.product_id, p.name,
+                   SUM(oi.quantity)::bigint AS quantity_sold,
+                   SUM(oi.price * oi.quantity) AS revenue
+            FROM order_items oi
+            JOIN orders o ON o.id = oi.order_id
+            JOIN products p ON p.id = oi.product_id
+            WHERE {}
+            GROUP BY oi.product_id, p.name
+            ORDER BY quantity_sold DESC, revenue DESC, p.name
+            LIMIT $4
+            "#,
+            SALES_CLAUSE
+        ))
+        .bind(sale_statuses())
+        .bind(from)
+        .bind(to)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+    }
+}
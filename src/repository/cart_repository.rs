@@ -1,5 +1,5 @@
 use crate::model::cart::{Cart, CartItem};
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
 use chrono::Utc;
 
@@ -35,16 +35,45 @@ impl CartRepository {
         Ok(cart)
     }
 
+    /// Adds to the product's existing line in the cart, or starts a new one. Waits for a
+    /// checkout of the same cart to finish, so the item isn't cleared along with the ones ordered.
     pub async fn add_item(&self, cart_id: Uuid, product_id: Uuid, quantity: i32) -> Result<CartItem, sqlx::Error> {
-        sqlx::query_as::<_, CartItem>(
-            "INSERT INTO cart_items (id, cart_id, product_id, quantity) VALUES ($1, $2, $3, $4) RETURNING *"
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("SELECT id FROM carts WHERE id = $1 FOR SHARE")
+            .bind(cart_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let existing = sqlx::query_as::<_, CartItem>(
+            r#"
+            UPDATE cart_items SET quantity = quantity + $3
+            WHERE id = (SELECT id FROM cart_items WHERE cart_id = $1 AND product_id = $2 ORDER BY id LIMIT 1)
+            RETURNING *
+            "#
         )
-        .bind(Uuid::new_v4())
         .bind(cart_id)
         .bind(product_id)
         .bind(quantity)
-        .fetch_one(&self.pool)
-        .await
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let item = match existing {
+            Some(item) => item,
+            None => {
+                sqlx::query_as::<_, CartItem>(
+                    "INSERT INTO cart_items (id, cart_id, product_id, quantity) VALUES ($1, $2, $3, $4) RETURNING *"
+                )
+                .bind(Uuid::new_v4())
+                .bind(cart_id)
+                .bind(product_id)
+                .bind(quantity)
+                .fetch_one(&mut *tx)
+                .await?
+            }
+        };
+
+        tx.commit().await?;
+        Ok(item)
     }
 
     pub async fn get_cart_items(&self, cart_id: Uuid) -> Result<Vec<CartItem>, sqlx::Error> {
@@ -56,13 +85,31 @@ impl CartRepository {
         .await
     }
 
-    pub async fn clear_cart(&self, cart_id: Uuid) -> Result<(), sqlx::Error> {
+    pub async fn clear_cart(conn: &mut PgConnection, cart_id: Uuid) -> Result<(), sqlx::Error> {
         sqlx::query!("DELETE FROM cart_items WHERE cart_id = $1", cart_id)
-            .execute(&self.pool)
+            .execute(conn)
             .await?;
         Ok(())
     }
 
+    /// Locks the user's cart for checkout and returns it with its items. Other checkouts and
+    /// additions to the cart wait until the caller's transaction ends.
+    pub async fn lock_cart_by_user(conn: &mut PgConnection, user_id: Uuid) -> Result<Option<(Cart, Vec<CartItem>)>, sqlx::Error> {
+        let cart = sqlx::query_as::<_, Cart>("SELECT * FROM carts WHERE user_id = $1 FOR UPDATE")
+            .bind(user_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+        let Some(cart) = cart else {
+            return Ok(None);
+        };
+
+        let items = sqlx::query_as::<_, CartItem>("SELECT * FROM cart_items WHERE cart_id = $1 ORDER BY id")
+            .bind(cart.id)
+            .fetch_all(&mut *conn)
+            .await?;
+        Ok(Some((cart, items)))
+    }
+
     pub async fn get_cart_by_user(&self, user_id: Uuid) -> Result<Option<Cart>, sqlx::Error> {
         sqlx::query_as::<_, Cart>(
             "SELECT * FROM carts WHERE user_id = $1"
@@ -1,8 +1,23 @@
-use crate::model::category::Category;
+use crate::model::category::{Category, CategoryWithCount};
+use crate::model::product::ProductWithAvailableStock;
+use crate::repository::product_repository::SELECT_WITH_AVAILABILITY;
 use chrono::Utc;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
 
+/// Ids of the category bound to `$1` and all of its ancestors.
+const ANCESTOR_IDS_QUERY: &str = r#"
+    WITH RECURSIVE ancestors AS (
+        SELECT id, parent_id FROM categories WHERE id = $1
+        UNION
+        SELECT c.id, c.parent_id FROM categories c JOIN ancestors a ON c.id = a.parent_id
+    )
+    SELECT id FROM ancestors
+"#;
+
+/// Deepest category path [`CategoryRepository::ancestors`] will walk, counting the category itself.
+pub const MAX_CATEGORY_DEPTH: i32 = 32;
+
 #[derive(Clone)]
 pub struct CategoryRepository {
     pub pool: PgPool,
@@ -13,20 +28,21 @@ impl CategoryRepository {
         Self { pool }
     }
 
-    pub async fn create(&self, name: &str, description: Option<&str>) -> Result<Category, sqlx::Error> {
+    pub async fn create(&self, name: &str, description: Option<&str>, parent_id: Option<Uuid>) -> Result<Category, sqlx::Error> {
         let id = Uuid::new_v4();
         let created_at = Utc::now();
 
         sqlx::query_as::<_, Category>(
             r#"
-            INSERT INTO categories (id, name, description, created_at)
-            VALUES ($1, $2, $3, $4)
-            RETURNING id, name, description, created_at, updated_at
+            INSERT INTO categories (id, name, description, parent_id, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, name, description, parent_id, created_at, updated_at
             "#
         )
         .bind(id)
         .bind(name)
         .bind(description)
+        .bind(parent_id)
         .bind(created_at)
         .fetch_one(&self.pool)
         .await
@@ -34,16 +50,37 @@ impl CategoryRepository {
 
     pub async fn get(&self, id: Uuid) -> Result<Option<Category>, sqlx::Error> {
         sqlx::query_as::<_, Category>(
-            "SELECT id, name, description, created_at, updated_at FROM categories WHERE id = $1"
+            "SELECT id, name, description, parent_id, created_at, updated_at FROM categories WHERE id = $1"
         )
         .bind(id)
         .fetch_optional(&self.pool)
         .await
     }
 
-    pub async fn list(&self, limit: i64, offset: i64) -> Result<Vec<Category>, sqlx::Error> {
-        sqlx::query_as::<_, Category>(
-            "SELECT id, name, description, created_at, updated_at FROM categories ORDER BY created_at DESC LIMIT $1 OFFSET $2"
+    /// Whether another category already uses `name`, ignoring case. `exclude_id` leaves out
+    /// the category being renamed.
+    pub async fn name_taken(&self, name: &str, exclude_id: Option<Uuid>) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM categories WHERE lower(name) = lower($1) AND ($2::uuid IS NULL OR id <> $2))"
+        )
+        .bind(name)
+        .bind(exclude_id)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn list_with_counts(&self, limit: i64, offset: i64) -> Result<Vec<CategoryWithCount>, sqlx::Error> {
+        sqlx::query_as::<_, CategoryWithCount>(
+            r#"
+            SELECT c.id, c.name, c.description, c.parent_id, c.created_at, c.updated_at,
+                   COUNT(p.id) AS product_count
+            FROM categories c
+            LEFT JOIN product_categories pc ON pc.category_id = c.id
+            LEFT JOIN products p ON p.id = pc.product_id AND p.deleted_at IS NULL AND p.is_published
+            GROUP BY c.id
+            ORDER BY c.created_at DESC
+            LIMIT $1 OFFSET $2
+            "#
         )
         .bind(limit)
         .bind(offset)
@@ -51,25 +88,126 @@ impl CategoryRepository {
         .await
     }
 
-    pub async fn update(&self, id: Uuid, name: Option<&str>, description: Option<&str>) -> Result<Option<Category>, sqlx::Error> {
-        if let Some(c) = self.get(id).await? {
+    pub async fn count(&self) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM categories")
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    /// Every category, unpaginated, for building the full tree.
+    pub async fn list_all(&self) -> Result<Vec<Category>, sqlx::Error> {
+        sqlx::query_as::<_, Category>(
+            "SELECT id, name, description, parent_id, created_at, updated_at FROM categories ORDER BY name"
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn list_children(&self, parent_id: Uuid) -> Result<Vec<Category>, sqlx::Error> {
+        sqlx::query_as::<_, Category>(
+            "SELECT id, name, description, parent_id, created_at, updated_at FROM categories WHERE parent_id = $1 ORDER BY name"
+        )
+        .bind(parent_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Ids of the category and all of its ancestors, walking `parent_id` up to the root.
+    /// Empty if the category doesn't exist.
+    pub async fn ancestor_ids(&self, id: Uuid) -> Result<Vec<Uuid>, sqlx::Error> {
+        sqlx::query_scalar::<_, Uuid>(ANCESTOR_IDS_QUERY)
+            .bind(id)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Like [`ancestor_ids`](Self::ancestor_ids) for `parent_id`, but first locks those rows and
+    /// `category_id` until the caller's transaction ends, so no concurrent move can change the
+    /// chain before `category_id` is re-parented. Rows are locked in id order; if the chain
+    /// changed while waiting for the locks, the new rows are locked too.
+    pub async fn lock_ancestor_ids(conn: &mut PgConnection, category_id: Uuid, parent_id: Uuid) -> Result<Vec<Uuid>, sqlx::Error> {
+        let mut locked: Vec<Uuid> = Vec::new();
+        loop {
+            let ancestors = sqlx::query_scalar::<_, Uuid>(ANCESTOR_IDS_QUERY)
+                .bind(parent_id)
+                .fetch_all(&mut *conn)
+                .await?;
+            if ancestors.iter().all(|id| locked.contains(id)) {
+                return Ok(ancestors);
+            }
+
+            locked.extend(ancestors);
+            locked.push(category_id);
+            locked.sort();
+            locked.dedup();
+            sqlx::query("SELECT id FROM categories WHERE id = ANY($1) ORDER BY id FOR UPDATE")
+                .bind(&locked)
+                .execute(&mut *conn)
+                .await?;
+        }
+    }
+
+    /// The category and its ancestors ordered from the root down to the category itself.
+    /// Empty if the category doesn't exist. The walk stops after [`MAX_CATEGORY_DEPTH`] levels,
+    /// so a loop in `parent_id` left by bad data can't make it run forever.
+    pub async fn ancestors(&self, category_id: Uuid) -> Result<Vec<Category>, sqlx::Error> {
+        sqlx::query_as::<_, Category>(
+            r#"
+            WITH RECURSIVE ancestors AS (
+                SELECT id, name, description, parent_id, created_at, updated_at, 0 AS depth
+                FROM categories WHERE id = $1
+                UNION ALL
+                SELECT c.id, c.name, c.description, c.parent_id, c.created_at, c.updated_at, a.depth + 1
+                FROM categories c JOIN ancestors a ON c.id = a.parent_id
+                WHERE a.depth + 1 < $2
+            )
+            SELECT id, name, description, parent_id, created_at, updated_at
+            FROM ancestors
+            ORDER BY depth DESC
+            "#
+        )
+        .bind(category_id)
+        .bind(MAX_CATEGORY_DEPTH)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Applies the given fields inside the caller's transaction, keeping the rest. The nullable
+    /// ones take `Some(None)` to clear them.
+    pub async fn update(
+        conn: &mut PgConnection,
+        id: Uuid,
+        name: Option<&str>,
+        description: Option<Option<&str>>,
+        parent_id: Option<Option<Uuid>>,
+    ) -> Result<Option<Category>, sqlx::Error> {
+        let current = sqlx::query_as::<_, Category>(
+            "SELECT id, name, description, parent_id, created_at, updated_at FROM categories WHERE id = $1 FOR UPDATE"
+        )
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        if let Some(c) = current {
             let new_name = name.unwrap_or(&c.name);
-            let new_description = description.or(c.description.as_deref());
+            let new_description = description.unwrap_or(c.description.as_deref());
+            let new_parent_id = parent_id.unwrap_or(c.parent_id);
             let updated_at = Utc::now();
 
             let rec = sqlx::query_as::<_, Category>(
                 r#"
                 UPDATE categories
-                SET name = $1, description = $2, updated_at = $3
-                WHERE id = $4
-                RETURNING id, name, description, created_at, updated_at
+                SET name = $1, description = $2, parent_id = $3, updated_at = $4
+                WHERE id = $5
+                RETURNING id, name, description, parent_id, created_at, updated_at
                 "#
             )
             .bind(new_name)
             .bind(new_description)
+            .bind(new_parent_id)
             .bind(updated_at)
             .bind(id)
-            .fetch_one(&self.pool)
+            .fetch_one(&mut *conn)
             .await?;
 
             Ok(Some(rec))
@@ -86,6 +224,54 @@ impl CategoryRepository {
         Ok(res.rows_affected() > 0)
     }
 
+    /// Products (not soft-deleted) linked to the category, published or not.
+    pub async fn count_assigned_products(&self, id: Uuid) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COUNT(*)
+            FROM product_categories pc
+            JOIN products p ON p.id = pc.product_id
+            WHERE pc.category_id = $1 AND p.deleted_at IS NULL
+            "#
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    /// Moves every product in `id` into `target_id` and deletes `id`, in one transaction.
+    /// Products already in the target keep their single link.
+    pub async fn reassign_and_delete(&self, id: Uuid, target_id: Uuid) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO product_categories (product_id, category_id)
+            SELECT product_id, $2 FROM product_categories WHERE category_id = $1
+            ON CONFLICT DO NOTHING
+            "#
+        )
+        .bind(id)
+        .bind(target_id)
+        .execute(&mut *tx)
+        .await?;
+        let res = sqlx::query("DELETE FROM categories WHERE id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    /// Whether a product with this id exists and hasn't been deleted.
+    pub async fn product_exists(&self, product_id: Uuid) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM products WHERE id = $1 AND deleted_at IS NULL)")
+            .bind(product_id)
+            .fetch_one(&self.pool)
+            .await
+    }
+
     pub async fn assign_product(&self, category_id: Uuid, product_id: Uuid) -> Result<(), sqlx::Error> {
         sqlx::query(
             "INSERT INTO product_categories (product_id, category_id) VALUES ($1, $2) ON CONFLICT DO NOTHING"
@@ -96,4 +282,65 @@ impl CategoryRepository {
         .await?;
         Ok(())
     }
+
+    /// Links every existing product in `product_ids` to the category in one statement,
+    /// returning how many links are new. Unknown or deleted ids and existing links are skipped.
+    pub async fn assign_products(&self, category_id: Uuid, product_ids: &[Uuid]) -> Result<u64, sqlx::Error> {
+        let res = sqlx::query(
+            r#"
+            INSERT INTO product_categories (product_id, category_id)
+            SELECT p.id, $1 FROM products p WHERE p.id = ANY($2) AND p.deleted_at IS NULL
+            ON CONFLICT DO NOTHING
+            "#
+        )
+        .bind(category_id)
+        .bind(product_ids)
+        .execute(&self.pool)
+        .await?;
+        Ok(res.rows_affected())
+    }
+
+    /// Removes the link; returns false if the product wasn't in the category.
+    pub async fn unassign_product(&self, category_id: Uuid, product_id: Uuid) -> Result<bool, sqlx::Error> {
+        let res = sqlx::query("DELETE FROM product_categories WHERE product_id = $1 AND category_id = $2")
+            .bind(product_id)
+            .bind(category_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    /// How many products [`CategoryRepository::list_products`] pages through.
+    pub async fn count_products(&self, category_id: Uuid) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM products p
+            JOIN product_categories pc ON pc.product_id = p.id
+            WHERE pc.category_id = $1 AND p.deleted_at IS NULL AND p.is_published
+            "#
+        )
+        .bind(category_id)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    /// Live, published products assigned to the category, alphabetically.
+    pub async fn list_products(&self, category_id: Uuid, limit: i64, offset: i64) -> Result<Vec<ProductWithAvailableStock>, sqlx::Error> {
+        sqlx::query_as::<_, ProductWithAvailableStock>(&format!(
+            r#"{}
+            JOIN product_categories pc ON pc.product_id = p.id
+            WHERE pc.category_id = $1 AND p.deleted_at IS NULL AND p.is_published
+            GROUP BY p.id
+            ORDER BY p.name, p.id
+            LIMIT $2 OFFSET $3
+            "#,
+            SELECT_WITH_AVAILABILITY
+        ))
+        .bind(category_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+    }
 }
@@ -0,0 +1,122 @@
+use crate::model::coupon::Coupon;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct CouponRepository {
+    pub pool: PgPool,
+}
+
+impl CouponRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(
+        &self,
+        code: &str,
+        percent_off: Option<Decimal>,
+        amount_off: Option<Decimal>,
+        expires_at: Option<DateTime<Utc>>,
+        usage_limit: Option<i32>,
+    ) -> Result<Coupon, sqlx::Error> {
+        sqlx::query_as::<_, Coupon>(
+            r#"
+            INSERT INTO coupons (id, code, percent_off, amount_off, expires_at, usage_limit)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(code)
+        .bind(percent_off)
+        .bind(amount_off)
+        .bind(expires_at)
+        .bind(usage_limit)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn list(&self, limit: i64, offset: i64) -> Result<Vec<Coupon>, sqlx::Error> {
+        sqlx::query_as::<_, Coupon>("SELECT * FROM coupons ORDER BY created_at DESC LIMIT $1 OFFSET $2")
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    pub async fn count(&self) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM coupons")
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    pub async fn get(&self, id: Uuid) -> Result<Option<Coupon>, sqlx::Error> {
+        sqlx::query_as::<_, Coupon>("SELECT * FROM coupons WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    pub async fn find_by_code(&self, code: &str) -> Result<Option<Coupon>, sqlx::Error> {
+        sqlx::query_as::<_, Coupon>("SELECT * FROM coupons WHERE code = $1")
+            .bind(code)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    pub async fn update(
+        &self,
+        id: Uuid,
+        code: &str,
+        percent_off: Option<Decimal>,
+        amount_off: Option<Decimal>,
+        expires_at: Option<DateTime<Utc>>,
+        usage_limit: Option<i32>,
+    ) -> Result<Option<Coupon>, sqlx::Error> {
+        sqlx::query_as::<_, Coupon>(
+            r#"
+            UPDATE coupons
+            SET code = $2, percent_off = $3, amount_off = $4, expires_at = $5, usage_limit = $6
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(code)
+        .bind(percent_off)
+        .bind(amount_off)
+        .bind(expires_at)
+        .bind(usage_limit)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn delete(&self, id: Uuid) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM coupons WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Counts one use of the coupon, but only while it is unexpired and under its usage limit.
+    /// The check and the increment are a single statement, so concurrent checkouts can't
+    /// redeem it past the limit. Returns `None` when the coupon can no longer be used.
+    pub async fn redeem(conn: &mut PgConnection, id: Uuid) -> Result<Option<Coupon>, sqlx::Error> {
+        sqlx::query_as::<_, Coupon>(
+            r#"
+            UPDATE coupons SET times_used = times_used + 1
+            WHERE id = $1
+              AND (expires_at IS NULL OR expires_at > now())
+              AND (usage_limit IS NULL OR times_used < usage_limit)
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(conn)
+        .await
+    }
+}
@@ -1,5 +1,5 @@
 mod product_repository;
-pub use product_repository::ProductRepository;
+pub use product_repository::{ProductFilter, ProductRepository};
 mod category_repository;
 pub use category_repository::CategoryRepository;
 mod user_repository;
@@ -7,8 +7,14 @@ pub use user_repository::UserRepository;
 mod cart_repository;
 pub use cart_repository::CartRepository;
 mod order_repository;
-pub use order_repository::OrderRepository;
+pub use order_repository::{OrderFilter, OrderRepository};
 mod payment_repository;
 pub use payment_repository::PaymentRepository;
 mod stock_repository;
 pub use stock_repository::StockRepository;
+mod promotion_repository;
+pub use promotion_repository::PromotionRepository;
+mod coupon_repository;
+pub use coupon_repository::CouponRepository;
+mod analytics_repository;
+pub use analytics_repository::AnalyticsRepository;
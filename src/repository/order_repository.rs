@@ -1,10 +1,28 @@
-use crate::model::order::{Order, OrderItem};
-use sqlx::PgPool;
+use crate::model::order::{Order, OrderItem, OrderStatusChange, OrderTotals, OrderWithItems};
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
+use std::collections::HashMap;
 use std::str::FromStr;
 
+/// Optional narrowing applied to the admin order listings; `None` fields are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct OrderFilter {
+    pub status: Option<String>,
+    /// Created at or after this instant
+    pub created_after: Option<DateTime<Utc>>,
+    /// Created strictly before this instant
+    pub created_before: Option<DateTime<Utc>>,
+}
+
+// Binds $1 = status, $2 = created_after, $3 = created_before
+const FILTER_CLAUSE: &str = r#"
+    ($1::text IS NULL OR status = $1)
+    AND ($2::timestamptz IS NULL OR created_at >= $2)
+    AND ($3::timestamptz IS NULL OR created_at < $3)
+"#;
+
 #[derive(Clone)]
 pub struct OrderRepository {
     pub pool: PgPool,
@@ -15,20 +33,38 @@ impl OrderRepository {
         Self { pool }
     }
 
-    pub async fn create_order(&self, user_id: Uuid, total: f64, status: &str) -> Result<Order, sqlx::Error> {
+    /// `coupon` is the redeemed coupon's id and the discount it gave, already part of `totals.discount`.
+    pub async fn create_order(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        totals: &OrderTotals,
+        coupon: Option<(Uuid, Decimal)>,
+        status: &str,
+        notes: Option<&str>,
+    ) -> Result<Order, sqlx::Error> {
         sqlx::query_as::<_, Order>(
-            "INSERT INTO orders (id, user_id, total, status, created_at) VALUES ($1, $2, $3, $4, $5) RETURNING *"
+            r#"
+            INSERT INTO orders (id, user_id, subtotal, tax, shipping, total, coupon_id, coupon_discount, status, notes, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            RETURNING *
+            "#,
         )
         .bind(Uuid::new_v4())
         .bind(user_id)
-        .bind(total)
+        .bind(totals.subtotal)
+        .bind(totals.tax)
+        .bind(totals.shipping)
+        .bind(totals.total)
+        .bind(coupon.map(|(id, _)| id))
+        .bind(coupon.map(|(_, discount)| discount).unwrap_or(Decimal::ZERO))
         .bind(status)
+        .bind(notes)
         .bind(Utc::now())
-        .fetch_one(&self.pool)
+        .fetch_one(conn)
         .await
     }
 
-    pub async fn add_order_item(&self, order_id: Uuid, product_id: Uuid, quantity: i32, price: f64) -> Result<OrderItem, sqlx::Error> {
+    pub async fn add_order_item(conn: &mut PgConnection, order_id: Uuid, product_id: Uuid, quantity: i32, price: f64) -> Result<OrderItem, sqlx::Error> {
         sqlx::query_as::<_, OrderItem>(
             "INSERT INTO order_items (id, order_id, product_id, quantity, price) VALUES ($1, $2, $3, $4, $5) RETURNING *"
         )
@@ -37,20 +73,79 @@ impl OrderRepository {
         .bind(product_id)
         .bind(quantity)
         .bind(price)
+        .fetch_one(conn)
+        .await
+    }
+
+    pub async fn find_by_user(&self, user_id: Uuid, status: Option<&str>, limit: i64, offset: i64) -> Result<Vec<Order>, sqlx::Error> {
+        sqlx::query_as::<_, Order>(
+            "SELECT * FROM orders WHERE user_id = $1 AND ($2::text IS NULL OR status = $2) ORDER BY created_at DESC LIMIT $3 OFFSET $4"
+        )
+        .bind(user_id)
+        .bind(status)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn count_by_user(&self, user_id: Uuid, status: Option<&str>) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM orders WHERE user_id = $1 AND ($2::text IS NULL OR status = $2)"
+        )
+        .bind(user_id)
+        .bind(status)
         .fetch_one(&self.pool)
         .await
     }
 
-    pub async fn find_by_user(&self, user_id: Uuid) -> Result<Vec<Order>, sqlx::Error> {
-        sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE user_id = $1 ORDER BY created_at DESC")
-            .bind(user_id)
-            .fetch_all(&self.pool)
-            .await
+    pub async fn find_all(&self, filter: &OrderFilter, limit: i64, offset: i64) -> Result<Vec<Order>, sqlx::Error> {
+        sqlx::query_as::<_, Order>(&format!(
+            "SELECT * FROM orders WHERE {} ORDER BY created_at DESC LIMIT $4 OFFSET $5",
+            FILTER_CLAUSE
+        ))
+        .bind(filter.status.as_deref())
+        .bind(filter.created_after)
+        .bind(filter.created_before)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
     }
 
-    pub async fn find_all(&self) -> Result<Vec<Order>, sqlx::Error> {
-        sqlx::query_as::<_, Order>("SELECT * FROM orders ORDER BY created_at DESC")
-            .fetch_all(&self.pool)
+    /// A page of orders like [`find_all`](Self::find_all), each with its line items. Items for
+    /// the whole page are loaded in one query rather than one per order.
+    pub async fn find_all_with_items(&self, filter: &OrderFilter, limit: i64, offset: i64) -> Result<Vec<OrderWithItems>, sqlx::Error> {
+        let orders = self.find_all(filter, limit, offset).await?;
+        let order_ids: Vec<Uuid> = orders.iter().map(|o| o.id).collect();
+
+        let items = sqlx::query_as::<_, OrderItem>(
+            "SELECT * FROM order_items WHERE order_id = ANY($1) ORDER BY order_id, id"
+        )
+        .bind(&order_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut items_by_order: HashMap<Uuid, Vec<OrderItem>> = HashMap::new();
+        for item in items {
+            items_by_order.entry(item.order_id).or_default().push(item);
+        }
+
+        Ok(orders
+            .into_iter()
+            .map(|order| {
+                let items = items_by_order.remove(&order.id).unwrap_or_default();
+                OrderWithItems { order, items }
+            })
+            .collect())
+    }
+
+    pub async fn count_all(&self, filter: &OrderFilter) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(*) FROM orders WHERE {}", FILTER_CLAUSE))
+            .bind(filter.status.as_deref())
+            .bind(filter.created_after)
+            .bind(filter.created_before)
+            .fetch_one(&self.pool)
             .await
     }
 
@@ -61,13 +156,111 @@ impl OrderRepository {
             .await
     }
 
-    pub async fn update_status(&self, order_id: Uuid, status: &str) -> Result<Order, sqlx::Error> {
-        sqlx::query_as::<_, Order>(
-            "UPDATE orders SET status = $1 WHERE id = $2 RETURNING *"
+    /// Moves the order to `status` only if it is currently in one of `from`.
+    /// Returns `None` when the order is missing or in any other status.
+    pub async fn transition_status(
+        &self,
+        order_id: Uuid,
+        from: &[&str],
+        status: &str,
+        changed_by: Option<Uuid>,
+    ) -> Result<Option<Order>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let order = Self::transition_status_in(&mut tx, order_id, from, status, None, changed_by).await?;
+        if order.is_some() {
+            tx.commit().await?;
+        }
+        Ok(order)
+    }
+
+    /// Locks the order and, if it is in one of `from`, applies the status (and tracking, when
+    /// given) and records the change in the order's history, inside the caller's transaction.
+    /// Returns `None` when the order is missing or in any other status.
+    pub async fn transition_status_in(
+        conn: &mut PgConnection,
+        order_id: Uuid,
+        from: &[&str],
+        status: &str,
+        tracking: Option<(&str, Option<&str>)>,
+        changed_by: Option<Uuid>,
+    ) -> Result<Option<Order>, sqlx::Error> {
+        let previous: Option<String> = sqlx::query_scalar("SELECT status FROM orders WHERE id = $1 FOR UPDATE")
+            .bind(order_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+        let Some(previous) = previous else {
+            return Ok(None);
+        };
+        if !from.contains(&previous.as_str()) {
+            return Ok(None);
+        }
+
+        let order = match tracking {
+            Some((tracking_number, carrier)) => {
+                sqlx::query_as::<_, Order>(
+                    "UPDATE orders SET status = $1, tracking_number = $3, carrier = $4 WHERE id = $2 RETURNING *"
+                )
+                .bind(status)
+                .bind(order_id)
+                .bind(tracking_number)
+                .bind(carrier)
+                .fetch_one(&mut *conn)
+                .await?
+            }
+            None => Self::apply_status(&mut *conn, order_id, status).await?,
+        };
+
+        if previous != status {
+            Self::record_status_change(conn, order_id, &previous, status, changed_by).await?;
+        }
+        Ok(Some(order))
+    }
+
+    /// The order, locked until the caller's transaction ends.
+    pub async fn lock_for_update(conn: &mut PgConnection, order_id: Uuid) -> Result<Option<Order>, sqlx::Error> {
+        sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1 FOR UPDATE")
+            .bind(order_id)
+            .fetch_optional(conn)
+            .await
+    }
+
+    /// Sets the status of an order already locked with [`lock_for_update`](Self::lock_for_update);
+    /// the caller records the change in the history.
+    pub async fn apply_status(conn: &mut PgConnection, order_id: Uuid, status: &str) -> Result<Order, sqlx::Error> {
+        sqlx::query_as::<_, Order>("UPDATE orders SET status = $1 WHERE id = $2 RETURNING *")
+            .bind(status)
+            .bind(order_id)
+            .fetch_one(conn)
+            .await
+    }
+
+    /// Appends a step to the order's status history.
+    pub async fn record_status_change(
+        conn: &mut PgConnection,
+        order_id: Uuid,
+        from_status: &str,
+        to_status: &str,
+        changed_by: Option<Uuid>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO order_status_history (order_id, from_status, to_status, changed_by) VALUES ($1, $2, $3, $4)"
         )
-        .bind(status)
         .bind(order_id)
-        .fetch_one(&self.pool)
+        .bind(from_status)
+        .bind(to_status)
+        .bind(changed_by)
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+
+    /// The order's status changes, oldest first.
+    pub async fn list_status_history(&self, order_id: Uuid) -> Result<Vec<OrderStatusChange>, sqlx::Error> {
+        sqlx::query_as::<_, OrderStatusChange>(
+            "SELECT * FROM order_status_history WHERE order_id = $1 ORDER BY changed_at, id"
+        )
+        .bind(order_id)
+        .fetch_all(&self.pool)
         .await
     }
 
@@ -110,4 +303,39 @@ impl OrderRepository {
 
         Ok(result)
     }
+
+    /// The order created under the user's idempotency `key`, if the key was used within the
+    /// last `window_hours`.
+    pub async fn find_by_idempotency_key(&self, user_id: Uuid, key: &str, window_hours: i64) -> Result<Option<Order>, sqlx::Error> {
+        sqlx::query_as::<_, Order>(
+            r#"
+            SELECT o.* FROM idempotency_keys k
+            JOIN orders o ON o.id = k.order_id
+            WHERE k.user_id = $1 AND k.key = $2
+              AND k.created_at > now() - make_interval(hours => $3::int)
+            "#,
+        )
+        .bind(user_id)
+        .bind(key)
+        .bind(window_hours as i32)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Remembers that `key` produced `order_id`. A key past its window is reused for the new order.
+    pub async fn record_idempotency_key(conn: &mut PgConnection, user_id: Uuid, key: &str, order_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO idempotency_keys (user_id, key, order_id)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, key) DO UPDATE SET order_id = EXCLUDED.order_id, created_at = now()
+            "#,
+        )
+        .bind(user_id)
+        .bind(key)
+        .bind(order_id)
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
 }
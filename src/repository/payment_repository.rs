@@ -1,6 +1,6 @@
 use crate::model::payment::{Payment, PaymentWebhook};
 use bigdecimal::BigDecimal;
-use sqlx::{PgPool, Result};
+use sqlx::{PgConnection, PgPool, Result};
 use uuid::Uuid;
 
 #[derive(Clone)]
@@ -125,10 +125,35 @@ impl PaymentRepository {
         Ok(payment)
     }
 
-    pub async fn list_by_status(&self, status: &str, limit: i64, offset: i64) -> Result<Vec<Payment>> {
+    /// The payment's status, read inside the caller's transaction.
+    pub async fn status_in(conn: &mut PgConnection, id: Uuid) -> Result<Option<String>> {
+        let status = sqlx::query_scalar!("SELECT status FROM payments WHERE id = $1", id)
+            .fetch_optional(conn)
+            .await?;
+
+        Ok(status)
+    }
+
+    /// Sets the payment's status inside the caller's transaction.
+    pub async fn apply_status(conn: &mut PgConnection, id: Uuid, status: &str) -> Result<()> {
+        sqlx::query!("UPDATE payments SET status = $1, updated_at = now() WHERE id = $2", status, id)
+            .execute(conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// A page of payments, newest first, optionally only those in `status`.
+    pub async fn list_by_status(&self, status: Option<&str>, limit: i64, offset: i64) -> Result<Vec<Payment>> {
         let payments = sqlx::query_as!(
             Payment,
-            "SELECT id, order_id, stripe_payment_intent_id, amount, currency, status, payment_method, created_at, updated_at FROM payments WHERE status = $1 ORDER BY created_at DESC LIMIT $2 OFFSET $3",
+            r#"
+            SELECT id, order_id, stripe_payment_intent_id, amount, currency, status, payment_method, created_at, updated_at
+            FROM payments
+            WHERE ($1::text IS NULL OR status = $1)
+            ORDER BY created_at DESC, id
+            LIMIT $2 OFFSET $3
+            "#,
             status,
             limit,
             offset
@@ -139,6 +164,17 @@ impl PaymentRepository {
         Ok(payments)
     }
 
+    pub async fn count_by_status(&self, status: Option<&str>) -> Result<i64> {
+        let count: Option<i64> = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM payments WHERE ($1::text IS NULL OR status = $1)",
+            status
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(count.unwrap_or(0))
+    }
+
     // Webhook management
     pub async fn create_webhook_record(
         &self,
@@ -1,9 +1,49 @@
-use crate::model::product::Product;
-use chrono::Utc;
+use crate::dtos::NewProductDto;
+use crate::model::category::Category;
+use crate::model::product::{Product, ProductWithAvailableStock};
+use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use sqlx::PgPool;
 use uuid::Uuid;
 
+/// Optional narrowing applied to product listings; `None` fields are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct ProductFilter {
+    pub category_id: Option<Uuid>,
+    pub min_price: Option<Decimal>,
+    pub max_price: Option<Decimal>,
+    /// Created at or after this instant
+    pub created_after: Option<DateTime<Utc>>,
+    /// Created strictly before this instant
+    pub created_before: Option<DateTime<Utc>>,
+    /// Also list unpublished products (admin views)
+    pub include_unpublished: bool,
+}
+
+// Binds $1 = category_id, $2 = min_price, $3 = max_price, $4 = created_after, $5 = created_before,
+// $6 = include_unpublished
+const FILTER_CLAUSE: &str = r#"
+    p.deleted_at IS NULL
+    AND ($6 OR p.is_published)
+    AND ($1::uuid IS NULL OR EXISTS (
+        SELECT 1 FROM product_categories pc WHERE pc.product_id = p.id AND pc.category_id = $1
+    ))
+    AND ($2::numeric IS NULL OR p.price >= $2)
+    AND ($3::numeric IS NULL OR p.price <= $3)
+    AND ($4::timestamptz IS NULL OR p.created_at >= $4)
+    AND ($5::timestamptz IS NULL OR p.created_at < $5)
+"#;
+
+// Rows for `ProductWithAvailableStock`: the product plus its stock net of unexpired reservations,
+// joined the way `StockRepository::get_low_stock_alerts` does. Callers append WHERE and GROUP BY p.id.
+pub(crate) const SELECT_WITH_AVAILABILITY: &str = r#"
+    SELECT p.*,
+        (p.stock - COALESCE(SUM(sr.quantity), 0))::int4 AS available_stock,
+        COALESCE(p.stock - COALESCE(SUM(sr.quantity), 0) <= p.low_stock_threshold, false) AS is_low_stock
+    FROM products p
+    LEFT JOIN stock_reservations sr ON p.id = sr.product_id AND sr.expires_at > now()
+"#;
+
 #[derive(Clone)]
 pub struct ProductRepository {
     pub pool: PgPool,
@@ -13,6 +53,7 @@ impl ProductRepository {
     pub fn new(pool: PgPool) -> Self {
         Self { pool }
     }
+    /// Looks up a product even if it has been soft-deleted, so order history can still resolve it.
     pub async fn find_by_id(&self, id: Uuid) -> Result<Option<Product>, sqlx::Error> {
         sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1")
             .bind(id)
@@ -23,11 +64,13 @@ impl ProductRepository {
     pub async fn create(
         &self,
         name: &str,
+        sku: Option<&str>,
         description: Option<&str>,
         price: Decimal,
         stock: i32,
         image_url: Option<&str>,
         low_stock_threshold: Option<i32>,
+        reorder_target: Option<i32>,
         track_inventory: bool,
     ) -> Result<Product, sqlx::Error> {
         let id = Uuid::new_v4();
@@ -35,8 +78,8 @@ impl ProductRepository {
 
         let rec = sqlx::query_as::<_, Product>(
             r#"
-            INSERT INTO products (id, name, description, price, stock, image_url, low_stock_threshold, track_inventory, created_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            INSERT INTO products (id, name, description, price, stock, image_url, low_stock_threshold, track_inventory, created_at, sku, reorder_target)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
             RETURNING *
             "#,
         )
@@ -49,15 +92,58 @@ impl ProductRepository {
         .bind(low_stock_threshold)
         .bind(track_inventory)
         .bind(created_at)
+        .bind(sku)
+        .bind(reorder_target)
         .fetch_one(&self.pool)
         .await?;
 
         Ok(rec)
     }
 
+    /// Inserts all of `products` in one transaction: either every row is created or, on the
+    /// first failure, none are.
+    pub async fn bulk_create(&self, products: &[NewProductDto]) -> Result<Vec<Product>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let mut created = Vec::with_capacity(products.len());
+
+        for dto in products {
+            let rec = sqlx::query_as::<_, Product>(
+                r#"
+                INSERT INTO products (id, name, description, price, stock, image_url, low_stock_threshold, track_inventory, sku, reorder_target)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                RETURNING *
+                "#,
+            )
+            .bind(Uuid::new_v4())
+            .bind(&dto.name)
+            .bind(&dto.description)
+            .bind(dto.price)
+            .bind(dto.stock)
+            .bind(&dto.image_url)
+            .bind(dto.low_stock_threshold)
+            .bind(dto.track_inventory.unwrap_or(true))
+            .bind(&dto.sku)
+            .bind(dto.reorder_target)
+            .fetch_one(&mut *tx)
+            .await?;
+            created.push(rec);
+        }
+
+        tx.commit().await?;
+        Ok(created)
+    }
+
+    /// Which of `skus` already belong to a product, deleted or not.
+    pub async fn existing_skus(&self, skus: &[String]) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar::<_, String>("SELECT sku FROM products WHERE sku = ANY($1)")
+            .bind(skus)
+            .fetch_all(&self.pool)
+            .await
+    }
+
     pub async fn get(&self, id: Uuid) -> Result<Option<Product>, sqlx::Error> {
         let rec = sqlx::query_as::<_, Product>(
-            "SELECT * FROM products WHERE id = $1"
+            "SELECT * FROM products WHERE id = $1 AND deleted_at IS NULL"
         )
         .bind(id)
         .fetch_optional(&self.pool)
@@ -66,10 +152,58 @@ impl ProductRepository {
         Ok(rec)
     }
 
-    pub async fn list(&self, limit: i64, offset: i64) -> Result<Vec<Product>, sqlx::Error> {
-        let recs = sqlx::query_as::<_, Product>(
-            "SELECT * FROM products ORDER BY created_at DESC LIMIT $1 OFFSET $2"
+    pub async fn get_with_availability(&self, id: Uuid) -> Result<Option<ProductWithAvailableStock>, sqlx::Error> {
+        sqlx::query_as::<_, ProductWithAvailableStock>(&format!(
+            "{} WHERE p.id = $1 AND p.deleted_at IS NULL GROUP BY p.id",
+            SELECT_WITH_AVAILABILITY
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// The categories the product is assigned to, by name.
+    pub async fn list_categories_for_product(&self, product_id: Uuid) -> Result<Vec<Category>, sqlx::Error> {
+        sqlx::query_as::<_, Category>(
+            r#"
+            SELECT c.*
+            FROM categories c
+            JOIN product_categories pc ON pc.category_id = c.id
+            WHERE pc.product_id = $1
+            ORDER BY c.name, c.id
+            "#
         )
+        .bind(product_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn find_by_sku_with_availability(&self, sku: &str) -> Result<Option<ProductWithAvailableStock>, sqlx::Error> {
+        sqlx::query_as::<_, ProductWithAvailableStock>(&format!(
+            "{} WHERE p.sku = $1 AND p.deleted_at IS NULL GROUP BY p.id",
+            SELECT_WITH_AVAILABILITY
+        ))
+        .bind(sku)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn list_with_availability(
+        &self,
+        filter: &ProductFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ProductWithAvailableStock>, sqlx::Error> {
+        let recs = sqlx::query_as::<_, ProductWithAvailableStock>(&format!(
+            "{} WHERE {} GROUP BY p.id ORDER BY p.created_at DESC LIMIT $7 OFFSET $8",
+            SELECT_WITH_AVAILABILITY, FILTER_CLAUSE
+        ))
+        .bind(filter.category_id)
+        .bind(filter.min_price)
+        .bind(filter.max_price)
+        .bind(filter.created_after)
+        .bind(filter.created_before)
+        .bind(filter.include_unpublished)
         .bind(limit)
         .bind(offset)
         .fetch_all(&self.pool)
@@ -77,74 +211,93 @@ impl ProductRepository {
         Ok(recs)
     }
 
+    pub async fn count(&self, filter: &ProductFilter) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(*) FROM products p WHERE {}", FILTER_CLAUSE))
+            .bind(filter.category_id)
+            .bind(filter.min_price)
+            .bind(filter.max_price)
+            .bind(filter.created_after)
+            .bind(filter.created_before)
+            .bind(filter.include_unpublished)
+            .fetch_one(&self.pool)
+            .await
+    }
+
     pub async fn update(
         &self,
         id: Uuid,
         name: Option<&str>,
-        description: Option<&str>,
+        sku: Option<Option<&str>>,
+        description: Option<Option<&str>>,
         price: Option<Decimal>,
         stock: Option<i32>,
-        image_url: Option<&str>,
-        low_stock_threshold: Option<i32>,
+        image_url: Option<Option<&str>>,
+        low_stock_threshold: Option<Option<i32>>,
+        reorder_target: Option<Option<i32>>,
         track_inventory: Option<bool>,
     ) -> Result<Option<Product>, sqlx::Error> {
-        // Simple approach: fetch, then update only provided fields
-        if let Some(p) = self.get(id).await? {
-            let new_name = name.unwrap_or(&p.name);
-            let new_description = match description {
-                Some(s) => Some(s.to_string()),
-                None => p.description.clone(),
-            };
-            let new_price = price.unwrap_or(p.price);
-            let new_stock = stock.unwrap_or(p.stock);
-            let new_image_url = match image_url {
-                Some(s) => Some(s.to_string()),
-                None => p.image_url.clone(),
-            };
-            let new_low_stock_threshold = low_stock_threshold.or(p.low_stock_threshold);
-            let new_track_inventory = track_inventory.unwrap_or(p.track_inventory);
-            let updated_at = Utc::now();
-
-            let rec = sqlx::query_as::<_, Product>(
-                r#"
-                UPDATE products
-                SET name = $1, description = $2, price = $3, stock = $4, image_url = $5, 
-                    low_stock_threshold = $6, track_inventory = $7, updated_at = $8
-                WHERE id = $9
-                RETURNING *
-                "#,
-            )
-            .bind(new_name)
-            .bind(new_description)
-            .bind(new_price)
-            .bind(new_stock)
-            .bind(new_image_url)
-            .bind(new_low_stock_threshold)
-            .bind(new_track_inventory)
-            .bind(updated_at)
-            .bind(id)
-            .fetch_one(&self.pool)
-            .await?;
-
-            Ok(Some(rec))
-        } else {
-            Ok(None)
-        }
+        // One statement: fields passed as None keep their current value. The nullable columns
+        // take `Some(None)` to clear them, so each comes with a "was it given" flag.
+        sqlx::query_as::<_, Product>(
+            r#"
+            UPDATE products
+            SET name = COALESCE($2, name),
+                sku = CASE WHEN $10 THEN $3 ELSE sku END,
+                description = CASE WHEN $11 THEN $4 ELSE description END,
+                price = COALESCE($5, price),
+                stock = COALESCE($6, stock),
+                image_url = CASE WHEN $12 THEN $7 ELSE image_url END,
+                low_stock_threshold = CASE WHEN $13 THEN $8 ELSE low_stock_threshold END,
+                track_inventory = COALESCE($9, track_inventory),
+                reorder_target = CASE WHEN $15 THEN $14 ELSE reorder_target END,
+                updated_at = now()
+            WHERE id = $1 AND deleted_at IS NULL
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(name)
+        .bind(sku.flatten())
+        .bind(description.flatten())
+        .bind(price)
+        .bind(stock)
+        .bind(image_url.flatten())
+        .bind(low_stock_threshold.flatten())
+        .bind(track_inventory)
+        .bind(sku.is_some())
+        .bind(description.is_some())
+        .bind(image_url.is_some())
+        .bind(low_stock_threshold.is_some())
+        .bind(reorder_target.flatten())
+        .bind(reorder_target.is_some())
+        .fetch_optional(&self.pool)
+        .await
     }
 
     pub async fn delete(&self, id: Uuid) -> Result<bool, sqlx::Error> {
-        let res = sqlx::query("DELETE FROM products WHERE id = $1")
+        let res = sqlx::query("UPDATE products SET deleted_at = now() WHERE id = $1 AND deleted_at IS NULL")
             .bind(id)
             .execute(&self.pool)
             .await?;
         Ok(res.rows_affected() > 0)
     }
 
-    pub async fn update_stock(&self, id: Uuid, stock: i32) -> Result<Product, sqlx::Error> {
-        sqlx::query_as::<_, Product>("UPDATE products SET stock = $1 WHERE id = $2 RETURNING *")
-            .bind(stock)
-            .bind(id)
-            .fetch_one(&self.pool)
-            .await
+    pub async fn set_published(&self, id: Uuid, is_published: bool) -> Result<Option<Product>, sqlx::Error> {
+        sqlx::query_as::<_, Product>(
+            "UPDATE products SET is_published = $2, updated_at = now() WHERE id = $1 AND deleted_at IS NULL RETURNING *"
+        )
+        .bind(id)
+        .bind(is_published)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn restore(&self, id: Uuid) -> Result<Option<Product>, sqlx::Error> {
+        sqlx::query_as::<_, Product>(
+            "UPDATE products SET deleted_at = NULL, updated_at = now() WHERE id = $1 AND deleted_at IS NOT NULL RETURNING *"
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
     }
 }
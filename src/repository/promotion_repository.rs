@@ -0,0 +1,108 @@
+use crate::model::promotion::Promotion;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct PromotionRepository {
+    pub pool: PgPool,
+}
+
+impl PromotionRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        discount_percent: Decimal,
+        min_order_total: Decimal,
+        starts_at: DateTime<Utc>,
+        ends_at: Option<DateTime<Utc>>,
+    ) -> Result<Promotion, sqlx::Error> {
+        sqlx::query_as::<_, Promotion>(
+            r#"
+            INSERT INTO promotions (id, name, description, discount_percent, min_order_total, starts_at, ends_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(name)
+        .bind(description)
+        .bind(discount_percent)
+        .bind(min_order_total)
+        .bind(starts_at)
+        .bind(ends_at)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn list(&self, limit: i64, offset: i64) -> Result<Vec<Promotion>, sqlx::Error> {
+        sqlx::query_as::<_, Promotion>(
+            "SELECT * FROM promotions ORDER BY created_at DESC LIMIT $1 OFFSET $2"
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn list_active(&self) -> Result<Vec<Promotion>, sqlx::Error> {
+        sqlx::query_as::<_, Promotion>(
+            r#"
+            SELECT * FROM promotions
+            WHERE active = true AND starts_at <= now() AND (ends_at IS NULL OR ends_at > now())
+            ORDER BY created_at
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn set_active(&self, id: Uuid, active: bool) -> Result<Option<Promotion>, sqlx::Error> {
+        sqlx::query_as::<_, Promotion>("UPDATE promotions SET active = $1 WHERE id = $2 RETURNING *")
+            .bind(active)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    pub async fn record_for_order(
+        conn: &mut PgConnection,
+        order_id: Uuid,
+        promotion_id: Uuid,
+        name: &str,
+        discount_amount: Decimal,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO order_promotions (order_id, promotion_id, name, discount_amount) VALUES ($1, $2, $3, $4)"
+        )
+        .bind(order_id)
+        .bind(promotion_id)
+        .bind(name)
+        .bind(discount_amount)
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+
+    /// The promotions recorded against an order as (promotion id, name, percent, amount).
+    pub async fn find_for_order(&self, order_id: Uuid) -> Result<Vec<(Uuid, String, Decimal, Decimal)>, sqlx::Error> {
+        sqlx::query_as::<_, (Uuid, String, Decimal, Decimal)>(
+            r#"
+            SELECT op.promotion_id, op.name, p.discount_percent, op.discount_amount
+            FROM order_promotions op
+            JOIN promotions p ON p.id = op.promotion_id
+            WHERE op.order_id = $1
+            ORDER BY op.name
+            "#,
+        )
+        .bind(order_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+}
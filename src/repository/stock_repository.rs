@@ -1,8 +1,22 @@
-use crate::model::stock::{StockReservation, InventoryLog, InventoryChangeType, LowStockAlert};
-use chrono::{Utc, Duration};
+use crate::model::stock::{StockReservation, InventoryLog, InventoryChangeType, LowStockAlert, ReorderSuggestion, StockLevel};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
 use sqlx::{PgPool, Result};
 use uuid::Uuid;
 
+/// One entry for the inventory log. Reservations leave stock alone, so theirs record 0 for
+/// both the previous and new stock.
+struct InventoryChange<'a> {
+    product_id: Uuid,
+    change_type: InventoryChangeType,
+    quantity_change: i32,
+    previous_stock: i32,
+    new_stock: i32,
+    reference_id: Option<Uuid>,
+    notes: Option<&'a str>,
+    changed_by: Option<Uuid>,
+}
+
 #[derive(Clone)]
 pub struct StockRepository {
     db: PgPool,
@@ -20,6 +34,7 @@ impl StockRepository {
         cart_id: Uuid,
         quantity: i32,
         expires_in_minutes: i32,
+        changed_by: Option<Uuid>,
     ) -> Result<Option<StockReservation>> {
         let mut tx = self.db.begin().await?;
 
@@ -56,7 +71,7 @@ impl StockRepository {
             r#"
             INSERT INTO stock_reservations (id, product_id, cart_id, quantity, expires_at)
             VALUES ($1, $2, $3, $4, $5)
-            RETURNING id, product_id, cart_id, quantity, reserved_at, expires_at, created_at
+            RETURNING id, product_id, cart_id, order_id, quantity, reserved_at, expires_at, created_at
             "#,
             reservation_id,
             product_id,
@@ -70,26 +85,29 @@ impl StockRepository {
         // Log the reservation
         self.log_inventory_change(
             &mut tx,
-            product_id,
-            InventoryChangeType::Reserved,
-            -quantity, // negative because it reduces available stock
-            0, // we don't change actual stock here
-            0,
-            Some(cart_id),
-            Some(&format!("Reserved {} units for cart", quantity)),
+            InventoryChange {
+                product_id,
+                change_type: InventoryChangeType::Reserved,
+                quantity_change: -quantity, // negative because it reduces available stock
+                previous_stock: 0, // we don't change actual stock here
+                new_stock: 0,
+                reference_id: Some(cart_id),
+                notes: Some(&format!("Reserved {} units for cart", quantity)),
+                changed_by,
+            },
         ).await?;
 
         tx.commit().await?;
         Ok(Some(reservation))
     }
 
-    pub async fn cancel_reservation(&self, reservation_id: Uuid) -> Result<bool> {
+    pub async fn cancel_reservation(&self, reservation_id: Uuid, changed_by: Option<Uuid>) -> Result<bool> {
         let mut tx = self.db.begin().await?;
 
         // Get reservation details before deleting
         let reservation = sqlx::query_as!(
             StockReservation,
-            "SELECT id, product_id, cart_id, quantity, reserved_at, expires_at, created_at FROM stock_reservations WHERE id = $1",
+            "SELECT id, product_id, cart_id, order_id, quantity, reserved_at, expires_at, created_at FROM stock_reservations WHERE id = $1",
             reservation_id
         )
         .fetch_optional(&mut *tx)
@@ -109,13 +127,16 @@ impl StockRepository {
                 // Log unreservation
                 self.log_inventory_change(
                     &mut tx,
-                    res.product_id,
-                    InventoryChangeType::Unreserved,
-                    res.quantity, // positive because it increases available stock
-                    0,
-                    0,
-                    Some(res.cart_id),
-                    Some(&format!("Unreserved {} units from cart", res.quantity)),
+                    InventoryChange {
+                        product_id: res.product_id,
+                        change_type: InventoryChangeType::Unreserved,
+                        quantity_change: res.quantity, // positive because it increases available stock
+                        previous_stock: 0,
+                        new_stock: 0,
+                        reference_id: Some(res.cart_id),
+                        notes: Some(&format!("Unreserved {} units from cart", res.quantity)),
+                        changed_by,
+                    },
                 ).await?;
 
                 tx.commit().await?;
@@ -127,13 +148,76 @@ impl StockRepository {
         Ok(false)
     }
 
-    pub async fn cleanup_expired_reservations(&self) -> Result<i32> {
+    /// Unexpired holds on one product, soonest to expire first.
+    pub async fn list_reservations(&self, product_id: Uuid) -> Result<Vec<StockReservation>> {
+        let reservations = sqlx::query_as!(
+            StockReservation,
+            r#"
+            SELECT id, product_id, cart_id, order_id, quantity, reserved_at, expires_at, created_at
+            FROM stock_reservations
+            WHERE product_id = $1 AND expires_at > now()
+            ORDER BY expires_at
+            "#,
+            product_id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(reservations)
+    }
+
+    pub async fn list_all_reservations(
+        &self,
+        product_id: Option<Uuid>,
+        expired: Option<bool>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<StockReservation>> {
+        let reservations = sqlx::query_as!(
+            StockReservation,
+            r#"
+            SELECT id, product_id, cart_id, order_id, quantity, reserved_at, expires_at, created_at
+            FROM stock_reservations
+            WHERE ($1::uuid IS NULL OR product_id = $1)
+            AND ($2::bool IS NULL OR (expires_at <= now()) = $2)
+            ORDER BY reserved_at DESC
+            LIMIT $3 OFFSET $4
+            "#,
+            product_id,
+            expired,
+            limit,
+            offset
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(reservations)
+    }
+
+    pub async fn count_all_reservations(&self, product_id: Option<Uuid>, expired: Option<bool>) -> Result<i64> {
+        let count: Option<i64> = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*)
+            FROM stock_reservations
+            WHERE ($1::uuid IS NULL OR product_id = $1)
+            AND ($2::bool IS NULL OR (expires_at <= now()) = $2)
+            "#,
+            product_id,
+            expired
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(count.unwrap_or(0))
+    }
+
+    pub async fn cleanup_expired_reservations(&self, changed_by: Option<Uuid>) -> Result<i32> {
         let mut tx = self.db.begin().await?;
 
         // Get expired reservations for logging
         let expired_reservations = sqlx::query_as!(
             StockReservation,
-            "SELECT id, product_id, cart_id, quantity, reserved_at, expires_at, created_at FROM stock_reservations WHERE expires_at <= now()"
+            "SELECT id, product_id, cart_id, order_id, quantity, reserved_at, expires_at, created_at FROM stock_reservations WHERE expires_at <= now()"
         )
         .fetch_all(&mut *tx)
         .await?;
@@ -150,13 +234,16 @@ impl StockRepository {
         for res in expired_reservations {
             self.log_inventory_change(
                 &mut tx,
-                res.product_id,
-                InventoryChangeType::Unreserved,
-                res.quantity,
-                0,
-                0,
-                Some(res.cart_id),
-                Some("Expired reservation cleanup"),
+                InventoryChange {
+                    product_id: res.product_id,
+                    change_type: InventoryChangeType::Unreserved,
+                    quantity_change: res.quantity,
+                    previous_stock: 0,
+                    new_stock: 0,
+                    reference_id: Some(res.cart_id),
+                    notes: Some("Expired reservation cleanup"),
+                    changed_by,
+                },
             ).await?;
         }
 
@@ -164,58 +251,457 @@ impl StockRepository {
         Ok(deleted_rows as i32)
     }
 
+    /// Reserves stock for every item of an order inside the caller's transaction, which must
+    /// roll back on `None`. Returns `None` if any tracked product lacks available stock. The
+    /// cart's checkout holds on those products are replaced by the order's, so the customer's
+    /// own hold doesn't count against the order.
+    pub async fn reserve_for_order_in<'c>(
+        &self,
+        tx: &mut sqlx::Transaction<'c, sqlx::Postgres>,
+        order_id: Uuid,
+        cart_id: Uuid,
+        items: &[(Uuid, i32)],
+        expires_in_minutes: i32,
+    ) -> Result<Option<Vec<StockReservation>>> {
+        let product_ids: Vec<Uuid> = items.iter().map(|(product_id, _)| *product_id).collect();
+        let released = sqlx::query_as!(
+            StockReservation,
+            "DELETE FROM stock_reservations WHERE cart_id = $1 AND order_id IS NULL AND product_id = ANY($2) RETURNING id, product_id, cart_id, order_id, quantity, reserved_at, expires_at, created_at",
+            cart_id,
+            &product_ids
+        )
+        .fetch_all(&mut **tx)
+        .await?;
+
+        for res in &released {
+            self.log_inventory_change(
+                tx,
+                InventoryChange {
+                    product_id: res.product_id,
+                    change_type: InventoryChangeType::Unreserved,
+                    quantity_change: res.quantity,
+                    previous_stock: 0,
+                    new_stock: 0,
+                    reference_id: Some(order_id),
+                    notes: Some("Replaced by an order payment reservation"),
+                    changed_by: None,
+                },
+            ).await?;
+        }
+
+        Ok(self
+            .reserve_items(tx, cart_id, Some(order_id), items, expires_in_minutes, None)
+            .await?
+            .ok())
+    }
+
+    /// Reserves every cart item in one transaction, replacing the cart's earlier holds so a
+    /// retried checkout doesn't count twice against stock. On `Err` nothing changed and the
+    /// id is the first product that couldn't be covered.
+    pub async fn reserve_cart(
+        &self,
+        cart_id: Uuid,
+        items: &[(Uuid, i32)],
+        expires_in_minutes: i32,
+        changed_by: Option<Uuid>,
+    ) -> Result<std::result::Result<Vec<StockReservation>, Uuid>> {
+        let mut tx = self.db.begin().await?;
+
+        let released = sqlx::query_as!(
+            StockReservation,
+            "DELETE FROM stock_reservations WHERE cart_id = $1 AND order_id IS NULL RETURNING id, product_id, cart_id, order_id, quantity, reserved_at, expires_at, created_at",
+            cart_id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for res in &released {
+            self.log_inventory_change(
+                &mut tx,
+                InventoryChange {
+                    product_id: res.product_id,
+                    change_type: InventoryChangeType::Unreserved,
+                    quantity_change: res.quantity,
+                    previous_stock: 0,
+                    new_stock: 0,
+                    reference_id: Some(cart_id),
+                    notes: Some("Replaced by a whole-cart reservation"),
+                    changed_by,
+                },
+            ).await?;
+        }
+
+        let outcome = self
+            .reserve_items(&mut tx, cart_id, None, items, expires_in_minutes, changed_by)
+            .await?;
+        match outcome {
+            Ok(_) => tx.commit().await?,
+            Err(_) => tx.rollback().await?,
+        }
+        Ok(outcome)
+    }
+
+    /// Holds `items` inside `tx`, skipping products that don't track inventory. Stops at the
+    /// first product that is missing or short and returns its id; the caller rolls back.
+    async fn reserve_items<'c>(
+        &self,
+        tx: &mut sqlx::Transaction<'c, sqlx::Postgres>,
+        cart_id: Uuid,
+        order_id: Option<Uuid>,
+        items: &[(Uuid, i32)],
+        expires_in_minutes: i32,
+        changed_by: Option<Uuid>,
+    ) -> Result<std::result::Result<Vec<StockReservation>, Uuid>> {
+        let expires_at = Utc::now() + Duration::minutes(expires_in_minutes as i64);
+        let purpose = if order_id.is_some() { "order payment" } else { "cart checkout" };
+        let mut reservations = Vec::with_capacity(items.len());
+
+        for &(product_id, quantity) in items {
+            // Lock the product row so concurrent checkouts can't both pass the check
+            let product = sqlx::query!(
+                r#"
+                SELECT p.track_inventory,
+                    (p.stock - COALESCE((
+                        SELECT SUM(sr.quantity) FROM stock_reservations sr
+                        WHERE sr.product_id = p.id AND sr.expires_at > now()
+                    ), 0)) as "available_stock!"
+                FROM products p
+                WHERE p.id = $1
+                FOR UPDATE
+                "#,
+                product_id
+            )
+            .fetch_optional(&mut **tx)
+            .await?;
+
+            let Some(product) = product else {
+                return Ok(Err(product_id)); // Product not found
+            };
+
+            if !product.track_inventory {
+                continue;
+            }
+
+            if product.available_stock < quantity as i64 {
+                return Ok(Err(product_id)); // Not enough stock
+            }
+
+            let reservation = sqlx::query_as!(
+                StockReservation,
+                r#"
+                INSERT INTO stock_reservations (id, product_id, cart_id, order_id, quantity, expires_at)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                RETURNING id, product_id, cart_id, order_id, quantity, reserved_at, expires_at, created_at
+                "#,
+                Uuid::new_v4(),
+                product_id,
+                cart_id,
+                order_id,
+                quantity,
+                expires_at
+            )
+            .fetch_one(&mut **tx)
+            .await?;
+
+            self.log_inventory_change(
+                tx,
+                InventoryChange {
+                    product_id,
+                    change_type: InventoryChangeType::Reserved,
+                    quantity_change: -quantity,
+                    previous_stock: 0,
+                    new_stock: 0,
+                    reference_id: Some(order_id.unwrap_or(cart_id)),
+                    notes: Some(&format!("Reserved {} units for {}", quantity, purpose)),
+                    changed_by,
+                },
+            ).await?;
+
+            reservations.push(reservation);
+        }
+
+        Ok(Ok(reservations))
+    }
+
+    /// Drops the reservations held for an order, returning how many were released.
+    pub async fn release_order_reservations(&self, order_id: Uuid, reason: &str) -> Result<i32> {
+        let mut tx = self.db.begin().await?;
+        let released = self.release_order_reservations_in(&mut tx, order_id, reason).await?;
+        tx.commit().await?;
+        Ok(released)
+    }
+
+    /// [`release_order_reservations`](Self::release_order_reservations) inside the caller's
+    /// transaction.
+    pub async fn release_order_reservations_in<'c>(
+        &self,
+        tx: &mut sqlx::Transaction<'c, sqlx::Postgres>,
+        order_id: Uuid,
+        reason: &str,
+    ) -> Result<i32> {
+        let reservations = sqlx::query_as!(
+            StockReservation,
+            "DELETE FROM stock_reservations WHERE order_id = $1 RETURNING id, product_id, cart_id, order_id, quantity, reserved_at, expires_at, created_at",
+            order_id
+        )
+        .fetch_all(&mut **tx)
+        .await?;
+
+        for res in &reservations {
+            self.log_inventory_change(
+                tx,
+                InventoryChange {
+                    product_id: res.product_id,
+                    change_type: InventoryChangeType::Unreserved,
+                    quantity_change: res.quantity,
+                    previous_stock: 0,
+                    new_stock: 0,
+                    reference_id: Some(order_id),
+                    notes: Some(reason),
+                    changed_by: None,
+                },
+            ).await?;
+        }
+
+        Ok(reservations.len() as i32)
+    }
+
+    /// Turns an order's items into sales inside the caller's transaction: decrements stock for
+    /// each item of a product that tracks inventory, drops the cart's checkout holds and this
+    /// order's payment-window holds on those products, and logs a `Sold` change. Stock is
+    /// decremented from the order items, so a hold that already expired still converts, but a
+    /// tracked product whose stock net of every other hold (other carts', and the same cart's
+    /// other orders') can't cover its item stops the sale; its id is returned and the caller
+    /// rolls back.
+    pub async fn consume_reservation<'c>(
+        &self,
+        tx: &mut sqlx::Transaction<'c, sqlx::Postgres>,
+        cart_id: Uuid,
+        order_id: Uuid,
+        items: &[(Uuid, i32)],
+    ) -> Result<std::result::Result<(), Uuid>> {
+        for &(product_id, quantity) in items {
+            let product = sqlx::query!(
+                r#"
+                SELECT p.stock, p.track_inventory,
+                    (p.stock - COALESCE((
+                        SELECT SUM(sr.quantity) FROM stock_reservations sr
+                        WHERE sr.product_id = p.id AND sr.expires_at > now()
+                        AND NOT (sr.cart_id = $2 AND sr.order_id IS NULL)
+                        AND sr.order_id IS DISTINCT FROM $3
+                    ), 0)) as "available_stock!"
+                FROM products p
+                WHERE p.id = $1
+                FOR UPDATE
+                "#,
+                product_id,
+                cart_id,
+                order_id
+            )
+            .fetch_optional(&mut **tx)
+            .await?;
+
+            let Some(product) = product else {
+                continue;
+            };
+            if product.track_inventory && product.available_stock < quantity as i64 {
+                return Ok(Err(product_id));
+            }
+
+            // The sale replaces the hold; leaving it would count the same units twice
+            sqlx::query!(
+                "DELETE FROM stock_reservations WHERE product_id = $1 AND ((cart_id = $2 AND order_id IS NULL) OR order_id = $3)",
+                product_id,
+                cart_id,
+                order_id
+            )
+            .execute(&mut **tx)
+            .await?;
+
+            // Stock isn't counted for this product, so there is none to take
+            if !product.track_inventory {
+                continue;
+            }
+            let current = product.stock;
+            let new_stock = current - quantity;
+
+            sqlx::query!(
+                "UPDATE products SET stock = $1, updated_at = now() WHERE id = $2",
+                new_stock,
+                product_id
+            )
+            .execute(&mut **tx)
+            .await?;
+
+            self.log_inventory_change(
+                tx,
+                InventoryChange {
+                    product_id,
+                    change_type: InventoryChangeType::Sold,
+                    quantity_change: -quantity,
+                    previous_stock: current,
+                    new_stock,
+                    reference_id: Some(order_id),
+                    notes: Some("Converted reservation to sale"),
+                    changed_by: None,
+                },
+            ).await?;
+        }
+
+        sqlx::query!("DELETE FROM stock_reservations WHERE order_id = $1", order_id)
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(Ok(()))
+    }
+
+    /// Puts the items of a cancelled or refunded order back into stock inside the caller's
+    /// transaction, so the stock only comes back if the status change that returns it commits
+    /// too. Returns false without touching stock if the order was already restocked.
+    pub async fn restock_order_in<'c>(
+        &self,
+        tx: &mut sqlx::Transaction<'c, sqlx::Postgres>,
+        order_id: Uuid,
+        items: &[(Uuid, i32)],
+    ) -> Result<bool> {
+        // Claim the order first; a second transition finds the flag already set
+        let claimed = sqlx::query_scalar!(
+            "UPDATE orders SET restocked_at = now() WHERE id = $1 AND restocked_at IS NULL RETURNING id",
+            order_id
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        if claimed.is_none() {
+            return Ok(false);
+        }
+
+        for &(product_id, quantity) in items {
+            let current_stock: Option<i32> = sqlx::query_scalar!(
+                "SELECT stock FROM products WHERE id = $1 FOR UPDATE",
+                product_id
+            )
+            .fetch_optional(&mut **tx)
+            .await?;
+
+            let Some(current) = current_stock else {
+                continue;
+            };
+            let new_stock = current + quantity;
+
+            sqlx::query!(
+                "UPDATE products SET stock = $1, updated_at = now() WHERE id = $2",
+                new_stock,
+                product_id
+            )
+            .execute(&mut **tx)
+            .await?;
+
+            self.log_inventory_change(
+                tx,
+                InventoryChange {
+                    product_id,
+                    change_type: InventoryChangeType::StockIn,
+                    quantity_change: quantity,
+                    previous_stock: current,
+                    new_stock,
+                    reference_id: Some(order_id),
+                    notes: Some("Restocked after order cancellation or refund"),
+                    changed_by: None,
+                },
+            ).await?;
+        }
+
+        Ok(true)
+    }
+
     // Stock Management
-    pub async fn update_stock(
+    /// Sets the product's stock to `new_stock`. Returns the new stock, or `None` if the product
+    /// doesn't exist.
+    pub async fn set_stock(
         &self,
         product_id: Uuid,
         new_stock: i32,
-        change_type: InventoryChangeType,
         reference_id: Option<Uuid>,
         notes: Option<String>,
-    ) -> Result<bool> {
+        changed_by: Option<Uuid>,
+    ) -> Result<Option<i32>> {
+        self.change_stock(product_id, |_| new_stock, reference_id, notes, changed_by).await
+    }
+
+    /// Adds `delta` (which may be negative) to the product's stock. Returns the new stock, or
+    /// `None` if the product doesn't exist or the adjustment would take stock below zero.
+    pub async fn adjust_stock(
+        &self,
+        product_id: Uuid,
+        delta: i32,
+        reference_id: Option<Uuid>,
+        notes: Option<String>,
+        changed_by: Option<Uuid>,
+    ) -> Result<Option<i32>> {
+        self.change_stock(product_id, |current| current + delta, reference_id, notes, changed_by).await
+    }
+
+    async fn change_stock(
+        &self,
+        product_id: Uuid,
+        next_stock: impl FnOnce(i32) -> i32,
+        reference_id: Option<Uuid>,
+        notes: Option<String>,
+        changed_by: Option<Uuid>,
+    ) -> Result<Option<i32>> {
         let mut tx = self.db.begin().await?;
 
-        // Get current stock
+        // Lock the row so a concurrent adjustment can't be computed from a stale value
         let current_stock: Option<i32> = sqlx::query_scalar!(
-            "SELECT stock FROM products WHERE id = $1",
+            "SELECT stock FROM products WHERE id = $1 FOR UPDATE",
             product_id
         )
         .fetch_optional(&mut *tx)
         .await?;
 
-        if let Some(current) = current_stock {
-            let quantity_change = new_stock - current;
+        let Some(current) = current_stock else {
+            tx.rollback().await?;
+            return Ok(None);
+        };
 
-            // Update product stock
-            let updated_rows = sqlx::query!(
-                "UPDATE products SET stock = $1, updated_at = now() WHERE id = $2",
-                new_stock,
-                product_id
-            )
-            .execute(&mut *tx)
-            .await?
-            .rows_affected();
+        let new_stock = next_stock(current);
+        if new_stock < 0 {
+            tx.rollback().await?;
+            return Ok(None);
+        }
 
-            if updated_rows > 0 {
-                // Log the change
-                self.log_inventory_change(
-                    &mut tx,
-                    product_id,
-                    change_type,
-                    quantity_change,
-                    current,
-                    new_stock,
-                    reference_id,
-                    notes.as_deref(),
-                ).await?;
+        sqlx::query!(
+            "UPDATE products SET stock = $1, updated_at = now() WHERE id = $2",
+            new_stock,
+            product_id
+        )
+        .execute(&mut *tx)
+        .await?;
 
-                tx.commit().await?;
-                return Ok(true);
-            }
-        }
+        let quantity_change = new_stock - current;
+        let change_type = if quantity_change >= 0 {
+            InventoryChangeType::StockIn
+        } else {
+            InventoryChangeType::StockOut
+        };
 
-        tx.rollback().await?;
-        Ok(false)
+        self.log_inventory_change(
+            &mut tx,
+            InventoryChange {
+                product_id,
+                change_type,
+                quantity_change,
+                previous_stock: current,
+                new_stock,
+                reference_id,
+                notes: notes.as_deref(),
+                changed_by,
+            },
+        ).await?;
+
+        tx.commit().await?;
+        Ok(Some(new_stock))
     }
 
     pub async fn get_available_stock(&self, product_id: Uuid) -> Result<Option<i32>> {
@@ -235,7 +721,36 @@ impl StockRepository {
         Ok(available_stock.flatten().map(|s| s as i32))
     }
 
-    pub async fn get_low_stock_alerts(&self) -> Result<Vec<LowStockAlert>> {
+    /// Available stock for each of `product_ids` that exists; unknown ids are left out.
+    pub async fn get_available_stock_batch(&self, product_ids: &[Uuid]) -> Result<HashMap<Uuid, i32>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT p.id, (p.stock - COALESCE(SUM(sr.quantity), 0)) as available_stock
+            FROM products p
+            LEFT JOIN stock_reservations sr ON p.id = sr.product_id AND sr.expires_at > now()
+            WHERE p.id = ANY($1)
+            GROUP BY p.id, p.stock
+            "#,
+            product_ids
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.id, row.available_stock.unwrap_or(0) as i32))
+            .collect())
+    }
+
+    /// Tracked products whose available stock is at or below their threshold, least available
+    /// first. Products without a threshold of their own use `default_threshold`, and are left
+    /// out when there is no default. `limit` of `None` returns them all from `offset`.
+    pub async fn get_low_stock_alerts(
+        &self,
+        default_threshold: Option<i32>,
+        limit: Option<i64>,
+        offset: i64,
+    ) -> Result<Vec<LowStockAlert>> {
         let alerts = sqlx::query!(
             r#"
             SELECT 
@@ -243,15 +758,20 @@ impl StockRepository {
                 p.name as product_name,
                 p.stock as current_stock,
                 (p.stock - COALESCE(SUM(sr.quantity), 0)) as available_stock,
-                p.low_stock_threshold as threshold
+                COALESCE(p.low_stock_threshold, $1::int) as threshold
             FROM products p
             LEFT JOIN stock_reservations sr ON p.id = sr.product_id AND sr.expires_at > now()
             WHERE p.track_inventory = true 
-            AND p.low_stock_threshold IS NOT NULL
+            AND p.deleted_at IS NULL
+            AND COALESCE(p.low_stock_threshold, $1::int) IS NOT NULL
             GROUP BY p.id, p.name, p.stock, p.low_stock_threshold
-            HAVING (p.stock - COALESCE(SUM(sr.quantity), 0)) <= p.low_stock_threshold
-            ORDER BY available_stock ASC
-            "#
+            HAVING (p.stock - COALESCE(SUM(sr.quantity), 0)) <= COALESCE(p.low_stock_threshold, $1::int)
+            ORDER BY available_stock ASC, p.id
+            LIMIT $2 OFFSET $3
+            "#,
+            default_threshold,
+            limit,
+            offset
         )
         .fetch_all(&self.db)
         .await?;
@@ -271,33 +791,250 @@ impl StockRepository {
         Ok(low_stock_alerts)
     }
 
+    /// How many products [`StockRepository::get_low_stock_alerts`] reports in total.
+    pub async fn count_low_stock_alerts(&self, default_threshold: Option<i32>) -> Result<i64> {
+        let total = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!" FROM (
+                SELECT p.id
+                FROM products p
+                LEFT JOIN stock_reservations sr ON p.id = sr.product_id AND sr.expires_at > now()
+                WHERE p.track_inventory = true
+                AND p.deleted_at IS NULL
+                AND COALESCE(p.low_stock_threshold, $1::int) IS NOT NULL
+                GROUP BY p.id, p.stock, p.low_stock_threshold
+                HAVING (p.stock - COALESCE(SUM(sr.quantity), 0)) <= COALESCE(p.low_stock_threshold, $1::int)
+            ) alerting
+            "#,
+            default_threshold
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(total)
+    }
+
+    /// The products [`StockRepository::get_low_stock_alerts`] reports, with the quantity that
+    /// brings each back up to its reorder target, largest first.
+    pub async fn get_reorder_report(&self, default_threshold: Option<i32>) -> Result<Vec<ReorderSuggestion>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                p.id as product_id,
+                p.name as product_name,
+                p.stock as current_stock,
+                (p.stock - COALESCE(SUM(sr.quantity), 0))::int as "available_stock!",
+                COALESCE(p.low_stock_threshold, $1::int) as "threshold!",
+                p.reorder_target,
+                GREATEST(
+                    COALESCE(p.reorder_target, COALESCE(p.low_stock_threshold, $1::int) * 2)
+                        - (p.stock - COALESCE(SUM(sr.quantity), 0)),
+                    0
+                )::int as "suggested_quantity!"
+            FROM products p
+            LEFT JOIN stock_reservations sr ON p.id = sr.product_id AND sr.expires_at > now()
+            WHERE p.track_inventory = true
+            AND p.deleted_at IS NULL
+            AND COALESCE(p.low_stock_threshold, $1::int) IS NOT NULL
+            GROUP BY p.id, p.name, p.stock, p.low_stock_threshold, p.reorder_target
+            HAVING (p.stock - COALESCE(SUM(sr.quantity), 0)) <= COALESCE(p.low_stock_threshold, $1::int)
+            ORDER BY "suggested_quantity!" DESC, p.name
+            "#,
+            default_threshold
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| ReorderSuggestion {
+                product_id: row.product_id,
+                product_name: row.product_name,
+                current_stock: row.current_stock,
+                available_stock: row.available_stock,
+                threshold: row.threshold,
+                reorder_target: row.reorder_target,
+                suggested_quantity: row.suggested_quantity,
+            })
+            .collect())
+    }
+
+    /// Marks each of `product_ids` whose available stock is at or below its low-stock threshold
+    /// (or `default_threshold` if it has none) as notified, unless it was already notified
+    /// within the last `debounce_seconds`, and returns the ones marked. Claiming and marking is
+    /// one statement, so concurrent sales report a dip once.
+    pub async fn claim_low_stock_notifications(
+        &self,
+        product_ids: &[Uuid],
+        default_threshold: Option<i32>,
+        debounce_seconds: i64,
+    ) -> Result<Vec<LowStockAlert>> {
+        let rows = sqlx::query!(
+            r#"
+            UPDATE products p
+            SET low_stock_notified_at = now()
+            FROM (
+                SELECT p2.id, (p2.stock - COALESCE(SUM(sr.quantity), 0)) as available_stock
+                FROM products p2
+                LEFT JOIN stock_reservations sr ON p2.id = sr.product_id AND sr.expires_at > now()
+                WHERE p2.id = ANY($1)
+                GROUP BY p2.id, p2.stock
+            ) a
+            WHERE a.id = p.id
+              AND p.track_inventory = true
+              AND p.deleted_at IS NULL
+              AND a.available_stock <= COALESCE(p.low_stock_threshold, $2)
+              AND (p.low_stock_notified_at IS NULL
+                   OR p.low_stock_notified_at <= now() - make_interval(secs => $3::float8))
+            RETURNING
+                p.id as product_id,
+                p.name as product_name,
+                p.stock as current_stock,
+                a.available_stock,
+                COALESCE(p.low_stock_threshold, $2) as threshold
+            "#,
+            product_ids,
+            default_threshold as Option<i32>,
+            debounce_seconds as f64
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| LowStockAlert {
+                product_id: row.product_id,
+                product_name: row.product_name,
+                current_stock: row.current_stock,
+                available_stock: row.available_stock.unwrap_or(0) as i32,
+                threshold: row.threshold.unwrap_or(0),
+                is_critical: row.available_stock.unwrap_or(0) as i32 <= 0,
+            })
+            .collect())
+    }
+
+    /// Number of live (not soft-deleted) products.
+    pub async fn count_products(&self) -> Result<i64> {
+        let count: Option<i64> = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM products WHERE deleted_at IS NULL"
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(count.unwrap_or(0))
+    }
+
+    /// Units currently held by reservations that haven't expired.
+    pub async fn total_reserved(&self) -> Result<i64> {
+        let total: Option<i64> = sqlx::query_scalar!(
+            "SELECT COALESCE(SUM(quantity), 0)::bigint FROM stock_reservations WHERE expires_at > now()"
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(total.unwrap_or(0))
+    }
+
+    /// Stock minus active reservations, summed over live products with inventory tracking on.
+    pub async fn total_available(&self) -> Result<i64> {
+        let total: Option<i64> = sqlx::query_scalar!(
+            r#"
+            SELECT COALESCE(SUM(p.stock - COALESCE(r.reserved, 0)), 0)::bigint
+            FROM products p
+            LEFT JOIN (
+                SELECT product_id, SUM(quantity) as reserved
+                FROM stock_reservations
+                WHERE expires_at > now()
+                GROUP BY product_id
+            ) r ON r.product_id = p.id
+            WHERE p.track_inventory = true
+            AND p.deleted_at IS NULL
+            "#
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(total.unwrap_or(0))
+    }
+
+    /// A page of live products' stock positions ordered by id, starting after `after`.
+    /// Keyset pagination keeps each page cheap however deep into the catalog it is.
+    pub async fn stock_levels_page(
+        &self,
+        after: Option<Uuid>,
+        limit: i64,
+        default_threshold: Option<i32>,
+    ) -> Result<Vec<StockLevel>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                p.id as product_id,
+                p.name as product_name,
+                p.stock as current_stock,
+                COALESCE(r.reserved, 0)::int as "reserved!",
+                p.low_stock_threshold as threshold,
+                p.track_inventory
+            FROM products p
+            LEFT JOIN (
+                SELECT product_id, SUM(quantity) as reserved
+                FROM stock_reservations
+                WHERE expires_at > now()
+                GROUP BY product_id
+            ) r ON r.product_id = p.id
+            WHERE p.deleted_at IS NULL
+            AND ($1::uuid IS NULL OR p.id > $1)
+            ORDER BY p.id
+            LIMIT $2
+            "#,
+            after,
+            limit
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let available_stock = row.current_stock - row.reserved;
+                StockLevel {
+                    product_id: row.product_id,
+                    product_name: row.product_name,
+                    current_stock: row.current_stock,
+                    reserved: row.reserved,
+                    available_stock,
+                    // Same rule as the low stock alerts
+                    is_low_stock: row.track_inventory
+                        && row
+                            .threshold
+                            .or(default_threshold)
+                            .is_some_and(|threshold| available_stock <= threshold),
+                }
+            })
+            .collect())
+    }
+
     // Inventory Logging
     async fn log_inventory_change<'c>(
         &self,
         tx: &mut sqlx::Transaction<'c, sqlx::Postgres>,
-        product_id: Uuid,
-        change_type: InventoryChangeType,
-        quantity_change: i32,
-        previous_stock: i32,
-        new_stock: i32,
-        reference_id: Option<Uuid>,
-        notes: Option<&str>,
+        change: InventoryChange<'_>,
     ) -> Result<()> {
         let log_id = Uuid::new_v4();
         
         sqlx::query!(
             r#"
-            INSERT INTO inventory_logs (id, product_id, change_type, quantity_change, previous_stock, new_stock, reference_id, notes)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            INSERT INTO inventory_logs (id, product_id, change_type, quantity_change, previous_stock, new_stock, reference_id, notes, changed_by)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             "#,
             log_id,
-            product_id,
-            change_type.to_string(),
-            quantity_change,
-            previous_stock,
-            new_stock,
-            reference_id,
-            notes
+            change.product_id,
+            change.change_type.to_string(),
+            change.quantity_change,
+            change.previous_stock,
+            change.new_stock,
+            change.reference_id,
+            change.notes,
+            change.changed_by
         )
         .execute(&mut **tx)
         .await?;
@@ -305,16 +1042,33 @@ impl StockRepository {
         Ok(())
     }
 
+    /// The product's log entries, newest first, optionally narrowed to one change type and to
+    /// entries created in `[from, to)`.
     pub async fn get_inventory_history(
         &self,
         product_id: Uuid,
+        change_type: Option<InventoryChangeType>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
         limit: i64,
         offset: i64,
     ) -> Result<Vec<InventoryLog>> {
         let logs = sqlx::query_as!(
             InventoryLog,
-            "SELECT id, product_id, change_type, quantity_change, previous_stock, new_stock, reference_id, notes, created_at FROM inventory_logs WHERE product_id = $1 ORDER BY created_at DESC LIMIT $2 OFFSET $3",
+            r#"
+            SELECT id, product_id, change_type, quantity_change, previous_stock, new_stock, reference_id, notes, changed_by, created_at
+            FROM inventory_logs
+            WHERE product_id = $1
+            AND ($2::text IS NULL OR change_type = $2)
+            AND ($3::timestamptz IS NULL OR created_at >= $3)
+            AND ($4::timestamptz IS NULL OR created_at < $4)
+            ORDER BY created_at DESC
+            LIMIT $5 OFFSET $6
+            "#,
             product_id,
+            change_type.map(|t| t.to_string()),
+            from,
+            to,
             limit,
             offset
         )
@@ -323,4 +1077,32 @@ impl StockRepository {
 
         Ok(logs)
     }
+
+    /// How many entries [`StockRepository::get_inventory_history`] pages through.
+    pub async fn count_inventory_history(
+        &self,
+        product_id: Uuid,
+        change_type: Option<InventoryChangeType>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<i64> {
+        let total = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM inventory_logs
+            WHERE product_id = $1
+            AND ($2::text IS NULL OR change_type = $2)
+            AND ($3::timestamptz IS NULL OR created_at >= $3)
+            AND ($4::timestamptz IS NULL OR created_at < $4)
+            "#,
+            product_id,
+            change_type.map(|t| t.to_string()),
+            from,
+            to
+        )
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(total)
+    }
 }
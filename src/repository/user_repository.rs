@@ -1,7 +1,9 @@
+use crate::model::order::OrderStatus;
 use crate::model::user::User;
+use crate::repository::{OrderRepository, StockRepository};
 use sqlx::PgPool;
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 
 #[derive(Clone)]
 pub struct UserRepository {
@@ -21,7 +23,7 @@ impl UserRepository {
             r#"
             INSERT INTO users (id, email, password_hash, role, created_at)
             VALUES ($1, $2, $3, $4, $5)
-            RETURNING id, email, password_hash, role, created_at
+            RETURNING id, email, password_hash, role, email_verified, is_active, created_at
             "#
         )
         .bind(id)
@@ -35,7 +37,7 @@ impl UserRepository {
 
     pub async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
         sqlx::query_as::<_, User>(
-            "SELECT id, email, password_hash, role, created_at FROM users WHERE email = $1"
+            "SELECT id, email, password_hash, role, email_verified, is_active, created_at FROM users WHERE LOWER(email) = LOWER($1) AND deleted_at IS NULL"
         )
         .bind(email)
         .fetch_optional(&self.pool)
@@ -44,10 +46,232 @@ impl UserRepository {
 
     pub async fn find_by_id(&self, id: Uuid) -> Result<Option<User>, sqlx::Error> {
         sqlx::query_as::<_, User>(
-            "SELECT id, email, password_hash, role, created_at FROM users WHERE id = $1"
+            "SELECT id, email, password_hash, role, email_verified, is_active, created_at FROM users WHERE id = $1 AND deleted_at IS NULL"
         )
         .bind(id)
         .fetch_optional(&self.pool)
         .await
     }
+
+    pub async fn list(&self, limit: i64, offset: i64) -> Result<Vec<User>, sqlx::Error> {
+        sqlx::query_as::<_, User>(
+            "SELECT id, email, password_hash, role, email_verified, is_active, created_at FROM users WHERE deleted_at IS NULL ORDER BY created_at DESC LIMIT $1 OFFSET $2"
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn count(&self) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM users WHERE deleted_at IS NULL")
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    pub async fn update_role(&self, id: Uuid, role: &str) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query_as::<_, User>(
+            "UPDATE users SET role = $1 WHERE id = $2 AND deleted_at IS NULL RETURNING id, email, password_hash, role, email_verified, is_active, created_at"
+        )
+        .bind(role)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn set_active(&self, id: Uuid, active: bool) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query_as::<_, User>(
+            "UPDATE users SET is_active = $1 WHERE id = $2 AND deleted_at IS NULL RETURNING id, email, password_hash, role, email_verified, is_active, created_at"
+        )
+        .bind(active)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Erases an account while keeping its orders for accounting: orders still awaiting payment
+    /// are cancelled and the stock held for them released, the cart (and with it any stock held
+    /// for it), pending tokens and idempotency keys are deleted, and the user row is stripped of
+    /// its email and password and marked deleted. Returns `false` if there was no such live
+    /// account.
+    pub async fn delete(&self, id: Uuid) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let anonymized = sqlx::query(
+            r#"
+            UPDATE users
+            SET email = 'deleted-' || id || '@deleted.invalid',
+                password_hash = '',
+                email_verified = false,
+                deleted_at = now()
+            WHERE id = $1 AND deleted_at IS NULL
+            "#
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if anonymized == 0 {
+            tx.rollback().await?;
+            return Ok(false);
+        }
+
+        // Nobody can pay for these any more
+        let awaiting_payment = [OrderStatus::PendingPayment.to_string(), OrderStatus::PaymentProcessing.to_string()];
+        let awaiting_payment: Vec<&str> = awaiting_payment.iter().map(String::as_str).collect();
+        let unpaid: Vec<Uuid> = sqlx::query_scalar("SELECT id FROM orders WHERE user_id = $1 AND status = ANY($2)")
+            .bind(id)
+            .bind(&awaiting_payment)
+            .fetch_all(&mut *tx)
+            .await?;
+        let stock = StockRepository::new(self.pool.clone());
+        for order_id in unpaid {
+            let cancelled = OrderRepository::transition_status_in(
+                &mut tx,
+                order_id,
+                &awaiting_payment,
+                &OrderStatus::Cancelled.to_string(),
+                None,
+                Some(id),
+            )
+            .await?;
+            if cancelled.is_some() {
+                stock.release_order_reservations_in(&mut tx, order_id, "Released reservation after account deletion").await?;
+            }
+        }
+
+        for table in ["carts", "password_reset_tokens", "email_verification_tokens", "idempotency_keys"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE user_id = $1", table))
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    pub async fn update_password(&self, id: Uuid, password_hash: &str) -> Result<bool, sqlx::Error> {
+        let res = sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2")
+            .bind(password_hash)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    pub async fn create_reset_token(
+        &self,
+        user_id: Uuid,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO password_reset_tokens (id, user_id, token_hash, expires_at) VALUES ($1, $2, $3, $4)"
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(token_hash)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Marks a reset token used if it is still valid, returning the owning user.
+    /// The conditional update makes concurrent redemptions of the same token race-safe.
+    pub async fn consume_reset_token(&self, token_hash: &str) -> Result<Option<Uuid>, sqlx::Error> {
+        sqlx::query_scalar::<_, Uuid>(
+            r#"
+            UPDATE password_reset_tokens
+            SET used_at = now()
+            WHERE token_hash = $1 AND used_at IS NULL AND expires_at > now()
+            RETURNING user_id
+            "#
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn create_verification_token(
+        &self,
+        user_id: Uuid,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO email_verification_tokens (id, user_id, token_hash, expires_at) VALUES ($1, $2, $3, $4)"
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(token_hash)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Redeems a still-valid verification token and marks its user verified.
+    /// Returns the verified user's id, or `None` if the token is unknown, used or expired.
+    pub async fn verify_email(&self, token_hash: &str) -> Result<Option<Uuid>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let user_id = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            UPDATE email_verification_tokens
+            SET used_at = now()
+            WHERE token_hash = $1 AND used_at IS NULL AND expires_at > now()
+            RETURNING user_id
+            "#
+        )
+        .bind(token_hash)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some(user_id) = user_id {
+            sqlx::query("UPDATE users SET email_verified = true WHERE id = $1")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(user_id)
+    }
+
+    pub async fn revoke_token(&self, jti: Uuid, user_id: Uuid, expires_at: DateTime<Utc>) -> Result<(), sqlx::Error> {
+        // Entries past their expiry can't match a valid token any more
+        sqlx::query("DELETE FROM revoked_tokens WHERE expires_at < now()")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            "INSERT INTO revoked_tokens (jti, user_id, expires_at) VALUES ($1, $2, $3) ON CONFLICT (jti) DO NOTHING"
+        )
+        .bind(jti)
+        .bind(user_id)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Returns `(revoked, account)` for a presented token: whether its jti was revoked and,
+    /// for a live account, its current role and whether it is active. Deleted and unknown users
+    /// have no account, so a deleted account's tokens stop working with it.
+    pub async fn token_status(&self, jti: Uuid, user_id: Uuid) -> Result<(bool, Option<(String, bool)>), sqlx::Error> {
+        let (revoked, role, is_active) = sqlx::query_as::<_, (bool, Option<String>, Option<bool>)>(
+            r#"
+            SELECT EXISTS(SELECT 1 FROM revoked_tokens WHERE jti = $1), u.role, u.is_active
+            FROM (SELECT 1) AS one
+            LEFT JOIN users u ON u.id = $2 AND u.deleted_at IS NULL
+            "#
+        )
+        .bind(jti)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok((revoked, role.zip(is_active)))
+    }
 }
@@ -0,0 +1,62 @@
+use crate::{
+    errors::AppResult,
+    middleware::auth::AdminUser,
+    model::analytics::SalesSummary,
+    repository::AnalyticsRepository,
+    services::analytics_service::AnalyticsService,
+    state::AppState,
+};
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    response::IntoResponse,
+    routing::get,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+const DEFAULT_TOP_PRODUCTS: i64 = 5;
+const MAX_TOP_PRODUCTS: i64 = 50;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct SalesQuery {
+    /// Start of the range (inclusive), RFC 3339; unbounded when omitted
+    pub from: Option<DateTime<Utc>>,
+    /// End of the range (exclusive), RFC 3339; unbounded when omitted
+    pub to: Option<DateTime<Utc>>,
+    /// Number of top-selling products to include, defaults to 5 and is capped at 50
+    pub top: Option<i64>,
+}
+
+pub fn build_route() -> Router<AppState> {
+    Router::new().route("/sales", get(sales_summary))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/analytics/sales",
+    params(SalesQuery),
+    responses(
+        (status = 200, description = "Sales figures for paid, processing, shipped and delivered orders in the range", body = SalesSummary),
+        (status = 400, description = "from is later than to"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin access required"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Analytics"
+)]
+async fn sales_summary(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    Query(query): Query<SalesQuery>,
+) -> AppResult<impl IntoResponse> {
+    let svc = AnalyticsService::new(AnalyticsRepository::new(state.db.clone()));
+    let top = query.top.unwrap_or(DEFAULT_TOP_PRODUCTS).clamp(1, MAX_TOP_PRODUCTS);
+
+    let summary = svc.sales_summary(query.from, query.to, top).await?;
+    Ok(Json(summary))
+}
@@ -1,16 +1,47 @@
-use axum::{Router, routing::post, Json, extract::State, http::StatusCode, response::IntoResponse};
+use axum::{Router, routing::{delete, get, post, put}, Json, extract::{Path, Query, State}, http::StatusCode, response::IntoResponse};
 use crate::services::auth_service::AuthService;
+use crate::routes::pagination::{page, page_headers};
 use crate::state::AppState;
 use crate::repository::UserRepository;
 use crate::errors::{AppResult, AppError};
+use crate::middleware::auth::{AdminUser, AuthUser};
 use crate::middleware::validation::ValidatedJson;
-use crate::dtos::{SignupDto, LoginDto, UserResponse};
+use crate::dtos::{SignupDto, LoginDto, UserResponse, ForgotPasswordDto, ResetPasswordDto, VerifyEmailQuery, UpdateRoleDto, DeleteAccountDto};
+use serde::Deserialize;
 use serde_json::json;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct UserListQuery {
+    /// Page size, defaults to 50 and is capped at 100
+    pub limit: Option<i64>,
+    /// Number of users to skip, defaults to 0
+    pub offset: Option<i64>,
+}
+
+fn auth_service(state: &AppState) -> AuthService {
+    let repo = UserRepository::new(state.db.clone());
+    AuthService::new(repo, (*state.jwt_secret).clone())
+        .with_jwt_config((*state.jwt_config).clone())
+        .with_email_verification(state.auth_config.require_email_verification)
+        .with_mailer(state.mailer.clone())
+}
 
 pub fn build_route() -> Router<AppState> {
     Router::new()
         .route("/signup", post(signup))
         .route("/login", post(login))
+        .route("/me", get(me))
+        .route("/logout", post(logout))
+        .route("/account", delete(delete_account))
+        .route("/verify", get(verify_email))
+        .route("/forgot-password", post(forgot_password))
+        .route("/reset-password", post(reset_password))
+        .route("/users", get(list_users))
+        .route("/users/{id}/role", put(update_user_role))
+        .route("/users/{id}/deactivate", put(deactivate_user))
+        .route("/users/{id}/activate", put(activate_user))
 }
 
 #[utoipa::path(
@@ -25,8 +56,7 @@ pub fn build_route() -> Router<AppState> {
     tag = "Authentication"
 )]
 async fn signup(State(state): State<AppState>, ValidatedJson(dto): ValidatedJson<SignupDto>) -> AppResult<impl IntoResponse> {
-    let repo = UserRepository::new(state.db.clone());
-    let svc = AuthService::new(repo, (*state.jwt_secret).clone());
+    let svc = auth_service(&state);
 
     let user = svc.signup(dto).await?;
     Ok((StatusCode::CREATED, Json(UserResponse::from(user))))
@@ -39,17 +69,274 @@ async fn signup(State(state): State<AppState>, ValidatedJson(dto): ValidatedJson
     responses(
         (status = 200, description = "Login successful", body = inline(Object), example = json!({"token": "jwt_token_here"})),
         (status = 401, description = "Invalid credentials"),
+        (status = 403, description = "Account deactivated, or email not verified (when REQUIRE_EMAIL_VERIFICATION is enabled)"),
         (status = 400, description = "Validation error"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Authentication"
 )]
 async fn login(State(state): State<AppState>, ValidatedJson(dto): ValidatedJson<LoginDto>) -> AppResult<impl IntoResponse> {
-    let repo = UserRepository::new(state.db.clone());
-    let svc = AuthService::new(repo, (*state.jwt_secret).clone());
+    let svc = auth_service(&state);
 
     match svc.login(dto).await? {
         Some(token) => Ok((StatusCode::OK, Json(json!({"token": token})))),
         None => Err(AppError::Unauthorized),
     }
 }
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/logout",
+    responses(
+        (status = 204, description = "Token revoked"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Authentication"
+)]
+async fn logout(State(state): State<AppState>, AuthUser(claims): AuthUser) -> AppResult<impl IntoResponse> {
+    let svc = auth_service(&state);
+
+    svc.logout(&claims).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/auth/account",
+    request_body = DeleteAccountDto,
+    responses(
+        (status = 204, description = "Account deleted. Its cart, reservations and pending tokens are removed; orders are kept under an anonymized user for accounting"),
+        (status = 400, description = "Validation error"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Password is incorrect"),
+        (status = 404, description = "User no longer exists"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Authentication"
+)]
+async fn delete_account(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    ValidatedJson(dto): ValidatedJson<DeleteAccountDto>,
+) -> AppResult<impl IntoResponse> {
+    let svc = auth_service(&state);
+
+    svc.delete_account(claims.sub, &dto.password).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/me",
+    responses(
+        (status = 200, description = "Authenticated user's profile", body = UserResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "User no longer exists"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Authentication"
+)]
+async fn me(State(state): State<AppState>, AuthUser(claims): AuthUser) -> AppResult<impl IntoResponse> {
+    let svc = auth_service(&state);
+
+    match svc.profile(claims.sub).await? {
+        Some(user) => Ok((StatusCode::OK, Json(UserResponse::from(user)))),
+        None => Err(AppError::NotFound(format!("User with id {} not found", claims.sub))),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/forgot-password",
+    request_body = ForgotPasswordDto,
+    responses(
+        (status = 200, description = "Reset requested; the response is the same whether or not the email is registered"),
+        (status = 400, description = "Validation error"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Authentication"
+)]
+async fn forgot_password(State(state): State<AppState>, ValidatedJson(dto): ValidatedJson<ForgotPasswordDto>) -> AppResult<impl IntoResponse> {
+    let svc = auth_service(&state);
+
+    svc.request_password_reset(&dto.email).await?;
+    Ok((StatusCode::OK, Json(json!({"message": "If the email is registered, a reset link has been sent"}))))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/reset-password",
+    request_body = ResetPasswordDto,
+    responses(
+        (status = 200, description = "Password updated"),
+        (status = 400, description = "Invalid, expired or already used token"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Authentication"
+)]
+async fn reset_password(State(state): State<AppState>, ValidatedJson(dto): ValidatedJson<ResetPasswordDto>) -> AppResult<impl IntoResponse> {
+    let svc = auth_service(&state);
+
+    svc.reset_password(dto).await?;
+    Ok((StatusCode::OK, Json(json!({"message": "Password has been reset"}))))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/verify",
+    params(VerifyEmailQuery),
+    responses(
+        (status = 200, description = "Email verified"),
+        (status = 400, description = "Invalid, expired or already used token"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Authentication"
+)]
+async fn verify_email(State(state): State<AppState>, Query(query): Query<VerifyEmailQuery>) -> AppResult<impl IntoResponse> {
+    let svc = auth_service(&state);
+
+    svc.verify_email(&query.token).await?;
+    Ok((StatusCode::OK, Json(json!({"message": "Email verified"}))))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/users",
+    params(UserListQuery),
+    responses(
+        (status = 200, description = "List of users", body = [UserResponse],
+            headers(
+                ("X-Total-Count" = i64, description = "Total number of users"),
+                ("X-Page-Limit" = i64, description = "Page size used"),
+                ("X-Page-Offset" = i64, description = "Rows skipped before this page")
+            )),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin access required"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Authentication"
+)]
+async fn list_users(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    Query(query): Query<UserListQuery>,
+) -> AppResult<impl IntoResponse> {
+    let svc = auth_service(&state);
+
+    let (limit, offset) = page(query.limit, query.offset);
+
+    let (users, total) = svc.list_users(limit, offset).await?;
+    let res: Vec<UserResponse> = users.into_iter().map(UserResponse::from).collect();
+
+    Ok((StatusCode::OK, page_headers(total, limit, offset), Json(res)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/auth/users/{id}/role",
+    params(
+        ("id" = Uuid, Path, description = "User ID")
+    ),
+    request_body = UpdateRoleDto,
+    responses(
+        (status = 200, description = "Role updated", body = UserResponse),
+        (status = 400, description = "Unknown role or self-demotion"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin access required"),
+        (status = 404, description = "User not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Authentication"
+)]
+async fn update_user_role(
+    State(state): State<AppState>,
+    AdminUser(claims): AdminUser,
+    Path(id): Path<Uuid>,
+    Json(dto): Json<UpdateRoleDto>,
+) -> AppResult<impl IntoResponse> {
+    let svc = auth_service(&state);
+
+    match svc.update_role(claims.sub, id, &dto.role).await? {
+        Some(user) => Ok((StatusCode::OK, Json(UserResponse::from(user)))),
+        None => Err(AppError::NotFound(format!("User with id {} not found", id))),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/auth/users/{id}/deactivate",
+    params(
+        ("id" = Uuid, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "User deactivated", body = UserResponse),
+        (status = 400, description = "Admins cannot deactivate themselves"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin access required"),
+        (status = 404, description = "User not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Authentication"
+)]
+async fn deactivate_user(
+    State(state): State<AppState>,
+    AdminUser(claims): AdminUser,
+    Path(id): Path<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    let svc = auth_service(&state);
+
+    match svc.set_active(claims.sub, id, false).await? {
+        Some(user) => Ok((StatusCode::OK, Json(UserResponse::from(user)))),
+        None => Err(AppError::NotFound(format!("User with id {} not found", id))),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/auth/users/{id}/activate",
+    params(
+        ("id" = Uuid, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "User activated", body = UserResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin access required"),
+        (status = 404, description = "User not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Authentication"
+)]
+async fn activate_user(
+    State(state): State<AppState>,
+    AdminUser(claims): AdminUser,
+    Path(id): Path<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    let svc = auth_service(&state);
+
+    match svc.set_active(claims.sub, id, true).await? {
+        Some(user) => Ok((StatusCode::OK, Json(UserResponse::from(user)))),
+        None => Err(AppError::NotFound(format!("User with id {} not found", id))),
+    }
+}
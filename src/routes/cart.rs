@@ -1,14 +1,15 @@
-use axum::{Router, routing::post, extract::{State}, Json, response::IntoResponse, http::StatusCode};
+use axum::{Router, routing::{get, post}, extract::{State}, Json, response::IntoResponse, http::StatusCode};
 use crate::{middleware::auth::AuthUser, services::cart_service::CartService, state::AppState};
 use crate::middleware::validation::ValidatedJson;
 use crate::repository::CartRepository;
 use crate::errors::AppResult;
-use crate::dtos::AddToCartDto;
+use crate::dtos::{AddToCartDto, CartQuoteResponse};
 
 
 pub fn build_route() -> Router<AppState> {
     Router::new()
         .route("/add", post(add_to_cart))
+        .route("/quote", get(quote_cart))
 }
 
 #[utoipa::path(
@@ -17,7 +18,7 @@ pub fn build_route() -> Router<AppState> {
     request_body = AddToCartDto,
     responses(
         (status = 200, description = "Item added to cart"),
-        (status = 400, description = "Validation error"),
+        (status = 400, description = "Validation error, or the cart's quantity or product limit would be exceeded"),
         (status = 401, description = "Unauthorized"),
         (status = 500, description = "Internal server error")
     ),
@@ -32,8 +33,32 @@ async fn add_to_cart(
     ValidatedJson(dto): ValidatedJson<AddToCartDto>,
 ) -> AppResult<impl IntoResponse> {
     let repo = CartRepository::new(state.db.clone());
-    let svc = CartService::new(repo);
+    let svc = CartService::new(repo).with_limits(*state.cart_limits);
 
     let cart = svc.add_to_cart(claims.sub, dto).await?;
     Ok((StatusCode::OK, Json(cart)))
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/cart/quote",
+    responses(
+        (status = 200, description = "Cart priced with applicable promotions", body = CartQuoteResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Cart"
+)]
+async fn quote_cart(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+) -> AppResult<impl IntoResponse> {
+    let repo = CartRepository::new(state.db.clone());
+    let svc = CartService::new(repo).with_promotion_stacking(state.promotion_config.stacking);
+
+    let quote = svc.quote(claims.sub).await?;
+    Ok((StatusCode::OK, Json(quote)))
+}
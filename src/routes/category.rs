@@ -1,53 +1,66 @@
-use crate::dtos::{CategoryResponse, NewCategoryDto, UpdateCategoryDto};
+use crate::dtos::{BulkAssignDto, BulkAssignResponse, CategoryResponse, CategoryTreeNode, NewCategoryDto, ProductResponse, UpdateCategoryDto};
+use crate::errors::{AppError, AppResult};
+use crate::middleware::{auth::AdminUser, validation::ValidatedJson};
 use crate::repository::CategoryRepository;
-use crate::{services::category_service::CategoryService, state::AppState};
+use crate::{
+    routes::pagination::{page, page_headers, PageQuery},
+    services::category_service::CategoryService,
+    state::AppState,
+};
 use axum::{
     Json, Router,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
     routing::{get, post},
 };
 
+use serde::Deserialize;
 use serde_json::json;
+use utoipa::IntoParams;
 use uuid::Uuid;
 
 pub fn build_route() -> Router<AppState> {
     Router::new()
         .route("/", get(list_categories).post(create_category))
+        .route("/tree", get(category_tree))
         .route(
             "/{id}",
             get(get_category)
                 .put(update_category)
                 .delete(delete_category),
         )
-        .route("/{id}/assign/{product_id}", post(assign_product))
+        .route("/{id}/children", get(list_children))
+        .route("/{id}/breadcrumb", get(category_breadcrumb))
+        .route("/{id}/products", get(list_category_products))
+        .route("/{id}/assign/{product_id}", post(assign_product).delete(unassign_product))
+        .route("/{id}/assign-bulk", post(assign_products))
 }
 
 #[utoipa::path(
     get,
     path = "/api/category",
+    params(PageQuery),
     responses(
-        (status = 200, description = "List categories", body = [CategoryResponse]),
+        (status = 200, description = "List categories with their product counts", body = [CategoryResponse],
+            headers(
+                ("X-Total-Count" = i64, description = "Total number of categories"),
+                ("X-Page-Limit" = i64, description = "Page size used"),
+                ("X-Page-Offset" = i64, description = "Rows skipped before this page")
+            )),
         (status = 500, description = "Internal server error")
     ),
     tag = "Categories"
 )]
-async fn list_categories(State(state): State<AppState>) -> impl IntoResponse {
+async fn list_categories(State(state): State<AppState>, Query(query): Query<PageQuery>) -> AppResult<impl IntoResponse> {
     let repo = CategoryRepository::new(state.db.clone());
     let svc = CategoryService::new(repo);
 
-    match svc.list(50, 0).await {
-        Ok(cats) => {
-            let res: Vec<CategoryResponse> = cats.into_iter().map(|c| c.into()).collect();
-            (StatusCode::OK, Json(res)).into_response()
-        }
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": e.to_string()})),
-        )
-            .into_response(),
-    }
+    let (limit, offset) = page(query.limit, query.offset);
+    let (cats, total) = svc.list_with_counts(limit, offset).await?;
+    let res: Vec<CategoryResponse> = cats.into_iter().map(|c| c.into()).collect();
+
+    Ok((StatusCode::OK, page_headers(total, limit, offset), Json(res)))
 }
 
 #[utoipa::path(
@@ -56,30 +69,27 @@ async fn list_categories(State(state): State<AppState>) -> impl IntoResponse {
     request_body = NewCategoryDto,
     responses(
         (status = 201, description = "Category created", body = CategoryResponse),
-        (status = 400, description = "Validation error"),
+        (status = 400, description = "Validation error or unknown parent"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin access required"),
         (status = 500, description = "Internal server error")
     ),
+    security(
+        ("bearer_auth" = [])
+    ),
     tag = "Categories"
 )]
 async fn create_category(
     State(state): State<AppState>,
-    // AuthUser(claims): AuthUser,
+    AdminUser(_claims): AdminUser,
     Json(payload): Json<NewCategoryDto>,
 ) -> impl IntoResponse {
-    // if let Err(err) = require_admin(&claims) {
-    //     return err.into_response();
-    // }
-
     let repo = CategoryRepository::new(state.db.clone());
     let svc = CategoryService::new(repo);
 
     match svc.create(payload).await {
         Ok(c) => (StatusCode::CREATED, Json(CategoryResponse::from(c))).into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": e.to_string()})),
-        )
-            .into_response(),
+        Err(e) => e.into_response(),
     }
 }
 
@@ -109,6 +119,123 @@ async fn get_category(State(state): State<AppState>, Path(id): Path<Uuid>) -> im
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/category/tree",
+    responses(
+        (status = 200, description = "All categories nested under their parents", body = [CategoryTreeNode]),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Categories"
+)]
+async fn category_tree(State(state): State<AppState>) -> impl IntoResponse {
+    let repo = CategoryRepository::new(state.db.clone());
+    let svc = CategoryService::new(repo);
+
+    match svc.tree().await {
+        Ok(tree) => (StatusCode::OK, Json(tree)).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": e.to_string()})),
+        )
+            .into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/category/{id}/children",
+    params(("id" = Uuid, Path, description = "Parent category ID")),
+    responses(
+        (status = 200, description = "Direct subcategories", body = [CategoryResponse]),
+        (status = 404, description = "Not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Categories"
+)]
+async fn list_children(State(state): State<AppState>, Path(id): Path<Uuid>) -> impl IntoResponse {
+    let repo = CategoryRepository::new(state.db.clone());
+    let svc = CategoryService::new(repo);
+
+    match svc.get(id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return (StatusCode::NOT_FOUND, Json(json!({"error": "not found"}))).into_response(),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": e.to_string()})),
+            )
+                .into_response();
+        }
+    }
+
+    match svc.list_children(id).await {
+        Ok(cats) => {
+            let res: Vec<CategoryResponse> = cats.into_iter().map(|c| c.into()).collect();
+            (StatusCode::OK, Json(res)).into_response()
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": e.to_string()})),
+        )
+            .into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/category/{id}/breadcrumb",
+    params(("id" = Uuid, Path, description = "Category ID")),
+    responses(
+        (status = 200, description = "The category and its ancestors, from the root down to the category", body = [CategoryResponse]),
+        (status = 404, description = "Category not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Categories"
+)]
+async fn category_breadcrumb(State(state): State<AppState>, Path(id): Path<Uuid>) -> AppResult<impl IntoResponse> {
+    let repo = CategoryRepository::new(state.db.clone());
+    let svc = CategoryService::new(repo);
+
+    let path: Vec<CategoryResponse> = svc.breadcrumb(id).await?.into_iter().map(|c| c.into()).collect();
+    Ok((StatusCode::OK, Json(path)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/category/{id}/products",
+    params(
+        ("id" = Uuid, Path, description = "Category ID"),
+        PageQuery
+    ),
+    responses(
+        (status = 200, description = "Products in the category, by name", body = [ProductResponse],
+            headers(
+                ("X-Total-Count" = i64, description = "Total number of products in the category"),
+                ("X-Page-Limit" = i64, description = "Page size used"),
+                ("X-Page-Offset" = i64, description = "Rows skipped before this page")
+            )),
+        (status = 404, description = "Category not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Categories"
+)]
+async fn list_category_products(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<PageQuery>,
+) -> AppResult<impl IntoResponse> {
+    let repo = CategoryRepository::new(state.db.clone());
+    let svc = CategoryService::new(repo);
+
+    let (limit, offset) = page(query.limit, query.offset);
+
+    let (products, total) = svc.list_products(id, limit, offset).await?;
+    let res: Vec<ProductResponse> = products.into_iter().map(|p| p.into()).collect();
+
+    Ok((StatusCode::OK, page_headers(total, limit, offset), Json(res)))
+}
+
 #[utoipa::path(
     put,
     path = "/api/category/{id}",
@@ -116,67 +243,71 @@ async fn get_category(State(state): State<AppState>, Path(id): Path<Uuid>) -> im
     request_body = UpdateCategoryDto,
     responses(
         (status = 200, description = "Category updated", body = CategoryResponse),
+        (status = 400, description = "Unknown parent or parent would create a cycle"),
         (status = 404, description = "Not found"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin access required"),
         (status = 500, description = "Internal server error")
     ),
+    security(
+        ("bearer_auth" = [])
+    ),
     tag = "Categories"
 )]
 async fn update_category(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
-    // AuthUser(claims): AuthUser,
+    AdminUser(_claims): AdminUser,
     Json(payload): Json<UpdateCategoryDto>,
 ) -> impl IntoResponse {
-    // if let Err(err) = require_admin(&claims) {
-    //     return err.into_response();
-    // }
-
     let repo = CategoryRepository::new(state.db.clone());
     let svc = CategoryService::new(repo);
 
     match svc.update(id, payload).await {
         Ok(Some(c)) => (StatusCode::OK, Json(CategoryResponse::from(c))).into_response(),
         Ok(None) => (StatusCode::NOT_FOUND, Json(json!({"error": "not found"}))).into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": e.to_string()})),
-        )
-            .into_response(),
+        Err(e) => e.into_response(),
     }
 }
 
+#[derive(Deserialize, IntoParams)]
+struct DeleteCategoryQuery {
+    /// Category that takes over this category's products before it is deleted
+    reassign_to: Option<Uuid>,
+}
+
 #[utoipa::path(
     delete,
     path = "/api/category/{id}",
-    params(("id" = Uuid, Path, description = "Category ID")),
+    params(("id" = Uuid, Path, description = "Category ID"), DeleteCategoryQuery),
     responses(
         (status = 204, description = "Category deleted"),
+        (status = 400, description = "Reassignment category missing or the same as the deleted one"),
         (status = 404, description = "Not found"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin access required"),
+        (status = 409, description = "Category still has products and no reassign_to was given"),
         (status = 500, description = "Internal server error")
     ),
+    security(
+        ("bearer_auth" = [])
+    ),
     tag = "Categories"
 )]
 async fn delete_category(
     State(state): State<AppState>,
-    // AuthUser(claims): AuthUser,
+    AdminUser(_claims): AdminUser,
     Path(id): Path<Uuid>,
-) -> impl IntoResponse {
-    // if let Err(err) = require_admin(&claims) {
-    //     return err.into_response();
-    // }
-
+    Query(query): Query<DeleteCategoryQuery>,
+) -> AppResult<impl IntoResponse> {
     let repo = CategoryRepository::new(state.db.clone());
     let svc = CategoryService::new(repo);
 
-    match svc.delete(id).await {
-        Ok(true) => (StatusCode::NO_CONTENT).into_response(),
-        Ok(false) => (StatusCode::NOT_FOUND, Json(json!({"error": "not found"}))).into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": e.to_string()})),
-        )
-            .into_response(),
+    if !svc.delete(id, query.reassign_to).await? {
+        return Err(AppError::NotFound(format!("Category with id {} not found", id)));
     }
+
+    Ok(StatusCode::NO_CONTENT)
 }
 
 #[utoipa::path(
@@ -185,25 +316,86 @@ async fn delete_category(
     params(("id" = Uuid, Path), ("product_id" = Uuid, Path)),
     responses(
         (status = 204, description = "Product assigned to category"),
-        (status = 404, description = "Not found"),
+        (status = 404, description = "Category or product not found"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin access required"),
         (status = 500, description = "Internal server error")
     ),
+    security(
+        ("bearer_auth" = [])
+    ),
     tag = "Categories"
 )]
 async fn assign_product(
     State(state): State<AppState>,
-    // AuthUser(claims): AuthUser,
+    AdminUser(_claims): AdminUser,
     Path((id, product_id)): Path<(Uuid, Uuid)>,
-) -> impl IntoResponse {
-    // if let Err(err) = require_admin(&claims) {
-    //     return err.into_response();
-    // }
+) -> AppResult<impl IntoResponse> {
+    let repo = CategoryRepository::new(state.db.clone());
+    let svc = CategoryService::new(repo);
+
+    svc.assign_product(id, product_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/category/{id}/assign-bulk",
+    params(("id" = Uuid, Path, description = "Category ID")),
+    request_body = BulkAssignDto,
+    responses(
+        (status = 200, description = "Products added to the category", body = BulkAssignResponse),
+        (status = 400, description = "No product ids, or more than 100"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin access required"),
+        (status = 404, description = "Category not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Categories"
+)]
+async fn assign_products(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    Path(id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<BulkAssignDto>,
+) -> AppResult<impl IntoResponse> {
+    let repo = CategoryRepository::new(state.db.clone());
+    let svc = CategoryService::new(repo);
 
+    let linked = svc.assign_products(id, &payload.product_ids).await?;
+    Ok((StatusCode::OK, Json(BulkAssignResponse { linked })))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/category/{id}/assign/{product_id}",
+    params(("id" = Uuid, Path), ("product_id" = Uuid, Path)),
+    responses(
+        (status = 204, description = "Product removed from category"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin access required"),
+        (status = 404, description = "Product is not in this category"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Categories"
+)]
+async fn unassign_product(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    Path((id, product_id)): Path<(Uuid, Uuid)>,
+) -> impl IntoResponse {
     let repo = CategoryRepository::new(state.db.clone());
     let svc = CategoryService::new(repo);
 
-    match svc.assign_product(id, product_id).await {
-        Ok(_) => (StatusCode::NO_CONTENT).into_response(),
+    match svc.unassign_product(id, product_id).await {
+        Ok(true) => (StatusCode::NO_CONTENT).into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, Json(json!({"error": "not found"}))).into_response(),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(json!({"error": e.to_string()})),
@@ -0,0 +1,187 @@
+use crate::{
+    dtos::CouponDto,
+    errors::{AppError, AppResult},
+    middleware::auth::AdminUser,
+    middleware::validation::ValidatedJson,
+    model::coupon::Coupon,
+    repository::CouponRepository,
+    routes::pagination::{page, page_headers},
+    services::coupon_service::CouponService,
+    state::AppState,
+};
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::get,
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct CouponListQuery {
+    /// Page size, defaults to 50 and is capped at 100
+    pub limit: Option<i64>,
+    /// Number of coupons to skip, defaults to 0
+    pub offset: Option<i64>,
+}
+
+pub fn build_route() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_coupons).post(create_coupon))
+        .route("/{id}", get(get_coupon).put(update_coupon).delete(delete_coupon))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/coupon",
+    params(CouponListQuery),
+    responses(
+        (status = 200, description = "Coupons, newest first", body = [Coupon],
+            headers(
+                ("X-Total-Count" = i64, description = "Total number of coupons"),
+                ("X-Page-Limit" = i64, description = "Page size used"),
+                ("X-Page-Offset" = i64, description = "Rows skipped before this page")
+            )),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin required")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Coupons"
+)]
+async fn list_coupons(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    Query(query): Query<CouponListQuery>,
+) -> AppResult<impl IntoResponse> {
+    let svc = CouponService::new(CouponRepository::new(state.db.clone()));
+
+    let (limit, offset) = page(query.limit, query.offset);
+
+    let (coupons, total) = svc.list(limit, offset).await?;
+    Ok((StatusCode::OK, page_headers(total, limit, offset), Json(coupons)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/coupon",
+    request_body = CouponDto,
+    responses(
+        (status = 201, description = "Coupon created", body = Coupon),
+        (status = 400, description = "Validation error or code already in use"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin required")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Coupons"
+)]
+async fn create_coupon(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    ValidatedJson(payload): ValidatedJson<CouponDto>,
+) -> AppResult<impl IntoResponse> {
+    let svc = CouponService::new(CouponRepository::new(state.db.clone()));
+
+    let coupon = svc.create(payload).await?;
+    Ok((StatusCode::CREATED, Json(coupon)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/coupon/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Coupon ID")
+    ),
+    responses(
+        (status = 200, description = "Coupon found", body = Coupon),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin required"),
+        (status = 404, description = "Coupon not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Coupons"
+)]
+async fn get_coupon(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    Path(id): Path<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    let svc = CouponService::new(CouponRepository::new(state.db.clone()));
+
+    match svc.get(id).await? {
+        Some(coupon) => Ok((StatusCode::OK, Json(coupon))),
+        None => Err(AppError::NotFound(format!("Coupon with id {} not found", id))),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/coupon/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Coupon ID")
+    ),
+    request_body = CouponDto,
+    responses(
+        (status = 200, description = "Coupon updated", body = Coupon),
+        (status = 400, description = "Validation error or code already in use"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin required"),
+        (status = 404, description = "Coupon not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Coupons"
+)]
+async fn update_coupon(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    Path(id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<CouponDto>,
+) -> AppResult<impl IntoResponse> {
+    let svc = CouponService::new(CouponRepository::new(state.db.clone()));
+
+    match svc.update(id, payload).await? {
+        Some(coupon) => Ok((StatusCode::OK, Json(coupon))),
+        None => Err(AppError::NotFound(format!("Coupon with id {} not found", id))),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/coupon/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Coupon ID")
+    ),
+    responses(
+        (status = 204, description = "Coupon deleted"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin required"),
+        (status = 404, description = "Coupon not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Coupons"
+)]
+async fn delete_coupon(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    Path(id): Path<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    let svc = CouponService::new(CouponRepository::new(state.db.clone()));
+
+    if svc.delete(id).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound(format!("Coupon with id {} not found", id)))
+    }
+}
@@ -0,0 +1,68 @@
+use crate::state::AppState;
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use serde_json::json;
+use std::time::Duration;
+
+/// Longest the readiness probe waits on the database before reporting it unavailable
+const READY_CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+pub fn build_route() -> Router<AppState> {
+    Router::new()
+        .route("/health", get(liveness))
+        .route("/health/ready", get(readiness))
+}
+
+#[utoipa::path(
+    get,
+    path = "/health",
+    responses(
+        (status = 200, description = "The server is running", body = String, content_type = "text/plain")
+    ),
+    tag = "Health"
+)]
+async fn liveness() -> &'static str {
+    "OK"
+}
+
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    responses(
+        (status = 200, description = "Database reachable; includes connection pool stats"),
+        (status = 503, description = "Database unreachable")
+    ),
+    tag = "Health"
+)]
+async fn readiness(State(state): State<AppState>) -> impl IntoResponse {
+    let check = sqlx::query("SELECT 1").execute(&state.db);
+    let connected = match tokio::time::timeout(READY_CHECK_TIMEOUT, check).await {
+        Ok(Ok(_)) => true,
+        Ok(Err(e)) => {
+            tracing::warn!("Readiness check failed: {}", e);
+            false
+        }
+        Err(_) => {
+            tracing::warn!("Readiness check timed out after {:?}", READY_CHECK_TIMEOUT);
+            false
+        }
+    };
+
+    let status = if connected { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (
+        status,
+        Json(json!({
+            "status": if connected { "ready" } else { "unavailable" },
+            "database": {
+                "connected": connected,
+                "pool_size": state.db.size(),
+                "idle_connections": state.db.num_idle(),
+            }
+        })),
+    )
+}
@@ -1,17 +1,17 @@
 use crate::{
     errors::AppResult,
-    middleware::auth::{AuthUser, require_admin},
-    services::image_service::ImageService,
+    middleware::auth::AdminUser,
+    services::image_service::{extract_public_id_from_url, ImageService},
     state::AppState,
 };
 use axum::{
-    extract::{Multipart, State},
+    extract::{Multipart, Query, State},
     http::StatusCode,
     response::IntoResponse,
-    routing::post,
+    routing::{delete, post},
     Json, Router,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 
 #[derive(Debug, Serialize, utoipa::ToSchema)]
@@ -19,8 +19,15 @@ pub struct ImageUploadResponse {
     pub image_url: String,
 }
 
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct DeleteImageQuery {
+    /// Cloudinary public id or full image URL
+    pub public_id: String,
+}
+
 pub fn build_route() -> Router<AppState> {
     Router::new()
+        .route("/", delete(delete_image))
         .route("/upload", post(upload_image))
 }
 
@@ -33,17 +40,10 @@ pub fn build_route() -> Router<AppState> {
 )]
 async fn upload_image(
     State(state): State<AppState>,
-    AuthUser(claims): AuthUser,
+    AdminUser(_claims): AdminUser,
     multipart: Multipart,
 ) -> AppResult<impl IntoResponse> {
-    // Require admin privileges for image uploads
-    require_admin(&claims)?;
-
-    let image_service = ImageService::new(
-        &state.cloudinary_cloud_name,
-        &state.cloudinary_api_key,
-        &state.cloudinary_api_secret,
-    )?;
+    let image_service = ImageService::new(state.http.clone(), &state.cloudinary_config)?;
 
     let image_url = image_service.upload_product_image(multipart).await?;
 
@@ -51,3 +51,29 @@ async fn upload_image(
     
     Ok((StatusCode::CREATED, Json(response)))
 }
+
+#[utoipa::path(
+    delete,
+    path = "/api/image",
+    params(DeleteImageQuery),
+    responses(
+        (status = 204, description = "Image deleted"),
+        (status = 400, description = "Image deletion failed"),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Images"
+)]
+async fn delete_image(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    Query(query): Query<DeleteImageQuery>,
+) -> AppResult<impl IntoResponse> {
+    let image_service = ImageService::new(state.http.clone(), &state.cloudinary_config)?;
+
+    // Accept either a bare public id or the full Cloudinary URL
+    let public_id = extract_public_id_from_url(&query.public_id).unwrap_or(query.public_id);
+    image_service.delete_image(&public_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,23 +1,55 @@
 use crate::{
-    middleware::auth::{AuthUser, require_admin},
-    model::stock::{StockUpdateRequest, StockReservationRequest, InventoryChangeType, InventoryReport},
-    repository::StockRepository,
-    state::AppState,
+    errors::{AppError, AppResult},
+    middleware::{auth::{AdminUser, AuthUser}, validation::{field_messages, ValidatedJson}},
+    model::stock::{BatchStockRequest, InventoryChangeType, StockUpdateRequest, StockUpdateMode, StockReservationRequest, CartReservationRequest, InventoryReport, ProductReservations, StockLevel},
+    repository::{CartRepository, StockRepository},
+    routes::pagination::{page, page_headers, PageQuery},
+    services::stock_alert_service::LowStockNotifier,
+    state::{AppState, ReservationConfig},
 };
 use axum::{
+    body::Body,
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header, StatusCode},
     response::IntoResponse,
     routing::{get, post, put},
     Json, Router,
 };
+use chrono::{DateTime, Utc};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use std::collections::HashMap;
 use serde::{Deserialize};
 use serde_json::json;
 use uuid::Uuid;
+use validator::Validate;
 
-#[derive(Deserialize)]
-struct PaginationQuery {
+/// Products fetched per query while streaming the CSV export
+const EXPORT_BATCH_SIZE: i64 = 500;
+const EXPORT_CSV_HEADER: &str = "product_id,product_name,current_stock,available_stock,reserved,low_stock\n";
+
+#[derive(Deserialize, utoipa::IntoParams)]
+struct ReservationListQuery {
+    /// Only reservations for this product
+    product_id: Option<Uuid>,
+    /// `true` for expired reservations only, `false` for active ones only
+    expired: Option<bool>,
+    /// Page size, defaults to 50 and is capped at 100
+    limit: Option<i64>,
+    /// Number of reservations to skip, defaults to 0
+    offset: Option<i64>,
+}
+
+#[derive(Deserialize, utoipa::IntoParams)]
+struct HistoryQuery {
+    /// Only entries of this type: `stock_in`, `stock_out`, `reserved`, `unreserved` or `sold`
+    change_type: Option<String>,
+    /// Start of the range (inclusive), RFC 3339; unbounded when omitted
+    from: Option<DateTime<Utc>>,
+    /// End of the range (exclusive), RFC 3339; unbounded when omitted
+    to: Option<DateTime<Utc>>,
+    /// Page size, defaults to 50 and is capped at 100
     limit: Option<i64>,
+    /// Number of entries to skip, defaults to 0
     offset: Option<i64>,
 }
 
@@ -25,12 +57,18 @@ pub fn build_route() -> Router<AppState> {
     Router::new()
         .route("/products/{product_id}/stock", get(get_available_stock))
         .route("/products/{product_id}/stock", put(update_stock))
+        .route("/stock/batch", post(get_available_stock_batch))
         .route("/products/{product_id}/history", get(get_inventory_history))
+        .route("/products/{product_id}/reservations", get(list_product_reservations))
         .route("/reservations", post(create_reservation))
+        .route("/reservations/cart", post(reserve_cart))
+        .route("/reservations/all", get(list_all_reservations))
         .route("/reservations/{reservation_id}/cancel", post(cancel_reservation))
         .route("/cleanup-expired", post(cleanup_expired_reservations))
         .route("/alerts", get(get_low_stock_alerts))
         .route("/report", get(get_inventory_report))
+        .route("/reorder-report", get(get_reorder_report))
+        .route("/export", get(export_inventory))
 }
 
 #[utoipa::path(
@@ -47,29 +85,46 @@ pub fn build_route() -> Router<AppState> {
 async fn get_available_stock(
     State(state): State<AppState>,
     Path(product_id): Path<Uuid>,
-) -> impl IntoResponse {
+) -> AppResult<impl IntoResponse> {
     let repo = StockRepository::new(state.db.clone());
 
-    match repo.get_available_stock(product_id).await {
-        Ok(Some(available_stock)) => (
-            StatusCode::OK,
-            Json(json!({
-                "product_id": product_id,
-                "available_stock": available_stock
-            })),
-        )
-            .into_response(),
-        Ok(None) => (
-            StatusCode::NOT_FOUND,
-            Json(json!({"error": "Product not found"})),
-        )
-            .into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("Database error: {}", e)})),
-        )
-            .into_response(),
-    }
+    let available_stock = repo.get_available_stock(product_id).await?
+        .ok_or_else(|| AppError::NotFound("Product not found".into()))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "product_id": product_id,
+            "available_stock": available_stock
+        })),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/inventory/stock/batch",
+    request_body = BatchStockRequest,
+    responses(
+        (status = 200, description = "Available stock keyed by product id; null for unknown products", body = HashMap<Uuid, Option<i32>>),
+        (status = 400, description = "No product ids, or more than 100")
+    ),
+    tag = "Inventory"
+)]
+async fn get_available_stock_batch(
+    State(state): State<AppState>,
+    ValidatedJson(request): ValidatedJson<BatchStockRequest>,
+) -> AppResult<impl IntoResponse> {
+    let mut found = StockRepository::new(state.db.clone())
+        .get_available_stock_batch(&request.product_ids)
+        .await?;
+
+    let stock: HashMap<Uuid, Option<i32>> = request
+        .product_ids
+        .into_iter()
+        .map(|id| (id, found.remove(&id)))
+        .collect();
+
+    Ok((StatusCode::OK, Json(stock)))
 }
 
 #[utoipa::path(
@@ -79,6 +134,7 @@ async fn get_available_stock(
     request_body = StockUpdateRequest,
     responses(
         (status = 200, description = "Stock updated"),
+        (status = 400, description = "Negative stock"),
         (status = 404, description = "Product not found"),
         (status = 401, description = "Unauthorized"),
         (status = 403, description = "Admin required"),
@@ -89,58 +145,54 @@ async fn get_available_stock(
 )]
 async fn update_stock(
     State(state): State<AppState>,
-    AuthUser(claims): AuthUser,
+    AdminUser(claims): AdminUser,
     Path(product_id): Path<Uuid>,
     Json(request): Json<StockUpdateRequest>,
-) -> impl IntoResponse {
-    // Require admin permission for stock updates
-    if let Err(err) = require_admin(&claims) {
-        return err.into_response();
-    }
-
+) -> AppResult<impl IntoResponse> {
     let repo = StockRepository::new(state.db.clone());
-    
-    let change_type = if request.quantity > 0 {
-        InventoryChangeType::StockIn
-    } else {
-        InventoryChangeType::StockOut
+
+    let new_stock = match request.mode {
+        StockUpdateMode::Set => {
+            if request.quantity < 0 {
+                return Err(AppError::Validation("Stock cannot be negative".into()));
+            }
+            repo.set_stock(product_id, request.quantity, None, request.notes, Some(claims.sub)).await?
+        }
+        StockUpdateMode::Adjust => {
+            let adjusted = repo.adjust_stock(product_id, request.quantity, None, request.notes, Some(claims.sub)).await?;
+            if adjusted.is_none() && repo.get_available_stock(product_id).await?.is_some() {
+                return Err(AppError::Validation("Adjustment would take stock below zero".into()));
+            }
+            adjusted
+        }
     };
 
-    match repo.update_stock(
-        product_id,
-        request.quantity,
-        change_type,
-        None,
-        request.notes,
-    ).await {
-        Ok(true) => (
-            StatusCode::OK,
-            Json(json!({
-                "message": "Stock updated successfully",
-                "product_id": product_id,
-                "new_stock": request.quantity
-            })),
-        )
-            .into_response(),
-        Ok(false) => (
-            StatusCode::NOT_FOUND,
-            Json(json!({"error": "Product not found"})),
-        )
-            .into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("Database error: {}", e)})),
-        )
-            .into_response(),
-    }
+    let new_stock = new_stock.ok_or_else(|| AppError::NotFound("Product not found".into()))?;
+    LowStockNotifier::new(repo, state.http.clone(), &state.inventory_config, &state.low_stock_webhook_config)
+        .check(vec![product_id]);
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "message": "Stock updated successfully",
+            "product_id": product_id,
+            "new_stock": new_stock
+        })),
+    ))
 }
 
 #[utoipa::path(
     get,
     path = "/api/inventory/products/{product_id}/history",
-    params(("product_id" = Uuid, Path)),
+    params(("product_id" = Uuid, Path), HistoryQuery),
     responses(
-        (status = 200, description = "Inventory history"),
+        (status = 200, description = "Inventory history", body = [crate::model::stock::InventoryLog],
+            headers(
+                ("X-Total-Count" = i64, description = "Total number of matching entries"),
+                ("X-Page-Limit" = i64, description = "Page size used"),
+                ("X-Page-Offset" = i64, description = "Rows skipped before this page")
+            )),
+        (status = 400, description = "Unknown change_type, or from is later than to"),
         (status = 401, description = "Unauthorized"),
         (status = 403, description = "Admin required")
     ),
@@ -149,26 +201,80 @@ async fn update_stock(
 )]
 async fn get_inventory_history(
     State(state): State<AppState>,
-    AuthUser(claims): AuthUser,
+    AdminUser(_claims): AdminUser,
     Path(product_id): Path<Uuid>,
-    Query(pagination): Query<PaginationQuery>,
-) -> impl IntoResponse {
-    // Require admin permission for inventory history
-    if let Err(err) = require_admin(&claims) {
-        return err.into_response();
+    Query(query): Query<HistoryQuery>,
+) -> AppResult<impl IntoResponse> {
+    let repo = StockRepository::new(state.db.clone());
+    let (limit, offset) = page(query.limit, query.offset);
+
+    let change_type = query
+        .change_type
+        .as_deref()
+        .map(|value| {
+            InventoryChangeType::from_str(value).ok_or_else(|| {
+                AppError::Validation(format!(
+                    "Unknown change_type '{}'; expected stock_in, stock_out, reserved, unreserved or sold",
+                    value
+                ))
+            })
+        })
+        .transpose()?;
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return Err(AppError::Validation("from must not be later than to".into()));
+        }
     }
 
-    let repo = StockRepository::new(state.db.clone());
-    let limit = pagination.limit.unwrap_or(50);
-    let offset = pagination.offset.unwrap_or(0);
-
-    match repo.get_inventory_history(product_id, limit, offset).await {
-        Ok(history) => (StatusCode::OK, Json(history)).into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("Database error: {}", e)})),
-        )
-            .into_response(),
+    let history = repo
+        .get_inventory_history(product_id, change_type.clone(), query.from, query.to, limit, offset)
+        .await?;
+    let total = repo
+        .count_inventory_history(product_id, change_type, query.from, query.to)
+        .await?;
+    Ok((StatusCode::OK, page_headers(total, limit, offset), Json(history)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/inventory/products/{product_id}/reservations",
+    params(("product_id" = Uuid, Path)),
+    responses(
+        (status = 200, description = "Active reservations on the product and their total quantity", body = ProductReservations),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin required")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Inventory"
+)]
+async fn list_product_reservations(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    Path(product_id): Path<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    let reservations = StockRepository::new(state.db.clone()).list_reservations(product_id).await?;
+    let total_reserved = reservations.iter().map(|r| r.quantity).sum();
+
+    Ok((
+        StatusCode::OK,
+        Json(ProductReservations {
+            product_id,
+            total_reserved,
+            reservations,
+        }),
+    ))
+}
+
+/// Applies the configured default to an omitted expiry. Longer holds than the maximum are
+/// rejected rather than silently shortened, so the client knows when its hold ends.
+fn reservation_expiry(config: &ReservationConfig, requested: Option<i32>) -> AppResult<i32> {
+    match requested {
+        None => Ok(config.default_minutes),
+        Some(minutes) if minutes > config.max_minutes => Err(AppError::InvalidFields(HashMap::from([(
+            "expires_in_minutes".to_string(),
+            vec![format!("Expiry cannot exceed {} minutes", config.max_minutes)],
+        )]))),
+        Some(minutes) => Ok(minutes),
     }
 }
 
@@ -178,7 +284,8 @@ async fn get_inventory_history(
     request_body = StockReservationRequest,
     responses(
         (status = 201, description = "Reservation created"),
-        (status = 400, description = "Insufficient stock"),
+        (status = 400, description = "Quantity below 1, or expiry below 1 or above RESERVATION_MAX_MINUTES"),
+        (status = 409, description = "More than the available stock"),
         (status = 500, description = "Internal server error")
     ),
     security(("bearer_auth" = [])),
@@ -187,33 +294,111 @@ async fn get_inventory_history(
 async fn create_reservation(
     State(state): State<AppState>,
     AuthUser(claims): AuthUser,
-    Json(request): Json<StockReservationRequest>,
-) -> impl IntoResponse {
+    ValidatedJson(request): ValidatedJson<StockReservationRequest>,
+) -> AppResult<impl IntoResponse> {
     let repo = StockRepository::new(state.db.clone());
-    let expires_in_minutes = request.expires_in_minutes.unwrap_or(30);
-    
-    // For this example, we'll use the user ID as cart ID
-    // In a real implementation, you'd get the cart ID from the request or user session
-    let cart_id = Uuid::parse_str(&claims.sub.to_string()).unwrap_or_else(|_| Uuid::new_v4());
+    let expires_in_minutes = reservation_expiry(&state.reservation_config, request.expires_in_minutes)?;
+
+    // Reservations hang off the user's cart so checkout and payment can find them
+    let cart = CartRepository::new(state.db.clone()).get_or_create_cart(claims.sub).await?;
 
-    match repo.create_reservation(
+    let reservation = repo.create_reservation(
         request.product_id,
-        cart_id,
+        cart.id,
         request.quantity,
         expires_in_minutes,
-    ).await {
-        Ok(Some(reservation)) => (StatusCode::CREATED, Json(reservation)).into_response(),
-        Ok(None) => (
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "Insufficient stock available"})),
-        )
-            .into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("Database error: {}", e)})),
-        )
-            .into_response(),
+        Some(claims.sub),
+    ).await?
+        .ok_or_else(|| AppError::InsufficientStock(format!("Not enough stock to reserve product {}", request.product_id)))?;
+
+    Ok((StatusCode::CREATED, Json(reservation)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/inventory/reservations/cart",
+    request_body(content = Option<CartReservationRequest>, description = "Optional; holds last RESERVATION_DEFAULT_MINUTES by default"),
+    responses(
+        (status = 201, description = "Every tracked cart item reserved; earlier cart holds are replaced", body = [crate::model::stock::StockReservation]),
+        (status = 400, description = "Cart is empty, or expiry below 1 or above RESERVATION_MAX_MINUTES"),
+        (status = 401, description = "Unauthorized"),
+        (status = 409, description = "An item lacks stock; nothing was reserved"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Inventory"
+)]
+async fn reserve_cart(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    request: Option<Json<CartReservationRequest>>,
+) -> AppResult<impl IntoResponse> {
+    let requested = match request {
+        Some(Json(request)) => {
+            request.validate().map_err(|e| AppError::InvalidFields(field_messages(&e)))?;
+            request.expires_in_minutes
+        }
+        None => None,
+    };
+    let expires_in_minutes = reservation_expiry(&state.reservation_config, requested)?;
+
+    let carts = CartRepository::new(state.db.clone());
+    let cart = carts.get_or_create_cart(claims.sub).await?;
+    let items: Vec<(Uuid, i32)> = carts
+        .get_cart_items(cart.id)
+        .await?
+        .into_iter()
+        .map(|item| (item.product_id, item.quantity))
+        .collect();
+    if items.is_empty() {
+        return Err(AppError::Validation("Cart is empty".into()));
     }
+
+    let reservations = StockRepository::new(state.db.clone())
+        .reserve_cart(cart.id, &items, expires_in_minutes, Some(claims.sub))
+        .await?
+        .map_err(|product_id| {
+            AppError::InsufficientStock(format!("Not enough stock to reserve product {}", product_id))
+        })?;
+
+    Ok((StatusCode::CREATED, Json(reservations)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/inventory/reservations/all",
+    params(ReservationListQuery),
+    responses(
+        (status = 200, description = "Reservations across all carts", body = [crate::model::stock::StockReservation],
+            headers(
+                ("X-Total-Count" = i64, description = "Total number of matching reservations"),
+                ("X-Page-Limit" = i64, description = "Page size used"),
+                ("X-Page-Offset" = i64, description = "Rows skipped before this page")
+            )),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Inventory"
+)]
+async fn list_all_reservations(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    Query(query): Query<ReservationListQuery>,
+) -> AppResult<impl IntoResponse> {
+    let repo = StockRepository::new(state.db.clone());
+    let (limit, offset) = page(query.limit, query.offset);
+
+    let reservations = repo
+        .list_all_reservations(query.product_id, query.expired, limit, offset)
+        .await?;
+    let total = repo.count_all_reservations(query.product_id, query.expired).await?;
+
+    Ok((
+        StatusCode::OK,
+        page_headers(total, limit, offset),
+        Json(reservations),
+    ))
 }
 
 #[utoipa::path(
@@ -229,28 +414,19 @@ async fn create_reservation(
 )]
 async fn cancel_reservation(
     State(state): State<AppState>,
-    AuthUser(_claims): AuthUser,
+    AuthUser(claims): AuthUser,
     Path(reservation_id): Path<Uuid>,
-) -> impl IntoResponse {
+) -> AppResult<impl IntoResponse> {
     let repo = StockRepository::new(state.db.clone());
 
-    match repo.cancel_reservation(reservation_id).await {
-        Ok(true) => (
-            StatusCode::OK,
-            Json(json!({"message": "Reservation cancelled successfully"})),
-        )
-            .into_response(),
-        Ok(false) => (
-            StatusCode::NOT_FOUND,
-            Json(json!({"error": "Reservation not found"})),
-        )
-            .into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("Database error: {}", e)})),
-        )
-            .into_response(),
+    if !repo.cancel_reservation(reservation_id, Some(claims.sub)).await? {
+        return Err(AppError::NotFound("Reservation not found".into()));
     }
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({"message": "Reservation cancelled successfully"})),
+    ))
 }
 
 #[utoipa::path(
@@ -262,58 +438,67 @@ async fn cancel_reservation(
 )]
 async fn cleanup_expired_reservations(
     State(state): State<AppState>,
-    AuthUser(claims): AuthUser,
-) -> impl IntoResponse {
+    AdminUser(claims): AdminUser,
+) -> AppResult<impl IntoResponse> {
     // This could be a scheduled job, but we'll expose it as an endpoint for now
-    if let Err(err) = require_admin(&claims) {
-        return err.into_response();
-    }
-
     let repo = StockRepository::new(state.db.clone());
 
-    match repo.cleanup_expired_reservations().await {
-        Ok(count) => (
-            StatusCode::OK,
-            Json(json!({
-                "message": "Expired reservations cleaned up",
-                "count": count
-            })),
-        )
-            .into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("Database error: {}", e)})),
-        )
-            .into_response(),
-    }
+    let count = repo.cleanup_expired_reservations(Some(claims.sub)).await?;
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "message": "Expired reservations cleaned up",
+            "count": count
+        })),
+    ))
 }
 
 #[utoipa::path(
     get,
     path = "/api/inventory/alerts",
-    responses((status = 200, description = "Low stock alerts", body = [crate::model::stock::LowStockAlert])),
+    params(PageQuery),
+    responses((status = 200, description = "Low stock alerts, least available stock first", body = [crate::model::stock::LowStockAlert],
+        headers(
+            ("X-Total-Count" = i64, description = "Total number of products at or below their threshold"),
+            ("X-Page-Limit" = i64, description = "Page size used"),
+            ("X-Page-Offset" = i64, description = "Rows skipped before this page")
+        ))),
     security(("bearer_auth" = [])),
     tag = "Inventory"
 )]
 async fn get_low_stock_alerts(
     State(state): State<AppState>,
-    AuthUser(claims): AuthUser,
-) -> impl IntoResponse {
-    // Require admin permission for stock alerts
-    if let Err(err) = require_admin(&claims) {
-        return err.into_response();
-    }
+    AdminUser(_claims): AdminUser,
+    Query(query): Query<PageQuery>,
+) -> AppResult<impl IntoResponse> {
+    let repo = StockRepository::new(state.db.clone());
+    let (limit, offset) = page(query.limit, query.offset);
+    let default_threshold = state.inventory_config.default_low_stock_threshold;
 
+    let alerts = repo.get_low_stock_alerts(default_threshold, Some(limit), offset).await?;
+    let total = repo.count_low_stock_alerts(default_threshold).await?;
+    Ok((StatusCode::OK, page_headers(total, limit, offset), Json(alerts)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/inventory/reorder-report",
+    responses(
+        (status = 200, description = "Products at or below their low-stock threshold with suggested reorder quantities", body = [crate::model::stock::ReorderSuggestion]),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin required")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Inventory"
+)]
+async fn get_reorder_report(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+) -> AppResult<impl IntoResponse> {
     let repo = StockRepository::new(state.db.clone());
 
-    match repo.get_low_stock_alerts().await {
-        Ok(alerts) => (StatusCode::OK, Json(alerts)).into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("Database error: {}", e)})),
-        )
-            .into_response(),
-    }
+    let report = repo.get_reorder_report(state.inventory_config.default_low_stock_threshold).await?;
+    Ok((StatusCode::OK, Json(report)))
 }
 
 #[utoipa::path(
@@ -325,39 +510,91 @@ async fn get_low_stock_alerts(
 )]
 async fn get_inventory_report(
     State(state): State<AppState>,
-    AuthUser(claims): AuthUser,
-) -> impl IntoResponse {
-    // Require admin permission for inventory reports
-    if let Err(err) = require_admin(&claims) {
-        return err.into_response();
-    }
-
+    AdminUser(_claims): AdminUser,
+) -> AppResult<impl IntoResponse> {
     let repo = StockRepository::new(state.db.clone());
 
     // Get low stock alerts for the report
-    let alerts = match repo.get_low_stock_alerts().await {
-        Ok(alerts) => alerts,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Database error: {}", e)})),
-            )
-                .into_response();
-        }
-    };
+    let alerts = repo.get_low_stock_alerts(state.inventory_config.default_low_stock_threshold, None, 0).await?;
 
-    // Create a basic inventory report
     let low_stock_count = alerts.len() as i32;
     let out_of_stock_count = alerts.iter().filter(|a| a.is_critical).count() as i32;
 
     let report = InventoryReport {
-        total_products: 0, // Would need additional query
+        total_products: repo.count_products().await? as i32,
         low_stock_products: low_stock_count,
         out_of_stock_products: out_of_stock_count,
-        total_reserved: 0, // Would need additional query
-        total_available: 0, // Would need additional query
+        total_reserved: repo.total_reserved().await? as i32,
+        total_available: repo.total_available().await? as i32,
         alerts,
     };
 
-    (StatusCode::OK, Json(report)).into_response()
+    Ok((StatusCode::OK, Json(report)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/inventory/export",
+    responses(
+        (status = 200, description = "CSV of every product's stock position", content_type = "text/csv", body = String),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin required")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Inventory"
+)]
+async fn export_inventory(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+) -> AppResult<impl IntoResponse> {
+    let repo = StockRepository::new(state.db.clone());
+    let default_threshold = state.inventory_config.default_low_stock_threshold;
+
+    // Fetch the catalog a batch at a time as the client reads, rather than buffering all of it
+    let rows = stream::try_unfold(Some((repo, None)), move |cursor| async move {
+        let Some((repo, after)) = cursor else {
+            return Ok::<_, sqlx::Error>(None);
+        };
+
+        let page = repo.stock_levels_page(after, EXPORT_BATCH_SIZE, default_threshold).await?;
+        let Some(last) = page.last().map(|level| level.product_id) else {
+            return Ok(None);
+        };
+
+        let chunk: String = page.iter().map(csv_line).collect();
+        let next = (page.len() as i64 == EXPORT_BATCH_SIZE).then_some((repo, Some(last)));
+        Ok(Some((chunk, next)))
+    })
+    .inspect_err(|e: &sqlx::Error| tracing::error!("Inventory export failed: {}", e));
+
+    let body = stream::once(async { Ok(EXPORT_CSV_HEADER.to_string()) }).chain(rows);
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"inventory.csv\""),
+        ],
+        Body::from_stream(body),
+    ))
+}
+
+fn csv_line(level: &StockLevel) -> String {
+    format!(
+        "{},{},{},{},{},{}\n",
+        level.product_id,
+        csv_field(&level.product_name),
+        level.current_stock,
+        level.available_stock,
+        level.reserved,
+        level.is_low_stock,
+    )
+}
+
+/// Quotes a field when it contains a delimiter, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
 }
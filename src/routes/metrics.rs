@@ -0,0 +1,28 @@
+use crate::state::AppState;
+use axum::{extract::State, routing::get, Extension, Router};
+use metrics_exporter_prometheus::PrometheusHandle;
+
+/// The Prometheus scrape endpoint. Lives outside `/api` so scrapers don't need a token.
+pub fn build_route(handle: PrometheusHandle) -> Router<AppState> {
+    Router::new()
+        .route("/metrics", get(render_metrics))
+        .layer(Extension(handle))
+}
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses(
+        (status = 200, description = "Request and connection pool metrics in the Prometheus text format; only served when METRICS_ENABLED is set",
+            body = String, content_type = "text/plain")
+    ),
+    tag = "Health"
+)]
+async fn render_metrics(
+    State(state): State<AppState>,
+    Extension(handle): Extension<PrometheusHandle>,
+) -> String {
+    metrics::gauge!("db_pool_connections").set(state.db.size() as f64);
+    metrics::gauge!("db_pool_idle_connections").set(state.db.num_idle() as f64);
+    handle.render()
+}
@@ -1,14 +1,20 @@
+pub mod analytics;
 pub mod auth;
 pub mod cart;
 pub mod category;
+pub mod coupon;
+pub mod health;
 pub mod image;
 pub mod inventory;
+pub mod metrics;
 pub mod order;
+pub mod pagination;
 pub mod payment;
 pub mod product;
+pub mod promotion;
 
-use crate::{ state::AppState, openapi::ApiDoc};
-use axum::{routing::get, Router, Json};
+use crate::{ errors::AppError, middleware::body_limit::limit_body, state::AppState, openapi::ApiDoc};
+use axum::{http::{Method, Uri}, Router, Json};
 use utoipa::OpenApi;
 
 
@@ -19,14 +25,32 @@ pub fn build_route(state: AppState) -> Router {
         .nest("/category", category::build_route())
         .nest("/auth", auth::build_route())
         .nest("/cart", cart::build_route())
-        .nest("/image", image::build_route())
         .nest("/order", order::build_route())
         .nest("/payment", payment::build_route())
-        .nest("/inventory", inventory::build_route());
+        .nest("/inventory", inventory::build_route())
+        .nest("/promotion", promotion::build_route())
+        .nest("/coupon", coupon::build_route())
+        .nest("/admin/analytics", analytics::build_route());
+    // Image uploads get their own, larger limit; nesting them after the JSON limit keeps
+    // the smaller one off them
+    let router = limit_body(router, state.body_limits.max_request_bytes)
+        .nest("/image", limit_body(image::build_route(), state.body_limits.max_upload_bytes));
 
+    // The fallbacks come last so they cover every route above. Axum still sets the `Allow`
+    // header on the 405s; HEAD is answered wherever GET is.
     let api_router = Router::new()
         .nest("/api", router)
-        .route("/health", get(|| async { "OK" }))
+        .merge(health::build_route())
+        .fallback(route_not_found)
+        .method_not_allowed_fallback(method_not_allowed)
         .with_state(state);
     return api_router;
 }
+
+async fn route_not_found(method: Method, uri: Uri) -> AppError {
+    AppError::NotFound(format!("No route for {} {}", method, uri.path()))
+}
+
+async fn method_not_allowed(method: Method, uri: Uri) -> AppError {
+    AppError::MethodNotAllowed(format!("{} is not supported on {}", method, uri.path()))
+}
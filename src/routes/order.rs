@@ -1,38 +1,101 @@
-use crate::repository::OrderRepository;
+use crate::repository::{OrderFilter, OrderRepository, StockRepository};
 use crate::{
-    middleware::auth::{AuthUser, require_admin},
-    model::order::UpdateStatusDto,
-    services::order_service::OrderService,
+    middleware::auth::{AdminUser, AuthUser},
+    middleware::validation::ValidatedJson,
+    model::order::{Order, OrderStatusChange, OrderWithItems, UpdateStatusDto},
+    routes::pagination::{page, page_response},
+    services::{invoice_pdf, order_service::OrderService, order_webhook_service::OrderWebhookService, stock_alert_service::LowStockNotifier},
     state::AppState,
-    errors::AppResult,
-    dtos::order::{CreateOrderRequest, CreateOrderResponse, OrderDetailsResponse},
+    errors::{AppError, AppResult},
+    dtos::order::{BulkStatusRequest, BulkStatusResponse, CreateOrderRequest, CreateOrderResponse, InvoiceResponse, OrderDetailsResponse},
 };
 use axum::{
     Json, Router,
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     routing::{get, post, put},
 };
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use uuid::Uuid;
 
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
 
-use uuid::Uuid;
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct OrderListQuery {
+    /// Page size, defaults to 50 and is capped at 100
+    pub limit: Option<i64>,
+    /// Number of orders to skip, defaults to 0
+    pub offset: Option<i64>,
+    /// Only orders in this status, e.g. `paid`
+    pub status: Option<String>,
+    /// Wrap the page as `{"data": [...], "meta": {"total", "limit", "offset"}}` instead of a bare array
+    #[serde(default)]
+    pub envelope: bool,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AdminOrderListQuery {
+    /// Page size, defaults to 50 and is capped at 100
+    pub limit: Option<i64>,
+    /// Number of orders to skip, defaults to 0
+    pub offset: Option<i64>,
+    /// Only orders in this status, e.g. `paid`
+    pub status: Option<String>,
+    /// Only orders placed at or after this RFC 3339 timestamp
+    pub created_after: Option<DateTime<Utc>>,
+    /// Only orders placed before this RFC 3339 timestamp (exclusive)
+    pub created_before: Option<DateTime<Utc>>,
+    /// Wrap the page as `{"data": [...], "meta": {"total", "limit", "offset"}}` instead of a bare array
+    #[serde(default)]
+    pub envelope: bool,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct InvoiceQuery {
+    /// `json` (default) or `pdf`
+    pub format: Option<String>,
+}
+
+impl AdminOrderListQuery {
+    fn filter(&self) -> OrderFilter {
+        OrderFilter {
+            status: self.status.clone(),
+            created_after: self.created_after,
+            created_before: self.created_before,
+        }
+    }
+}
 
 pub fn build_route() -> Router<AppState> {
     Router::new()
         .route("/", post(create_order))
         .route("/my", get(my_orders))
         .route("/all", get(all_orders))
+        .route("/all/with-items", get(all_orders_with_items))
+        .route("/bulk-status", post(bulk_update_status))
         .route("/{id}", get(get_order_details))
+        .route("/{id}/invoice", get(get_invoice))
+        .route("/{id}/history", get(get_status_history))
         .route("/{id}/status", put(update_status))
         .route("/{id}/pay", post(pay_order))
+        .route("/{id}/cancel", post(cancel_order))
 }
 
 #[utoipa::path(
     get,
     path = "/api/order/my",
+    params(OrderListQuery),
     responses(
-        (status = 200, description = "User's orders"),
+        (status = 200, description = "User's orders, newest first", body = [Order],
+            headers(
+                ("X-Total-Count" = i64, description = "Total number of matching orders"),
+                ("X-Page-Limit" = i64, description = "Page size used"),
+                ("X-Page-Offset" = i64, description = "Rows skipped before this page")
+            )),
+        (status = 400, description = "Unknown status filter"),
         (status = 401, description = "Unauthorized"),
         (status = 500, description = "Internal server error")
     ),
@@ -41,19 +104,32 @@ pub fn build_route() -> Router<AppState> {
     ),
     tag = "Orders"
 )]
-async fn my_orders(State(state): State<AppState>, AuthUser(claims): AuthUser) -> AppResult<impl IntoResponse> {
+async fn my_orders(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Query(query): Query<OrderListQuery>,
+) -> AppResult<impl IntoResponse> {
     let repo = OrderRepository::new(state.db.clone());
     let svc = OrderService::new(repo);
 
-    let orders = svc.get_my_orders(claims.sub).await?;
-    Ok(Json(orders))
+    let (limit, offset) = page(query.limit, query.offset);
+
+    let (orders, total) = svc.get_my_orders(claims.sub, query.status.as_deref(), limit, offset).await?;
+    Ok(page_response(orders, total, limit, offset, query.envelope))
 }
 
 #[utoipa::path(
     get,
     path = "/api/order/all",
+    params(AdminOrderListQuery),
     responses(
-        (status = 200, description = "All orders (admin only)"),
+        (status = 200, description = "All orders (admin only), newest first", body = [Order],
+            headers(
+                ("X-Total-Count" = i64, description = "Total number of matching orders"),
+                ("X-Page-Limit" = i64, description = "Page size used"),
+                ("X-Page-Offset" = i64, description = "Rows skipped before this page")
+            )),
+        (status = 400, description = "Unknown status filter, or created_after is later than created_before"),
         (status = 401, description = "Unauthorized"),
         (status = 403, description = "Admin access required"),
         (status = 500, description = "Internal server error")
@@ -65,15 +141,51 @@ async fn my_orders(State(state): State<AppState>, AuthUser(claims): AuthUser) ->
 )]
 async fn all_orders(
     State(state): State<AppState>,
-    AuthUser(claims): AuthUser,
+    AdminUser(_claims): AdminUser,
+    Query(query): Query<AdminOrderListQuery>,
 ) -> AppResult<impl IntoResponse> {
-    require_admin(&claims)?;
+    let repo = OrderRepository::new(state.db.clone());
+    let svc = OrderService::new(repo);
+
+    let (limit, offset) = page(query.limit, query.offset);
 
+    let (orders, total) = svc.get_all_orders(&query.filter(), limit, offset).await?;
+    Ok(page_response(orders, total, limit, offset, query.envelope))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/order/all/with-items",
+    params(AdminOrderListQuery),
+    responses(
+        (status = 200, description = "All orders with their line items (admin only), newest first", body = [OrderWithItems],
+            headers(
+                ("X-Total-Count" = i64, description = "Total number of matching orders"),
+                ("X-Page-Limit" = i64, description = "Page size used"),
+                ("X-Page-Offset" = i64, description = "Rows skipped before this page")
+            )),
+        (status = 400, description = "Unknown status filter, or created_after is later than created_before"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin access required"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Orders"
+)]
+async fn all_orders_with_items(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    Query(query): Query<AdminOrderListQuery>,
+) -> AppResult<impl IntoResponse> {
     let repo = OrderRepository::new(state.db.clone());
     let svc = OrderService::new(repo);
 
-    let orders = svc.get_all_orders().await?;
-    Ok(Json(orders))
+    let (limit, offset) = page(query.limit, query.offset);
+
+    let (orders, total) = svc.get_all_orders_with_items(&query.filter(), limit, offset).await?;
+    Ok(page_response(orders, total, limit, offset, query.envelope))
 }
 
 #[utoipa::path(
@@ -82,11 +194,14 @@ async fn all_orders(
     params(
         ("id" = Uuid, Path, description = "Order ID")
     ),
+    request_body = UpdateStatusDto,
     responses(
-        (status = 200, description = "Order status updated"),
+        (status = 200, description = "Order status updated", body = Order),
+        (status = 400, description = "Unknown status, transition not allowed from the current status, or tracking details sent without shipping"),
         (status = 401, description = "Unauthorized"),
         (status = 403, description = "Admin access required"),
         (status = 404, description = "Order not found"),
+        (status = 409, description = "Order changed status while being updated"),
         (status = 500, description = "Internal server error")
     ),
     security(
@@ -96,18 +211,45 @@ async fn all_orders(
 )]
 async fn update_status(
     State(state): State<AppState>,
-    AuthUser(claims): AuthUser,
+    AdminUser(claims): AdminUser,
     Path(id): Path<Uuid>,
     Json(dto): Json<UpdateStatusDto>,
 ) -> AppResult<impl IntoResponse> {
-    require_admin(&claims)?;
-
     let repo = OrderRepository::new(state.db.clone());
     let svc = OrderService::new(repo);
 
-    let order = svc.update_order_status(id, dto.status).await?;
+    let order = svc.update_order_status(id, dto, Some(claims.sub)).await?;
     Ok(Json(order))
 }
+
+#[utoipa::path(
+    post,
+    path = "/api/order/bulk-status",
+    request_body = BulkStatusRequest,
+    responses(
+        (status = 200, description = "Orders updated; ones that couldn't be moved are reported in `results`", body = BulkStatusResponse),
+        (status = 400, description = "Unknown status, or no or more than 100 order ids"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin access required"),
+        (status = 409, description = "With `all_or_nothing`, at least one order couldn't be moved and none were updated"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Orders"
+)]
+async fn bulk_update_status(
+    State(state): State<AppState>,
+    AdminUser(claims): AdminUser,
+    ValidatedJson(dto): ValidatedJson<BulkStatusRequest>,
+) -> AppResult<Json<BulkStatusResponse>> {
+    let repo = OrderRepository::new(state.db.clone());
+    let svc = OrderService::new(repo);
+
+    Ok(Json(svc.bulk_update_status(dto, Some(claims.sub)).await?))
+}
+
 #[utoipa::path(
     post,
     path = "/api/order/{id}/pay",
@@ -116,8 +258,10 @@ async fn update_status(
     ),
     responses(
         (status = 200, description = "Payment processed"),
-        (status = 400, description = "Invalid order or already paid"),
+        (status = 400, description = "Order not awaiting payment, or one of its products no longer exists"),
         (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Order not found"),
+        (status = 409, description = "Insufficient stock for an item, or product prices changed since the order was placed (only when LOCK_PRICE_AT_ORDER is off)"),
         (status = 500, description = "Internal server error")
     ),
     security(
@@ -131,22 +275,36 @@ async fn pay_order(
     Path(order_id): Path<Uuid>,
 ) -> AppResult<impl IntoResponse> {
     let repo = OrderRepository::new(state.db.clone());
-    let svc = OrderService::new(repo);
+    let webhooks = OrderWebhookService::new(repo.clone(), state.http.clone(), &state.order_webhook_config);
+    let low_stock = LowStockNotifier::new(
+        StockRepository::new(state.db.clone()),
+        state.http.clone(),
+        &state.inventory_config,
+        &state.low_stock_webhook_config,
+    );
+    let svc = OrderService::new(repo)
+        .with_price_lock(state.order_config.lock_prices)
+        .with_webhooks(webhooks)
+        .with_low_stock_notifier(low_stock);
 
-    match svc.pay_order(claims.sub, order_id).await? {
-        Some(order) => Ok((StatusCode::OK, Json(order))),
-        None => Err(crate::errors::AppError::Validation("Invalid order or already paid".to_string())),
-    }
+    let order = svc.pay_order(claims.sub, order_id).await?;
+    Ok((StatusCode::OK, Json(order)))
 }
 
 #[utoipa::path(
     post,
     path = "/api/order",
     request_body = CreateOrderRequest,
+    params(
+        ("Idempotency-Key" = Option<String>, Header,
+            description = "Client-chosen key; retrying with the same key within 24 hours returns the original order")
+    ),
     responses(
+        (status = 200, description = "Order already created with this Idempotency-Key", body = CreateOrderResponse),
         (status = 201, description = "Order created successfully", body = CreateOrderResponse),
-        (status = 400, description = "Invalid request or empty cart"),
+        (status = 400, description = "Invalid request, notes too long, bad Idempotency-Key, unusable coupon, or empty cart"),
         (status = 401, description = "Unauthorized"),
+        (status = 409, description = "Insufficient stock for a cart item"),
         (status = 500, description = "Internal server error")
     ),
     security(
@@ -157,15 +315,24 @@ async fn pay_order(
 async fn create_order(
     State(state): State<AppState>,
     AuthUser(claims): AuthUser,
-    Json(_dto): Json<CreateOrderRequest>,
+    headers: HeaderMap,
+    ValidatedJson(dto): ValidatedJson<CreateOrderRequest>,
 ) -> AppResult<impl IntoResponse> {
     let repo = OrderRepository::new(state.db.clone());
-    let svc = OrderService::new(repo);
+    let webhooks = OrderWebhookService::new(repo.clone(), state.http.clone(), &state.order_webhook_config);
+    let svc = OrderService::new(repo)
+        .with_pricing(state.order_config.pricing.clone())
+        .with_promotion_stacking(state.promotion_config.stacking)
+        .with_webhooks(webhooks);
 
-    match svc.create_order_from_cart(claims.sub).await {
-        Ok(order) => Ok((StatusCode::CREATED, Json(order))),
-        Err(e) => Err(e),
-    }
+    let idempotency_key = headers
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .map(|v| v.to_str().map_err(|_| AppError::Validation("Idempotency-Key must be visible ASCII".to_string())))
+        .transpose()?;
+
+    let (order, replayed) = svc.create_order_idempotent(claims.sub, idempotency_key, &dto).await?;
+    let status = if replayed { StatusCode::OK } else { StatusCode::CREATED };
+    Ok((status, Json(order)))
 }
 
 #[utoipa::path(
@@ -195,7 +362,7 @@ async fn get_order_details(
     let svc = OrderService::new(repo);
 
     // Check if user is admin to allow accessing any order
-    let is_admin = claims.role == "admin";
+    let is_admin = claims.has_role("admin");
     
     let result = if is_admin {
         svc.get_order_details_admin(order_id).await
@@ -208,3 +375,113 @@ async fn get_order_details(
         Err(e) => Err(e),
     }
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/order/{id}/history",
+    params(
+        ("id" = Uuid, Path, description = "Order ID")
+    ),
+    responses(
+        (status = 200, description = "Status changes, oldest first", body = [OrderStatusChange]),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Access denied"),
+        (status = 404, description = "Order not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Orders"
+)]
+async fn get_status_history(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(order_id): Path<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    let repo = OrderRepository::new(state.db.clone());
+    let svc = OrderService::new(repo);
+
+    let history = svc.status_history(claims.sub, claims.has_role("admin"), order_id).await?;
+    Ok(Json(history))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/order/{id}/invoice",
+    params(
+        ("id" = Uuid, Path, description = "Order ID"),
+        InvoiceQuery
+    ),
+    responses(
+        (status = 200, description = "Invoice as JSON, or as an `application/pdf` document with `format=pdf`", body = InvoiceResponse),
+        (status = 400, description = "Unknown format"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Access denied"),
+        (status = 404, description = "Order not found")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Orders"
+)]
+async fn get_invoice(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(order_id): Path<Uuid>,
+    Query(query): Query<InvoiceQuery>,
+) -> AppResult<Response> {
+    let pdf = match query.format.as_deref() {
+        None | Some("json") => false,
+        Some("pdf") => true,
+        Some(other) => return Err(AppError::Validation(format!("Unknown invoice format: {}", other))),
+    };
+
+    let repo = OrderRepository::new(state.db.clone());
+    let svc = OrderService::new(repo);
+    let invoice = svc.get_invoice(claims.sub, claims.has_role("admin"), order_id).await?;
+
+    if !pdf {
+        return Ok(Json(invoice).into_response());
+    }
+
+    let bytes = invoice_pdf::render(&invoice)?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/pdf".to_string()),
+            (header::CONTENT_DISPOSITION, format!("inline; filename=\"{}.pdf\"", invoice.invoice_number)),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/order/{id}/cancel",
+    params(
+        ("id" = Uuid, Path, description = "Order ID")
+    ),
+    responses(
+        (status = 200, description = "Order cancelled and held stock released", body = Order),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Order belongs to another user"),
+        (status = 404, description = "Order not found"),
+        (status = 409, description = "Order is past payment and can no longer be cancelled"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Orders"
+)]
+async fn cancel_order(
+    State(state): State<AppState>,
+    AuthUser(claims): AuthUser,
+    Path(order_id): Path<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    let repo = OrderRepository::new(state.db.clone());
+    let svc = OrderService::new(repo);
+
+    let order = svc.cancel_order(claims.sub, order_id).await?;
+    Ok(Json(order))
+}
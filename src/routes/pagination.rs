@@ -0,0 +1,64 @@
+use axum::{response::{IntoResponse, Response}, Json};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// Page size used when a listing request doesn't give a `limit`.
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+/// Largest `limit` any listing accepts; bigger values are clamped to it.
+pub const MAX_PAGE_SIZE: i64 = 100;
+
+/// Query for listings that only page.
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PageQuery {
+    /// Page size, defaults to 50 and is capped at 100
+    pub limit: Option<i64>,
+    /// Number of rows to skip, defaults to 0
+    pub offset: Option<i64>,
+}
+
+/// The `(limit, offset)` to query with: `limit` defaults to `DEFAULT_PAGE_SIZE` and is clamped
+/// to `1..=MAX_PAGE_SIZE`, and a negative `offset` counts as 0.
+pub fn page(limit: Option<i64>, offset: Option<i64>) -> (i64, i64) {
+    (
+        limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE),
+        offset.unwrap_or(0).max(0),
+    )
+}
+
+/// Response headers describing one page of a listing: how many rows match in total, and the
+/// `limit` and `offset` (after [`page`]) the page was read with.
+pub fn page_headers(total: i64, limit: i64, offset: i64) -> [(&'static str, String); 3] {
+    [
+        ("X-Total-Count", total.to_string()),
+        ("X-Page-Limit", limit.to_string()),
+        ("X-Page-Offset", offset.to_string()),
+    ]
+}
+
+/// Paging details sent alongside an enveloped page; the same values as the page headers.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PageMeta {
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// A page of a listing wrapped as `{"data": [...], "meta": {...}}`, for clients that ask for it
+/// with `envelope=true`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListEnvelope<T> {
+    pub data: Vec<T>,
+    pub meta: PageMeta,
+}
+
+/// One page of a listing with its [`page_headers`]. The body is the bare array unless
+/// `envelope` is set, so existing clients see no change.
+pub fn page_response<T: Serialize>(items: Vec<T>, total: i64, limit: i64, offset: i64, envelope: bool) -> Response {
+    let headers = page_headers(total, limit, offset);
+    if envelope {
+        let meta = PageMeta { total, limit, offset };
+        (headers, Json(ListEnvelope { data: items, meta })).into_response()
+    } else {
+        (headers, Json(items)).into_response()
+    }
+}
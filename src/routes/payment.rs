@@ -1,73 +1,103 @@
 use crate::{
-    middleware::auth::AuthUser,
-    model::payment::{CreatePaymentIntentRequest},
-    repository::{PaymentRepository, OrderRepository},
-    services::payment_service::{PaymentService, PaymentError},
+    errors::{AppError, AppResult},
+    middleware::auth::{AdminUser, AuthUser},
+    model::payment::{CreatePaymentIntentRequest, Payment},
+    repository::{PaymentRepository, OrderRepository, StockRepository},
+    routes::pagination::{page, page_headers},
+    services::{order_webhook_service::OrderWebhookService, payment_service::PaymentService, stock_alert_service::LowStockNotifier},
     state::AppState,
 };
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
     routing::{get, post},
     Json, Router,
 };
+use serde::Deserialize;
 use serde_json::json;
+use utoipa::IntoParams;
 use uuid::Uuid;
 
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PaymentListQuery {
+    /// Only payments in this status, e.g. `succeeded`
+    pub status: Option<String>,
+    /// Page size, defaults to 50 and is capped at 100
+    pub limit: Option<i64>,
+    /// Number of payments to skip, defaults to 0
+    pub offset: Option<i64>,
+}
+
 pub fn build_route() -> Router<AppState> {
     Router::new()
+        .route("/", get(list_payments))
         .route("/create-payment-intent", post(create_payment_intent))
         .route("/order/{order_id}", get(get_payment_by_order))
+        .route("/intent/{payment_intent_id}", get(get_payment_by_intent))
         .route("/{payment_id}/refund", post(refund_payment))
         .route("/webhook", post(handle_stripe_webhook))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/payment",
+    params(PaymentListQuery),
+    responses(
+        (status = 200, description = "Payments, newest first", body = [Payment],
+            headers(
+                ("X-Total-Count" = i64, description = "Total number of matching payments"),
+                ("X-Page-Limit" = i64, description = "Page size used"),
+                ("X-Page-Offset" = i64, description = "Rows skipped before this page")
+            )),
+        (status = 400, description = "Unknown status filter"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin required")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Payments"
+)]
+async fn list_payments(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    Query(query): Query<PaymentListQuery>,
+) -> AppResult<impl IntoResponse> {
+    let payment_repo = PaymentRepository::new(state.db.clone());
+    let order_repo = OrderRepository::new(state.db.clone());
+    let service = PaymentService::new(payment_repo, order_repo, state.http.clone(), &state.stripe_config);
+
+    let (limit, offset) = page(query.limit, query.offset);
+
+    let (payments, total) = service.list_payments(query.status.as_deref(), limit, offset).await?;
+    Ok((
+        StatusCode::OK,
+        page_headers(total, limit, offset),
+        Json(payments),
+    ))
+}
+
 #[utoipa::path(
     post,
     path = "/api/payment/create-payment-intent",
     request_body = crate::model::payment::CreatePaymentIntentRequest,
-    responses((status = 200, description = "Payment intent created")),
+    responses(
+        (status = 200, description = "Payment intent created"),
+        (status = 404, description = "Order not found or not the caller's")
+    ),
     security(("bearer_auth" = [])),
     tag = "Payments"
 )]
 async fn create_payment_intent(
     State(state): State<AppState>,
-    AuthUser(_claims): AuthUser,
+    AuthUser(claims): AuthUser,
     Json(request): Json<CreatePaymentIntentRequest>,
-) -> impl IntoResponse {
+) -> AppResult<impl IntoResponse> {
     let payment_repo = PaymentRepository::new(state.db.clone());
     let order_repo = OrderRepository::new(state.db.clone());
-    let service = PaymentService::new(payment_repo, order_repo);
-
-    match service.create_payment_intent(request).await {
-        Ok(response) => (StatusCode::OK, Json(response)).into_response(),
-        Err(PaymentError::OrderNotFound) => (
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "Order not found"})),
-        )
-            .into_response(),
-        Err(PaymentError::InvalidOrderStatus(status)) => (
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": format!("Invalid order status: {}", status)})),
-        )
-            .into_response(),
-        Err(PaymentError::InvalidAmount) => (
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": "Invalid amount"})),
-        )
-            .into_response(),
-        Err(PaymentError::StripeApiError(msg)) => (
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": format!("Payment processing error: {}", msg)})),
-        )
-            .into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("Internal error: {}", e)})),
-        )
-            .into_response(),
-    }
+    let service = PaymentService::new(payment_repo, order_repo, state.http.clone(), &state.stripe_config);
+
+    let response = service.create_payment_intent(claims.sub, request).await?;
+    Ok((StatusCode::OK, Json(response)))
 }
 
 #[utoipa::path(
@@ -82,23 +112,42 @@ async fn get_payment_by_order(
     State(state): State<AppState>,
     AuthUser(_claims): AuthUser,
     Path(order_id): Path<Uuid>,
-) -> impl IntoResponse {
+) -> AppResult<impl IntoResponse> {
     let payment_repo = PaymentRepository::new(state.db.clone());
     let order_repo = OrderRepository::new(state.db.clone());
-    let service = PaymentService::new(payment_repo, order_repo);
-
-    match service.get_payment_by_order(order_id).await {
-        Ok(Some(payment)) => (StatusCode::OK, Json(payment)).into_response(),
-        Ok(None) => (
-            StatusCode::NOT_FOUND,
-            Json(json!({"error": "Payment not found for this order"})),
-        )
-            .into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("Internal error: {}", e)})),
-        )
-            .into_response(),
+    let service = PaymentService::new(payment_repo, order_repo, state.http.clone(), &state.stripe_config);
+
+    match service.get_payment_by_order(order_id).await? {
+        Some(payment) => Ok((StatusCode::OK, Json(payment))),
+        None => Err(AppError::NotFound("Payment not found for this order".into())),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/payment/intent/{payment_intent_id}",
+    params(("payment_intent_id" = String, Path, description = "Stripe payment intent id, e.g. `pi_...`")),
+    responses(
+        (status = 200, description = "Payment for the intent", body = Payment),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin required"),
+        (status = 404, description = "No payment for this intent")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "Payments"
+)]
+async fn get_payment_by_intent(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    Path(payment_intent_id): Path<String>,
+) -> AppResult<impl IntoResponse> {
+    let payment_repo = PaymentRepository::new(state.db.clone());
+    let order_repo = OrderRepository::new(state.db.clone());
+    let service = PaymentService::new(payment_repo, order_repo, state.http.clone(), &state.stripe_config);
+
+    match service.get_payment_by_intent(&payment_intent_id).await? {
+        Some(payment) => Ok((StatusCode::OK, Json(payment))),
+        None => Err(AppError::NotFound("Payment not found for this payment intent".into())),
     }
 }
 
@@ -106,48 +155,37 @@ async fn get_payment_by_order(
     post,
     path = "/api/payment/{payment_id}/refund",
     params(("payment_id" = Uuid, Path)),
-    responses((status = 200, description = "Refund processed")),
+    responses(
+        (status = 200, description = "Refund processed"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin required")
+    ),
     security(("bearer_auth" = [])),
     tag = "Payments"
 )]
 async fn refund_payment(
     State(state): State<AppState>,
-    AuthUser(_claims): AuthUser,
+    AdminUser(_claims): AdminUser,
     Path(payment_id): Path<Uuid>,
-) -> impl IntoResponse {
-    // TODO: Add admin check
+) -> AppResult<impl IntoResponse> {
     let payment_repo = PaymentRepository::new(state.db.clone());
     let order_repo = OrderRepository::new(state.db.clone());
-    let service = PaymentService::new(payment_repo, order_repo);
-
-    match service.refund_payment(payment_id).await {
-        Ok(()) => (
-            StatusCode::OK,
-            Json(json!({"message": "Refund processed successfully"})),
-        )
-            .into_response(),
-        Err(PaymentError::PaymentNotFound) => (
-            StatusCode::NOT_FOUND,
-            Json(json!({"error": "Payment not found"})),
-        )
-            .into_response(),
-        Err(PaymentError::StripeApiError(msg)) => (
-            StatusCode::BAD_REQUEST,
-            Json(json!({"error": format!("Refund processing error: {}", msg)})),
-        )
-            .into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({"error": format!("Internal error: {}", e)})),
-        )
-            .into_response(),
-    }
+    let service = PaymentService::new(payment_repo, order_repo, state.http.clone(), &state.stripe_config);
+
+    service.refund_payment(payment_id).await?;
+    Ok((
+        StatusCode::OK,
+        Json(json!({"message": "Refund processed successfully"})),
+    ))
 }
 
 #[utoipa::path(
     post,
     path = "/api/payment/webhook",
-    responses((status = 200, description = "Webhook processed")),
+    responses(
+        (status = 200, description = "Webhook processed"),
+        (status = 500, description = "Webhook not processed; Stripe should deliver it again")
+    ),
     tag = "Payments"
 )]
 async fn handle_stripe_webhook(
@@ -162,44 +200,73 @@ async fn handle_stripe_webhook(
             let event_type = event["type"].as_str().unwrap_or("");
             let event_id = event["id"].as_str().unwrap_or("");
             
-            // Store webhook for processing
+            // Store webhook for processing; a redelivery of an event that failed is processed again
             let payment_repo = PaymentRepository::new(state.db.clone());
-            
-            if let Err(e) = payment_repo.create_webhook_record(
-                event_id.to_string(),
-                event_type.to_string(),
-                event.clone(),
-            ).await {
-                tracing::error!("Failed to store webhook: {}", e);
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Failed to process webhook"})),
-                )
-                    .into_response();
+            let stored = match payment_repo.get_webhook_by_stripe_event_id(event_id).await {
+                Ok(Some(webhook)) => Ok(webhook),
+                Ok(None) => payment_repo.create_webhook_record(
+                    event_id.to_string(),
+                    event_type.to_string(),
+                    event.clone(),
+                ).await,
+                Err(e) => Err(e),
+            };
+            let webhook = match stored {
+                Ok(webhook) => webhook,
+                Err(e) => {
+                    tracing::error!("Failed to store webhook: {}", e);
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(json!({"error": "Failed to process webhook"})),
+                    )
+                        .into_response();
+                }
+            };
+            if webhook.processed && webhook.error_message.is_none() {
+                return (StatusCode::OK, Json(json!({"received": true}))).into_response();
             }
 
             // Process webhook based on type
             let order_repo = OrderRepository::new(state.db.clone());
-            let service = PaymentService::new(payment_repo, order_repo);
-
-            match event_type {
-                "payment_intent.succeeded" => {
-                    if let Some(payment_intent_id) = event["data"]["object"]["id"].as_str() {
-                        if let Err(e) = service.handle_payment_succeeded(payment_intent_id).await {
-                            tracing::error!("Failed to handle payment succeeded: {}", e);
-                        }
-                    }
-                }
-                "payment_intent.payment_failed" => {
-                    if let Some(payment_intent_id) = event["data"]["object"]["id"].as_str() {
-                        if let Err(e) = service.handle_payment_failed(payment_intent_id).await {
-                            tracing::error!("Failed to handle payment failed: {}", e);
-                        }
-                    }
-                }
+            let webhooks = OrderWebhookService::new(order_repo.clone(), state.http.clone(), &state.order_webhook_config);
+            let low_stock = LowStockNotifier::new(
+                StockRepository::new(state.db.clone()),
+                state.http.clone(),
+                &state.inventory_config,
+                &state.low_stock_webhook_config,
+            );
+            let service = PaymentService::new(payment_repo.clone(), order_repo, state.http.clone(), &state.stripe_config)
+                .with_order_webhooks(webhooks)
+                .with_low_stock_notifier(low_stock);
+
+            let outcome = match event_type {
+                "payment_intent.succeeded" => match event["data"]["object"]["id"].as_str() {
+                    Some(payment_intent_id) => service.handle_payment_succeeded(payment_intent_id).await
+                        .map_err(|e| format!("Failed to handle payment succeeded: {}", e)),
+                    None => Ok(()),
+                },
+                "payment_intent.payment_failed" => match event["data"]["object"]["id"].as_str() {
+                    Some(payment_intent_id) => service.handle_payment_failed(payment_intent_id).await
+                        .map_err(|e| format!("Failed to handle payment failed: {}", e)),
+                    None => Ok(()),
+                },
                 _ => {
                     tracing::info!("Unhandled webhook event type: {}", event_type);
+                    Ok(())
                 }
+            };
+
+            if let Err(e) = payment_repo.mark_webhook_processed(webhook.id, outcome.as_ref().err().cloned()).await {
+                tracing::error!("Failed to mark webhook {} processed: {}", webhook.id, e);
+            }
+            // Stripe redelivers an event that wasn't acknowledged, so a failure gets another try
+            if let Err(e) = outcome {
+                tracing::error!("{}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({"error": "Failed to process webhook"})),
+                )
+                    .into_response();
             }
 
             (StatusCode::OK, Json(json!({"received": true}))).into_response()
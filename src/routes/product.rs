@@ -1,20 +1,49 @@
 use crate::{
-    dtos::{NewProductDto, ProductResponse, UpdateProductDto},
+    dtos::{NewProductDto, ProductDetailResponse, ProductImportReport, ProductResponse, UpdateProductDto},
     errors::{AppError, AppResult},
-    middleware::auth::{AuthUser, require_admin},
+    middleware::auth::{AdminUser, AuthUser},
     middleware::validation::ValidatedJson,
-    repository::ProductRepository,
+    repository::{ProductFilter, ProductRepository},
+    routes::pagination::{page, page_response},
     services::product_service::ProductService,
     state::AppState,
 };
 use axum::{
     Json, Router,
-    extract::{Path, State},
+    extract::{Multipart, Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
-    routing::{get},
+    routing::{get, post, put},
 };
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use utoipa::IntoParams;
 use uuid::Uuid;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ProductListQuery {
+    /// Page size, defaults to 50 and is capped at 100
+    pub limit: Option<i64>,
+    /// Number of products to skip, defaults to 0
+    pub offset: Option<i64>,
+    /// Only return products assigned to this category
+    pub category_id: Option<Uuid>,
+    /// Only return products priced at or above this amount
+    #[param(value_type = Option<String>)]
+    pub min_price: Option<Decimal>,
+    /// Only return products priced at or below this amount
+    #[param(value_type = Option<String>)]
+    pub max_price: Option<Decimal>,
+    /// Only products created at or after this RFC 3339 timestamp
+    pub created_after: Option<DateTime<Utc>>,
+    /// Only products created before this RFC 3339 timestamp (exclusive)
+    pub created_before: Option<DateTime<Utc>>,
+    /// Wrap the page as `{"data": [...], "meta": {"total", "limit", "offset"}}` instead of a bare array
+    #[serde(default)]
+    pub envelope: bool,
+}
+
 pub fn build_route() -> Router<AppState> {
     Router::new()
         .route("/", get(list_products).post(create_product))
@@ -22,25 +51,54 @@ pub fn build_route() -> Router<AppState> {
             "/{id}",
             get(get_product).put(update_product).delete(delete_product),
         )
+        .route("/{id}/restore", post(restore_product))
+        .route("/{id}/duplicate", post(duplicate_product))
+        .route("/{id}/publish", put(publish_product))
+        .route("/{id}/unpublish", put(unpublish_product))
+        .route("/by-sku/{sku}", get(get_product_by_sku))
+        .route("/import", post(import_products))
 }
 
 #[utoipa::path(
     get,
     path = "/api/product",
+    params(ProductListQuery),
     responses(
-        (status = 200, description = "List of products", body = [ProductResponse]),
+        (status = 200, description = "Published products; admins also see unpublished ones", body = [ProductResponse],
+            headers(
+                ("X-Total-Count" = i64, description = "Total number of products"),
+                ("X-Page-Limit" = i64, description = "Page size used"),
+                ("X-Page-Offset" = i64, description = "Rows skipped before this page")
+            )),
+        (status = 400, description = "min_price is greater than max_price, or created_after is later than created_before"),
         (status = 500, description = "Internal server error")
     ),
     tag = "Products"
 )]
-async fn list_products(State(state): State<AppState>) -> AppResult<impl IntoResponse> {
+async fn list_products(
+    State(state): State<AppState>,
+    auth: Option<AuthUser>,
+    Query(query): Query<ProductListQuery>,
+) -> AppResult<impl IntoResponse> {
     let repo = ProductRepository::new(state.db.clone());
     let svc = ProductService::new(repo);
 
-    let products = svc.list(50, 0).await?;
+    let (limit, offset) = page(query.limit, query.offset);
+
+    let filter = ProductFilter {
+        category_id: query.category_id,
+        min_price: query.min_price,
+        max_price: query.max_price,
+        created_after: query.created_after,
+        created_before: query.created_before,
+        include_unpublished: auth.is_some_and(|AuthUser(claims)| claims.has_role("admin")),
+    };
+
+    let products = svc.list(&filter, limit, offset).await?;
+    let total = svc.count(&filter).await?;
     let res: Vec<ProductResponse> = products.into_iter().map(|p| p.into()).collect();
-    
-    Ok((StatusCode::OK, Json(res)))
+
+    Ok(page_response(res, total, limit, offset, query.envelope))
 }
 
 #[utoipa::path(
@@ -61,11 +119,9 @@ async fn list_products(State(state): State<AppState>) -> AppResult<impl IntoResp
 )]
 async fn create_product(
     State(state): State<AppState>,
-    AuthUser(claims): AuthUser,
+    AdminUser(_claims): AdminUser,
     ValidatedJson(payload): ValidatedJson<NewProductDto>,
 ) -> AppResult<impl IntoResponse> {
-    require_admin(&claims)?;
-
     let repo = ProductRepository::new(state.db.clone());
     let svc = ProductService::new(repo);
 
@@ -81,7 +137,7 @@ async fn create_product(
         ("id" = Uuid, Path, description = "Product ID")
     ),
     responses(
-        (status = 200, description = "Product found", body = ProductResponse),
+        (status = 200, description = "Product found, with its categories", body = ProductDetailResponse),
         (status = 404, description = "Product not found"),
         (status = 500, description = "Internal server error")
     ),
@@ -91,12 +147,41 @@ async fn get_product(State(state): State<AppState>, Path(id): Path<Uuid>) -> App
     let repo = ProductRepository::new(state.db.clone());
     let svc = ProductService::new(repo);
 
-    match svc.get(id).await? {
-        Some(product) => Ok((StatusCode::OK, Json(ProductResponse::from(product)))),
+    match svc.get_with_categories(id).await? {
+        Some((product, categories)) => Ok((
+            StatusCode::OK,
+            Json(ProductDetailResponse {
+                product: product.into(),
+                categories: categories.into_iter().map(|c| c.into()).collect(),
+            }),
+        )),
         None => Err(AppError::NotFound(format!("Product with id {} not found", id))),
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/product/by-sku/{sku}",
+    params(
+        ("sku" = String, Path, description = "Product SKU")
+    ),
+    responses(
+        (status = 200, description = "Product found", body = ProductResponse),
+        (status = 404, description = "Product not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Products"
+)]
+async fn get_product_by_sku(State(state): State<AppState>, Path(sku): Path<String>) -> AppResult<impl IntoResponse> {
+    let repo = ProductRepository::new(state.db.clone());
+    let svc = ProductService::new(repo);
+
+    match svc.get_by_sku(&sku).await? {
+        Some(product) => Ok((StatusCode::OK, Json(ProductResponse::from(product)))),
+        None => Err(AppError::NotFound(format!("Product with SKU {} not found", sku))),
+    }
+}
+
 #[utoipa::path(
     put,
     path = "/api/product/{id}",
@@ -120,11 +205,9 @@ async fn get_product(State(state): State<AppState>, Path(id): Path<Uuid>) -> App
 async fn update_product(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
-    AuthUser(claims): AuthUser,
+    AdminUser(_claims): AdminUser,
     ValidatedJson(payload): ValidatedJson<UpdateProductDto>,
 ) -> AppResult<impl IntoResponse> {
-    require_admin(&claims)?;
-
     let repo = ProductRepository::new(state.db.clone());
     let svc = ProductService::new(repo);
 
@@ -154,11 +237,9 @@ async fn update_product(
 )]
 async fn delete_product(
     State(state): State<AppState>,
-    AuthUser(claims): AuthUser,
+    AdminUser(_claims): AdminUser,
     Path(id): Path<Uuid>,
 ) -> AppResult<impl IntoResponse> {
-    require_admin(&claims)?;
-
     let repo = ProductRepository::new(state.db.clone());
     let svc = ProductService::new(repo);
 
@@ -167,3 +248,174 @@ async fn delete_product(
         false => Err(AppError::NotFound(format!("Product with id {} not found", id))),
     }
 }
+
+#[utoipa::path(
+    post,
+    path = "/api/product/{id}/restore",
+    params(
+        ("id" = Uuid, Path, description = "Product ID")
+    ),
+    responses(
+        (status = 200, description = "Product restored", body = ProductResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin access required"),
+        (status = 404, description = "No deleted product with this id"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Products"
+)]
+async fn restore_product(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    Path(id): Path<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    let repo = ProductRepository::new(state.db.clone());
+    let svc = ProductService::new(repo);
+
+    match svc.restore(id).await? {
+        Some(product) => Ok((StatusCode::OK, Json(ProductResponse::from(product)))),
+        None => Err(AppError::NotFound(format!("Deleted product with id {} not found", id))),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/product/{id}/duplicate",
+    params(
+        ("id" = Uuid, Path, description = "ID of the product to copy")
+    ),
+    responses(
+        (status = 201, description = "Copy created with a \"(copy)\" name suffix, no stock and no SKU", body = ProductResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin access required"),
+        (status = 404, description = "Product not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Products"
+)]
+async fn duplicate_product(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    Path(id): Path<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    let repo = ProductRepository::new(state.db.clone());
+    let svc = ProductService::new(repo);
+
+    match svc.duplicate(id).await? {
+        Some(product) => Ok((StatusCode::CREATED, Json(ProductResponse::from(product)))),
+        None => Err(AppError::NotFound(format!("Product with id {} not found", id))),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/product/{id}/publish",
+    params(
+        ("id" = Uuid, Path, description = "Product ID")
+    ),
+    responses(
+        (status = 200, description = "Product listed publicly again", body = ProductResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin access required"),
+        (status = 404, description = "Product not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Products"
+)]
+async fn publish_product(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    Path(id): Path<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    set_published(state, id, true).await
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/product/{id}/unpublish",
+    params(
+        ("id" = Uuid, Path, description = "Product ID")
+    ),
+    responses(
+        (status = 200, description = "Product hidden from public listings", body = ProductResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin access required"),
+        (status = 404, description = "Product not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Products"
+)]
+async fn unpublish_product(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    Path(id): Path<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    set_published(state, id, false).await
+}
+
+async fn set_published(state: AppState, id: Uuid, is_published: bool) -> AppResult<impl IntoResponse> {
+    let repo = ProductRepository::new(state.db.clone());
+    let svc = ProductService::new(repo);
+
+    match svc.set_published(id, is_published).await? {
+        Some(product) => Ok((StatusCode::OK, Json(ProductResponse::from(product)))),
+        None => Err(AppError::NotFound(format!("Product with id {} not found", id))),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/product/import",
+    request_body(content_type = "multipart/form-data", description = "A `file` field holding a CSV with a header row of `name`, `price`, `stock` and optionally `description`, `sku`, `image_url`"),
+    responses(
+        (status = 200, description = "Valid rows were created; the report lists each line's outcome", body = ProductImportReport),
+        (status = 400, description = "Missing file, unreadable header, missing required column or too many rows"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Admin access required"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Products"
+)]
+async fn import_products(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    mut multipart: Multipart,
+) -> AppResult<impl IntoResponse> {
+    let mut data = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::Validation(format!("Invalid multipart body: {}", e)))?
+    {
+        if field.name() == Some("file") {
+            let bytes = field
+                .bytes()
+                .await
+                .map_err(|e| AppError::Validation(format!("Failed to read file: {}", e)))?;
+            data = Some(bytes);
+            break;
+        }
+    }
+    let data = data.ok_or_else(|| AppError::Validation("Missing CSV file in the file field".into()))?;
+
+    let repo = ProductRepository::new(state.db.clone());
+    let svc = ProductService::new(repo);
+
+    let report = svc.import_csv(&data).await?;
+    Ok((StatusCode::OK, Json(report)))
+}
@@ -0,0 +1,106 @@
+use crate::{
+    dtos::NewPromotionDto,
+    errors::{AppError, AppResult},
+    middleware::auth::AdminUser,
+    middleware::validation::ValidatedJson,
+    model::promotion::Promotion,
+    repository::PromotionRepository,
+    services::promotion_service::PromotionService,
+    state::AppState,
+};
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+};
+use uuid::Uuid;
+
+pub fn build_route() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_promotions).post(create_promotion))
+        .route("/{id}/deactivate", post(deactivate_promotion))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/promotion",
+    responses(
+        (status = 200, description = "List of promotions", body = [Promotion]),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Promotions"
+)]
+async fn list_promotions(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+) -> AppResult<impl IntoResponse> {
+    let repo = PromotionRepository::new(state.db.clone());
+    let svc = PromotionService::new(repo);
+
+    let promotions = svc.list(50, 0).await?;
+    Ok((StatusCode::OK, Json(promotions)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/promotion",
+    request_body = NewPromotionDto,
+    responses(
+        (status = 201, description = "Promotion created", body = Promotion),
+        (status = 400, description = "Validation error"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Promotions"
+)]
+async fn create_promotion(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    ValidatedJson(payload): ValidatedJson<NewPromotionDto>,
+) -> AppResult<impl IntoResponse> {
+    let repo = PromotionRepository::new(state.db.clone());
+    let svc = PromotionService::new(repo);
+
+    let promotion = svc.create(payload).await?;
+    Ok((StatusCode::CREATED, Json(promotion)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/promotion/{id}/deactivate",
+    params(
+        ("id" = Uuid, Path, description = "Promotion ID")
+    ),
+    responses(
+        (status = 200, description = "Promotion deactivated", body = Promotion),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Promotion not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "Promotions"
+)]
+async fn deactivate_promotion(
+    State(state): State<AppState>,
+    AdminUser(_claims): AdminUser,
+    Path(id): Path<Uuid>,
+) -> AppResult<impl IntoResponse> {
+    let repo = PromotionRepository::new(state.db.clone());
+    let svc = PromotionService::new(repo);
+
+    match svc.deactivate(id).await? {
+        Some(promotion) => Ok((StatusCode::OK, Json(promotion))),
+        None => Err(AppError::NotFound(format!("Promotion with id {} not found", id))),
+    }
+}
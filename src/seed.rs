@@ -0,0 +1,138 @@
+//! Sample data for development databases, used by the `seed` binary.
+
+use argon2::{password_hash::{rand_core::OsRng, SaltString}, Argon2, PasswordHasher};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::env;
+
+use crate::repository::{CategoryRepository, ProductRepository, UserRepository};
+
+/// What to insert. Names are prefixed with `label` so several seeded sets can live side by side.
+#[derive(Debug, Clone)]
+pub struct SeedConfig {
+    pub label: String,
+    pub categories: usize,
+    pub products: usize,
+    pub demo_email: String,
+    pub demo_password: String,
+}
+
+impl Default for SeedConfig {
+    fn default() -> Self {
+        Self {
+            label: "Demo".to_string(),
+            categories: 5,
+            products: 25,
+            demo_email: "demo@example.com".to_string(),
+            demo_password: "demo-password".to_string(),
+        }
+    }
+}
+
+impl SeedConfig {
+    /// Reads `SEED_CATEGORIES`, `SEED_PRODUCTS`, `SEED_DEMO_EMAIL` and `SEED_DEMO_PASSWORD`.
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = Self::default();
+        let read = |key: &str, default: usize, min: usize| -> Result<usize, String> {
+            match env::var(key) {
+                Err(_) => Ok(default),
+                Ok(raw) => raw
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|v| *v >= min)
+                    .ok_or_else(|| format!("{} must be an integer of at least {}, got '{}'", key, min, raw)),
+            }
+        };
+
+        Ok(Self {
+            categories: read("SEED_CATEGORIES", defaults.categories, 1)?,
+            products: read("SEED_PRODUCTS", defaults.products, 0)?,
+            demo_email: env::var("SEED_DEMO_EMAIL").unwrap_or(defaults.demo_email),
+            demo_password: env::var("SEED_DEMO_PASSWORD").unwrap_or(defaults.demo_password),
+            ..defaults
+        })
+    }
+}
+
+/// What a seed run did. `skipped` is set when the demo user already existed and nothing was inserted.
+#[derive(Debug, Default)]
+pub struct SeedSummary {
+    pub skipped: bool,
+    pub categories: usize,
+    pub products: usize,
+    pub demo_user_email: String,
+}
+
+/// Inserts the demo user, `config.categories` categories and `config.products` products spread
+/// across them. The demo user doubles as the marker: when it already exists, the run is skipped.
+pub async fn seed(pool: &PgPool, config: &SeedConfig) -> Result<SeedSummary, sqlx::Error> {
+    let users = UserRepository::new(pool.clone());
+    let mut summary = SeedSummary {
+        demo_user_email: config.demo_email.clone(),
+        ..Default::default()
+    };
+
+    if users.find_by_email(&config.demo_email).await?.is_some() {
+        summary.skipped = true;
+        return Ok(summary);
+    }
+
+    let salt = SaltString::generate(&mut OsRng);
+    let password_hash = Argon2::default()
+        .hash_password(config.demo_password.as_bytes(), &salt)
+        .expect("Cannot hash the password")
+        .to_string();
+    let user = users.create(&config.demo_email, &password_hash, "client").await?;
+    sqlx::query("UPDATE users SET email_verified = true WHERE id = $1")
+        .bind(user.id)
+        .execute(pool)
+        .await?;
+
+    let categories = CategoryRepository::new(pool.clone());
+    let mut category_ids = Vec::with_capacity(config.categories);
+    for i in 1..=config.categories {
+        let category = categories
+            .create(
+                &format!("{} Category {}", config.label, i),
+                Some("Sample category for local development"),
+                None,
+            )
+            .await?;
+        category_ids.push(category.id);
+    }
+    summary.categories = category_ids.len();
+
+    let products = ProductRepository::new(pool.clone());
+    for i in 1..=config.products {
+        let product = products
+            .create(
+                &format!("{} Product {}", config.label, i),
+                None,
+                Some("Sample product for local development"),
+                sample_price(i),
+                sample_stock(i),
+                None,
+                Some(5),
+                None,
+                true,
+            )
+            .await?;
+        if let Some(category_id) = category_ids.get(i % category_ids.len().max(1)) {
+            categories.assign_product(*category_id, product.id).await?;
+        }
+        summary.products += 1;
+    }
+
+    Ok(summary)
+}
+
+/// Prices between 4.99 and 49.99 in whole-dollar steps.
+fn sample_price(i: usize) -> Decimal {
+    Decimal::new(((i * 7) % 46 + 4) as i64 * 100 + 99, 2)
+}
+
+/// Cycles through out of stock, low and plenty so every stock view has something to show.
+fn sample_stock(i: usize) -> i32 {
+    [0, 3, 12, 40, 150][i % 5]
+}
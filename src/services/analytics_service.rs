@@ -0,0 +1,51 @@
+use crate::errors::{AppError, AppResult};
+use crate::model::analytics::SalesSummary;
+use crate::repository::AnalyticsRepository;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+
+#[derive(Clone)]
+pub struct AnalyticsService {
+    repo: AnalyticsRepository,
+}
+
+impl AnalyticsService {
+    pub fn new(repo: AnalyticsRepository) -> Self {
+        Self { repo }
+    }
+
+    /// Revenue, order count, average order value and best sellers for sales placed in
+    /// `[from, to)`. Either bound may be omitted; a range with no sales reports zeros.
+    pub async fn sales_summary(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        top: i64,
+    ) -> AppResult<SalesSummary> {
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                return Err(AppError::Validation("from must not be later than to".into()));
+            }
+        }
+
+        let (order_count, total_revenue) = self.repo.sales_totals(from, to).await.map_err(AppError::Database)?;
+        let top_products = self.repo.top_products(from, to, top).await.map_err(AppError::Database)?;
+
+        Ok(SalesSummary {
+            from,
+            to,
+            total_revenue,
+            order_count,
+            average_order_value: average(total_revenue, order_count),
+            top_products,
+        })
+    }
+}
+
+/// Mean order value rounded to the cent; zero when there were no orders.
+pub fn average(revenue: Decimal, order_count: i64) -> Decimal {
+    if order_count == 0 {
+        return Decimal::ZERO;
+    }
+    (revenue / Decimal::from(order_count)).round_dp(2)
+}
@@ -1,26 +1,61 @@
-use crate::dtos::{Claims, LoginDto, SignupDto};
+use crate::dtos::{Claims, LoginDto, ResetPasswordDto, SignupDto};
+use crate::errors::{AppError, AppResult};
 use crate::model::user::User;
 use crate::repository::UserRepository;
+use crate::services::mailer::{Mailer, NoopMailer};
+use crate::state::JwtConfig;
 use argon2::password_hash::SaltString;
-use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
 use argon2::{self, Argon2, PasswordVerifier as _};
 use argon2::{PasswordHash, PasswordHasher};
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use jsonwebtoken::{EncodingKey, Header, encode};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use uuid::Uuid;
 
+/// How long a password reset token stays valid after it is issued.
+const RESET_TOKEN_TTL_MINUTES: i64 = 60;
+/// How long an email verification token stays valid after signup.
+const VERIFICATION_TOKEN_TTL_HOURS: i64 = 24;
 
 #[derive(Clone)]
 pub struct AuthService {
     repo: UserRepository,
     jwt_secret: String,
+    jwt_config: JwtConfig,
+    require_email_verification: bool,
+    mailer: Arc<dyn Mailer>,
 }
 
 impl AuthService {
     pub fn new(repo: UserRepository, jwt_secret: String) -> Self {
-        Self { repo, jwt_secret }
+        Self { repo, jwt_secret, jwt_config: JwtConfig::default(), require_email_verification: false, mailer: Arc::new(NoopMailer) }
     }
 
-    pub async fn signup(&self, dto: SignupDto) -> Result<User, sqlx::Error> {
+    pub fn with_jwt_config(mut self, jwt_config: JwtConfig) -> Self {
+        self.jwt_config = jwt_config;
+        self
+    }
+
+    /// Refuses login to accounts that haven't verified their email address yet.
+    pub fn with_email_verification(mut self, required: bool) -> Self {
+        self.require_email_verification = required;
+        self
+    }
+
+    /// Sends account emails through `mailer` instead of dropping them.
+    pub fn with_mailer(mut self, mailer: Arc<dyn Mailer>) -> Self {
+        self.mailer = mailer;
+        self
+    }
+
+    pub async fn signup(&self, dto: SignupDto) -> AppResult<User> {
+        let email = normalize_email(&dto.email);
+        if self.repo.find_by_email(&email).await?.is_some() {
+            return Err(AppError::Validation("Email already registered".into()));
+        }
+
         let salt = SaltString::generate(&mut OsRng);
 
         let password_hash = Argon2::default()
@@ -28,26 +63,90 @@ impl AuthService {
             .expect("Cannot hash the password")
             .to_string();
 
-        self.repo.create(&dto.email, &password_hash, "client").await
+        // A concurrent signup can still slip past the check above; the unique index catches it
+        let user = self.repo.create(&email, &password_hash, "client").await
+            .map_err(|e| match e {
+                sqlx::Error::Database(ref db_err) if db_err.is_unique_violation() => {
+                    AppError::Validation("Email already registered".into())
+                }
+                e => AppError::Database(e),
+            })?;
+
+        let token = self.issue_verification_token(user.id).await?;
+        self.mailer.send_email_verification(&user.email, &token).await;
+
+        Ok(user)
+    }
+
+    /// Creates a fresh verification token for the user and returns it in raw form.
+    pub async fn issue_verification_token(&self, user_id: Uuid) -> Result<String, sqlx::Error> {
+        let token = generate_token();
+        let expires_at = Utc::now() + Duration::hours(VERIFICATION_TOKEN_TTL_HOURS);
+
+        self.repo.create_verification_token(user_id, &hash_token(&token), expires_at).await?;
+        Ok(token)
+    }
+
+    pub async fn verify_email(&self, token: &str) -> AppResult<()> {
+        self.repo.verify_email(&hash_token(token)).await?
+            .ok_or_else(|| AppError::Validation("Invalid or expired verification token".into()))?;
+        Ok(())
+    }
+
+    pub async fn profile(&self, user_id: Uuid) -> Result<Option<User>, sqlx::Error> {
+        self.repo.find_by_id(user_id).await
+    }
+
+    pub async fn list_users(&self, limit: i64, offset: i64) -> AppResult<(Vec<User>, i64)> {
+        let users = self.repo.list(limit, offset).await?;
+        let total = self.repo.count().await?;
+        Ok((users, total))
+    }
+
+    /// Changes a user's role. `acting_admin` is the caller, who may not demote themselves
+    /// so a store can't end up locked out of its last admin account by accident.
+    pub async fn update_role(&self, acting_admin: Uuid, user_id: Uuid, role: &str) -> AppResult<Option<User>> {
+        if role != "client" && role != "admin" {
+            return Err(AppError::Validation("Role must be 'client' or 'admin'".into()));
+        }
+        if acting_admin == user_id && role != "admin" {
+            return Err(AppError::Validation("Admins cannot demote themselves".into()));
+        }
+
+        Ok(self.repo.update_role(user_id, role).await?)
+    }
+
+    /// Enables or disables an account. Like role changes, an admin may not lock themselves out.
+    pub async fn set_active(&self, acting_admin: Uuid, user_id: Uuid, active: bool) -> AppResult<Option<User>> {
+        if acting_admin == user_id && !active {
+            return Err(AppError::Validation("Admins cannot deactivate themselves".into()));
+        }
+
+        Ok(self.repo.set_active(user_id, active).await?)
     }
 
-    pub async fn login(&self, dto: LoginDto) -> Result<Option<String>, sqlx::Error> {
-        if let Some(user) = self.repo.find_by_email(&dto.email).await? {
-            let parsed_hash = PasswordHash::new(&user.password_hash)
-                .expect("Cannot create password hash from raw password");
-            if Argon2::default()
-                .verify_password(dto.password.as_bytes(), &parsed_hash)
-                .is_ok()
-            {
-                let exp = (Utc::now() + Duration::hours(24)).timestamp() as usize;
+    pub async fn login(&self, dto: LoginDto) -> AppResult<Option<String>> {
+        if let Some(user) = self.repo.find_by_email(&normalize_email(&dto.email)).await? {
+            if password_matches(&user.password_hash, &dto.password) {
+                if !user.is_active {
+                    return Err(AppError::Forbidden("Account is deactivated".into()));
+                }
+                if self.require_email_verification && !user.email_verified {
+                    return Err(AppError::Forbidden("Email address has not been verified".into()));
+                }
+
+                let exp = (Utc::now() + Duration::seconds(self.jwt_config.expiry_seconds)).timestamp() as usize;
                 let claims = Claims {
                     sub: user.id,
                     email: user.email.clone(),
                     role: user.role.clone(),
                     exp,
+                    jti: Uuid::new_v4(),
+                    iss: self.jwt_config.issuer.clone(),
+                    aud: self.jwt_config.audience.clone(),
                 };
                 let token = encode(
-                    &Header::default(),
+                    &Header::new(self.jwt_config.algorithm),
                     &claims,
                     &EncodingKey::from_secret(self.jwt_secret.as_bytes()),
                 )
@@ -60,4 +159,78 @@ impl AuthService {
             Ok(None)
         }
     }
+
+    /// Deletes the caller's own account once they re-enter their password. Orders are kept
+    /// under the anonymized user; see `UserRepository::delete`.
+    pub async fn delete_account(&self, user_id: Uuid, password: &str) -> AppResult<()> {
+        let user = self.repo.find_by_id(user_id).await?
+            .ok_or_else(|| AppError::NotFound(format!("User with id {} not found", user_id)))?;
+        if !password_matches(&user.password_hash, password) {
+            return Err(AppError::Forbidden("Password is incorrect".into()));
+        }
+
+        self.repo.delete(user_id).await?;
+        Ok(())
+    }
+
+    pub async fn logout(&self, claims: &Claims) -> AppResult<()> {
+        let expires_at = DateTime::from_timestamp(claims.exp as i64, 0)
+            .ok_or_else(|| AppError::Validation("Token has an invalid expiry".into()))?;
+        self.repo.revoke_token(claims.jti, claims.sub, expires_at).await?;
+        Ok(())
+    }
+
+    /// Issues a reset token for the account with this email, if one exists, and mails it to
+    /// the user; only its hash is stored.
+    pub async fn request_password_reset(&self, email: &str) -> Result<(), sqlx::Error> {
+        let Some(user) = self.repo.find_by_email(&normalize_email(email)).await? else {
+            return Ok(());
+        };
+
+        let token = generate_token();
+        let expires_at = Utc::now() + Duration::minutes(RESET_TOKEN_TTL_MINUTES);
+
+        self.repo.create_reset_token(user.id, &hash_token(&token), expires_at).await?;
+        self.mailer.send_password_reset(&user.email, &token).await;
+        Ok(())
+    }
+
+    pub async fn reset_password(&self, dto: ResetPasswordDto) -> AppResult<()> {
+        let user_id = self.repo.consume_reset_token(&hash_token(&dto.token)).await?
+            .ok_or_else(|| AppError::Validation("Invalid or expired reset token".into()))?;
+
+        let salt = SaltString::generate(&mut OsRng);
+        let password_hash = Argon2::default()
+            .hash_password(dto.new_password.as_bytes(), &salt)
+            .map_err(|e| AppError::Internal(e.to_string()))?
+            .to_string();
+
+        self.repo.update_password(user_id, &password_hash).await?;
+        Ok(())
+    }
+}
+
+fn password_matches(password_hash: &str, password: &str) -> bool {
+    let parsed_hash = PasswordHash::new(password_hash)
+        .expect("Cannot create password hash from raw password");
+    Argon2::default()
+        .verify_password(password.as_bytes(), &parsed_hash)
+        .is_ok()
+}
+
+/// Emails are stored and looked up lowercased so `User@x.com` and `user@x.com` are one account.
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Random 256-bit token, hex encoded, for links sent to the user.
+fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Tokens are stored hashed so a leaked table can't be replayed.
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
 }
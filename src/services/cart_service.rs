@@ -1,20 +1,109 @@
-use crate::repository::CartRepository;
-use crate::dtos::AddToCartDto;
+use crate::repository::{CartRepository, ProductRepository, PromotionRepository};
+use crate::dtos::{AddToCartDto, CartQuoteItem, CartQuoteResponse};
+use crate::errors::{AppError, AppResult};
 use crate::model::cart::Cart;
+use crate::model::promotion::PromotionStacking;
+use crate::services::promotion_service::PromotionService;
+use crate::state::CartLimits;
+use rust_decimal::Decimal;
 
 #[derive(Clone)]
 pub struct CartService {
     repo: CartRepository,
+    limits: CartLimits,
+    promotion_stacking: PromotionStacking,
 }
 
 impl CartService {
     pub fn new(repo: CartRepository) -> Self {
-        Self { repo }
+        Self { repo, limits: CartLimits::default(), promotion_stacking: PromotionStacking::default() }
     }
 
-    pub async fn add_to_cart(&self, user_id: uuid::Uuid, dto: AddToCartDto) -> Result<Cart, sqlx::Error> {
+    pub fn with_limits(mut self, limits: CartLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// How promotions combine in quotes, which should match how orders are priced.
+    pub fn with_promotion_stacking(mut self, stacking: PromotionStacking) -> Self {
+        self.promotion_stacking = stacking;
+        self
+    }
+
+    /// Adds `dto.quantity` units to the product's line in the user's cart, within the cart limits.
+    pub async fn add_to_cart(&self, user_id: uuid::Uuid, dto: AddToCartDto) -> AppResult<Cart> {
+        if dto.quantity <= 0 {
+            return Err(AppError::Validation("quantity: Quantity must be at least 1".into()));
+        }
+
         let cart = self.repo.get_or_create_cart(user_id).await?;
+        let items = self.repo.get_cart_items(cart.id).await?;
+
+        let in_cart: i64 = items
+            .iter()
+            .filter(|item| item.product_id == dto.product_id)
+            .map(|item| item.quantity as i64)
+            .sum();
+        if in_cart + dto.quantity as i64 > self.limits.max_item_quantity as i64 {
+            return Err(AppError::Validation(format!(
+                "quantity: At most {} of a product fit in the cart ({} already there)",
+                self.limits.max_item_quantity, in_cart
+            )));
+        }
+
+        let mut distinct: Vec<uuid::Uuid> = items.iter().map(|item| item.product_id).collect();
+        distinct.sort();
+        distinct.dedup();
+        if in_cart == 0 && distinct.len() >= self.limits.max_distinct_items {
+            return Err(AppError::Validation(format!(
+                "The cart already holds the maximum of {} different products",
+                self.limits.max_distinct_items
+            )));
+        }
+
         self.repo.add_item(cart.id, dto.product_id, dto.quantity).await?;
         Ok(cart)
     }
+
+    /// Prices the user's cart with the promotions that would apply at checkout.
+    pub async fn quote(&self, user_id: uuid::Uuid) -> AppResult<CartQuoteResponse> {
+        let product_repo = ProductRepository::new(self.repo.pool.clone());
+
+        let cart_items = match self.repo.get_cart_by_user(user_id).await.map_err(AppError::Database)? {
+            Some(cart) => self.repo.get_cart_items(cart.id).await.map_err(AppError::Database)?,
+            None => Vec::new(),
+        };
+
+        let mut subtotal = Decimal::new(0, 2);
+        let mut items = Vec::with_capacity(cart_items.len());
+
+        for cart_item in cart_items {
+            let product = product_repo.get(cart_item.product_id).await
+                .map_err(AppError::Database)?
+                .ok_or_else(|| AppError::Validation(format!("Product {} not found", cart_item.product_id)))?;
+
+            let line_total = product.price * Decimal::new(cart_item.quantity as i64, 0);
+            subtotal += line_total;
+
+            items.push(CartQuoteItem {
+                product_id: product.id,
+                product_name: product.name,
+                quantity: cart_item.quantity,
+                price: product.price,
+                subtotal: line_total,
+            });
+        }
+
+        let promotion_svc = PromotionService::new(PromotionRepository::new(self.repo.pool.clone()))
+            .with_stacking(self.promotion_stacking);
+        let promotions = promotion_svc.evaluate(subtotal).await?;
+
+        Ok(CartQuoteResponse {
+            items,
+            subtotal,
+            discount: promotions.discount,
+            applied_promotions: promotions.applied,
+            total: subtotal - promotions.discount,
+        })
+    }
 }
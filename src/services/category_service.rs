@@ -1,6 +1,9 @@
 use crate::repository::CategoryRepository;
-use crate::dtos::{NewCategoryDto, UpdateCategoryDto};
-use crate::model::category::Category;
+use crate::dtos::{CategoryResponse, CategoryTreeNode, NewCategoryDto, UpdateCategoryDto};
+use crate::errors::{AppError, AppResult};
+use crate::model::category::{Category, CategoryWithCount};
+use crate::model::product::ProductWithAvailableStock;
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
 #[derive(Clone)]
@@ -13,27 +16,184 @@ impl CategoryService {
         Self { repo }
     }
 
-    pub async fn create(&self, dto: NewCategoryDto) -> Result<Category, sqlx::Error> {
-        self.repo.create(&dto.name, dto.description.as_deref()).await
+    pub async fn create(&self, dto: NewCategoryDto) -> AppResult<Category> {
+        self.check_name(&dto.name, None).await?;
+        if let Some(parent_id) = dto.parent_id {
+            check_ancestors(None, &self.repo.ancestor_ids(parent_id).await?)?;
+        }
+
+        self.repo
+            .create(&dto.name, dto.description.as_deref(), dto.parent_id)
+            .await
+            .map_err(|e| duplicate_name(e, &dto.name))
     }
 
     pub async fn get(&self, id: Uuid) -> Result<Option<Category>, sqlx::Error> {
         self.repo.get(id).await
     }
 
-    pub async fn list(&self, limit: i64, offset: i64) -> Result<Vec<Category>, sqlx::Error> {
-        self.repo.list(limit, offset).await
+    /// A page of categories with their product counts, and the total number of categories.
+    pub async fn list_with_counts(&self, limit: i64, offset: i64) -> Result<(Vec<CategoryWithCount>, i64), sqlx::Error> {
+        let categories = self.repo.list_with_counts(limit, offset).await?;
+        let total = self.repo.count().await?;
+        Ok((categories, total))
+    }
+
+    pub async fn list_children(&self, parent_id: Uuid) -> Result<Vec<Category>, sqlx::Error> {
+        self.repo.list_children(parent_id).await
+    }
+
+    /// The path from the root category down to `id`, for breadcrumbs.
+    pub async fn breadcrumb(&self, id: Uuid) -> AppResult<Vec<Category>> {
+        let path = self.repo.ancestors(id).await?;
+        if path.is_empty() {
+            return Err(AppError::NotFound(format!("Category with id {} not found", id)));
+        }
+        Ok(path)
+    }
+
+    pub async fn tree(&self) -> Result<Vec<CategoryTreeNode>, sqlx::Error> {
+        Ok(build_tree(self.repo.list_all().await?))
+    }
+
+    /// A page of the category's products and the total number it has.
+    pub async fn list_products(&self, category_id: Uuid, limit: i64, offset: i64) -> AppResult<(Vec<ProductWithAvailableStock>, i64)> {
+        if self.repo.get(category_id).await?.is_none() {
+            return Err(AppError::NotFound(format!("Category with id {} not found", category_id)));
+        }
+
+        let products = self.repo.list_products(category_id, limit, offset).await?;
+        let total = self.repo.count_products(category_id).await?;
+        Ok((products, total))
     }
 
-    pub async fn update(&self, id: Uuid, dto: UpdateCategoryDto) -> Result<Option<Category>, sqlx::Error> {
-        self.repo.update(id, dto.name.as_deref(), dto.description.as_deref()).await
+    pub async fn update(&self, id: Uuid, dto: UpdateCategoryDto) -> AppResult<Option<Category>> {
+        if let Some(name) = &dto.name {
+            self.check_name(name, Some(id)).await?;
+        }
+
+        // Check and move in one transaction with the chain locked, so two concurrent moves
+        // can't each pass the check and leave a loop between them
+        let mut tx = self.repo.pool.begin().await?;
+        if let Some(Some(parent_id)) = dto.parent_id {
+            let ancestors = CategoryRepository::lock_ancestor_ids(&mut tx, id, parent_id).await?;
+            check_ancestors(Some(id), &ancestors)?;
+        }
+
+        let updated = CategoryRepository::update(&mut tx, id, dto.name.as_deref(), dto.description.as_ref().map(|d| d.as_deref()), dto.parent_id)
+            .await
+            .map_err(|e| duplicate_name(e, dto.name.as_deref().unwrap_or_default()))?;
+        tx.commit().await?;
+        Ok(updated)
     }
 
-    pub async fn delete(&self, id: Uuid) -> Result<bool, sqlx::Error> {
-        self.repo.delete(id).await
+    /// Deletes the category, first moving its products into `reassign_to` when given. Without
+    /// a target, a category that still has products is left alone rather than unlinking them.
+    pub async fn delete(&self, id: Uuid, reassign_to: Option<Uuid>) -> AppResult<bool> {
+        if self.repo.get(id).await?.is_none() {
+            return Ok(false);
+        }
+
+        match reassign_to {
+            Some(target_id) if target_id == id => {
+                Err(AppError::Validation("Cannot reassign products to the category being deleted".into()))
+            }
+            Some(target_id) => {
+                if self.repo.get(target_id).await?.is_none() {
+                    return Err(AppError::Validation("Reassignment category not found".into()));
+                }
+                Ok(self.repo.reassign_and_delete(id, target_id).await?)
+            }
+            None => {
+                let assigned = self.repo.count_assigned_products(id).await?;
+                if assigned > 0 {
+                    return Err(AppError::Conflict(format!(
+                        "Category has {} assigned product(s); pass reassign_to to move them first",
+                        assigned
+                    )));
+                }
+                Ok(self.repo.delete(id).await?)
+            }
+        }
     }
 
-    pub async fn assign_product(&self, category_id: Uuid, product_id: Uuid) -> Result<(), sqlx::Error> {
-        self.repo.assign_product(category_id, product_id).await
+    pub async fn assign_product(&self, category_id: Uuid, product_id: Uuid) -> AppResult<()> {
+        if self.repo.get(category_id).await?.is_none() {
+            return Err(AppError::NotFound(format!("Category with id {} not found", category_id)));
+        }
+        if !self.repo.product_exists(product_id).await? {
+            return Err(AppError::NotFound(format!("Product with id {} not found", product_id)));
+        }
+
+        Ok(self.repo.assign_product(category_id, product_id).await?)
     }
+
+    pub async fn assign_products(&self, category_id: Uuid, product_ids: &[Uuid]) -> AppResult<u64> {
+        if self.repo.get(category_id).await?.is_none() {
+            return Err(AppError::NotFound(format!("Category with id {} not found", category_id)));
+        }
+
+        Ok(self.repo.assign_products(category_id, product_ids).await?)
+    }
+
+    pub async fn unassign_product(&self, category_id: Uuid, product_id: Uuid) -> Result<bool, sqlx::Error> {
+        self.repo.unassign_product(category_id, product_id).await
+    }
+
+    /// Category names are unique regardless of case; `category_id` is the one being renamed.
+    async fn check_name(&self, name: &str, category_id: Option<Uuid>) -> AppResult<()> {
+        if self.repo.name_taken(name, category_id).await? {
+            return Err(AppError::Validation(format!("A category named '{}' already exists", name)));
+        }
+        Ok(())
+    }
+}
+
+/// `ancestors` are the new parent's ids up to the root. The parent must exist and, when
+/// re-parenting `category_id`, must not be the category itself or one of its descendants,
+/// which would turn the hierarchy into a loop.
+fn check_ancestors(category_id: Option<Uuid>, ancestors: &[Uuid]) -> AppResult<()> {
+    if ancestors.is_empty() {
+        return Err(AppError::Validation("Parent category not found".into()));
+    }
+    if category_id.is_some_and(|id| ancestors.contains(&id)) {
+        return Err(AppError::Validation("A category cannot be nested under itself or its subcategories".into()));
+    }
+    Ok(())
+}
+
+/// A concurrent create or rename can still slip past [`CategoryService::check_name`]; the
+/// unique index catches it.
+fn duplicate_name(err: sqlx::Error, name: &str) -> AppError {
+    match err {
+        sqlx::Error::Database(ref db_err) if db_err.is_unique_violation() => {
+            AppError::Validation(format!("A category named '{}' already exists", name))
+        }
+        e => AppError::Database(e),
+    }
+}
+
+/// Nests a flat list of categories under their parents. Categories whose parent isn't in
+/// the list are treated as roots; sibling order follows the input order.
+pub fn build_tree(categories: Vec<Category>) -> Vec<CategoryTreeNode> {
+    let ids: HashSet<Uuid> = categories.iter().map(|c| c.id).collect();
+    let mut roots = Vec::new();
+    let mut children: HashMap<Uuid, Vec<Category>> = HashMap::new();
+
+    for c in categories {
+        match c.parent_id {
+            Some(parent_id) if ids.contains(&parent_id) => children.entry(parent_id).or_default().push(c),
+            _ => roots.push(c),
+        }
+    }
+
+    fn attach(c: Category, children: &mut HashMap<Uuid, Vec<Category>>) -> CategoryTreeNode {
+        let kids = children.remove(&c.id).unwrap_or_default();
+        CategoryTreeNode {
+            category: CategoryResponse::from(c),
+            children: kids.into_iter().map(|k| attach(k, children)).collect(),
+        }
+    }
+
+    roots.into_iter().map(|c| attach(c, &mut children)).collect()
 }
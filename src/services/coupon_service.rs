@@ -0,0 +1,131 @@
+use crate::dtos::CouponDto;
+use crate::errors::{AppError, AppResult};
+use crate::model::coupon::Coupon;
+use crate::repository::CouponRepository;
+use chrono::{DateTime, Utc};
+use sqlx::PgConnection;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct CouponService {
+    repo: CouponRepository,
+}
+
+impl CouponService {
+    pub fn new(repo: CouponRepository) -> Self {
+        Self { repo }
+    }
+
+    pub async fn create(&self, dto: CouponDto) -> AppResult<Coupon> {
+        let code = validate(&dto)?;
+
+        self.repo
+            .create(&code, dto.percent_off, dto.amount_off, dto.expires_at, dto.usage_limit)
+            .await
+            .map_err(map_code_conflict)
+    }
+
+    /// A page of coupons and the total number of coupons.
+    pub async fn list(&self, limit: i64, offset: i64) -> AppResult<(Vec<Coupon>, i64)> {
+        let coupons = self.repo.list(limit, offset).await.map_err(AppError::Database)?;
+        let total = self.repo.count().await.map_err(AppError::Database)?;
+        Ok((coupons, total))
+    }
+
+    pub async fn get(&self, id: Uuid) -> AppResult<Option<Coupon>> {
+        self.repo.get(id).await.map_err(AppError::Database)
+    }
+
+    pub async fn update(&self, id: Uuid, dto: CouponDto) -> AppResult<Option<Coupon>> {
+        let code = validate(&dto)?;
+
+        self.repo
+            .update(id, &code, dto.percent_off, dto.amount_off, dto.expires_at, dto.usage_limit)
+            .await
+            .map_err(map_code_conflict)
+    }
+
+    pub async fn delete(&self, id: Uuid) -> AppResult<bool> {
+        self.repo.delete(id).await.map_err(AppError::Database)
+    }
+
+    /// Looks up a code entered at checkout and checks it can still be used.
+    pub async fn find_usable(&self, code: &str) -> AppResult<Coupon> {
+        let coupon = self.repo
+            .find_by_code(&normalize_code(code))
+            .await
+            .map_err(AppError::Database)?
+            .ok_or_else(|| AppError::Validation("coupon_code: Unknown coupon code".to_string()))?;
+
+        check_usable(&coupon, Utc::now())?;
+        Ok(coupon)
+    }
+
+    /// Counts a use of the coupon, as part of the transaction that creates the order. Fails if it
+    /// expired or ran out of uses since it was checked.
+    pub async fn redeem(conn: &mut PgConnection, coupon: &Coupon) -> AppResult<()> {
+        match CouponRepository::redeem(conn, coupon.id).await.map_err(AppError::Database)? {
+            Some(_) => Ok(()),
+            None => Err(AppError::Validation("coupon_code: Coupon can no longer be used".to_string())),
+        }
+    }
+}
+
+/// Codes are case-insensitive and stored upper-case.
+pub fn normalize_code(code: &str) -> String {
+    code.trim().to_uppercase()
+}
+
+/// Whether the coupon may be redeemed at `now`.
+pub fn check_usable(coupon: &Coupon, now: DateTime<Utc>) -> AppResult<()> {
+    if coupon.expires_at.is_some_and(|expires_at| expires_at <= now) {
+        return Err(AppError::Validation("coupon_code: Coupon has expired".to_string()));
+    }
+    if coupon.usage_limit.is_some_and(|limit| coupon.times_used >= limit) {
+        return Err(AppError::Validation("coupon_code: Coupon has reached its usage limit".to_string()));
+    }
+    Ok(())
+}
+
+/// The discount the coupon gives on `amount`, rounded to the cent and never more than `amount`.
+pub fn coupon_discount(coupon: &Coupon, amount: Decimal) -> Decimal {
+    let discount = match (coupon.percent_off, coupon.amount_off) {
+        (Some(percent), _) => (amount * percent / Decimal::ONE_HUNDRED).round_dp(2),
+        (None, Some(fixed)) => fixed,
+        (None, None) => Decimal::ZERO,
+    };
+    discount.min(amount).max(Decimal::ZERO)
+}
+
+/// Checks the settings and returns the normalized code.
+fn validate(dto: &CouponDto) -> AppResult<String> {
+    let code = normalize_code(&dto.code);
+    if code.is_empty() {
+        return Err(AppError::Validation("code: Coupon code cannot be blank".to_string()));
+    }
+
+    match (dto.percent_off, dto.amount_off) {
+        (Some(percent), None) if percent > Decimal::ZERO && percent <= Decimal::ONE_HUNDRED => Ok(code),
+        (Some(_), None) => Err(AppError::Validation(
+            "percent_off: Discount must be greater than 0 and at most 100".to_string(),
+        )),
+        (None, Some(amount)) if amount > Decimal::ZERO => Ok(code),
+        (None, Some(_)) => Err(AppError::Validation(
+            "amount_off: Discount must be greater than 0".to_string(),
+        )),
+        _ => Err(AppError::Validation(
+            "Set exactly one of percent_off or amount_off".to_string(),
+        )),
+    }
+}
+
+/// Turns a violation of the coupons code unique constraint into a client error.
+fn map_code_conflict(err: sqlx::Error) -> AppError {
+    if let sqlx::Error::Database(ref db_err) = err {
+        if db_err.constraint() == Some("coupons_code_key") {
+            return AppError::Validation("Coupon code already in use".into());
+        }
+    }
+    AppError::Database(err)
+}
@@ -0,0 +1,37 @@
+use std::time::Duration;
+
+/// Attempts per request, counting the first one.
+pub const MAX_ATTEMPTS: u32 = 3;
+
+/// Sends the request `build` makes, sending it again after server errors (5xx), timeouts and
+/// connection failures, up to [`MAX_ATTEMPTS`] times. The wait starts at `initial_backoff` and
+/// doubles after each retry. Returns the last response or error, which the caller judges;
+/// `what` names the request in the retry warnings.
+pub async fn send_with_retry(
+    build: impl Fn() -> reqwest::RequestBuilder,
+    initial_backoff: Duration,
+    what: &str,
+) -> Result<reqwest::Response, reqwest::Error> {
+    let mut backoff = initial_backoff;
+    let mut attempt = 1;
+
+    loop {
+        let result = build().send().await;
+
+        let error = match &result {
+            Ok(response) if response.status().is_server_error() && attempt < MAX_ATTEMPTS => {
+                format!("returned {}", response.status())
+            }
+            Err(e) if (e.is_timeout() || e.is_connect()) && attempt < MAX_ATTEMPTS => e.to_string(),
+            _ => return result,
+        };
+
+        tracing::warn!(
+            "{} failed (attempt {}/{}), retrying in {:?}: {}",
+            what, attempt, MAX_ATTEMPTS, backoff, error
+        );
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+        attempt += 1;
+    }
+}
@@ -1,20 +1,35 @@
-use crate::errors::{AppResult};
+use crate::errors::{AppError, AppResult};
+use crate::state::CloudinaryConfig;
 use axum::extract::multipart::Multipart;
+use chrono::Utc;
 // use cloudinary::{
 //     client::Cloudinary,
 //     upload::{Upload, UploadOptions},
 // };
 // use std::io::Cursor;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 pub struct ImageService {
     // cloudinary: Cloudinary, // Temporarily disabled
+    http: reqwest::Client,
+    cloud_name: String,
+    api_key: String,
+    api_secret: String,
+    api_base: String,
 }
 
 impl ImageService {
-    pub fn new(_cloud_name: &str, _api_key: &str, _api_secret: &str) -> AppResult<Self> {
+    pub fn new(http: reqwest::Client, config: &CloudinaryConfig) -> AppResult<Self> {
         // Temporarily disabled cloudinary initialization
-        Ok(Self { /* cloudinary */ })
+        Ok(Self {
+            http,
+            cloud_name: config.cloud_name.clone(),
+            api_key: config.api_key.clone(),
+            api_secret: config.api_secret.clone(),
+            api_base: config.api_base.trim_end_matches('/').to_string(),
+        })
     }
 
     pub async fn upload_product_image(&self, mut _multipart: Multipart) -> AppResult<String> {
@@ -23,9 +38,48 @@ impl ImageService {
         Ok(format!("https://placeholder.com/products/{}.jpg", fake_image_id))
     }
 
-    pub async fn delete_image(&self, _public_id: &str) -> AppResult<()> {
-        // Placeholder implementation
-        Ok(())
+    pub async fn delete_image(&self, public_id: &str) -> AppResult<()> {
+        let timestamp = Utc::now().timestamp().to_string();
+
+        // Cloudinary signs the alphabetically sorted params followed by the API secret
+        let to_sign = format!("public_id={}&timestamp={}{}", public_id, timestamp, self.api_secret);
+        let signature = hex::encode(Sha1::digest(to_sign.as_bytes()));
+
+        let mut params = HashMap::new();
+        params.insert("public_id", public_id.to_string());
+        params.insert("timestamp", timestamp);
+        params.insert("api_key", self.api_key.clone());
+        params.insert("signature", signature);
+
+        let response = self
+            .http
+            .post(format!(
+                "{}/v1_1/{}/image/destroy",
+                self.api_base, self.cloud_name
+            ))
+            .form(&params)
+            .send()
+            .await
+            .map_err(|e| AppError::ImageUpload(e.to_string()))?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await
+                .unwrap_or_else(|_| "Unknown Cloudinary API error".to_string());
+            return Err(AppError::ImageUpload(error_text));
+        }
+
+        let body: serde_json::Value = response.json().await
+            .map_err(|e| AppError::ImageUpload(e.to_string()))?;
+
+        // Cloudinary answers 200 with {"result": "not found"} for unknown ids
+        match body["result"].as_str() {
+            Some("ok") => Ok(()),
+            Some(result) => Err(AppError::ImageUpload(format!(
+                "Cloudinary destroy failed for {}: {}",
+                public_id, result
+            ))),
+            None => Err(AppError::ImageUpload("Missing result in Cloudinary response".to_string())),
+        }
     }
 }
 
@@ -0,0 +1,126 @@
+use crate::dtos::order::InvoiceResponse;
+use crate::errors::{AppError, AppResult};
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Document, Object, Stream};
+
+const PAGE_WIDTH: i64 = 595; // A4 in points
+const PAGE_HEIGHT: i64 = 842;
+const MARGIN: i64 = 50;
+const FONT_SIZE: i64 = 10;
+const LINE_HEIGHT: i64 = 14;
+const LINES_PER_PAGE: usize = ((PAGE_HEIGHT - 2 * MARGIN) / LINE_HEIGHT) as usize;
+
+/// Renders an invoice as a plain monospaced PDF, continuing onto further pages when the item
+/// list is long.
+pub fn render(invoice: &InvoiceResponse) -> AppResult<Vec<u8>> {
+    let lines = invoice_lines(invoice);
+
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Courier",
+    });
+    let resources_id = doc.add_object(dictionary! {
+        "Font" => dictionary! { "F1" => font_id },
+    });
+
+    let mut page_ids = Vec::new();
+    for chunk in lines.chunks(LINES_PER_PAGE) {
+        let mut operations = vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec!["F1".into(), FONT_SIZE.into()]),
+            Operation::new("TL", vec![LINE_HEIGHT.into()]),
+            Operation::new("Td", vec![MARGIN.into(), (PAGE_HEIGHT - MARGIN).into()]),
+        ];
+        for line in chunk {
+            operations.push(Operation::new("Tj", vec![Object::string_literal(line.as_str())]));
+            operations.push(Operation::new("T*", vec![]));
+        }
+        operations.push(Operation::new("ET", vec![]));
+
+        let content = Content { operations }
+            .encode()
+            .map_err(|e| AppError::Internal(format!("Failed to encode invoice page: {}", e)))?;
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content));
+        page_ids.push(doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+        }));
+    }
+
+    let page_count = page_ids.len() as i64;
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => page_ids.into_iter().map(Object::Reference).collect::<Vec<_>>(),
+            "Count" => page_count,
+            "Resources" => resources_id,
+            "MediaBox" => vec![0.into(), 0.into(), PAGE_WIDTH.into(), PAGE_HEIGHT.into()],
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+
+    let mut bytes = Vec::new();
+    doc.save_to(&mut bytes)
+        .map_err(|e| AppError::Internal(format!("Failed to write invoice PDF: {}", e)))?;
+    Ok(bytes)
+}
+
+fn invoice_lines(invoice: &InvoiceResponse) -> Vec<String> {
+    let mut lines = vec![
+        format!("INVOICE {}", invoice.invoice_number),
+        String::new(),
+        format!("Order:    {}", invoice.order_id),
+        format!("Date:     {}", invoice.issued_at.format("%Y-%m-%d")),
+        format!("Customer: {}", invoice.customer_email),
+        format!("Status:   {} (payment: {})", invoice.order_status, invoice.payment_status.as_deref().unwrap_or("none")),
+        String::new(),
+        format!("{:<40} {:>5} {:>10} {:>10}", "Item", "Qty", "Price", "Amount"),
+        "-".repeat(68),
+    ];
+    for item in &invoice.items {
+        lines.push(format!(
+            "{:<40} {:>5} {:>10} {:>10}",
+            truncate(&item.product_name, 40),
+            item.quantity,
+            item.price.round_dp(2),
+            item.subtotal.round_dp(2),
+        ));
+    }
+    lines.push("-".repeat(68));
+    for (label, amount) in [
+        ("Subtotal", invoice.subtotal),
+        ("Discount", -invoice.discount),
+        ("Tax", invoice.tax),
+        ("Shipping", invoice.shipping),
+        ("Total", invoice.total),
+    ] {
+        lines.push(format!("{:>57} {:>10}", label, amount.round_dp(2)));
+    }
+    if let Some(notes) = &invoice.notes {
+        lines.push(String::new());
+        lines.push(format!("Notes: {}", notes));
+    }
+
+    // The built-in PDF fonts only cover ASCII reliably
+    lines
+        .into_iter()
+        .map(|line| line.chars().map(|c| if c.is_ascii() { c } else { '?' }).collect())
+        .collect()
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        s.to_string()
+    } else {
+        s.chars().take(max - 3).chain("...".chars()).collect()
+    }
+}
@@ -0,0 +1,109 @@
+use crate::state::MailConfig;
+use async_trait::async_trait;
+use serde_json::json;
+use std::sync::Arc;
+
+/// Delivers account emails carrying one-time tokens. Implementations get the raw token and
+/// must never write it to logs; a failure to send is theirs to report.
+#[async_trait]
+pub trait Mailer: std::fmt::Debug + Send + Sync {
+    /// Sends `email` the link for resetting their password with `token`.
+    async fn send_password_reset(&self, email: &str, token: &str);
+
+    /// Sends `email` the link for verifying their address with `token`.
+    async fn send_email_verification(&self, email: &str, token: &str);
+}
+
+/// The mailer `config` asks for: [`HttpMailer`] once an API key is set, [`NoopMailer`] otherwise.
+pub fn from_config(http: reqwest::Client, config: &MailConfig) -> Arc<dyn Mailer> {
+    match &config.api_key {
+        Some(api_key) => Arc::new(HttpMailer {
+            http,
+            api_key: api_key.clone(),
+            api_base: config.api_base.trim_end_matches('/').to_string(),
+            from: config.from.clone(),
+            app_url: config.app_url.clone(),
+        }),
+        None => Arc::new(NoopMailer),
+    }
+}
+
+/// Used when no mail provider is configured: drops every message.
+#[derive(Debug, Clone, Default)]
+pub struct NoopMailer;
+
+#[async_trait]
+impl Mailer for NoopMailer {
+    async fn send_password_reset(&self, _email: &str, _token: &str) {
+        tracing::warn!("No mailer configured; password reset email not sent");
+    }
+
+    async fn send_email_verification(&self, _email: &str, _token: &str) {
+        tracing::warn!("No mailer configured; email verification email not sent");
+    }
+}
+
+/// Sends plain-text emails through an email API that takes `POST {api_base}/emails` with a
+/// bearer key and a `from`/`to`/`subject`/`text` JSON body, as Resend does. The links point at
+/// the app, which passes the token on to `/api/auth/verify` or `/api/auth/reset-password`.
+#[derive(Clone)]
+pub struct HttpMailer {
+    http: reqwest::Client,
+    api_key: String,
+    api_base: String,
+    from: String,
+    app_url: String,
+}
+
+impl std::fmt::Debug for HttpMailer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpMailer")
+            .field("api_key", &"<redacted>")
+            .field("api_base", &self.api_base)
+            .field("from", &self.from)
+            .field("app_url", &self.app_url)
+            .finish()
+    }
+}
+
+impl HttpMailer {
+    /// Posts one email; failures are logged by `kind` only, since the body holds the token.
+    async fn send(&self, kind: &str, to: &str, subject: &str, text: String) {
+        let result = self
+            .http
+            .post(format!("{}/emails", self.api_base))
+            .bearer_auth(&self.api_key)
+            .json(&json!({
+                "from": self.from,
+                "to": [to],
+                "subject": subject,
+                "text": text,
+            }))
+            .send()
+            .await;
+
+        match result {
+            Ok(res) if res.status().is_success() => tracing::info!("Sent {} email", kind),
+            Ok(res) => tracing::error!("Mail API returned {} for a {} email", res.status(), kind),
+            Err(e) => tracing::error!("Failed to send {} email: {}", kind, e.without_url()),
+        }
+    }
+}
+
+#[async_trait]
+impl Mailer for HttpMailer {
+    async fn send_password_reset(&self, email: &str, token: &str) {
+        let link = format!("{}/reset-password?token={}", self.app_url, token);
+        let text = format!(
+            "Someone asked to reset the password for this account. Open this link to choose a new one:\n\n{}\n\nThe link works once and expires soon. If you didn't ask for it, you can ignore this email.",
+            link
+        );
+        self.send("password reset", email, "Reset your password", text).await;
+    }
+
+    async fn send_email_verification(&self, email: &str, token: &str) {
+        let link = format!("{}/verify-email?token={}", self.app_url, token);
+        let text = format!("Welcome! Open this link to verify your email address:\n\n{}", link);
+        self.send("email verification", email, "Verify your email address", text).await;
+    }
+}
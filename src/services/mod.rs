@@ -1,10 +1,19 @@
+pub mod analytics_service;
 pub mod auth_service;
 pub mod cart_service;
 pub mod category_service;
+pub mod coupon_service;
+pub mod http_retry;
 pub mod image_service;
+pub mod invoice_pdf;
+pub mod mailer;
 pub mod order_service;
+pub mod order_webhook_service;
 pub mod product_service;
 pub mod payment_service;
+pub mod promotion_service;
+pub mod stock_alert_service;
+pub mod stripe_client;
 
 // TODO: Re-enable when image service is actually used
 // pub use image_service::*;
@@ -1,99 +1,442 @@
-use crate::repository::{OrderRepository, ProductRepository, CartRepository};
-use crate::model::order::{Order, OrderStatus};
-use crate::dtos::order::{CreateOrderResponse, OrderDetailsResponse, OrderItemResponse};
+use crate::repository::{OrderFilter, OrderRepository, ProductRepository, CartRepository, CouponRepository, PaymentRepository, PromotionRepository, StockRepository, UserRepository};
+use crate::services::coupon_service::{self, CouponService};
+use crate::services::order_webhook_service::OrderWebhookService;
+use crate::services::promotion_service::PromotionService;
+use crate::services::stock_alert_service::LowStockNotifier;
+use crate::model::order::{Order, OrderStatus, OrderStatusChange, OrderTotals, OrderWithItems, UpdateStatusDto};
+use crate::model::promotion::PromotionStacking;
+use crate::dtos::order::{BulkStatusRequest, BulkStatusResponse, BulkStatusResult, CreateOrderRequest, CreateOrderResponse, InvoiceResponse, OrderDetailsResponse, OrderItemResponse};
 use crate::errors::AppError;
-use rust_decimal::Decimal;
+use chrono::{DateTime, Utc};
+use rust_decimal::{Decimal, RoundingStrategy};
 use uuid::Uuid;
 
+/// How long an `Idempotency-Key` keeps returning the order it first created.
+pub const IDEMPOTENCY_WINDOW_HOURS: i64 = 24;
+/// Longest accepted `Idempotency-Key` value.
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// Tax and shipping charged on top of an order's items.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OrderPricing {
+    /// Tax as a percentage of the discounted subtotal, e.g. `8.25`
+    pub tax_rate_percent: Decimal,
+    /// Shipping charged on every order below the free-shipping threshold
+    pub shipping_fee: Decimal,
+    /// Discounted subtotal from which shipping is free; `None` always charges shipping
+    pub free_shipping_threshold: Option<Decimal>,
+}
+
+impl OrderPricing {
+    /// Prices an order. Tax and the free-shipping threshold both apply to the subtotal after
+    /// promotions; tax is rounded to the cent, half away from zero.
+    pub fn totals(&self, subtotal: Decimal, discount: Decimal) -> OrderTotals {
+        let discounted = subtotal - discount;
+        let tax = (discounted * self.tax_rate_percent / Decimal::ONE_HUNDRED)
+            .round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero);
+        let shipping = match self.free_shipping_threshold {
+            Some(threshold) if discounted >= threshold => Decimal::ZERO,
+            _ => self.shipping_fee,
+        };
+
+        OrderTotals {
+            subtotal,
+            discount,
+            tax,
+            shipping,
+            total: discounted + tax + shipping,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct OrderService {
     repo: OrderRepository,
+    pricing: OrderPricing,
+    promotion_stacking: PromotionStacking,
+    /// Charge the prices captured when the order was placed, even if products were repriced since
+    lock_prices: bool,
+    webhooks: Option<OrderWebhookService>,
+    low_stock: Option<LowStockNotifier>,
 }
 
 impl OrderService {
     pub fn new(repo: OrderRepository) -> Self {
-        Self { repo }
+        Self {
+            repo,
+            pricing: OrderPricing::default(),
+            promotion_stacking: PromotionStacking::default(),
+            lock_prices: true,
+            webhooks: None,
+            low_stock: None,
+        }
     }
 
-    pub async fn get_my_orders(&self, user_id: Uuid) -> Result<Vec<Order>, sqlx::Error> {
-        self.repo.find_by_user(user_id).await
+    /// Charges tax and shipping on the orders this service creates; without it orders cost
+    /// just their items.
+    pub fn with_pricing(mut self, pricing: OrderPricing) -> Self {
+        self.pricing = pricing;
+        self
     }
 
-    pub async fn get_all_orders(&self) -> Result<Vec<Order>, sqlx::Error> {
-        self.repo.find_all().await
+    pub fn with_promotion_stacking(mut self, stacking: PromotionStacking) -> Self {
+        self.promotion_stacking = stacking;
+        self
     }
 
-    pub async fn update_order_status(&self, order_id: Uuid, status: String) -> Result<Order, sqlx::Error> {
-        self.repo.update_status(order_id, &status).await
+    pub fn with_price_lock(mut self, lock_prices: bool) -> Self {
+        self.lock_prices = lock_prices;
+        self
+    }
+
+    /// Sends `order.created` and `order.paid` events for the orders this service creates and pays.
+    pub fn with_webhooks(mut self, webhooks: OrderWebhookService) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    /// Checks the stock of what a payment took against its low-stock threshold.
+    pub fn with_low_stock_notifier(mut self, notifier: LowStockNotifier) -> Self {
+        self.low_stock = Some(notifier);
+        self
+    }
+
+    /// A page of the user's orders, newest first, with the total matching count.
+    pub async fn get_my_orders(&self, user_id: Uuid, status: Option<&str>, limit: i64, offset: i64) -> Result<(Vec<Order>, i64), AppError> {
+        validate_status_filter(status)?;
+        let orders = self.repo.find_by_user(user_id, status, limit, offset).await?;
+        let total = self.repo.count_by_user(user_id, status).await?;
+        Ok((orders, total))
+    }
+
+    pub async fn get_all_orders(&self, filter: &OrderFilter, limit: i64, offset: i64) -> Result<(Vec<Order>, i64), AppError> {
+        validate_order_filter(filter)?;
+        let orders = self.repo.find_all(filter, limit, offset).await?;
+        let total = self.repo.count_all(filter).await?;
+        Ok((orders, total))
+    }
+
+    /// Like [`get_all_orders`](Self::get_all_orders), with each order's line items included.
+    pub async fn get_all_orders_with_items(&self, filter: &OrderFilter, limit: i64, offset: i64) -> Result<(Vec<OrderWithItems>, i64), AppError> {
+        validate_order_filter(filter)?;
+        let orders = self.repo.find_all_with_items(filter, limit, offset).await?;
+        let total = self.repo.count_all(filter).await?;
+        Ok((orders, total))
+    }
+
+    /// Moves an order along its lifecycle. Tracking details may only come with the move to
+    /// `shipped`.
+    pub async fn update_order_status(&self, order_id: Uuid, dto: UpdateStatusDto, changed_by: Option<Uuid>) -> Result<Order, AppError> {
+        let status = dto.status;
+        let next: OrderStatus = status.parse().map_err(AppError::Validation)?;
+        let tracking = shipment_tracking(&next, dto.tracking_number, dto.carrier)?;
+
+        let previous = self.repo.get_by_id(order_id).await?
+            .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
+
+        if !previous.status.can_transition_to(&next) {
+            return Err(AppError::Validation(transition_refusal(previous.status, next)));
+        }
+
+        // Only applied if the order is still in the status checked above; a payment or another
+        // update landing in between turns this into a conflict rather than being overwritten
+        let from = previous.status.to_string();
+        let mut tx = self.repo.pool.begin().await?;
+        let order = OrderRepository::transition_status_in(
+            &mut tx,
+            order_id,
+            &[from.as_str()],
+            &status,
+            tracking.as_ref().map(|(number, carrier)| (number.as_str(), carrier.as_deref())),
+            changed_by,
+        )
+        .await?;
+        let Some(order) = order else {
+            drop(tx);
+            return Err(match self.repo.get_by_id(order_id).await? {
+                Some(current) => AppError::Conflict(format!(
+                    "Order moved from '{}' to '{}' meanwhile; reload and try again", previous.status, current.status
+                )),
+                None => AppError::NotFound("Order not found".to_string()),
+            });
+        };
+
+        // The move only applied from `previous.status`, so that is the status the order left
+        self.settle_stock_in(&mut tx, order_id, previous.status, next).await?;
+        tx.commit().await?;
+        Ok(order)
+    }
+
+    /// Moves every order in `request` to its status in one transaction. An order that is missing
+    /// or can't make the transition is skipped and reported, unless `all_or_nothing` is set, in
+    /// which case nothing is changed and the request fails with a conflict listing them.
+    pub async fn bulk_update_status(&self, request: BulkStatusRequest, changed_by: Option<Uuid>) -> Result<BulkStatusResponse, AppError> {
+        let next: OrderStatus = request.status.parse().map_err(AppError::Validation)?;
+        let status = next.to_string();
+
+        let mut tx = self.repo.pool.begin().await?;
+        let mut results = Vec::with_capacity(request.order_ids.len());
+
+        for order_id in request.order_ids {
+            let error = match OrderRepository::lock_for_update(&mut tx, order_id).await? {
+                None => Some("Order not found".to_string()),
+                Some(order) if !order.status.can_transition_to(&next) => Some(transition_refusal(order.status, next)),
+                Some(order) => {
+                    OrderRepository::apply_status(&mut tx, order_id, &status).await?;
+                    OrderRepository::record_status_change(&mut tx, order_id, &order.status.to_string(), &status, changed_by).await?;
+                    self.settle_stock_in(&mut tx, order_id, order.status, next).await?;
+                    None
+                }
+            };
+            results.push(BulkStatusResult { order_id, updated: error.is_none(), error });
+        }
+
+        let failed = results.iter().filter(|r| !r.updated).count();
+        if request.all_or_nothing && failed > 0 {
+            let reasons: Vec<String> = results
+                .iter()
+                .filter_map(|r| r.error.as_ref().map(|e| format!("{}: {}", r.order_id, e)))
+                .collect();
+            return Err(AppError::Conflict(format!("No orders were updated; {}", reasons.join("; "))));
+        }
+        tx.commit().await?;
+
+        Ok(BulkStatusResponse { updated: results.len() - failed, failed, results })
+    }
+
+    /// Gives back what the order held or took as it moves from `from` to `to`, inside the
+    /// transaction applying the move: the payment-window holds once it stops awaiting payment,
+    /// and the sold items once a paid order is cancelled or refunded.
+    pub(crate) async fn settle_stock_in(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        order_id: Uuid,
+        from: OrderStatus,
+        to: OrderStatus,
+    ) -> Result<(), sqlx::Error> {
+        let stock = StockRepository::new(self.repo.pool.clone());
+        if releases_reservations(from, to) {
+            stock.release_order_reservations_in(tx, order_id, &format!("Released reservation after order moved to {}", to)).await?;
+        }
+        if returns_stock(from, to) {
+            let items = self.item_quantities(order_id).await?;
+            stock.restock_order_in(tx, order_id, &items).await?;
+        }
+        Ok(())
+    }
+
+    async fn item_quantities(&self, order_id: Uuid) -> Result<Vec<(Uuid, i32)>, sqlx::Error> {
+        Ok(self.repo.find_items(order_id).await?
+            .into_iter()
+            .map(|item| (item.product_id, item.quantity))
+            .collect())
+    }
+
+
+    /// Cancels one of the user's own orders while it is still awaiting payment and gives
+    /// any stock held for the payment window back.
+    pub async fn cancel_order(&self, user_id: Uuid, order_id: Uuid) -> Result<Order, AppError> {
+        let order = self.repo.get_by_id(order_id).await
+            .map_err(AppError::Database)?
+            .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
+
+        if order.user_id != user_id {
+            return Err(AppError::Forbidden("Access denied".to_string()));
+        }
+
+        let cancellable = [
+            OrderStatus::PendingPayment.to_string(),
+            OrderStatus::PaymentProcessing.to_string(),
+        ];
+        let from: Vec<&str> = cancellable.iter().map(String::as_str).collect();
+
+        // Conditional update; a payment webhook only moves orders still processing payment, so
+        // whichever of the two lands second leaves the other's status alone
+        let mut tx = self.repo.pool.begin().await?;
+        let cancelled = OrderRepository::transition_status_in(&mut tx, order_id, &from, &OrderStatus::Cancelled.to_string(), None, Some(user_id)).await
+            .map_err(AppError::Database)?
+            .ok_or_else(|| AppError::Conflict(format!("Order in status '{}' can no longer be cancelled", order.status)))?;
+
+        self.settle_stock_in(&mut tx, order_id, order.status, OrderStatus::Cancelled).await?;
+        tx.commit().await?;
+
+        Ok(cancelled)
+    }
+
+    /// Creates an order from the cart unless the user already created one with
+    /// `idempotency_key` in the last [`IDEMPOTENCY_WINDOW_HOURS`], in which case that order is
+    /// returned instead. The flag is `true` when the response is such a replay. Retries racing
+    /// the original request wait for its checkout and then replay its order.
+    pub async fn create_order_idempotent(
+        &self,
+        user_id: Uuid,
+        idempotency_key: Option<&str>,
+        request: &CreateOrderRequest,
+    ) -> Result<(CreateOrderResponse, bool), AppError> {
+        let Some(key) = idempotency_key else {
+            return Ok((self.create_order_from_cart(user_id, request).await?, false));
+        };
+        validate_idempotency_key(key)?;
+
+        if let Some(order) = self.repo.find_by_idempotency_key(user_id, key, IDEMPOTENCY_WINDOW_HOURS).await? {
+            return Ok((self.created_order_response(order).await?, true));
+        }
+
+        self.checkout(user_id, request, Some(key)).await
+    }
+
+    /// Rebuilds the creation response of an existing order from what was stored for it.
+    async fn created_order_response(&self, order: Order) -> Result<CreateOrderResponse, AppError> {
+        let items = self.repo.find_items(order.id).await?;
+        let applied_promotions = PromotionService::new(PromotionRepository::new(self.repo.pool.clone()))
+            .applied_to_order(order.id)
+            .await?;
+        let coupon_code = match order.coupon_id {
+            Some(coupon_id) => CouponRepository::new(self.repo.pool.clone()).get(coupon_id).await?.map(|c| c.code),
+            None => None,
+        };
+        let promotion_discount: Decimal = applied_promotions.iter().map(|p| p.discount_amount).sum();
+
+        Ok(CreateOrderResponse {
+            id: order.id,
+            subtotal: order.subtotal,
+            discount: promotion_discount + order.coupon_discount,
+            applied_promotions,
+            coupon_code,
+            coupon_discount: order.coupon_discount,
+            tax: order.tax,
+            shipping: order.shipping,
+            total: order.total,
+            status: order.status.to_string(),
+            items_count: items.len() as i32,
+            created_at: order.created_at,
+        })
     }
 
+    /// Turns the user's cart into an order awaiting payment. Everything from reading the cart to
+    /// clearing it happens in one transaction holding a lock on the cart, so simultaneous
+    /// checkouts of one cart are serialized: the first places the order and the rest find the
+    /// cart empty.
+    pub async fn create_order_from_cart(&self, user_id: Uuid, request: &CreateOrderRequest) -> Result<CreateOrderResponse, AppError> {
+        let (created, _) = self.checkout(user_id, request, None).await?;
+        Ok(created)
+    }
 
-    pub async fn create_order_from_cart(&self, user_id: Uuid) -> Result<CreateOrderResponse, AppError> {
-        let cart_repo = CartRepository::new(self.repo.pool.clone());
+    /// [`create_order_from_cart`](Self::create_order_from_cart), recording `idempotency_key` with
+    /// the new order in the same transaction. The key is checked again once the cart is locked,
+    /// so a request that waited on an earlier checkout with the same key replays its order.
+    async fn checkout(
+        &self,
+        user_id: Uuid,
+        request: &CreateOrderRequest,
+        idempotency_key: Option<&str>,
+    ) -> Result<(CreateOrderResponse, bool), AppError> {
         let product_repo = ProductRepository::new(self.repo.pool.clone());
+        let mut tx = self.repo.pool.begin().await?;
         
-        // Get user's cart
-        let cart = cart_repo.get_cart_by_user(user_id).await
+        // Lock the user's cart and read its items
+        let (cart, cart_items) = CartRepository::lock_cart_by_user(&mut tx, user_id).await
             .map_err(AppError::Database)?
             .ok_or_else(|| AppError::Validation("No cart found for user".to_string()))?;
-        
-        // Get cart items
-        let cart_items = cart_repo.get_cart_items(cart.id).await
-            .map_err(AppError::Database)?;
+
+        if let Some(key) = idempotency_key {
+            if let Some(order) = self.repo.find_by_idempotency_key(user_id, key, IDEMPOTENCY_WINDOW_HOURS).await? {
+                return Ok((self.created_order_response(order).await?, true));
+            }
+        }
         
         if cart_items.is_empty() {
             return Err(AppError::Validation("Cart is empty".to_string()));
         }
         
-        // Calculate total and validate products
-        let mut total = Decimal::new(0, 2);
+        // Calculate subtotal and validate products
+        let mut subtotal = Decimal::new(0, 2);
         let mut order_items = Vec::new();
         
         for cart_item in &cart_items {
-            let product = product_repo.find_by_id(cart_item.product_id).await
+            let product = product_repo.get(cart_item.product_id).await
                 .map_err(AppError::Database)?
                 .ok_or_else(|| AppError::Validation(format!("Product {} not found", cart_item.product_id)))?;
             
             // Check stock availability
             if product.stock < cart_item.quantity {
-                return Err(AppError::Validation(
-                    format!("Insufficient stock for product {}. Available: {}, Requested: {}", 
+                return Err(AppError::InsufficientStock(
+                    format!("product {}. Available: {}, Requested: {}", 
                            product.name, product.stock, cart_item.quantity)
                 ));
             }
             
             let item_price = product.price;
             let item_total = item_price * Decimal::new(cart_item.quantity as i64, 0);
-            total += item_total;
+            subtotal += item_total;
             
             order_items.push((cart_item, product, item_price));
         }
+
+        // Apply store-wide promotions
+        let promotion_svc = PromotionService::new(PromotionRepository::new(self.repo.pool.clone()))
+            .with_stacking(self.promotion_stacking);
+        let promotions = promotion_svc.evaluate(subtotal).await?;
+
+        // A coupon comes off whatever is left after promotions
+        let coupon_svc = CouponService::new(CouponRepository::new(self.repo.pool.clone()));
+        let coupon = match request.coupon_code.as_deref() {
+            Some(code) => Some(coupon_svc.find_usable(code).await?),
+            None => None,
+        };
+        let coupon_discount = coupon.as_ref()
+            .map(|c| coupon_service::coupon_discount(c, subtotal - promotions.discount))
+            .unwrap_or(Decimal::ZERO);
+        let totals = self.pricing.totals(subtotal, promotions.discount + coupon_discount);
+
+        if let Some(coupon) = &coupon {
+            CouponService::redeem(&mut tx, coupon).await?;
+        }
         
         // Create order
-        let order = self.repo.create_order(user_id, total.try_into().unwrap_or(0.0), &OrderStatus::PendingPayment.to_string()).await
+        let applied_coupon = coupon.as_ref().map(|c| (c.id, coupon_discount));
+        let order = OrderRepository::create_order(&mut tx, user_id, &totals, applied_coupon, &OrderStatus::PendingPayment.to_string(), request.notes.as_deref()).await
             .map_err(AppError::Database)?;
         
         // Create order items
         for (cart_item, _product, item_price) in &order_items {
-            self.repo.add_order_item(
+            OrderRepository::add_order_item(
+                &mut tx,
                 order.id, 
                 cart_item.product_id, 
                 cart_item.quantity, 
                 (*item_price).try_into().unwrap_or(0.0)
             ).await.map_err(AppError::Database)?;
         }
+
+        PromotionService::record_for_order(&mut tx, order.id, &promotions).await?;
         
         // Clear the cart
-        cart_repo.clear_cart(cart.id).await
+        CartRepository::clear_cart(&mut tx, cart.id).await
             .map_err(AppError::Database)?;
+        if let Some(key) = idempotency_key {
+            OrderRepository::record_idempotency_key(&mut tx, user_id, key, order.id).await?;
+        }
+        tx.commit().await?;
+
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.order_created(order.id);
+        }
         
-        Ok(CreateOrderResponse {
+        Ok((CreateOrderResponse {
             id: order.id,
+            subtotal: order.subtotal,
+            discount: totals.discount,
+            applied_promotions: promotions.applied,
+            coupon_code: coupon.map(|c| c.code),
+            coupon_discount,
+            tax: order.tax,
+            shipping: order.shipping,
             total: order.total,
-            status: order.status,
+            status: order.status.to_string(),
             items_count: cart_items.len() as i32,
             created_at: order.created_at,
-        })
+        }, false))
     }
     
     pub async fn get_order_details(&self, user_id: Uuid, order_id: Uuid) -> Result<OrderDetailsResponse, AppError> {
@@ -125,13 +468,24 @@ impl OrderService {
                 }
             })
             .collect();
+        let applied_promotions = PromotionService::new(PromotionRepository::new(self.repo.pool.clone()))
+            .applied_to_order(order_id)
+            .await?;
         
         Ok(OrderDetailsResponse {
             id: order.id,
             user_id: order.user_id,
+            subtotal: order.subtotal,
+            discount: order.subtotal + order.tax + order.shipping - order.total,
+            applied_promotions,
+            tax: order.tax,
+            shipping: order.shipping,
             total: order.total,
-            status: order.status,
+            status: order.status.to_string(),
             payment_id: order.payment_id,
+            notes: order.notes,
+            tracking_number: order.tracking_number,
+            carrier: order.carrier,
             items,
             created_at: order.created_at,
         })
@@ -162,53 +516,228 @@ impl OrderService {
                 }
             })
             .collect();
+        let applied_promotions = PromotionService::new(PromotionRepository::new(self.repo.pool.clone()))
+            .applied_to_order(order_id)
+            .await?;
         
         Ok(OrderDetailsResponse {
             id: order.id,
             user_id: order.user_id,
+            subtotal: order.subtotal,
+            discount: order.subtotal + order.tax + order.shipping - order.total,
+            applied_promotions,
+            tax: order.tax,
+            shipping: order.shipping,
             total: order.total,
-            status: order.status,
+            status: order.status.to_string(),
             payment_id: order.payment_id,
+            notes: order.notes,
+            tracking_number: order.tracking_number,
+            carrier: order.carrier,
             items,
             created_at: order.created_at,
         })
     }
 
-   pub async fn pay_order(&self, user_id: Uuid, order_id: Uuid) -> Result<Option<Order>, sqlx::Error> {
-        let orders = self.repo.find_by_user(user_id).await?;
-        if let Some(order) = orders.into_iter().find(|o| o.id == order_id) {
-            if order.status != "pending_payment" {
-                return Ok(None);
-            }
+    /// The order's status timeline, oldest first, for its owner or an admin.
+    pub async fn status_history(&self, user_id: Uuid, is_admin: bool, order_id: Uuid) -> Result<Vec<OrderStatusChange>, AppError> {
+        let order = self.repo.get_by_id(order_id).await?
+            .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
+        if !is_admin && order.user_id != user_id {
+            return Err(AppError::Forbidden("Access denied".to_string()));
+        }
 
-            // fetch order items
-            let items = self.repo.find_items(order.id).await?;
-            let product_repo = ProductRepository::new(self.repo.pool.clone());
-
-            // check stock
-            for item in &items {
-                if let Some(product) = product_repo.find_by_id(item.product_id).await? {
-                    if product.stock < item.quantity {
-                        return Ok(None); // not enough stock
-                    }
-                } else {
-                    return Ok(None); // product not found
-                }
-            }
+        Ok(self.repo.list_status_history(order_id).await?)
+    }
 
-            // reduce stock
-            for item in &items {
-                if let Some(product) = product_repo.find_by_id(item.product_id).await? {
-                    let new_stock = product.stock - item.quantity;
-                    product_repo.update_stock(product.id, new_stock).await?;
-                }
+    /// The invoice for an order, visible to the same people as its details: the owner or an
+    /// admin.
+    pub async fn get_invoice(&self, user_id: Uuid, is_admin: bool, order_id: Uuid) -> Result<InvoiceResponse, AppError> {
+        let details = if is_admin {
+            self.get_order_details_admin(order_id).await?
+        } else {
+            self.get_order_details(user_id, order_id).await?
+        };
+
+        let customer = UserRepository::new(self.repo.pool.clone())
+            .find_by_id(details.user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Customer not found".to_string()))?;
+        let payment = PaymentRepository::new(self.repo.pool.clone())
+            .get_by_order_id(order_id)
+            .await?;
+
+        Ok(InvoiceResponse {
+            invoice_number: invoice_number(details.id, details.created_at),
+            order_id: details.id,
+            issued_at: details.created_at,
+            customer_id: customer.id,
+            customer_email: customer.email,
+            items: details.items,
+            subtotal: details.subtotal,
+            discount: details.discount,
+            tax: details.tax,
+            shipping: details.shipping,
+            total: details.total,
+            order_status: details.status,
+            payment_status: payment.map(|p| p.status),
+            notes: details.notes,
+        })
+    }
+
+    /// Pays the user's order awaiting payment. With prices locked the order's stored total is
+    /// what gets charged; otherwise the order is refused with a conflict when any item's product
+    /// price has changed since it was placed, so the customer can re-order at current prices.
+    /// The order stays locked from the status check until it is marked paid, so concurrent
+    /// payments or a cancellation can't both go through. A stock shortage is reported as
+    /// [`AppError::InsufficientStock`] naming the product.
+    pub async fn pay_order(&self, user_id: Uuid, order_id: Uuid) -> Result<Order, AppError> {
+        // Created up front so the cart lookup doesn't wait on the order lock
+        let cart = CartRepository::new(self.repo.pool.clone()).get_or_create_cart(user_id).await?;
+        let mut tx = self.repo.pool.begin().await?;
+
+        let order = OrderRepository::lock_for_update(&mut tx, order_id).await?.filter(|o| o.user_id == user_id);
+        let Some(order) = order else {
+            return Err(AppError::NotFound("Order not found".to_string()));
+        };
+        if order.status != OrderStatus::PendingPayment {
+            return Err(AppError::Validation(format!(
+                "Order is '{}'; only orders awaiting payment can be paid", order.status
+            )));
+        }
+
+        // fetch order items
+        let items = self.repo.find_items(order.id).await?;
+        let product_repo = ProductRepository::new(self.repo.pool.clone());
+
+        // check prices unless they are locked in
+        let mut repriced = Vec::new();
+        for item in &items {
+            let Some(product) = product_repo.find_by_id(item.product_id).await? else {
+                return Err(AppError::Validation(format!("Product {} is no longer available", item.product_id)));
+            };
+            if !self.lock_prices && product.price != item.price {
+                repriced.push(format!("{} ({} -> {})", product.name, item.price, product.price));
             }
+        }
+        if !repriced.is_empty() {
+            return Err(AppError::Conflict(format!(
+                "Prices changed since the order was placed: {}",
+                repriced.join(", ")
+            )));
+        }
+
+        // reduce stock, consuming whatever the user's cart had reserved for these items
+        let sold: Vec<(Uuid, i32)> = items.iter().map(|item| (item.product_id, item.quantity)).collect();
+        let stock_repo = StockRepository::new(self.repo.pool.clone());
+        if let Err(product_id) = stock_repo.consume_reservation(&mut tx, cart.id, order.id, &sold).await? {
+            return Err(AppError::InsufficientStock(format!("Not enough stock for product {}", product_id)));
+        }
+
+        // mark order as paid
+        let paid_status = OrderStatus::Paid.to_string();
+        let paid = OrderRepository::apply_status(&mut tx, order_id, &paid_status).await?;
+        OrderRepository::record_status_change(&mut tx, order_id, &order.status.to_string(), &paid_status, Some(user_id)).await?;
+        tx.commit().await?;
+
+        if let Some(notifier) = &self.low_stock {
+            notifier.check(sold.iter().map(|(product_id, _)| *product_id).collect());
+        }
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.order_paid(order_id);
+        }
+        Ok(paid)
+    }
+
+}
+
+/// Longest tracking number or carrier name accepted
+const MAX_TRACKING_LENGTH: usize = 100;
+
+/// Checks the tracking details sent with a status change, returning them trimmed. They are only
+/// allowed when the order is being shipped, and a carrier needs a tracking number.
+fn shipment_tracking(
+    next: &OrderStatus,
+    tracking_number: Option<String>,
+    carrier: Option<String>,
+) -> Result<Option<(String, Option<String>)>, AppError> {
+    let tracking_number = tracking_number.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+    let carrier = carrier.map(|c| c.trim().to_string()).filter(|c| !c.is_empty());
+
+    let Some(tracking_number) = tracking_number else {
+        if carrier.is_some() {
+            return Err(AppError::Validation("A carrier needs a tracking_number".into()));
+        }
+        return Ok(None);
+    };
+    if !matches!(next, OrderStatus::Shipped) {
+        return Err(AppError::Validation(
+            "Tracking details can only be set when the order is shipped".into(),
+        ));
+    }
+    if tracking_number.len() > MAX_TRACKING_LENGTH || carrier.as_ref().is_some_and(|c| c.len() > MAX_TRACKING_LENGTH) {
+        return Err(AppError::Validation(format!(
+            "Tracking number and carrier are limited to {} characters", MAX_TRACKING_LENGTH
+        )));
+    }
+    Ok(Some((tracking_number, carrier)))
+}
+
+/// Whether moving an order from `from` to `to` should give its stock back: stock is taken
+/// when an order is paid, so it returns when a paid or processing order is cancelled or refunded.
+pub fn returns_stock(from: OrderStatus, to: OrderStatus) -> bool {
+    matches!(from, OrderStatus::Paid | OrderStatus::Processing)
+        && matches!(to, OrderStatus::Cancelled | OrderStatus::Refunded)
+}
+
+/// Whether moving an order from `from` to `to` ends its payment window, so the stock held
+/// for that window should be released.
+pub fn releases_reservations(from: OrderStatus, to: OrderStatus) -> bool {
+    let awaiting_payment = |status: OrderStatus| matches!(status, OrderStatus::PendingPayment | OrderStatus::PaymentProcessing);
+    awaiting_payment(from) && !awaiting_payment(to)
+}
+
+/// Why an admin can't move an order from `from` to `to`. A paid order is refunded rather than
+/// cancelled, so the customer gets their money back.
+fn transition_refusal(from: OrderStatus, to: OrderStatus) -> String {
+    if to == OrderStatus::Cancelled && from.can_transition_to(&OrderStatus::Refunded) {
+        format!("Order '{}' has been paid for; refund it instead of cancelling it", from)
+    } else {
+        format!("Cannot move order from '{}' to '{}'", from, to)
+    }
+}
 
-            // mark order as paid
-            let updated = self.repo.update_status(order_id, &OrderStatus::Paid.to_string()).await?;
-            return Ok(Some(updated));
+/// Invoice reference for an order: its placement date and the first eight hex digits of its id,
+/// e.g. `INV-20250912-1A2B3C4D`.
+pub fn invoice_number(order_id: Uuid, placed_at: DateTime<Utc>) -> String {
+    let id = order_id.simple().to_string().to_uppercase();
+    format!("INV-{}-{}", placed_at.format("%Y%m%d"), &id[..8])
+}
+
+fn validate_idempotency_key(key: &str) -> Result<(), AppError> {
+    if key.trim().is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+        return Err(AppError::Validation(format!(
+            "Idempotency-Key must be between 1 and {} characters", MAX_IDEMPOTENCY_KEY_LEN
+        )));
+    }
+    Ok(())
+}
+
+fn validate_order_filter(filter: &OrderFilter) -> Result<(), AppError> {
+    validate_status_filter(filter.status.as_deref())?;
+    if let (Some(after), Some(before)) = (filter.created_after, filter.created_before) {
+        if after > before {
+            return Err(AppError::Validation("created_after must not be later than created_before".to_string()));
         }
-        Ok(None)
     }
+    Ok(())
+}
 
+fn validate_status_filter(status: Option<&str>) -> Result<(), AppError> {
+    match status {
+        Some(s) if s.parse::<OrderStatus>().is_err() => {
+            Err(AppError::Validation(format!("Unknown order status '{}'", s)))
+        }
+        _ => Ok(()),
+    }
 }
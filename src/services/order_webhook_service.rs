@@ -0,0 +1,149 @@
+use crate::repository::OrderRepository;
+use crate::services::http_retry::send_with_retry;
+use crate::state::OrderWebhookConfig;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde_json::json;
+use sha2::Sha256;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Header carrying `sha256=<hex HMAC-SHA256 of the body>`, keyed with `ORDER_WEBHOOK_SECRET`.
+pub const SIGNATURE_HEADER: &str = "X-Order-Webhook-Signature";
+/// Header naming the event, the same as the payload's `event` field.
+pub const EVENT_HEADER: &str = "X-Order-Webhook-Event";
+
+/// Delay before the first retry of an event.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// What happened to the order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderEvent {
+    Created,
+    Paid,
+}
+
+impl OrderEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderEvent::Created => "order.created",
+            OrderEvent::Paid => "order.paid",
+        }
+    }
+}
+
+/// Posts order events to `ORDER_WEBHOOK_URL` for external systems such as a fulfillment
+/// provider. Does nothing when the URL isn't set.
+///
+/// Each event is sent from a background task so the caller's response isn't held up; server
+/// errors and connection failures are retried with exponential backoff and a final failure is
+/// logged.
+#[derive(Clone)]
+pub struct OrderWebhookService {
+    repo: OrderRepository,
+    http: reqwest::Client,
+    url: Option<String>,
+    secret: String,
+}
+
+impl OrderWebhookService {
+    pub fn new(repo: OrderRepository, http: reqwest::Client, config: &OrderWebhookConfig) -> Self {
+        Self {
+            repo,
+            http,
+            url: config.url.clone(),
+            secret: config.secret.clone(),
+        }
+    }
+
+    pub fn order_created(&self, order_id: Uuid) {
+        self.notify(OrderEvent::Created, order_id);
+    }
+
+    pub fn order_paid(&self, order_id: Uuid) {
+        self.notify(OrderEvent::Paid, order_id);
+    }
+
+    /// Loads the order as it stands now and sends it as `event`.
+    fn notify(&self, event: OrderEvent, order_id: Uuid) {
+        let Some(url) = self.url.clone() else {
+            return;
+        };
+        let this = self.clone();
+
+        tokio::spawn(async move {
+            let payload = match this.payload(event, order_id).await {
+                Ok(Some(payload)) => payload,
+                Ok(None) => {
+                    tracing::warn!("Order {} disappeared before its {} webhook was sent", order_id, event.as_str());
+                    return;
+                }
+                Err(e) => {
+                    tracing::error!("Failed to load order {} for its {} webhook: {}", order_id, event.as_str(), e);
+                    return;
+                }
+            };
+            this.deliver(&url, event, order_id, payload.to_string()).await;
+        });
+    }
+
+    async fn payload(&self, event: OrderEvent, order_id: Uuid) -> Result<Option<serde_json::Value>, sqlx::Error> {
+        let Some(order) = self.repo.get_by_id(order_id).await? else {
+            return Ok(None);
+        };
+        let items: Vec<_> = self.repo.find_items(order_id).await?
+            .into_iter()
+            .map(|item| json!({
+                "product_id": item.product_id,
+                "quantity": item.quantity,
+                "price": item.price,
+            }))
+            .collect();
+
+        Ok(Some(json!({
+            "event": event.as_str(),
+            "order_id": order.id,
+            "user_id": order.user_id,
+            "status": order.status,
+            "total": order.total,
+            "items": items,
+            "occurred_at": Utc::now(),
+        })))
+    }
+
+    async fn deliver(&self, url: &str, event: OrderEvent, order_id: Uuid, body: String) {
+        let signature = format!("sha256={}", sign(&self.secret, &body));
+        let result = send_with_retry(
+            || {
+                self.http
+                    .post(url)
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .header(EVENT_HEADER, event.as_str())
+                    .header(SIGNATURE_HEADER, &signature)
+                    .body(body.clone())
+            },
+            INITIAL_BACKOFF,
+            &format!("{} webhook for order {}", event.as_str(), order_id),
+        )
+        .await;
+
+        match result {
+            Ok(res) if res.status().is_success() => {
+                tracing::info!("Sent {} webhook for order {}", event.as_str(), order_id);
+            }
+            Ok(res) => {
+                tracing::warn!("{} webhook for order {} returned {}", event.as_str(), order_id, res.status());
+            }
+            Err(e) => {
+                tracing::warn!("{} webhook for order {} failed: {}", event.as_str(), order_id, e);
+            }
+        }
+    }
+}
+
+/// Hex HMAC-SHA256 of `body` keyed with `secret`.
+fn sign(secret: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
@@ -1,73 +1,178 @@
+use crate::errors::AppError;
 use crate::model::payment::{Payment, PaymentIntentResponse, CreatePaymentIntentRequest, PaymentStatus};
 use crate::model::order::OrderStatus;
-use crate::repository::{PaymentRepository, OrderRepository};
+use crate::repository::{PaymentRepository, OrderRepository, CartRepository, StockRepository};
+use crate::services::stock_alert_service::LowStockNotifier;
+use crate::services::order_service::OrderService;
+use crate::services::order_webhook_service::OrderWebhookService;
+use crate::services::stripe_client::StripeClient;
+use crate::state::StripeConfig;
 use bigdecimal::{BigDecimal, ToPrimitive};
-use std::collections::HashMap;
-use std::env;
+use std::str::FromStr;
 use uuid::Uuid;
 
+/// How long stock stays reserved for an order while its payment is in flight.
+const PAYMENT_RESERVATION_MINUTES: i32 = 30;
+
+/// ISO-4217 codes, in Stripe's lowercase form, that the store accepts payment in.
+pub const SUPPORTED_CURRENCIES: &[&str] = &["usd", "eur", "gbp", "cad", "aud"];
+
 pub struct PaymentService {
     payment_repo: PaymentRepository,
     order_repo: OrderRepository,
-    stripe_secret_key: String,
+    stripe: StripeClient,
+    order_webhooks: Option<OrderWebhookService>,
+    low_stock: Option<LowStockNotifier>,
 }
 
 impl PaymentService {
-    pub fn new(payment_repo: PaymentRepository, order_repo: OrderRepository) -> Self {
-        let stripe_secret_key = env::var("STRIPE_SECRET_KEY")
-            .expect("STRIPE_SECRET_KEY environment variable is required");
-        
+    pub fn new(
+        payment_repo: PaymentRepository,
+        order_repo: OrderRepository,
+        http: reqwest::Client,
+        stripe_config: &StripeConfig,
+    ) -> Self {
         Self {
             payment_repo,
             order_repo,
-            stripe_secret_key,
+            stripe: StripeClient::new(http, stripe_config.secret_key.clone(), stripe_config.api_base.clone()),
+            order_webhooks: None,
+            low_stock: None,
         }
     }
 
+    /// Sends an `order.paid` event when a successful payment marks its order paid.
+    pub fn with_order_webhooks(mut self, webhooks: OrderWebhookService) -> Self {
+        self.order_webhooks = Some(webhooks);
+        self
+    }
+
+    /// Checks the stock a successful payment took against its low-stock threshold.
+    pub fn with_low_stock_notifier(mut self, notifier: LowStockNotifier) -> Self {
+        self.low_stock = Some(notifier);
+        self
+    }
+
+    /// Starts paying for one of `user_id`'s orders; another user's order is reported as not found.
     pub async fn create_payment_intent(
         &self,
+        user_id: Uuid,
         request: CreatePaymentIntentRequest,
     ) -> Result<PaymentIntentResponse, PaymentError> {
-        // Validate order exists and is ready for payment
-        let order = self.order_repo.get_by_id(request.order_id).await
+        // Catch bad currencies here rather than relaying Stripe's error
+        let currency = normalize_currency(&request.currency)?;
+
+        let items: Vec<(Uuid, i32)> = self.order_repo.find_items(request.order_id).await
+            .map_err(|e| PaymentError::Database(e.to_string()))?
+            .into_iter()
+            .map(|item| (item.product_id, item.quantity))
+            .collect();
+
+        // Lock the order while it is checked, its stock held and it is moved to
+        // payment_processing, so a concurrent intent or cancellation waits and then sees the move
+        let mut tx = self.order_repo.pool.begin().await
             .map_err(|e| PaymentError::Database(e.to_string()))?;
-        
-        let order = order.ok_or(PaymentError::OrderNotFound)?;
-        
+        let order = OrderRepository::lock_for_update(&mut tx, request.order_id).await
+            .map_err(|e| PaymentError::Database(e.to_string()))?
+            .filter(|order| order.user_id == user_id)
+            .ok_or(PaymentError::OrderNotFound)?;
+
         // Check if order is in correct status for payment
-        if order.status != OrderStatus::PendingPayment.to_string() {
-            return Err(PaymentError::InvalidOrderStatus(order.status));
+        if order.status != OrderStatus::PendingPayment {
+            return Err(PaymentError::InvalidOrderStatus(order.status.to_string()));
+        }
+
+        // The charge is always the order total; a client-supplied amount only has to agree with it
+        let amount = BigDecimal::from_str(&order.total.to_string())
+            .map_err(|_| PaymentError::InvalidAmount)?;
+        if let Some(requested) = request.amount.as_ref().filter(|requested| **requested != amount) {
+            return Err(PaymentError::AmountMismatch { expected: amount.to_string(), actual: requested.to_string() });
         }
 
         // Convert amount to cents for Stripe (Stripe expects integer cents)
-        let amount_cents = (&request.amount * BigDecimal::from(100i32))
+        let amount_cents = (&amount * BigDecimal::from(100i32))
             .to_i64()
             .ok_or(PaymentError::InvalidAmount)?;
 
-        // Create Stripe PaymentIntent
-        let client = reqwest::Client::new();
-        let mut params = HashMap::new();
-        params.insert("amount", amount_cents.to_string());
-        params.insert("currency", request.currency.clone());
-        params.insert("automatic_payment_methods[enabled]", "true".to_string());
-        params.insert("metadata[order_id]", request.order_id.to_string());
-
-        let response = client
-            .post("https://api.stripe.com/v1/payment_intents")
-            .header("Authorization", format!("Bearer {}", self.stripe_secret_key))
-            .form(&params)
-            .send()
+        // Hold the order's stock for the payment window so it can't be oversold
+        let cart = CartRepository::new(self.order_repo.pool.clone())
+            .get_or_create_cart(order.user_id)
+            .await
+            .map_err(|e| PaymentError::Database(e.to_string()))?;
+        let stock_repo = StockRepository::new(self.order_repo.pool.clone());
+        stock_repo
+            .reserve_for_order_in(&mut tx, order.id, cart.id, &items, PAYMENT_RESERVATION_MINUTES)
+            .await
+            .map_err(|e| PaymentError::Database(e.to_string()))?
+            .ok_or(PaymentError::InsufficientStock)?;
+
+        let processing = OrderStatus::PaymentProcessing.to_string();
+        OrderRepository::apply_status(&mut tx, order.id, &processing).await
+            .map_err(|e| PaymentError::Database(e.to_string()))?;
+        OrderRepository::record_status_change(&mut tx, order.id, &order.status.to_string(), &processing, None).await
+            .map_err(|e| PaymentError::Database(e.to_string()))?;
+        tx.commit().await
+            .map_err(|e| PaymentError::Database(e.to_string()))?;
+
+        let (payment_intent_id, client_secret) = match self
+            .create_stripe_payment_intent(amount_cents, &currency, request.order_id)
             .await
-            .map_err(|e| PaymentError::StripeApiError(e.to_string()))?;
+        {
+            Ok(intent) => intent,
+            Err(e) => {
+                self.abandon_payment_attempt(&stock_repo, order.id, "Released reservation after Stripe error").await;
+                return Err(e);
+            }
+        };
 
-        if !response.status().is_success() {
-            let error_text = response.text().await
-                .unwrap_or_else(|_| "Unknown Stripe API error".to_string());
-            return Err(PaymentError::StripeApiError(error_text));
+        // Record the payment; without the record no webhook could settle it, so drop the hold too
+        if let Err(e) = self.payment_repo.create(request.order_id, payment_intent_id.clone(), amount.clone(), currency.clone()).await {
+            self.abandon_payment_attempt(&stock_repo, order.id, "Released reservation after failing to record payment").await;
+            return Err(PaymentError::Database(e.to_string()));
         }
 
-        let stripe_response: serde_json::Value = response.json().await
-            .map_err(|e| PaymentError::StripeApiError(e.to_string()))?;
+        Ok(PaymentIntentResponse {
+            payment_intent_id,
+            client_secret,
+            amount,
+            currency,
+        })
+    }
+
+    /// Gives back the stock held for a payment attempt that failed before it was recorded and
+    /// puts the order back to awaiting payment, unless it was cancelled meanwhile.
+    async fn abandon_payment_attempt(&self, stock_repo: &StockRepository, order_id: Uuid, reason: &str) {
+        if let Err(e) = stock_repo.release_order_reservations(order_id, reason).await {
+            tracing::error!("Failed to release reservations for order {}: {}", order_id, e);
+        }
+        let reverted = self.order_repo.transition_status(
+            order_id,
+            &[OrderStatus::PaymentProcessing.to_string().as_str()],
+            &OrderStatus::PendingPayment.to_string(),
+            None,
+        ).await;
+        if let Err(e) = reverted {
+            tracing::error!("Failed to put order {} back to pending payment: {}", order_id, e);
+        }
+    }
+
+    async fn create_stripe_payment_intent(
+        &self,
+        amount_cents: i64,
+        currency: &str,
+        order_id: Uuid,
+    ) -> Result<(String, String), PaymentError> {
+        let params = [
+            ("amount", amount_cents.to_string()),
+            ("currency", currency.to_string()),
+            ("automatic_payment_methods[enabled]", "true".to_string()),
+            ("metadata[order_id]", order_id.to_string()),
+        ];
+
+        // One key per intent, shared by the retries, so a lost response can't create a second one
+        let stripe_response = self.stripe
+            .post_form("/v1/payment_intents", &params, &Uuid::new_v4().to_string())
+            .await?;
 
         let payment_intent_id = stripe_response["id"]
             .as_str()
@@ -77,57 +182,130 @@ impl PaymentService {
             .as_str()
             .ok_or(PaymentError::StripeApiError("Missing client secret".to_string()))?;
 
-        // Create payment record in database
-        let _payment = self.payment_repo.create(
-            request.order_id,
-            payment_intent_id.to_string(),
-            request.amount.clone(),
-            request.currency.clone(),
-        ).await
-        .map_err(|e| PaymentError::Database(e.to_string()))?;
-
-        // Update order status to payment_processing
-        self.order_repo.update_status(
-            request.order_id,
-            &OrderStatus::PaymentProcessing.to_string(),
-        ).await
-        .map_err(|e| PaymentError::Database(e.to_string()))?;
-
-        Ok(PaymentIntentResponse {
-            payment_intent_id: payment_intent_id.to_string(),
-            client_secret: client_secret.to_string(),
-            amount: request.amount,
-            currency: request.currency,
-        })
+        Ok((payment_intent_id.to_string(), client_secret.to_string()))
     }
 
+    /// Settles a payment Stripe reports as succeeded. If its order is still awaiting payment,
+    /// in `payment_processing` or back in `pending_payment` after an earlier attempt on the same
+    /// intent failed, the order's stock is taken and the order and payment are marked paid in one
+    /// transaction. A charge the order can't take, because it was cancelled or paid another way
+    /// meanwhile or its stock ran out, is refunded; running out of stock is also reported as an
+    /// error. A payment settled by an earlier event is left alone.
     pub async fn handle_payment_succeeded(&self, payment_intent_id: &str) -> Result<(), PaymentError> {
-        // Update payment status
-        let payment = self.payment_repo.update_status_by_stripe_id(
-            payment_intent_id,
-            PaymentStatus::Succeeded.to_string(),
-            None,
-        ).await
-        .map_err(|e| PaymentError::Database(e.to_string()))?;
+        let Some(payment) = self.payment_repo.get_by_stripe_payment_intent_id(payment_intent_id).await
+            .map_err(|e| PaymentError::Database(e.to_string()))?
+        else {
+            return Ok(());
+        };
 
-        if let Some(payment) = payment {
-            // Update order status to paid
-            self.order_repo.update_status(
-                payment.order_id,
-                &OrderStatus::Paid.to_string(),
-            ).await
+        let order = self.order_repo.get_by_id(payment.order_id).await
+            .map_err(|e| PaymentError::Database(e.to_string()))?
+            .ok_or(PaymentError::OrderNotFound)?;
+        let items: Vec<(Uuid, i32)> = self.order_repo.find_items(payment.order_id).await
+            .map_err(|e| PaymentError::Database(e.to_string()))?
+            .into_iter()
+            .map(|item| (item.product_id, item.quantity))
+            .collect();
+        let cart = CartRepository::new(self.order_repo.pool.clone())
+            .get_or_create_cart(order.user_id)
+            .await
+            .map_err(|e| PaymentError::Database(e.to_string()))?;
+
+        // Repeated deliveries of the event wait on the order lock, then find the payment settled
+        let mut tx = self.order_repo.pool.begin().await
+            .map_err(|e| PaymentError::Database(e.to_string()))?;
+        let locked = OrderRepository::lock_for_update(&mut tx, payment.order_id).await
+            .map_err(|e| PaymentError::Database(e.to_string()))?
+            .ok_or(PaymentError::OrderNotFound)?;
+        let payment_status = PaymentRepository::status_in(&mut tx, payment.id).await
+            .map_err(|e| PaymentError::Database(e.to_string()))?
+            .and_then(|status| PaymentStatus::from_str(&status));
+        if matches!(payment_status, Some(PaymentStatus::Succeeded | PaymentStatus::Canceled)) {
+            return Ok(());
+        }
+
+        if !matches!(locked.status, OrderStatus::PendingPayment | OrderStatus::PaymentProcessing) {
+            // The customer was charged for an order that no longer needs the money
+            drop(tx);
+            tracing::warn!(
+                "Refunding payment {} that succeeded for order {} in status '{}'", payment.id, locked.id, locked.status
+            );
+            return self.refund_unclaimed_payment(&payment).await;
+        }
+
+        // Convert the reservations into sales
+        let stock_repo = StockRepository::new(self.order_repo.pool.clone());
+        let consumed = stock_repo
+            .consume_reservation(&mut tx, cart.id, payment.order_id, &items)
+            .await
+            .map_err(|e| PaymentError::Database(e.to_string()))?;
+        if let Err(product_id) = consumed {
+            drop(tx);
+            tracing::error!(
+                "Refunding payment {}: product {} is out of stock for order {}", payment.id, product_id, locked.id
+            );
+            self.refund_unclaimed_payment(&payment).await?;
+            self.abandon_payment_attempt(&stock_repo, locked.id, "Released reservation after refunding an unfillable payment").await;
+            return Err(PaymentError::InsufficientStock);
+        }
+
+        // Update payment and order status to paid
+        PaymentRepository::apply_status(&mut tx, payment.id, &PaymentStatus::Succeeded.to_string()).await
+            .map_err(|e| PaymentError::Database(e.to_string()))?;
+        let paid_status = OrderStatus::Paid.to_string();
+        OrderRepository::apply_status(&mut tx, payment.order_id, &paid_status).await
+            .map_err(|e| PaymentError::Database(e.to_string()))?;
+        OrderRepository::record_status_change(&mut tx, payment.order_id, &locked.status.to_string(), &paid_status, None).await
+            .map_err(|e| PaymentError::Database(e.to_string()))?;
+        tx.commit().await
             .map_err(|e| PaymentError::Database(e.to_string()))?;
 
-            // Here you would typically:
-            // 1. Convert stock reservations to actual sales
-            // 2. Update inventory
-            // 3. Send confirmation emails
-            // 4. Trigger order fulfillment process
+        if let Some(notifier) = &self.low_stock {
+            notifier.check(items.iter().map(|(product_id, _)| *product_id).collect());
         }
 
+        // Hand the order to fulfillment
+        if let Some(webhooks) = &self.order_webhooks {
+            webhooks.order_paid(payment.order_id);
+        }
+
+        // Here you would typically send confirmation emails
+
+        Ok(())
+    }
+
+    /// Refunds a successful charge its order didn't take. Until the refund goes through the
+    /// payment keeps its earlier status, so a redelivered event tries again.
+    async fn refund_unclaimed_payment(&self, payment: &Payment) -> Result<(), PaymentError> {
+        if let Err(e) = self.refund_charge(payment).await {
+            tracing::error!(
+                "Payment {} for order {} needs a manual refund: {}", payment.id, payment.order_id, e
+            );
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Refunds the payment with Stripe and marks it refunded; the order is left as it is.
+    async fn refund_charge(&self, payment: &Payment) -> Result<(), PaymentError> {
+        // One key per intent so a retry can't refund twice
+        let params = [("payment_intent", payment.stripe_payment_intent_id.clone())];
+        self.stripe
+            .post_form("/v1/refunds", &params, &format!("refund-{}", payment.stripe_payment_intent_id))
+            .await?;
+
+        self.payment_repo.update_status(
+            payment.id,
+            PaymentStatus::Canceled.to_string(), // Using canceled for refunded
+            None,
+        ).await
+        .map_err(|e| PaymentError::Database(e.to_string()))?;
         Ok(())
     }
 
+    /// Marks the payment failed and puts its order back to awaiting payment, giving the held
+    /// stock back. Only an order still in `payment_processing` is moved, so a late failure
+    /// event can't undo a payment or a cancellation.
     pub async fn handle_payment_failed(&self, payment_intent_id: &str) -> Result<(), PaymentError> {
         // Update payment status
         let payment = self.payment_repo.update_status_by_stripe_id(
@@ -139,11 +317,24 @@ impl PaymentService {
 
         if let Some(payment) = payment {
             // Update order status back to pending_payment
-            self.order_repo.update_status(
+            let reverted = self.order_repo.transition_status(
                 payment.order_id,
+                &[OrderStatus::PaymentProcessing.to_string().as_str()],
                 &OrderStatus::PendingPayment.to_string(),
+                None,
             ).await
             .map_err(|e| PaymentError::Database(e.to_string()))?;
+
+            if reverted.is_none() {
+                tracing::info!("Ignoring payment failure for order {} no longer processing payment", payment.order_id);
+                return Ok(());
+            }
+
+            // Give the held stock back
+            StockRepository::new(self.order_repo.pool.clone())
+                .release_order_reservations(payment.order_id, "Released reservation after failed payment")
+                .await
+                .map_err(|e| PaymentError::Database(e.to_string()))?;
         }
 
         Ok(())
@@ -154,50 +345,114 @@ impl PaymentService {
             .map_err(|e| PaymentError::Database(e.to_string()))
     }
 
+    pub async fn get_payment_by_intent(&self, payment_intent_id: &str) -> Result<Option<Payment>, PaymentError> {
+        self.payment_repo.get_by_stripe_payment_intent_id(payment_intent_id).await
+            .map_err(|e| PaymentError::Database(e.to_string()))
+    }
+
+    /// A page of payments across all orders, newest first, with the total matching count.
+    pub async fn list_payments(&self, status: Option<&str>, limit: i64, offset: i64) -> Result<(Vec<Payment>, i64), PaymentError> {
+        if let Some(status) = status {
+            if PaymentStatus::from_str(status).is_none() {
+                return Err(PaymentError::InvalidPaymentStatus(status.to_string()));
+            }
+        }
+
+        let payments = self.payment_repo.list_by_status(status, limit, offset).await
+            .map_err(|e| PaymentError::Database(e.to_string()))?;
+        let total = self.payment_repo.count_by_status(status).await
+            .map_err(|e| PaymentError::Database(e.to_string()))?;
+        Ok((payments, total))
+    }
+
     pub async fn refund_payment(&self, payment_id: Uuid) -> Result<(), PaymentError> {
         let payment = self.payment_repo.get_by_id(payment_id).await
             .map_err(|e| PaymentError::Database(e.to_string()))?;
 
         let payment = payment.ok_or(PaymentError::PaymentNotFound)?;
 
-        // Create refund with Stripe
-        let client = reqwest::Client::new();
-        let mut params = HashMap::new();
-        params.insert("payment_intent", payment.stripe_payment_intent_id.clone());
-
-        let response = client
-            .post("https://api.stripe.com/v1/refunds")
-            .header("Authorization", format!("Bearer {}", self.stripe_secret_key))
-            .form(&params)
-            .send()
-            .await
-            .map_err(|e| PaymentError::StripeApiError(e.to_string()))?;
-
-        if !response.status().is_success() {
-            let error_text = response.text().await
-                .unwrap_or_else(|_| "Unknown Stripe API error".to_string());
-            return Err(PaymentError::StripeApiError(error_text));
+        // Only an order that was paid for can be refunded; check before any money moves. A
+        // payment that went through after its order was cancelled is refunded on its own.
+        let order = self.order_repo.get_by_id(payment.order_id).await
+            .map_err(|e| PaymentError::Database(e.to_string()))?
+            .ok_or(PaymentError::OrderNotFound)?;
+        let paid_after_cancel = order.status == OrderStatus::Cancelled
+            && payment.status == PaymentStatus::Succeeded.to_string();
+        if !paid_after_cancel && !order.status.can_transition_to(&OrderStatus::Refunded) {
+            return Err(PaymentError::InvalidOrderStatus(order.status.to_string()));
         }
 
-        // Update payment status
-        self.payment_repo.update_status(
-            payment_id,
-            PaymentStatus::Canceled.to_string(), // Using canceled for refunded
-            None,
-        ).await
-        .map_err(|e| PaymentError::Database(e.to_string()))?;
+        self.refund_charge(&payment).await?;
+        if paid_after_cancel {
+            return Ok(());
+        }
 
-        // Update order status
-        self.order_repo.update_status(
+        // Move the order and return its goods in one transaction, unless it moved since the check above
+        let mut tx = self.order_repo.pool.begin().await
+            .map_err(|e| PaymentError::Database(e.to_string()))?;
+        let refunded = OrderRepository::transition_status_in(
+            &mut tx,
             payment.order_id,
+            &[order.status.to_string().as_str()],
             &OrderStatus::Refunded.to_string(),
+            None,
+            None,
         ).await
         .map_err(|e| PaymentError::Database(e.to_string()))?;
+        if refunded.is_none() {
+            tracing::error!("Order {} changed status while payment {} was being refunded", payment.order_id, payment_id);
+            return Err(PaymentError::InvalidOrderStatus(order.status.to_string()));
+        }
+        OrderService::new(self.order_repo.clone())
+            .settle_stock_in(&mut tx, payment.order_id, order.status, OrderStatus::Refunded)
+            .await
+            .map_err(|e| PaymentError::Database(e.to_string()))?;
+        tx.commit().await
+            .map_err(|e| PaymentError::Database(e.to_string()))?;
 
         Ok(())
     }
 }
 
+/// Lowercases `currency` and checks it against [`SUPPORTED_CURRENCIES`].
+pub fn normalize_currency(currency: &str) -> Result<String, PaymentError> {
+    let normalized = currency.trim().to_ascii_lowercase();
+    if SUPPORTED_CURRENCIES.contains(&normalized.as_str()) {
+        Ok(normalized)
+    } else {
+        Err(PaymentError::UnsupportedCurrency(currency.to_string()))
+    }
+}
+
+impl From<PaymentError> for AppError {
+    fn from(err: PaymentError) -> Self {
+        match err {
+            PaymentError::Database(msg) => AppError::Internal(msg),
+            PaymentError::StripeApiError(msg) => {
+                AppError::Validation(format!("Payment processing error: {}", msg))
+            }
+            PaymentError::OrderNotFound => AppError::NotFound("Order not found".into()),
+            PaymentError::PaymentNotFound => AppError::NotFound("Payment not found".into()),
+            PaymentError::InvalidOrderStatus(status) => {
+                AppError::Validation(format!("Invalid order status: {}", status))
+            }
+            PaymentError::InvalidPaymentStatus(status) => {
+                AppError::Validation(format!("Unknown payment status '{}'", status))
+            }
+            PaymentError::InvalidAmount => AppError::Validation("Invalid amount".into()),
+            PaymentError::AmountMismatch { expected, actual } => AppError::Validation(format!(
+                "Payment amount {} does not match the order total {}", actual, expected
+            )),
+            PaymentError::UnsupportedCurrency(currency) => AppError::Validation(format!(
+                "Unsupported currency '{}', expected one of: {}", currency, SUPPORTED_CURRENCIES.join(", ")
+            )),
+            PaymentError::InsufficientStock => {
+                AppError::InsufficientStock("not enough stock to reserve order items".into())
+            }
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum PaymentError {
     #[error("Database error: {0}")]
@@ -214,7 +469,19 @@ pub enum PaymentError {
     
     #[error("Invalid order status: {0}")]
     InvalidOrderStatus(String),
+
+    #[error("Unknown payment status: {0}")]
+    InvalidPaymentStatus(String),
     
     #[error("Invalid amount")]
     InvalidAmount,
+
+    #[error("Unsupported currency: {0}")]
+    UnsupportedCurrency(String),
+
+    #[error("Amount {actual} does not match order total {expected}")]
+    AmountMismatch { expected: String, actual: String },
+
+    #[error("Insufficient stock to reserve order items")]
+    InsufficientStock,
 }
@@ -1,8 +1,18 @@
-use crate::repository::ProductRepository;
-use crate::dtos::{NewProductDto, UpdateProductDto};
-use crate::model::product::Product;
+use crate::repository::{ProductFilter, ProductRepository};
+use crate::dtos::{NewProductDto, ProductImportReport, ProductImportRow, UpdateProductDto};
+use crate::middleware::validation::describe_validation_errors;
+use crate::model::category::Category;
+use crate::model::product::{Product, ProductWithAvailableStock};
 use crate::errors::{AppError, AppResult};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::str::FromStr;
 use uuid::Uuid;
+use validator::Validate;
+
+/// Largest number of data rows accepted in one CSV import.
+pub const MAX_IMPORT_ROWS: usize = 1000;
 
 
 #[derive(Clone)]
@@ -22,37 +32,246 @@ impl ProductService {
         
         self.repo.create(
             &dto.name, 
+            dto.sku.as_deref(),
             dto.description.as_deref(), 
             dto.price, 
             dto.stock,
             dto.image_url.as_deref(),
             low_stock_threshold,
+            dto.reorder_target,
             track_inventory,
-        ).await.map_err(AppError::Database)
+        ).await.map_err(map_sku_conflict)
+    }
+
+    /// The product with the categories it belongs to, for the detail page.
+    pub async fn get_with_categories(&self, id: Uuid) -> AppResult<Option<(ProductWithAvailableStock, Vec<Category>)>> {
+        let Some(product) = self.repo.get_with_availability(id).await? else {
+            return Ok(None);
+        };
+        let categories = self.repo.list_categories_for_product(id).await?;
+        Ok(Some((product, categories)))
     }
 
-    pub async fn get(&self, id: Uuid) -> AppResult<Option<Product>> {
-        self.repo.get(id).await.map_err(AppError::Database)
+    pub async fn get_by_sku(&self, sku: &str) -> AppResult<Option<ProductWithAvailableStock>> {
+        self.repo.find_by_sku_with_availability(sku).await.map_err(AppError::Database)
     }
 
-    pub async fn list(&self, limit: i64, offset: i64) -> AppResult<Vec<Product>> {
-        self.repo.list(limit, offset).await.map_err(AppError::Database)
+    pub async fn list(&self, filter: &ProductFilter, limit: i64, offset: i64) -> AppResult<Vec<ProductWithAvailableStock>> {
+        Self::validate_filter(filter)?;
+        self.repo.list_with_availability(filter, limit, offset).await.map_err(AppError::Database)
+    }
+
+    pub async fn count(&self, filter: &ProductFilter) -> AppResult<i64> {
+        Self::validate_filter(filter)?;
+        self.repo.count(filter).await.map_err(AppError::Database)
+    }
+
+    fn validate_filter(filter: &ProductFilter) -> AppResult<()> {
+        if let (Some(min), Some(max)) = (filter.min_price, filter.max_price) {
+            if min > max {
+                return Err(AppError::Validation("min_price must not exceed max_price".into()));
+            }
+        }
+        if let (Some(after), Some(before)) = (filter.created_after, filter.created_before) {
+            if after > before {
+                return Err(AppError::Validation("created_after must not be later than created_before".into()));
+            }
+        }
+        Ok(())
     }
 
     pub async fn update(&self, id: Uuid, dto: UpdateProductDto) -> AppResult<Option<Product>> {
         self.repo.update(
             id, 
             dto.name.as_deref(), 
-            dto.description.as_deref(), 
+            dto.sku.as_ref().map(|v| v.as_deref()),
+            dto.description.as_ref().map(|v| v.as_deref()),
             dto.price, 
             dto.stock,
-            dto.image_url.as_deref(),
+            dto.image_url.as_ref().map(|v| v.as_deref()),
             dto.low_stock_threshold,
+            dto.reorder_target,
             dto.track_inventory,
-        ).await.map_err(AppError::Database)
+        ).await.map_err(map_sku_conflict)
+    }
+
+    /// Creates a product for every valid row of a CSV file. Valid rows are inserted together in
+    /// one transaction; invalid ones are reported by line and skipped.
+    pub async fn import_csv(&self, data: &[u8]) -> AppResult<ProductImportReport> {
+        let mut parsed = parse_product_csv(data)?;
+
+        // SKUs must be new to the catalogue and unique within the file
+        let skus: Vec<String> = parsed
+            .iter()
+            .filter_map(|(_, dto)| dto.as_ref()?.sku.clone())
+            .collect();
+        let taken: HashSet<String> = self.repo.existing_skus(&skus).await?.into_iter().collect();
+        let mut seen = HashSet::new();
+        for (row, dto) in parsed.iter_mut() {
+            let Some(sku) = dto.as_ref().and_then(|d| d.sku.clone()) else { continue };
+            let error = if taken.contains(&sku) {
+                format!("sku: {} is already in use", sku)
+            } else if !seen.insert(sku.clone()) {
+                format!("sku: {} appears more than once in the file", sku)
+            } else {
+                continue;
+            };
+            row.error = Some(error);
+            *dto = None;
+        }
+
+        let valid: Vec<NewProductDto> = parsed.iter_mut().filter_map(|(_, dto)| dto.take()).collect();
+        let created = self.repo.bulk_create(&valid).await.map_err(map_sku_conflict)?;
+
+        let mut ids = created.into_iter().map(|p| p.id);
+        let rows: Vec<ProductImportRow> = parsed
+            .into_iter()
+            .map(|(mut row, _)| {
+                if row.error.is_none() {
+                    row.product_id = ids.next();
+                }
+                row
+            })
+            .collect();
+
+        Ok(ProductImportReport {
+            imported: valid.len(),
+            failed: rows.len() - valid.len(),
+            rows,
+        })
     }
 
     pub async fn delete(&self, id: Uuid) -> AppResult<bool> {
         self.repo.delete(id).await.map_err(AppError::Database)
     }
+
+    pub async fn set_published(&self, id: Uuid, is_published: bool) -> AppResult<Option<Product>> {
+        self.repo.set_published(id, is_published).await.map_err(AppError::Database)
+    }
+
+    pub async fn restore(&self, id: Uuid) -> AppResult<Option<Product>> {
+        self.repo.restore(id).await.map_err(AppError::Database)
+    }
+
+    /// Creates a copy of a live product named "<name> (copy)", with no stock and no SKU (SKUs
+    /// are unique). Categories aren't copied. `None` when the source doesn't exist.
+    pub async fn duplicate(&self, id: Uuid) -> AppResult<Option<Product>> {
+        let Some(source) = self.repo.get(id).await? else {
+            return Ok(None);
+        };
+
+        let created = self.repo.create(
+            &copy_name(&source.name),
+            None,
+            source.description.as_deref(),
+            source.price,
+            0,
+            source.image_url.as_deref(),
+            source.low_stock_threshold,
+            source.reorder_target,
+            source.track_inventory,
+        ).await?;
+        Ok(Some(created))
+    }
+}
+
+/// Longest product name accepted by the product DTOs.
+const MAX_NAME_CHARS: usize = 255;
+const COPY_SUFFIX: &str = " (copy)";
+
+/// `name` with the copy suffix, shortening the original so the result stays a valid name.
+fn copy_name(name: &str) -> String {
+    let keep = MAX_NAME_CHARS - COPY_SUFFIX.chars().count();
+    let base: String = name.chars().take(keep).collect();
+    format!("{}{}", base, COPY_SUFFIX)
+}
+
+/// Turns a violation of the products SKU unique constraint into a client error.
+fn map_sku_conflict(err: sqlx::Error) -> AppError {
+    if let sqlx::Error::Database(ref db_err) = err {
+        if db_err.constraint() == Some("products_sku_key") {
+            return AppError::Validation("SKU already in use".into());
+        }
+    }
+    AppError::Database(err)
+}
+
+#[derive(Debug, Deserialize)]
+struct CsvProductRow {
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    price: String,
+    stock: String,
+    #[serde(default)]
+    sku: Option<String>,
+    #[serde(default)]
+    image_url: Option<String>,
+}
+
+/// Parses an import file with a header row naming the columns `name`, `price` and `stock`, and
+/// optionally `description`, `sku` and `image_url`, in any order. Each data line yields its
+/// report row plus the product to create, or `None` with the row's error filled in.
+pub fn parse_product_csv(data: &[u8]) -> AppResult<Vec<(ProductImportRow, Option<NewProductDto>)>> {
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(data);
+    let headers = reader
+        .headers()
+        .map_err(|e| AppError::Validation(format!("Unreadable CSV header: {}", e)))?
+        .clone();
+    for required in ["name", "price", "stock"] {
+        if !headers.iter().any(|h| h == required) {
+            return Err(AppError::Validation(format!("CSV is missing the {} column", required)));
+        }
+    }
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        if rows.len() == MAX_IMPORT_ROWS {
+            return Err(AppError::Validation(format!("An import may contain at most {} products", MAX_IMPORT_ROWS)));
+        }
+
+        let (line, name, result) = match record {
+            Ok(record) => {
+                let line = record.position().map_or(0, |p| p.line());
+                match record.deserialize::<CsvProductRow>(Some(&headers)) {
+                    Ok(raw) => (line, Some(raw.name.clone()), row_to_product(raw)),
+                    Err(e) => (line, None, Err(e.to_string())),
+                }
+            }
+            Err(e) => (e.position().map_or(0, |p| p.line()), None, Err(e.to_string())),
+        };
+
+        let (error, dto) = match result {
+            Ok(dto) => (None, Some(dto)),
+            Err(error) => (Some(error), None),
+        };
+        rows.push((ProductImportRow { line, name, product_id: None, error }, dto));
+    }
+    Ok(rows)
+}
+
+fn row_to_product(raw: CsvProductRow) -> Result<NewProductDto, String> {
+    let price = Decimal::from_str(&raw.price).map_err(|_| format!("price: '{}' is not a number", raw.price))?;
+    if price.is_sign_negative() {
+        return Err("price: Price cannot be negative".into());
+    }
+    let stock = raw
+        .stock
+        .parse::<i32>()
+        .map_err(|_| format!("stock: '{}' is not a whole number", raw.stock))?;
+
+    let non_empty = |v: Option<String>| v.filter(|s| !s.is_empty());
+    let dto = NewProductDto {
+        name: raw.name,
+        sku: non_empty(raw.sku),
+        description: non_empty(raw.description),
+        price,
+        stock,
+        image_url: non_empty(raw.image_url),
+        low_stock_threshold: None,
+        reorder_target: None,
+        track_inventory: None,
+    };
+    dto.validate().map_err(|e| describe_validation_errors(&e))?;
+    Ok(dto)
 }
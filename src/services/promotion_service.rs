@@ -0,0 +1,137 @@
+use crate::dtos::{AppliedPromotion, NewPromotionDto};
+use crate::errors::{AppError, AppResult};
+use crate::model::promotion::{Promotion, PromotionStacking};
+use crate::repository::PromotionRepository;
+use chrono::Utc;
+use rust_decimal::Decimal;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+/// Result of evaluating promotions against an order subtotal.
+#[derive(Debug, Clone)]
+pub struct PromotionOutcome {
+    pub discount: Decimal,
+    pub applied: Vec<AppliedPromotion>,
+}
+
+#[derive(Clone)]
+pub struct PromotionService {
+    repo: PromotionRepository,
+    stacking: PromotionStacking,
+}
+
+impl PromotionService {
+    pub fn new(repo: PromotionRepository) -> Self {
+        Self { repo, stacking: PromotionStacking::default() }
+    }
+
+    pub fn with_stacking(mut self, stacking: PromotionStacking) -> Self {
+        self.stacking = stacking;
+        self
+    }
+
+    pub async fn create(&self, dto: NewPromotionDto) -> AppResult<Promotion> {
+        if dto.discount_percent <= Decimal::ZERO || dto.discount_percent > Decimal::ONE_HUNDRED {
+            return Err(AppError::Validation(
+                "discount_percent: Discount must be greater than 0 and at most 100".to_string(),
+            ));
+        }
+
+        let min_order_total = dto.min_order_total.unwrap_or(Decimal::ZERO);
+        if min_order_total < Decimal::ZERO {
+            return Err(AppError::Validation(
+                "min_order_total: Minimum order total cannot be negative".to_string(),
+            ));
+        }
+
+        let starts_at = dto.starts_at.unwrap_or_else(Utc::now);
+        if let Some(ends_at) = dto.ends_at {
+            if ends_at <= starts_at {
+                return Err(AppError::Validation(
+                    "ends_at: Promotion must end after it starts".to_string(),
+                ));
+            }
+        }
+
+        self.repo
+            .create(
+                &dto.name,
+                dto.description.as_deref(),
+                dto.discount_percent,
+                min_order_total,
+                starts_at,
+                dto.ends_at,
+            )
+            .await
+            .map_err(AppError::Database)
+    }
+
+    pub async fn list(&self, limit: i64, offset: i64) -> AppResult<Vec<Promotion>> {
+        self.repo.list(limit, offset).await.map_err(AppError::Database)
+    }
+
+    pub async fn deactivate(&self, id: Uuid) -> AppResult<Option<Promotion>> {
+        self.repo.set_active(id, false).await.map_err(AppError::Database)
+    }
+
+    /// Evaluates the currently running promotions against `subtotal`.
+    pub async fn evaluate(&self, subtotal: Decimal) -> AppResult<PromotionOutcome> {
+        let promotions = self.repo.list_active().await.map_err(AppError::Database)?;
+        Ok(apply_promotions(subtotal, &promotions, self.stacking))
+    }
+
+    /// Records the promotions applied to a new order, as part of the transaction that creates it.
+    pub async fn record_for_order(conn: &mut PgConnection, order_id: Uuid, outcome: &PromotionOutcome) -> AppResult<()> {
+        for applied in &outcome.applied {
+            PromotionRepository::record_for_order(&mut *conn, order_id, applied.promotion_id, &applied.name, applied.discount_amount)
+                .await
+                .map_err(AppError::Database)?;
+        }
+        Ok(())
+    }
+
+    /// The promotions that were applied when the order was placed.
+    pub async fn applied_to_order(&self, order_id: Uuid) -> AppResult<Vec<AppliedPromotion>> {
+        let rows = self.repo.find_for_order(order_id).await.map_err(AppError::Database)?;
+        Ok(rows
+            .into_iter()
+            .map(|(promotion_id, name, discount_percent, discount_amount)| AppliedPromotion {
+                promotion_id,
+                name,
+                discount_percent,
+                discount_amount,
+            })
+            .collect())
+    }
+}
+
+/// Applies every promotion whose threshold `subtotal` meets, combined according to `stacking`.
+/// The total discount never exceeds the subtotal.
+pub fn apply_promotions(
+    subtotal: Decimal,
+    promotions: &[Promotion],
+    stacking: PromotionStacking,
+) -> PromotionOutcome {
+    let mut eligible: Vec<AppliedPromotion> = promotions
+        .iter()
+        .filter(|p| subtotal >= p.min_order_total)
+        .map(|p| AppliedPromotion {
+            promotion_id: p.id,
+            name: p.name.clone(),
+            discount_percent: p.discount_percent,
+            discount_amount: (subtotal * p.discount_percent / Decimal::ONE_HUNDRED).round_dp(2),
+        })
+        .collect();
+
+    if stacking == PromotionStacking::BestOnly {
+        eligible.sort_by_key(|p| std::cmp::Reverse(p.discount_amount));
+        eligible.truncate(1);
+    }
+
+    let discount: Decimal = eligible.iter().map(|p| p.discount_amount).sum();
+
+    PromotionOutcome {
+        discount: discount.min(subtotal),
+        applied: eligible,
+    }
+}
@@ -0,0 +1,79 @@
+use crate::model::stock::LowStockAlert;
+use crate::repository::StockRepository;
+use crate::state::{InventoryConfig, LowStockWebhookConfig};
+use serde_json::json;
+use uuid::Uuid;
+
+/// Posts to `LOW_STOCK_WEBHOOK_URL` when an operation that takes stock leaves a product at or
+/// below its low-stock threshold, or the configured default for products without one. Does
+/// nothing when the URL isn't set.
+#[derive(Clone)]
+pub struct LowStockNotifier {
+    repo: StockRepository,
+    http: reqwest::Client,
+    webhook_url: Option<String>,
+    default_threshold: Option<i32>,
+    debounce_seconds: i64,
+}
+
+impl LowStockNotifier {
+    pub fn new(
+        repo: StockRepository,
+        http: reqwest::Client,
+        inventory_config: &InventoryConfig,
+        webhook_config: &LowStockWebhookConfig,
+    ) -> Self {
+        Self {
+            repo,
+            http,
+            webhook_url: webhook_config.url.clone(),
+            default_threshold: inventory_config.default_low_stock_threshold,
+            debounce_seconds: webhook_config.debounce_seconds,
+        }
+    }
+
+    /// Checks `product_ids` in a background task so the caller's response isn't held up by
+    /// the webhook; failures are logged.
+    pub fn check(&self, product_ids: Vec<Uuid>) {
+        let Some(url) = self.webhook_url.clone() else {
+            return;
+        };
+        let repo = self.repo.clone();
+        let client = self.http.clone();
+        let default_threshold = self.default_threshold;
+        let debounce_seconds = self.debounce_seconds;
+
+        tokio::spawn(async move {
+            let alerts = match repo.claim_low_stock_notifications(&product_ids, default_threshold, debounce_seconds).await {
+                Ok(alerts) => alerts,
+                Err(e) => {
+                    tracing::error!("Failed to check low stock: {}", e);
+                    return;
+                }
+            };
+
+            for alert in alerts {
+                send(&client, &url, &alert).await;
+            }
+        });
+    }
+}
+
+async fn send(client: &reqwest::Client, url: &str, alert: &LowStockAlert) {
+    let payload = json!({
+        "event": "low_stock",
+        "alert": alert,
+    });
+
+    match client.post(url).json(&payload).send().await {
+        Ok(res) if res.status().is_success() => {
+            tracing::info!("Sent low stock alert for product {}", alert.product_id);
+        }
+        Ok(res) => {
+            tracing::warn!("Low stock webhook for product {} returned {}", alert.product_id, res.status());
+        }
+        Err(e) => {
+            tracing::warn!("Low stock webhook for product {} failed: {}", alert.product_id, e);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use crate::services::http_retry::send_with_retry;
+use crate::services::payment_service::PaymentError;
+use std::time::Duration;
+
+/// Delay before the first retry of a call.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(200);
+
+/// Form-encoded POSTs to the Stripe API over the shared HTTP client, whose timeout bounds each
+/// attempt.
+///
+/// Server errors (5xx), timeouts and connection failures are retried with exponential backoff;
+/// 4xx responses are returned straight away. Every attempt of a call carries the same
+/// `Idempotency-Key`, so a retry after a lost response can't charge or refund twice.
+#[derive(Clone)]
+pub struct StripeClient {
+    http: reqwest::Client,
+    secret_key: String,
+    api_base: String,
+}
+
+impl StripeClient {
+    pub fn new(http: reqwest::Client, secret_key: String, api_base: String) -> Self {
+        Self {
+            http,
+            secret_key,
+            api_base: api_base.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// POSTs `params` to `path` (e.g. `/v1/refunds`) and returns the decoded response body.
+    pub async fn post_form(
+        &self,
+        path: &str,
+        params: &[(&str, String)],
+        idempotency_key: &str,
+    ) -> Result<serde_json::Value, PaymentError> {
+        let url = format!("{}{}", self.api_base, path);
+        let response = send_with_retry(
+            || {
+                self.http
+                    .post(&url)
+                    .header("Authorization", format!("Bearer {}", self.secret_key))
+                    .header("Idempotency-Key", idempotency_key)
+                    .form(params)
+            },
+            INITIAL_BACKOFF,
+            &format!("Stripe request to {}", path),
+        )
+        .await
+        .map_err(|e| PaymentError::StripeApiError(e.to_string()))?;
+
+        if response.status().is_success() {
+            response
+                .json()
+                .await
+                .map_err(|e| PaymentError::StripeApiError(e.to_string()))
+        } else {
+            let error_text = response.text().await
+                .unwrap_or_else(|_| "Unknown Stripe API error".to_string());
+            Err(PaymentError::StripeApiError(error_text))
+        }
+    }
+}
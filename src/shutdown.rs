@@ -0,0 +1,27 @@
+use std::future::Future;
+
+/// Resolves on the first SIGINT or SIGTERM. The handlers are registered when this is called
+/// rather than when the future is first polled, so a signal arriving in between isn't lost.
+#[cfg(unix)]
+pub fn shutdown_signal() -> impl Future<Output = ()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut interrupt = signal(SignalKind::interrupt()).expect("failed to install SIGINT handler");
+    let mut terminate = signal(SignalKind::terminate()).expect("failed to install SIGTERM handler");
+
+    async move {
+        let name = tokio::select! {
+            _ = interrupt.recv() => "SIGINT",
+            _ = terminate.recv() => "SIGTERM",
+        };
+        tracing::info!("Received {}, draining in-flight requests", name);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn shutdown_signal() -> impl Future<Output = ()> {
+    async {
+        tokio::signal::ctrl_c().await.expect("failed to install Ctrl+C handler");
+        tracing::info!("Received Ctrl+C, draining in-flight requests");
+    }
+}
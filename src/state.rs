@@ -1,12 +1,650 @@
+use std::env;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 
-use sqlx::PgPool;
+use jsonwebtoken::Algorithm;
+use rust_decimal::Decimal;
+use sqlx::{postgres::PgPoolOptions, PgPool};
+
+use crate::model::promotion::PromotionStacking;
+use crate::services::mailer::Mailer;
+use crate::services::order_service::OrderPricing;
+
+/// Settings read from environment variables once at startup. Unset variables take the
+/// defaults; a variable that is set but invalid is an error naming it, so a typo stops the
+/// server from starting instead of being ignored.
+pub trait EnvConfig: Sized {
+    /// Reads the settings through `lookup`, which returns a variable's value if it is set.
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String>;
+
+    /// Reads the settings from the process environment.
+    fn from_env() -> Result<Self, String> {
+        Self::from_lookup(|key| env::var(key).ok())
+    }
+}
+
+/// Reads `key` through `lookup` as a `T` that `valid` accepts, or `None` if it is unset.
+/// Anything else is an error saying the variable must be `expected`.
+fn parse_env<T: FromStr>(
+    lookup: &impl Fn(&str) -> Option<String>,
+    key: &str,
+    expected: &str,
+    valid: impl Fn(&T) -> bool,
+) -> Result<Option<T>, String> {
+    match lookup(key) {
+        None => Ok(None),
+        Some(raw) => raw
+            .trim()
+            .parse::<T>()
+            .ok()
+            .filter(|v| valid(v))
+            .map(Some)
+            .ok_or_else(|| format!("{} must be {}, got '{}'", key, expected, raw)),
+    }
+}
+
+/// Reads an on/off switch: `true`/`1` or `false`/`0`, in any case.
+fn parse_flag(lookup: &impl Fn(&str) -> Option<String>, key: &str) -> Result<Option<bool>, String> {
+    match lookup(key) {
+        None => Ok(None),
+        Some(raw) => match raw.trim().to_ascii_lowercase().as_str() {
+            "true" | "1" => Ok(Some(true)),
+            "false" | "0" => Ok(Some(false)),
+            _ => Err(format!("{} must be true or false, got '{}'", key, raw)),
+        },
+    }
+}
+
+/// Token lifetime and claim checks shared by login (encoding) and `AuthUser` (validation).
+#[derive(Debug, Clone)]
+pub struct JwtConfig {
+    pub expiry_seconds: i64,
+    pub algorithm: Algorithm,
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+}
+
+impl Default for JwtConfig {
+    fn default() -> Self {
+        Self {
+            expiry_seconds: 24 * 60 * 60,
+            algorithm: Algorithm::HS256,
+            issuer: None,
+            audience: None,
+        }
+    }
+}
+
+impl EnvConfig for JwtConfig {
+    /// `JWT_EXPIRY_SECONDS` (at least 1), `JWT_ALGORITHM` (HS256/HS384/HS512), `JWT_ISSUER` and
+    /// `JWT_AUDIENCE`.
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let defaults = Self::default();
+        let algorithm = match lookup("JWT_ALGORITHM").as_deref().map(str::trim) {
+            None | Some("HS256") => Algorithm::HS256,
+            Some("HS384") => Algorithm::HS384,
+            Some("HS512") => Algorithm::HS512,
+            Some(other) => {
+                return Err(format!("JWT_ALGORITHM must be HS256, HS384 or HS512, got '{}'", other));
+            }
+        };
+
+        Ok(Self {
+            expiry_seconds: parse_env(&lookup, "JWT_EXPIRY_SECONDS", "a whole number of seconds of at least 1", |v: &i64| *v >= 1)?
+                .unwrap_or(defaults.expiry_seconds),
+            algorithm,
+            issuer: lookup("JWT_ISSUER").filter(|v| !v.is_empty()),
+            audience: lookup("JWT_AUDIENCE").filter(|v| !v.is_empty()),
+        })
+    }
+}
+
+/// Account rules applied at login.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuthConfig {
+    pub require_email_verification: bool,
+}
+
+impl EnvConfig for AuthConfig {
+    /// `REQUIRE_EMAIL_VERIFICATION`, off unless set. Turning it on needs `MAIL_API_KEY`, or no
+    /// new account could ever receive its verification link.
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let require_email_verification = parse_flag(&lookup, "REQUIRE_EMAIL_VERIFICATION")?.unwrap_or(false);
+        if require_email_verification && lookup("MAIL_API_KEY").filter(|v| !v.trim().is_empty()).is_none() {
+            return Err("MAIL_API_KEY is required when REQUIRE_EMAIL_VERIFICATION is on".to_string());
+        }
+
+        Ok(Self { require_email_verification })
+    }
+}
+
+/// The email API account emails go through, who they come from and the app the links in them
+/// open. No API key means email is off and account emails are dropped.
+#[derive(Clone)]
+pub struct MailConfig {
+    pub api_key: Option<String>,
+    pub api_base: String,
+    pub from: String,
+    pub app_url: String,
+}
+
+impl std::fmt::Debug for MailConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MailConfig")
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .field("api_base", &self.api_base)
+            .field("from", &self.from)
+            .field("app_url", &self.app_url)
+            .finish()
+    }
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            api_key: None,
+            api_base: Self::DEFAULT_API_BASE.to_string(),
+            from: String::new(),
+            app_url: String::new(),
+        }
+    }
+}
+
+impl MailConfig {
+    pub const DEFAULT_API_BASE: &'static str = "https://api.resend.com";
+}
+
+impl EnvConfig for MailConfig {
+    /// `MAIL_API_KEY`, `MAIL_API_BASE`, `MAIL_FROM` and `APP_URL`. The sender and the app URL
+    /// are required once a key is set, so every email sent has both.
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let set = |key: &str| lookup(key).filter(|v| !v.trim().is_empty());
+        let Some(api_key) = set("MAIL_API_KEY") else {
+            return Ok(Self::default());
+        };
+        let from = set("MAIL_FROM").ok_or_else(|| "MAIL_FROM is required when MAIL_API_KEY is set".to_string())?;
+        let app_url = set("APP_URL").ok_or_else(|| "APP_URL is required when MAIL_API_KEY is set".to_string())?;
+
+        Ok(Self {
+            api_key: Some(api_key),
+            api_base: set("MAIL_API_BASE").unwrap_or_else(|| Self::DEFAULT_API_BASE.to_string()),
+            from,
+            app_url: app_url.trim_end_matches('/').to_string(),
+        })
+    }
+}
+
+/// Connection pool sizing, tunable per deployment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbPoolConfig {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout: Duration,
+}
+
+impl Default for DbPoolConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 10,
+            min_connections: 0,
+            acquire_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl EnvConfig for DbPoolConfig {
+    /// `DB_MAX_CONNECTIONS`, `DB_MIN_CONNECTIONS` and `DB_ACQUIRE_TIMEOUT_SECS`. The minimum
+    /// can't exceed the maximum.
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let defaults = Self::default();
+        let max_connections = parse_env(&lookup, "DB_MAX_CONNECTIONS", "an integer of at least 1", |v: &u32| *v >= 1)?
+            .unwrap_or(defaults.max_connections);
+        let min_connections = parse_env(&lookup, "DB_MIN_CONNECTIONS", "an integer of at least 0", |_: &u32| true)?
+            .unwrap_or(defaults.min_connections);
+        let acquire_timeout = parse_env(&lookup, "DB_ACQUIRE_TIMEOUT_SECS", "an integer of at least 1", |v: &u64| *v >= 1)?
+            .map(Duration::from_secs)
+            .unwrap_or(defaults.acquire_timeout);
+
+        if min_connections > max_connections {
+            return Err(format!(
+                "DB_MIN_CONNECTIONS ({}) cannot exceed DB_MAX_CONNECTIONS ({})",
+                min_connections, max_connections
+            ));
+        }
+
+        Ok(Self {
+            max_connections,
+            min_connections,
+            acquire_timeout,
+        })
+    }
+}
+
+impl DbPoolConfig {
+    pub fn pool_options(&self) -> PgPoolOptions {
+        PgPoolOptions::new()
+            .max_connections(self.max_connections)
+            .min_connections(self.min_connections)
+            .acquire_timeout(self.acquire_timeout)
+    }
+}
+
+/// Timeouts for the outbound HTTP client shared by the Stripe and Cloudinary integrations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpClientConfig {
+    pub connect_timeout: Duration,
+    pub timeout: Duration,
+}
+
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl EnvConfig for HttpClientConfig {
+    /// `HTTP_CONNECT_TIMEOUT_SECS` and `HTTP_TIMEOUT_SECS`, each at least a second.
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let defaults = Self::default();
+        let seconds = |key: &str, default: Duration| -> Result<Duration, String> {
+            Ok(parse_env(&lookup, key, "a whole number of seconds of at least 1", |v: &u64| *v >= 1)?
+                .map(Duration::from_secs)
+                .unwrap_or(default))
+        };
+
+        Ok(Self {
+            connect_timeout: seconds("HTTP_CONNECT_TIMEOUT_SECS", defaults.connect_timeout)?,
+            timeout: seconds("HTTP_TIMEOUT_SECS", defaults.timeout)?,
+        })
+    }
+}
+
+impl HttpClientConfig {
+    /// Builds the client; clones of it share one connection pool.
+    pub fn build_client(&self) -> reqwest::Client {
+        reqwest::Client::builder()
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.timeout)
+            .build()
+            .expect("Failed to build HTTP client")
+    }
+}
+
+/// Stripe credentials and endpoint, read once at startup.
+#[derive(Clone)]
+pub struct StripeConfig {
+    pub secret_key: String,
+    pub api_base: String,
+}
+
+impl std::fmt::Debug for StripeConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StripeConfig")
+            .field("secret_key", &"<redacted>")
+            .field("api_base", &self.api_base)
+            .finish()
+    }
+}
+
+impl StripeConfig {
+    pub const DEFAULT_API_BASE: &'static str = "https://api.stripe.com";
+
+    /// Config for `secret_key` against the live Stripe API.
+    pub fn new(secret_key: impl Into<String>) -> Self {
+        Self {
+            secret_key: secret_key.into(),
+            api_base: Self::DEFAULT_API_BASE.to_string(),
+        }
+    }
+}
+
+impl EnvConfig for StripeConfig {
+    /// `STRIPE_SECRET_KEY`, which is required, and `STRIPE_API_BASE`.
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let secret_key = lookup("STRIPE_SECRET_KEY")
+            .filter(|v| !v.trim().is_empty())
+            .ok_or_else(|| "STRIPE_SECRET_KEY is required for payment processing".to_string())?;
+
+        let mut config = Self::new(secret_key);
+        if let Some(api_base) = lookup("STRIPE_API_BASE").filter(|v| !v.trim().is_empty()) {
+            config.api_base = api_base;
+        }
+        Ok(config)
+    }
+}
+
+/// Cloudinary credentials and the API the image endpoints call.
+#[derive(Clone)]
+pub struct CloudinaryConfig {
+    pub cloud_name: String,
+    pub api_key: String,
+    pub api_secret: String,
+    pub api_base: String,
+}
+
+impl std::fmt::Debug for CloudinaryConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CloudinaryConfig")
+            .field("cloud_name", &self.cloud_name)
+            .field("api_key", &self.api_key)
+            .field("api_secret", &"<redacted>")
+            .field("api_base", &self.api_base)
+            .finish()
+    }
+}
+
+impl CloudinaryConfig {
+    pub const DEFAULT_API_BASE: &'static str = "https://api.cloudinary.com";
+
+    /// Config for the `cloud_name` account against the live Cloudinary API.
+    pub fn new(cloud_name: impl Into<String>, api_key: impl Into<String>, api_secret: impl Into<String>) -> Self {
+        Self {
+            cloud_name: cloud_name.into(),
+            api_key: api_key.into(),
+            api_secret: api_secret.into(),
+            api_base: Self::DEFAULT_API_BASE.to_string(),
+        }
+    }
+}
+
+impl EnvConfig for CloudinaryConfig {
+    /// `CLOUDINARY_CLOUD_NAME`, `CLOUDINARY_API_KEY` and `CLOUDINARY_API_SECRET`, which are
+    /// required, and `CLOUDINARY_API_BASE`.
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let required = |key: &str| {
+            lookup(key)
+                .filter(|v| !v.trim().is_empty())
+                .ok_or_else(|| format!("{} is required for image uploads", key))
+        };
+
+        let mut config = Self::new(
+            required("CLOUDINARY_CLOUD_NAME")?,
+            required("CLOUDINARY_API_KEY")?,
+            required("CLOUDINARY_API_SECRET")?,
+        );
+        if let Some(api_base) = lookup("CLOUDINARY_API_BASE").filter(|v| !v.trim().is_empty()) {
+            config.api_base = api_base;
+        }
+        Ok(config)
+    }
+}
+
+/// Stock reservation lifetimes: what a hold lasts when the client doesn't say, and the longest it may ask for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReservationConfig {
+    pub default_minutes: i32,
+    pub max_minutes: i32,
+}
+
+impl Default for ReservationConfig {
+    fn default() -> Self {
+        Self {
+            default_minutes: 30,
+            max_minutes: 1440,
+        }
+    }
+}
+
+impl EnvConfig for ReservationConfig {
+    /// `RESERVATION_DEFAULT_MINUTES` and `RESERVATION_MAX_MINUTES`. Both must be positive and
+    /// the default can't exceed the maximum.
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let defaults = Self::default();
+        let minutes = |key: &str, default: i32| -> Result<i32, String> {
+            Ok(parse_env(&lookup, key, "a whole number of minutes of at least 1", |v: &i32| *v >= 1)?.unwrap_or(default))
+        };
+
+        let default_minutes = minutes("RESERVATION_DEFAULT_MINUTES", defaults.default_minutes)?;
+        let max_minutes = minutes("RESERVATION_MAX_MINUTES", defaults.max_minutes)?;
+        if default_minutes > max_minutes {
+            return Err(format!(
+                "RESERVATION_DEFAULT_MINUTES ({}) cannot exceed RESERVATION_MAX_MINUTES ({})",
+                default_minutes, max_minutes
+            ));
+        }
+
+        Ok(Self {
+            default_minutes,
+            max_minutes,
+        })
+    }
+}
+
+/// Request body size limits: one for ordinary JSON endpoints and a larger one for image uploads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BodyLimits {
+    pub max_request_bytes: usize,
+    pub max_upload_bytes: usize,
+}
+
+impl Default for BodyLimits {
+    fn default() -> Self {
+        Self {
+            max_request_bytes: 2 * 1024 * 1024,
+            max_upload_bytes: 10 * 1024 * 1024,
+        }
+    }
+}
+
+impl EnvConfig for BodyLimits {
+    /// `MAX_REQUEST_BYTES` and `MAX_UPLOAD_BYTES`, both positive.
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let defaults = Self::default();
+        let bytes = |key: &str, default: usize| -> Result<usize, String> {
+            Ok(parse_env(&lookup, key, "a positive number of bytes", |v: &usize| *v >= 1)?.unwrap_or(default))
+        };
+
+        Ok(Self {
+            max_request_bytes: bytes("MAX_REQUEST_BYTES", defaults.max_request_bytes)?,
+            max_upload_bytes: bytes("MAX_UPLOAD_BYTES", defaults.max_upload_bytes)?,
+        })
+    }
+}
+
+/// Inventory settings: the low-stock threshold used for products that don't set their own.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InventoryConfig {
+    pub default_low_stock_threshold: Option<i32>,
+}
+
+impl EnvConfig for InventoryConfig {
+    /// `DEFAULT_LOW_STOCK_THRESHOLD`, a whole number of units, zero or more.
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        Ok(Self {
+            default_low_stock_threshold: parse_env(
+                &lookup,
+                "DEFAULT_LOW_STOCK_THRESHOLD",
+                "a whole number of at least 0",
+                |v: &i32| *v >= 0,
+            )?,
+        })
+    }
+}
+
+/// Where low-stock alerts are posted and how often one product may be reported. No URL means
+/// low-stock webhooks are off.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LowStockWebhookConfig {
+    pub url: Option<String>,
+    pub debounce_seconds: i64,
+}
+
+impl Default for LowStockWebhookConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            // Report a product at most once an hour
+            debounce_seconds: 60 * 60,
+        }
+    }
+}
+
+impl EnvConfig for LowStockWebhookConfig {
+    /// `LOW_STOCK_WEBHOOK_URL` and `LOW_STOCK_WEBHOOK_DEBOUNCE_SECONDS`, a whole number of
+    /// seconds, zero or more.
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let defaults = Self::default();
+
+        Ok(Self {
+            url: lookup("LOW_STOCK_WEBHOOK_URL").filter(|v| !v.trim().is_empty()),
+            debounce_seconds: parse_env(
+                &lookup,
+                "LOW_STOCK_WEBHOOK_DEBOUNCE_SECONDS",
+                "a whole number of seconds of at least 0",
+                |v: &i64| *v >= 0,
+            )?
+            .unwrap_or(defaults.debounce_seconds),
+        })
+    }
+}
+
+/// Where order events are posted and the secret their signature is made with. No URL means
+/// order webhooks are off.
+#[derive(Clone, Default)]
+pub struct OrderWebhookConfig {
+    pub url: Option<String>,
+    pub secret: String,
+}
+
+impl std::fmt::Debug for OrderWebhookConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OrderWebhookConfig")
+            .field("url", &self.url)
+            .field("secret", &"<redacted>")
+            .finish()
+    }
+}
+
+impl EnvConfig for OrderWebhookConfig {
+    /// `ORDER_WEBHOOK_URL` and `ORDER_WEBHOOK_SECRET`. The secret is required once a URL is
+    /// set, so every event sent can be signed.
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let url = lookup("ORDER_WEBHOOK_URL").filter(|v| !v.trim().is_empty());
+        let secret = lookup("ORDER_WEBHOOK_SECRET").filter(|v| !v.trim().is_empty());
+
+        match (url, secret) {
+            (None, _) => Ok(Self::default()),
+            (Some(_), None) => Err("ORDER_WEBHOOK_SECRET is required when ORDER_WEBHOOK_URL is set".to_string()),
+            (Some(url), Some(secret)) => Ok(Self { url: Some(url), secret }),
+        }
+    }
+}
+
+/// How much a single cart may hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CartLimits {
+    /// Most units of one product in a cart
+    pub max_item_quantity: i32,
+    /// Most different products in a cart
+    pub max_distinct_items: usize,
+}
+
+impl Default for CartLimits {
+    fn default() -> Self {
+        Self { max_item_quantity: 100, max_distinct_items: 50 }
+    }
+}
+
+impl EnvConfig for CartLimits {
+    /// `CART_MAX_ITEM_QUANTITY` and `CART_MAX_DISTINCT_ITEMS`, each at least 1.
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let defaults = Self::default();
+
+        Ok(Self {
+            max_item_quantity: parse_env(&lookup, "CART_MAX_ITEM_QUANTITY", "a whole number of at least 1", |v: &i32| *v >= 1)?
+                .unwrap_or(defaults.max_item_quantity),
+            max_distinct_items: parse_env(&lookup, "CART_MAX_DISTINCT_ITEMS", "a whole number of at least 1", |v: &usize| *v >= 1)?
+                .unwrap_or(defaults.max_distinct_items),
+        })
+    }
+}
+
+/// What orders charge on top of their items, and whether payment charges the prices an order
+/// was placed at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderConfig {
+    pub pricing: OrderPricing,
+    pub lock_prices: bool,
+}
+
+impl Default for OrderConfig {
+    fn default() -> Self {
+        Self {
+            pricing: OrderPricing::default(),
+            lock_prices: true,
+        }
+    }
+}
+
+impl EnvConfig for OrderConfig {
+    /// `TAX_RATE`, `SHIPPING_FEE` and `FREE_SHIPPING_THRESHOLD`, numbers of at least 0 that
+    /// default to no tax, no shipping fee and no threshold, and `LOCK_PRICE_AT_ORDER`, on
+    /// unless set.
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let amount = |key: &str| parse_env(&lookup, key, "a number of at least 0", |v: &Decimal| *v >= Decimal::ZERO);
+
+        Ok(Self {
+            pricing: OrderPricing {
+                tax_rate_percent: amount("TAX_RATE")?.unwrap_or_default(),
+                shipping_fee: amount("SHIPPING_FEE")?.unwrap_or_default(),
+                free_shipping_threshold: amount("FREE_SHIPPING_THRESHOLD")?,
+            },
+            lock_prices: parse_flag(&lookup, "LOCK_PRICE_AT_ORDER")?.unwrap_or(true),
+        })
+    }
+}
+
+/// How eligible promotions combine, for cart quotes and orders alike.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PromotionConfig {
+    pub stacking: PromotionStacking,
+}
+
+impl EnvConfig for PromotionConfig {
+    /// `PROMOTION_STACKING`: `best` (the default) or `stack`.
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        Ok(Self {
+            stacking: parse_env(&lookup, "PROMOTION_STACKING", "best or stack", |_: &PromotionStacking| true)?
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// Whether Prometheus metrics are recorded and served at `/metrics`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+}
+
+impl EnvConfig for MetricsConfig {
+    /// `METRICS_ENABLED`, off unless set.
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        Ok(Self {
+            enabled: parse_flag(&lookup, "METRICS_ENABLED")?.unwrap_or(false),
+        })
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct AppState {
     pub db: PgPool,
     pub jwt_secret: Arc<String>,
-    pub cloudinary_cloud_name: Arc<String>,
-    pub cloudinary_api_key: Arc<String>,
-    pub cloudinary_api_secret: Arc<String>,
+    pub jwt_config: Arc<JwtConfig>,
+    pub auth_config: Arc<AuthConfig>,
+    pub reservation_config: Arc<ReservationConfig>,
+    pub inventory_config: Arc<InventoryConfig>,
+    pub low_stock_webhook_config: Arc<LowStockWebhookConfig>,
+    pub order_webhook_config: Arc<OrderWebhookConfig>,
+    pub order_config: Arc<OrderConfig>,
+    pub cart_limits: Arc<CartLimits>,
+    pub promotion_config: Arc<PromotionConfig>,
+    pub body_limits: Arc<BodyLimits>,
+    pub http: reqwest::Client,
+    pub mailer: Arc<dyn Mailer>,
+    pub stripe_config: Arc<StripeConfig>,
+    pub cloudinary_config: Arc<CloudinaryConfig>,
 }
@@ -0,0 +1,117 @@
+mod common;
+
+use hemp_backend::services::analytics_service::average;
+use rust_decimal::Decimal;
+use serde_json::json;
+use uuid::Uuid;
+
+#[test]
+fn average_order_value_is_zero_without_orders() {
+    assert_eq!(average(Decimal::ZERO, 0), Decimal::ZERO);
+    assert_eq!(average(Decimal::new(10000, 2), 3), Decimal::new(3333, 2));
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn sales_summary_requires_admin() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+    server
+        .get("/api/admin/analytics/sales")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_user()))
+        .await
+        .assert_status_forbidden();
+}
+
+/// Seeds an order placed at `created_at` with the given `(product, quantity, unit price)` lines;
+/// the order total is the sum of the lines.
+async fn seed_sale(pool: &sqlx::PgPool, user_id: Uuid, status: &str, created_at: &str, lines: &[(Uuid, i32, &str)]) {
+    let order_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO orders (id, user_id, total, status, created_at) VALUES ($1, $2, 0, $3, $4::timestamptz)")
+        .bind(order_id)
+        .bind(user_id)
+        .bind(status)
+        .bind(created_at)
+        .execute(pool)
+        .await
+        .unwrap();
+    for (product_id, quantity, price) in lines {
+        sqlx::query("INSERT INTO order_items (id, order_id, product_id, quantity, price) VALUES ($1, $2, $3, $4, $5::numeric)")
+            .bind(Uuid::new_v4())
+            .bind(order_id)
+            .bind(product_id)
+            .bind(quantity)
+            .bind(price)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+    sqlx::query("UPDATE orders SET total = (SELECT SUM(price * quantity) FROM order_items WHERE order_id = $1) WHERE id = $1")
+        .bind(order_id)
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn sales_summary_aggregates_revenue_and_top_products() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let user_id = common::seed_user(pool, "client").await;
+    let oil = common::seed_product(pool, "Analytics Oil", "20.00", 100).await;
+    let balm = common::seed_product(pool, "Analytics Balm", "5.00", 100).await;
+
+    // Inside March 2002
+    seed_sale(pool, user_id, "paid", "2002-03-01T00:00:00Z", &[(oil, 1, "20.00"), (balm, 2, "5.00")]).await;
+    seed_sale(pool, user_id, "delivered", "2002-03-10T10:00:00Z", &[(balm, 4, "5.00")]).await;
+    seed_sale(pool, user_id, "shipped", "2002-03-20T10:00:00Z", &[(oil, 2, "20.00")]).await;
+    // Not sales, or outside the range
+    seed_sale(pool, user_id, "cancelled", "2002-03-05T00:00:00Z", &[(oil, 9, "20.00")]).await;
+    seed_sale(pool, user_id, "pending_payment", "2002-03-06T00:00:00Z", &[(balm, 9, "5.00")]).await;
+    seed_sale(pool, user_id, "paid", "2002-04-01T00:00:00Z", &[(oil, 9, "20.00")]).await;
+
+    let res = server
+        .get("/api/admin/analytics/sales?from=2002-03-01T00:00:00Z&to=2002-04-01T00:00:00Z")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_admin()))
+        .await;
+    res.assert_status_ok();
+    let summary = res.json::<serde_json::Value>();
+
+    // 30.00 + 20.00 + 40.00 over three orders
+    assert_eq!(summary["order_count"], 3);
+    assert_eq!(summary["total_revenue"], json!(90.0));
+    assert_eq!(summary["average_order_value"], json!(30.0));
+
+    let top = summary["top_products"].as_array().unwrap();
+    assert_eq!(top.len(), 2);
+    assert_eq!(top[0]["product_id"], balm.to_string());
+    assert_eq!(top[0]["quantity_sold"], 6);
+    assert_eq!(top[0]["revenue"], json!(30.0));
+    assert_eq!(top[1]["product_id"], oil.to_string());
+    assert_eq!(top[1]["quantity_sold"], 3);
+    assert_eq!(top[1]["revenue"], json!(60.0));
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn sales_summary_of_empty_range_is_zero() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+    let admin = format!("Bearer {}", common::jwt_admin());
+
+    let res = server
+        .get("/api/admin/analytics/sales?from=1990-01-01T00:00:00Z&to=1990-02-01T00:00:00Z")
+        .add_header("Authorization", admin.clone())
+        .await;
+    res.assert_status_ok();
+    let summary = res.json::<serde_json::Value>();
+    assert_eq!(summary["order_count"], 0);
+    assert_eq!(summary["total_revenue"], json!(0.0));
+    assert_eq!(summary["average_order_value"], json!(0.0));
+    assert!(summary["top_products"].as_array().unwrap().is_empty());
+
+    server
+        .get("/api/admin/analytics/sales?from=1990-02-01T00:00:00Z&to=1990-01-01T00:00:00Z")
+        .add_header("Authorization", admin)
+        .await
+        .assert_status_bad_request();
+}
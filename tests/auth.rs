@@ -0,0 +1,462 @@
+mod common;
+
+use hemp_backend::repository::{StockRepository, UserRepository};
+use hemp_backend::services::auth_service::AuthService;
+use hemp_backend::services::mailer::Mailer;
+use hemp_backend::state::AuthConfig;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+/// Keeps the tokens mailed through it so tests can use them.
+#[derive(Debug, Default)]
+struct RecordingMailer {
+    reset_tokens: Mutex<Vec<(String, String)>>,
+    verification_tokens: Mutex<Vec<(String, String)>>,
+}
+
+impl RecordingMailer {
+    fn reset_token_for(&self, email: &str) -> Option<String> {
+        last_token_for(&self.reset_tokens, email)
+    }
+
+    fn verification_token_for(&self, email: &str) -> Option<String> {
+        last_token_for(&self.verification_tokens, email)
+    }
+}
+
+fn last_token_for(sent: &Mutex<Vec<(String, String)>>, email: &str) -> Option<String> {
+    sent.lock().unwrap().iter().rev().find(|(to, _)| to == email).map(|(_, token)| token.clone())
+}
+
+#[async_trait::async_trait]
+impl Mailer for RecordingMailer {
+    async fn send_password_reset(&self, email: &str, token: &str) {
+        self.reset_tokens.lock().unwrap().push((email.to_string(), token.to_string()));
+    }
+
+    async fn send_email_verification(&self, email: &str, token: &str) {
+        self.verification_tokens.lock().unwrap().push((email.to_string(), token.to_string()));
+    }
+}
+
+async fn issue_reset_token(pool: &sqlx::PgPool, user_id: Uuid) -> String {
+    let mailer = Arc::new(RecordingMailer::default());
+    let svc = AuthService::new(UserRepository::new(pool.clone()), "test_secret".to_string())
+        .with_mailer(mailer.clone());
+    let email = format!("{}@example.com", user_id);
+    svc.request_password_reset(&email).await.unwrap();
+    mailer.reset_token_for(&email).expect("registered user should get a token")
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn password_reset_happy_path() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let user_id = common::seed_user(&state.db, "client").await;
+    let token = issue_reset_token(&state.db, user_id).await;
+
+    server
+        .post("/api/auth/reset-password")
+        .json(&json!({"token": token, "new_password": "brand-new-secret1"}))
+        .await
+        .assert_status_ok();
+
+    server
+        .post("/api/auth/login")
+        .json(&json!({"email": format!("{}@example.com", user_id), "password": "brand-new-secret1"}))
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn password_reset_rejects_expired_token() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let user_id = common::seed_user(&state.db, "client").await;
+    let token = issue_reset_token(&state.db, user_id).await;
+
+    sqlx::query("UPDATE password_reset_tokens SET expires_at = now() - interval '1 minute' WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&state.db)
+        .await
+        .unwrap();
+
+    server
+        .post("/api/auth/reset-password")
+        .json(&json!({"token": token, "new_password": "brand-new-secret1"}))
+        .await
+        .assert_status_bad_request();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn password_reset_token_is_single_use() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let user_id = common::seed_user(&state.db, "client").await;
+    let token = issue_reset_token(&state.db, user_id).await;
+
+    server
+        .post("/api/auth/reset-password")
+        .json(&json!({"token": token, "new_password": "first-secret1"}))
+        .await
+        .assert_status_ok();
+    server
+        .post("/api/auth/reset-password")
+        .json(&json!({"token": token, "new_password": "second-secret2"}))
+        .await
+        .assert_status_bad_request();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn forgot_password_mails_a_working_reset_token() {
+    let mut state = common::test_state_db().await.expect("test database unavailable");
+    let mailer = Arc::new(RecordingMailer::default());
+    state.mailer = mailer.clone();
+    let server = axum_test::TestServer::new(common::app_with_state(state.clone()).await).unwrap();
+    let user_id = common::seed_user(&state.db, "client").await;
+    let email = format!("{}@example.com", user_id);
+
+    server
+        .post("/api/auth/forgot-password")
+        .json(&json!({"email": email}))
+        .await
+        .assert_status_ok();
+    let token = mailer.reset_token_for(&email).expect("reset token should be mailed");
+
+    server
+        .post("/api/auth/reset-password")
+        .json(&json!({"token": token, "new_password": "brand-new-secret1"}))
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn forgot_password_does_not_reveal_unknown_email() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+
+    server
+        .post("/api/auth/forgot-password")
+        .json(&json!({"email": format!("{}@example.com", Uuid::new_v4())}))
+        .await
+        .assert_status_ok();
+}
+
+async fn issue_verification_token(pool: &sqlx::PgPool, user_id: Uuid) -> String {
+    let svc = AuthService::new(UserRepository::new(pool.clone()), "test_secret".to_string());
+    svc.issue_verification_token(user_id).await.unwrap()
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn verify_email_with_valid_token() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let user_id = common::seed_user(&state.db, "client").await;
+    let token = issue_verification_token(&state.db, user_id).await;
+
+    server
+        .get("/api/auth/verify")
+        .add_query_param("token", &token)
+        .await
+        .assert_status_ok();
+
+    let res = server
+        .get("/api/auth/me")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_for_user(user_id, "client")))
+        .await;
+    res.assert_status_ok();
+    assert_eq!(res.json::<serde_json::Value>()["email_verified"], true);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn unverified_accounts_cannot_log_in_when_verification_is_required() {
+    let mut state = common::test_state_db().await.expect("test database unavailable");
+    state.auth_config = std::sync::Arc::new(AuthConfig { require_email_verification: true });
+    let server = axum_test::TestServer::new(common::app_with_state(state.clone()).await).unwrap();
+    let credentials = json!({"email": format!("{}@example.com", Uuid::new_v4()), "password": "secret123"});
+
+    let res = server.post("/api/auth/signup").json(&credentials).await;
+    res.assert_status(axum::http::StatusCode::CREATED);
+    let user_id: Uuid = res.json::<serde_json::Value>()["id"].as_str().unwrap().parse().unwrap();
+
+    server.post("/api/auth/login").json(&credentials).await.assert_status_forbidden();
+
+    let token = issue_verification_token(&state.db, user_id).await;
+    server.get("/api/auth/verify").add_query_param("token", token).await.assert_status_ok();
+    server.post("/api/auth/login").json(&credentials).await.assert_status_ok();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn signup_mails_a_working_verification_token() {
+    let mut state = common::test_state_db().await.expect("test database unavailable");
+    let mailer = Arc::new(RecordingMailer::default());
+    state.mailer = mailer.clone();
+    let server = axum_test::TestServer::new(common::app_with_state(state.clone()).await).unwrap();
+    let email = format!("{}@example.com", Uuid::new_v4());
+
+    server
+        .post("/api/auth/signup")
+        .json(&json!({"email": email, "password": "secret123"}))
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+    let token = mailer.verification_token_for(&email).expect("verification token should be mailed");
+
+    server.get("/api/auth/verify").add_query_param("token", token).await.assert_status_ok();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn verify_email_rejects_expired_token() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let user_id = common::seed_user(&state.db, "client").await;
+    let token = issue_verification_token(&state.db, user_id).await;
+
+    sqlx::query("UPDATE email_verification_tokens SET expires_at = now() - interval '1 minute' WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&state.db)
+        .await
+        .unwrap();
+
+    server
+        .get("/api/auth/verify")
+        .add_query_param("token", &token)
+        .await
+        .assert_status_bad_request();
+
+    let verified: bool = sqlx::query_scalar("SELECT email_verified FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap();
+    assert!(!verified);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn logout_revokes_token() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let user_id = common::seed_user(&state.db, "client").await;
+    let bearer = format!("Bearer {}", common::jwt_for_user(user_id, "client"));
+
+    server
+        .get("/api/auth/me")
+        .add_header("Authorization", bearer.clone())
+        .await
+        .assert_status_ok();
+
+    server
+        .post("/api/auth/logout")
+        .add_header("Authorization", bearer.clone())
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+
+    server
+        .get("/api/auth/me")
+        .add_header("Authorization", bearer)
+        .await
+        .assert_status_unauthorized();
+}
+
+async fn signup_user(server: &axum_test::TestServer, password: &str) -> (Uuid, String) {
+    let email = format!("delete-{}@example.com", Uuid::new_v4());
+    let res = server
+        .post("/api/auth/signup")
+        .json(&json!({"email": email, "password": password}))
+        .await;
+    res.assert_status(axum::http::StatusCode::CREATED);
+    let user_id: Uuid = res.json::<serde_json::Value>()["id"].as_str().unwrap().parse().unwrap();
+    (user_id, email)
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn account_deletion_requires_the_password() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+    let (user_id, _) = signup_user(&server, "secret123").await;
+    let bearer = format!("Bearer {}", common::jwt_for_user(user_id, "client"));
+
+    let res = server
+        .delete("/api/auth/account")
+        .add_header("Authorization", bearer.clone())
+        .json(&json!({"password": "wrong-password1"}))
+        .await;
+    res.assert_status_forbidden();
+
+    server
+        .get("/api/auth/me")
+        .add_header("Authorization", bearer)
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn deleted_account_is_anonymized() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let (user_id, email) = signup_user(&server, "secret123").await;
+    let cart_id = common::seed_cart(&state.db, user_id).await;
+    let bearer = format!("Bearer {}", common::jwt_for_user(user_id, "client"));
+
+    // An order awaiting payment, with stock held for it
+    let product_id = common::seed_product(&state.db, "Deleted Account Oil", "10.00", 5).await;
+    let order_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO orders (id, user_id, total, status) VALUES ($1, $2, 20.00, 'pending_payment')")
+        .bind(order_id)
+        .bind(user_id)
+        .execute(&state.db)
+        .await
+        .unwrap();
+    let stock_repo = StockRepository::new(state.db.clone());
+    let mut tx = state.db.begin().await.unwrap();
+    stock_repo
+        .reserve_for_order_in(&mut tx, order_id, cart_id, &[(product_id, 2)], 30)
+        .await
+        .unwrap()
+        .expect("stock should be reservable");
+    tx.commit().await.unwrap();
+
+    server
+        .delete("/api/auth/account")
+        .add_header("Authorization", bearer.clone())
+        .json(&json!({"password": "secret123"}))
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+
+    // The old token no longer gets through, and doesn't bring a cart back for the account
+    let res = server
+        .get("/api/auth/me")
+        .add_header("Authorization", bearer.clone())
+        .await;
+    res.assert_status_not_found();
+    assert_eq!(res.json::<serde_json::Value>()["code"], "account_not_found");
+    server
+        .get("/api/cart/quote")
+        .add_header("Authorization", bearer)
+        .await
+        .assert_status_not_found();
+    server
+        .post("/api/auth/login")
+        .json(&json!({"email": email, "password": "secret123"}))
+        .await
+        .assert_status_unauthorized();
+
+    // The row stays for the orders that reference it, without the personal data
+    let stored: (String, bool) = sqlx::query_as("SELECT email, deleted_at IS NOT NULL FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap();
+    assert_ne!(stored.0, email);
+    assert!(stored.1);
+    let carts: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM carts WHERE id = $1 OR user_id = $2")
+        .bind(cart_id)
+        .bind(user_id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap();
+    assert_eq!(carts, 0);
+
+    // Its unpaid order is cancelled and the stock held for it is available again
+    let status: String = sqlx::query_scalar("SELECT status FROM orders WHERE id = $1")
+        .bind(order_id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap();
+    assert_eq!(status, "cancelled");
+    assert_eq!(stock_repo.get_available_stock(product_id).await.unwrap(), Some(5));
+
+    // The email is free for a new signup
+    server
+        .post("/api/auth/signup")
+        .json(&json!({"email": email, "password": "secret123"}))
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn signup_rejects_duplicate_email() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+    let email = format!("dup-{}@example.com", Uuid::new_v4());
+
+    server
+        .post("/api/auth/signup")
+        .json(&json!({"email": email, "password": "secret123"}))
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+
+    let res = server
+        .post("/api/auth/signup")
+        .json(&json!({"email": email, "password": "secret123"}))
+        .await;
+    res.assert_status_bad_request();
+    assert!(res.json::<serde_json::Value>()["details"]
+        .as_str()
+        .unwrap()
+        .contains("Email already registered"));
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn signup_email_collision_is_case_insensitive() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+    let local = format!("case-{}", Uuid::new_v4().simple());
+
+    let res = server
+        .post("/api/auth/signup")
+        .json(&json!({"email": format!("{}@Example.COM", local.to_uppercase()), "password": "secret123"}))
+        .await;
+    res.assert_status(axum::http::StatusCode::CREATED);
+    assert_eq!(res.json::<serde_json::Value>()["email"], format!("{}@example.com", local));
+
+    server
+        .post("/api/auth/signup")
+        .json(&json!({"email": format!("{}@example.com", local), "password": "secret123"}))
+        .await
+        .assert_status_bad_request();
+
+    server
+        .post("/api/auth/login")
+        .json(&json!({"email": format!("{}@EXAMPLE.com", local), "password": "secret123"}))
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn signup_rejects_weak_passwords() {
+    let server = common::test_server_lazy().await;
+
+    for weak in ["short1", "onlyletters", "1234567890"] {
+        let res = server
+            .post("/api/auth/signup")
+            .json(&json!({"email": "weak@example.com", "password": weak}))
+            .await;
+        res.assert_status_bad_request();
+        let details = res.json::<serde_json::Value>()["details"].as_str().unwrap().to_string();
+        assert!(details.contains("password:"), "expected password field error, got {details}");
+    }
+}
+
+#[tokio::test]
+async fn validation_errors_are_keyed_by_field() {
+    let server = common::test_server_lazy().await;
+
+    let res = server
+        .post("/api/auth/signup")
+        .json(&json!({"email": "not-an-email", "password": "short"}))
+        .await;
+    res.assert_status_bad_request();
+    let body = res.json::<serde_json::Value>();
+    assert_eq!(body["code"], "validation_error");
+    assert_eq!(body["fields"]["email"], json!(["Invalid email format"]));
+    let password = body["fields"]["password"].as_array().unwrap();
+    assert!(password.contains(&json!("Password must be between 8 and 100 characters")));
+    // The flat summary is still there for logs and simple clients
+    let details = body["details"].as_str().unwrap();
+    assert!(details.contains("email: Invalid email format"));
+    assert!(details.contains("password:"));
+}
@@ -27,3 +27,388 @@ async fn auth_protected_endpoints_require_token() {
         .assert_status_unauthorized();
 }
 
+
+#[tokio::test]
+async fn me_requires_token() {
+    let server = common::test_server_lazy().await;
+
+    server.get("/api/auth/me").await.assert_status_unauthorized();
+    server
+        .get("/api/auth/me")
+        .add_header("Authorization", "Bearer not-a-jwt")
+        .await
+        .assert_status_unauthorized();
+}
+
+fn expired_token() -> String {
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    let claims = hemp_backend::dtos::Claims {
+        sub: uuid::Uuid::new_v4(),
+        email: "expired@example.com".to_string(),
+        role: "client".to_string(),
+        exp: 946684800,
+        jti: uuid::Uuid::new_v4(),
+        iss: None,
+        aud: None,
+    };
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(b"test_secret")).unwrap()
+}
+
+#[tokio::test]
+async fn auth_failures_carry_a_bearer_challenge_and_code() {
+    let server = common::test_server_lazy().await;
+
+    let res = server.get("/api/auth/me").await;
+    res.assert_status_unauthorized();
+    assert_eq!(res.header("WWW-Authenticate"), "Bearer");
+    assert_eq!(res.json::<serde_json::Value>()["code"], "missing_token");
+
+    let res = server
+        .get("/api/auth/me")
+        .add_header("Authorization", "Bearer not-a-jwt")
+        .await;
+    res.assert_status_unauthorized();
+    assert_eq!(res.header("WWW-Authenticate"), r#"Bearer error="invalid_token""#);
+    assert_eq!(res.json::<serde_json::Value>()["code"], "invalid_token");
+
+    let res = server
+        .get("/api/auth/me")
+        .add_header("Authorization", format!("Bearer {}", expired_token()))
+        .await;
+    res.assert_status_unauthorized();
+    assert_eq!(
+        res.header("WWW-Authenticate"),
+        r#"Bearer error="invalid_token", error_description="The access token expired""#
+    );
+    let body = res.json::<serde_json::Value>();
+    assert_eq!(body["code"], "token_expired");
+    assert_eq!(body["details"], "Token has expired");
+
+    // Admin-only routes report the same reason rather than a bare 401
+    let res = server
+        .get("/api/inventory/alerts")
+        .add_header("Authorization", format!("Bearer {}", expired_token()))
+        .await;
+    res.assert_status_unauthorized();
+    assert_eq!(res.json::<serde_json::Value>()["code"], "token_expired");
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn me_returns_profile_for_valid_token() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let user_id = common::seed_user(&state.db, "client").await;
+
+    let res = server
+        .get("/api/auth/me")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_for_user(user_id, "client")))
+        .await;
+    res.assert_status_ok();
+    let body = res.json::<serde_json::Value>();
+    assert_eq!(body["id"], user_id.to_string());
+    assert_eq!(body["email"], format!("{}@example.com", user_id));
+    assert_eq!(body["role"], "client");
+    assert!(body.get("password_hash").is_none());
+
+    // Token for a user that no longer exists
+    server
+        .get("/api/auth/me")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_for_user(uuid::Uuid::new_v4(), "client")))
+        .await
+        .assert_status_not_found();
+}
+
+fn token_with_issuer(issuer: &str) -> String {
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    let claims = hemp_backend::dtos::Claims {
+        sub: common::fixture_user_id("client"),
+        email: "issuer@example.com".to_string(),
+        role: "client".to_string(),
+        exp: 4102444800,
+        jti: uuid::Uuid::new_v4(),
+        iss: Some(issuer.to_string()),
+        aud: Some("hemp-frontend".to_string()),
+    };
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(b"test_secret")).unwrap()
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn token_from_other_issuer_is_rejected() {
+    let mut state = common::test_state_db().await.expect("test database unavailable");
+    state.jwt_config = std::sync::Arc::new(hemp_backend::state::JwtConfig {
+        issuer: Some("hemp-backend".to_string()),
+        audience: Some("hemp-frontend".to_string()),
+        ..Default::default()
+    });
+    let server = TestServer::new(common::app_with_state(state).await).unwrap();
+
+    server
+        .get("/api/order/my")
+        .add_header("Authorization", format!("Bearer {}", token_with_issuer("someone-else")))
+        .await
+        .assert_status_unauthorized();
+
+    // Same token shape from the configured issuer gets past authentication
+    let res = server
+        .get("/api/order/my")
+        .add_header("Authorization", format!("Bearer {}", token_with_issuer("hemp-backend")))
+        .await;
+    assert_ne!(res.status_code().as_u16(), 401);
+
+    // Tokens without an issuer claim are rejected once one is required
+    server
+        .get("/api/order/my")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_user()))
+        .await
+        .assert_status_unauthorized();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn user_management_requires_admin() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+    let user = format!("Bearer {}", common::jwt_user());
+
+    server.get("/api/auth/users").await.assert_status_unauthorized();
+    server
+        .get("/api/auth/users")
+        .add_header("Authorization", user.clone())
+        .await
+        .assert_status_forbidden();
+    server
+        .put(&format!("/api/auth/users/{}/role", uuid::Uuid::new_v4()))
+        .add_header("Authorization", user)
+        .json(&json!({"role": "admin"}))
+        .await
+        .assert_status_forbidden();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn admin_routes_reject_users_before_reading_the_body() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+    let user = format!("Bearer {}", common::jwt_user());
+
+    // A malformed body would be a 400/422 if the handler's extractors ran first
+    server
+        .put(&format!("/api/inventory/products/{}/stock", uuid::Uuid::new_v4()))
+        .add_header("Authorization", user.clone())
+        .add_header("Content-Type", "application/json")
+        .bytes("not json".into())
+        .await
+        .assert_status_forbidden();
+    server
+        .post("/api/product")
+        .add_header("Authorization", user.clone())
+        .json(&json!({"unexpected": true}))
+        .await
+        .assert_status_forbidden();
+    server
+        .put(&format!("/api/order/{}/status", uuid::Uuid::new_v4()))
+        .add_header("Authorization", user)
+        .json(&json!({"status": 42}))
+        .await
+        .assert_status_forbidden();
+
+    // Admins get past extraction and hit the body rejection instead
+    let res = server
+        .put(&format!("/api/inventory/products/{}/stock", uuid::Uuid::new_v4()))
+        .add_header("Authorization", format!("Bearer {}", common::jwt_admin()))
+        .add_header("Content-Type", "application/json")
+        .bytes("not json".into())
+        .await;
+    assert_eq!(res.status_code().as_u16(), 400);
+}
+
+#[test]
+fn claims_has_role_matches_exactly() {
+    let claims = hemp_backend::dtos::Claims {
+        sub: uuid::Uuid::new_v4(),
+        email: "role@example.com".to_string(),
+        role: "admin".to_string(),
+        exp: 4102444800,
+        jti: uuid::Uuid::new_v4(),
+        iss: None,
+        aud: None,
+    };
+    assert!(claims.has_role("admin"));
+    assert!(!claims.has_role("user"));
+    assert!(!claims.has_role("Admin"));
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn admin_can_list_users_and_change_role() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let admin_id = common::seed_user(&state.db, "admin").await;
+    let client_id = common::seed_user(&state.db, "client").await;
+    let admin = format!("Bearer {}", common::jwt_for_user(admin_id, "admin"));
+
+    let res = server
+        .get("/api/auth/users")
+        .add_query_param("limit", 100)
+        .add_header("Authorization", admin.clone())
+        .await;
+    res.assert_status_ok();
+    let total: i64 = res.header("x-total-count").to_str().unwrap().parse().unwrap();
+    assert!(total >= 2);
+    let users = res.json::<Vec<serde_json::Value>>();
+    assert!(users.iter().all(|u| u.get("password_hash").is_none()));
+
+    let res = server
+        .put(&format!("/api/auth/users/{}/role", client_id))
+        .add_header("Authorization", admin.clone())
+        .json(&json!({"role": "admin"}))
+        .await;
+    res.assert_status_ok();
+    assert_eq!(res.json::<serde_json::Value>()["role"], "admin");
+
+    server
+        .put(&format!("/api/auth/users/{}/role", client_id))
+        .add_header("Authorization", admin.clone())
+        .json(&json!({"role": "superuser"}))
+        .await
+        .assert_status_bad_request();
+
+    // Admins can't demote themselves
+    server
+        .put(&format!("/api/auth/users/{}/role", admin_id))
+        .add_header("Authorization", admin)
+        .json(&json!({"role": "client"}))
+        .await
+        .assert_status_bad_request();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn deleted_accounts_are_left_out_of_user_management() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let admin = format!("Bearer {}", common::jwt_admin());
+    let deleted_id = common::seed_user(&state.db, "client").await;
+    sqlx::query("UPDATE users SET deleted_at = now() WHERE id = $1")
+        .bind(deleted_id)
+        .execute(&state.db)
+        .await
+        .unwrap();
+
+    let res = server
+        .get("/api/auth/users")
+        .add_query_param("limit", 100)
+        .add_header("Authorization", admin.clone())
+        .await;
+    res.assert_status_ok();
+    let users = res.json::<Vec<serde_json::Value>>();
+    assert!(users.iter().all(|u| u["id"] != deleted_id.to_string()));
+
+    server
+        .put(&format!("/api/auth/users/{}/role", deleted_id))
+        .add_header("Authorization", admin.clone())
+        .json(&json!({"role": "admin"}))
+        .await
+        .assert_status_not_found();
+    server
+        .put(&format!("/api/auth/users/{}/activate", deleted_id))
+        .add_header("Authorization", admin)
+        .await
+        .assert_status_not_found();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn role_changes_apply_to_tokens_already_issued() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let admin = format!("Bearer {}", common::jwt_for_user(common::seed_user(&state.db, "admin").await, "admin"));
+    let other_id = common::seed_user(&state.db, "admin").await;
+    let other = format!("Bearer {}", common::jwt_for_user(other_id, "admin"));
+
+    server
+        .get("/api/auth/users")
+        .add_header("Authorization", other.clone())
+        .await
+        .assert_status_ok();
+
+    server
+        .put(&format!("/api/auth/users/{}/role", other_id))
+        .add_header("Authorization", admin)
+        .json(&json!({"role": "client"}))
+        .await
+        .assert_status_ok();
+
+    // The token still says admin, but the account no longer is
+    server
+        .get("/api/auth/users")
+        .add_header("Authorization", other)
+        .await
+        .assert_status_forbidden();
+
+    // A client token for an admin account gets admin access
+    let promoted = format!("Bearer {}", common::jwt_for_user(common::seed_user(&state.db, "admin").await, "client"));
+    server
+        .get("/api/auth/users")
+        .add_header("Authorization", promoted)
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn deactivated_user_is_locked_out() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let admin_id = common::seed_user(&state.db, "admin").await;
+    let admin = format!("Bearer {}", common::jwt_for_user(admin_id, "admin"));
+
+    let email = format!("{}@example.com", uuid::Uuid::new_v4());
+    let res = server
+        .post("/api/auth/signup")
+        .json(&json!({"email": email, "password": "secret123"}))
+        .await;
+    res.assert_status(axum::http::StatusCode::CREATED);
+    let user_id = res.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+
+    let res = server
+        .post("/api/auth/login")
+        .json(&json!({"email": email, "password": "secret123"}))
+        .await;
+    res.assert_status_ok();
+    let user = format!("Bearer {}", res.json::<serde_json::Value>()["token"].as_str().unwrap());
+
+    let res = server
+        .put(&format!("/api/auth/users/{}/deactivate", user_id))
+        .add_header("Authorization", admin.clone())
+        .await;
+    res.assert_status_ok();
+    assert_eq!(res.json::<serde_json::Value>()["is_active"], false);
+
+    // The token issued before deactivation no longer works, and neither does logging in again
+    server
+        .get("/api/auth/me")
+        .add_header("Authorization", user.clone())
+        .await
+        .assert_status_forbidden();
+    server
+        .post("/api/auth/login")
+        .json(&json!({"email": email, "password": "secret123"}))
+        .await
+        .assert_status_forbidden();
+
+    server
+        .put(&format!("/api/auth/users/{}/activate", user_id))
+        .add_header("Authorization", admin.clone())
+        .await
+        .assert_status_ok();
+    server
+        .get("/api/auth/me")
+        .add_header("Authorization", user)
+        .await
+        .assert_status_ok();
+
+    // Admins can't deactivate themselves
+    server
+        .put(&format!("/api/auth/users/{}/deactivate", admin_id))
+        .add_header("Authorization", admin)
+        .await
+        .assert_status_bad_request();
+}
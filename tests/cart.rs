@@ -0,0 +1,132 @@
+mod common;
+
+use hemp_backend::dtos::AddToCartDto;
+use hemp_backend::repository::CartRepository;
+use hemp_backend::services::cart_service::CartService;
+use hemp_backend::state::CartLimits;
+use serde_json::json;
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn non_positive_quantities_are_rejected() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+
+    for quantity in [0, -5] {
+        let res = server
+            .post("/api/cart/add")
+            .add_header("Authorization", format!("Bearer {}", common::jwt_user()))
+            .json(&json!({"product_id": uuid::Uuid::new_v4(), "quantity": quantity}))
+            .await;
+        res.assert_status_bad_request();
+        assert!(res.json::<serde_json::Value>()["fields"]["quantity"].is_array());
+    }
+
+    // The service checks too, for callers that skip the DTO validation
+    let svc = CartService::new(CartRepository::new(common::test_state_lazy().await.db));
+    let err = svc
+        .add_to_cart(uuid::Uuid::new_v4(), AddToCartDto { product_id: uuid::Uuid::new_v4(), quantity: -1 })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), "validation_error");
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn fractional_and_oversized_quantities_name_the_field() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+
+    for (quantity, message) in [
+        (json!(2.5), "Quantity must be a whole number"),
+        (json!(10_000_000_000_i64), "Quantity is out of range"),
+    ] {
+        let res = server
+            .post("/api/cart/add")
+            .add_header("Authorization", format!("Bearer {}", common::jwt_user()))
+            .json(&json!({"product_id": uuid::Uuid::new_v4(), "quantity": quantity}))
+            .await;
+        res.assert_status_bad_request();
+        let body = res.json::<serde_json::Value>();
+        assert_eq!(body["code"], "validation_error");
+        assert_eq!(body["fields"]["quantity"], json!([message]), "for {}", quantity);
+    }
+
+    // Other shape errors go through the same error body
+    let res = server
+        .post("/api/cart/add")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_user()))
+        .json(&json!({"product_id": "not-a-uuid", "quantity": 1}))
+        .await;
+    res.assert_status_bad_request();
+    assert!(res.json::<serde_json::Value>()["fields"]["product_id"].is_array());
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn quantity_per_product_is_capped() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let user_id = common::seed_user(&state.db, "client").await;
+    let product_id = common::seed_product(&state.db, "Capped Oil", "5.00", 500).await;
+    let user = format!("Bearer {}", common::jwt_for_user(user_id, "client"));
+
+    server
+        .post("/api/cart/add")
+        .add_header("Authorization", user.clone())
+        .json(&json!({"product_id": product_id, "quantity": 60}))
+        .await
+        .assert_status_ok();
+
+    // Adding again counts against the same line, which would reach 120
+    server
+        .post("/api/cart/add")
+        .add_header("Authorization", user.clone())
+        .json(&json!({"product_id": product_id, "quantity": 60}))
+        .await
+        .assert_status_bad_request();
+
+    server
+        .post("/api/cart/add")
+        .add_header("Authorization", user)
+        .json(&json!({"product_id": product_id, "quantity": 40}))
+        .await
+        .assert_status_ok();
+
+    let lines: Vec<i32> = sqlx::query_scalar(
+        "SELECT ci.quantity FROM cart_items ci JOIN carts c ON c.id = ci.cart_id WHERE c.user_id = $1",
+    )
+    .bind(user_id)
+    .fetch_all(&state.db)
+    .await
+    .unwrap();
+    assert_eq!(lines, vec![100]);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn distinct_products_per_cart_are_capped() {
+    let state = common::test_state_db().await.expect("test database unavailable");
+    let user_id = common::seed_user(&state.db, "client").await;
+    let svc = CartService::new(CartRepository::new(state.db.clone()))
+        .with_limits(CartLimits { max_item_quantity: 10, max_distinct_items: 2 });
+
+    let mut products = Vec::new();
+    for name in ["Distinct A", "Distinct B", "Distinct C"] {
+        products.push(common::seed_product(&state.db, name, "5.00", 10).await);
+    }
+
+    for product_id in &products[..2] {
+        svc.add_to_cart(user_id, AddToCartDto { product_id: *product_id, quantity: 1 }).await.unwrap();
+    }
+    let err = svc
+        .add_to_cart(user_id, AddToCartDto { product_id: products[2], quantity: 1 })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), "validation_error");
+
+    // More of a product already in the cart is still fine
+    svc.add_to_cart(user_id, AddToCartDto { product_id: products[0], quantity: 2 }).await.unwrap();
+}
+
+#[test]
+fn cart_limits_default_without_env() {
+    assert_eq!(CartLimits::default(), CartLimits { max_item_quantity: 100, max_distinct_items: 50 });
+}
@@ -4,8 +4,9 @@ use axum_test::TestServer;
 use serde_json::json;
 
 #[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
 async fn category_crud_secured() {
-    let server = common::test_server_lazy().await;
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
 
     // Create category (admin required)
     let res = server
@@ -13,7 +14,6 @@ async fn category_crud_secured() {
         .add_header("Authorization", format!("Bearer {}", common::jwt_admin()))
         .json(&json!({"name":"Cat A","description":"Desc"}))
         .await;
-    // Without DB, allow 500/404; but not 401/403
     let status = res.status_code().as_u16();
     assert!(status != 401 && status != 403);
 
@@ -22,3 +22,730 @@ async fn category_crud_secured() {
     assert!(res.status_code().as_u16() != 401 && res.status_code().as_u16() != 403);
 }
 
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn category_writes_require_admin() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+    let user = format!("Bearer {}", common::jwt_user());
+    let id = uuid::Uuid::new_v4();
+    let body = json!({"name": "Cat A", "description": null});
+
+    server.post("/api/category").json(&body).await.assert_status_unauthorized();
+    server
+        .post("/api/category")
+        .add_header("Authorization", user.clone())
+        .json(&body)
+        .await
+        .assert_status_forbidden();
+
+    server.put(&format!("/api/category/{}", id)).json(&body).await.assert_status_unauthorized();
+    server
+        .put(&format!("/api/category/{}", id))
+        .add_header("Authorization", user.clone())
+        .json(&body)
+        .await
+        .assert_status_forbidden();
+
+    server.delete(&format!("/api/category/{}", id)).await.assert_status_unauthorized();
+    server
+        .delete(&format!("/api/category/{}", id))
+        .add_header("Authorization", user.clone())
+        .await
+        .assert_status_forbidden();
+
+    let assign = format!("/api/category/{}/assign/{}", id, uuid::Uuid::new_v4());
+    server.post(&assign).await.assert_status_unauthorized();
+    server
+        .post(&assign)
+        .add_header("Authorization", user.clone())
+        .await
+        .assert_status_forbidden();
+    server.delete(&assign).await.assert_status_unauthorized();
+    server
+        .delete(&assign)
+        .add_header("Authorization", user)
+        .await
+        .assert_status_forbidden();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn admin_can_manage_categories() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+    let admin = format!("Bearer {}", common::jwt_admin());
+
+    let res = server
+        .post("/api/category")
+        .add_header("Authorization", admin.clone())
+        .json(&json!({"name": format!("Balms {}", uuid::Uuid::new_v4()), "description": null}))
+        .await;
+    res.assert_status(axum::http::StatusCode::CREATED);
+    let category_id = res.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+
+    let res = server
+        .put(&format!("/api/category/{}", category_id))
+        .add_header("Authorization", admin.clone())
+        .json(&json!({"description": "Soothing"}))
+        .await;
+    res.assert_status_ok();
+    assert_eq!(res.json::<serde_json::Value>()["description"], "Soothing");
+
+    server
+        .delete(&format!("/api/category/{}", category_id))
+        .add_header("Authorization", admin)
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+}
+
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn products_filtered_by_category() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let admin = format!("Bearer {}", common::jwt_admin());
+
+    let res = server
+        .post("/api/category")
+        .add_header("Authorization", admin.clone())
+        .json(&json!({"name": format!("Oils {}", uuid::Uuid::new_v4()), "description": null}))
+        .await;
+    res.assert_status(axum::http::StatusCode::CREATED);
+    let category_id = res.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+
+    let first = common::seed_product(pool, "Category Oil 1", "10.00", 5).await;
+    let second = common::seed_product(pool, "Category Oil 2", "12.00", 5).await;
+    let unrelated = common::seed_product(pool, "Unrelated Balm", "8.00", 5).await;
+
+    for product_id in [first, second] {
+        server
+            .post(&format!("/api/category/{}/assign/{}", category_id, product_id))
+            .add_header("Authorization", admin.clone())
+            .await
+            .assert_status(axum::http::StatusCode::NO_CONTENT);
+    }
+
+    let res = server
+        .get("/api/product")
+        .add_query_param("category_id", &category_id)
+        .await;
+    res.assert_status_ok();
+    assert_eq!(res.header("x-total-count"), "2");
+
+    let ids: Vec<String> = res
+        .json::<Vec<serde_json::Value>>()
+        .iter()
+        .map(|p| p["id"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(ids.len(), 2);
+    assert!(ids.contains(&first.to_string()));
+    assert!(ids.contains(&second.to_string()));
+    assert!(!ids.contains(&unrelated.to_string()));
+
+    let res = server.get("/api/category").await;
+    res.assert_status_ok();
+    let categories = res.json::<Vec<serde_json::Value>>();
+    let listed = categories.iter().find(|c| c["id"] == category_id.as_str()).unwrap();
+    assert_eq!(listed["product_count"], 2);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn product_detail_lists_its_categories() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let admin = format!("Bearer {}", common::jwt_admin());
+    let product_id = common::seed_product(&state.db, "Two Category Oil", "10.00", 5).await;
+
+    let mut category_ids = Vec::new();
+    for prefix in ["Tinctures", "Gifts"] {
+        let res = server
+            .post("/api/category")
+            .add_header("Authorization", admin.clone())
+            .json(&json!({"name": format!("{} {}", prefix, uuid::Uuid::new_v4()), "description": null}))
+            .await;
+        res.assert_status(axum::http::StatusCode::CREATED);
+        let category_id = res.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+        server
+            .post(&format!("/api/category/{}/assign/{}", category_id, product_id))
+            .add_header("Authorization", admin.clone())
+            .await
+            .assert_status(axum::http::StatusCode::NO_CONTENT);
+        category_ids.push(category_id);
+    }
+
+    let res = server.get(&format!("/api/product/{}", product_id)).await;
+    res.assert_status_ok();
+    let body = res.json::<serde_json::Value>();
+    assert_eq!(body["id"], product_id.to_string());
+    let mut listed: Vec<String> = body["categories"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["id"].as_str().unwrap().to_string())
+        .collect();
+    listed.sort();
+    category_ids.sort();
+    assert_eq!(listed, category_ids);
+
+    // Listings stay without categories
+    let res = server.get("/api/product").add_query_param("category_id", &category_ids[0]).await;
+    res.assert_status_ok();
+    assert!(res.json::<Vec<serde_json::Value>>()[0].get("categories").is_none());
+}
+
+fn category(name: &str, parent_id: Option<uuid::Uuid>) -> hemp_backend::model::category::Category {
+    hemp_backend::model::category::Category {
+        id: uuid::Uuid::new_v4(),
+        name: name.to_string(),
+        description: None,
+        parent_id,
+        created_at: chrono::Utc::now(),
+        updated_at: None,
+    }
+}
+
+#[test]
+fn build_tree_nests_two_levels() {
+    use hemp_backend::services::category_service::build_tree;
+
+    let wellness = category("Wellness", None);
+    let oil = category("CBD Oil", Some(wellness.id));
+    let drops = category("Drops", Some(oil.id));
+    let apparel = category("Apparel", None);
+    let (wellness_id, oil_id, drops_id) = (wellness.id, oil.id, drops.id);
+
+    let tree = build_tree(vec![drops, wellness, oil, apparel]);
+
+    assert_eq!(tree.len(), 2);
+    let root = tree.iter().find(|n| n.category.id == wellness_id).unwrap();
+    assert_eq!(root.children.len(), 1);
+    assert_eq!(root.children[0].category.id, oil_id);
+    assert_eq!(root.children[0].children[0].category.id, drops_id);
+    assert!(tree.iter().any(|n| n.category.name == "Apparel" && n.children.is_empty()));
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn subcategories_and_cycle_guard() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+    let admin = format!("Bearer {}", common::jwt_admin());
+
+    let res = server
+        .post("/api/category")
+        .add_header("Authorization", admin.clone())
+        .json(&json!({"name": format!("Wellness {}", uuid::Uuid::new_v4())}))
+        .await;
+    res.assert_status(axum::http::StatusCode::CREATED);
+    let parent_id = res.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+
+    let res = server
+        .post("/api/category")
+        .add_header("Authorization", admin.clone())
+        .json(&json!({"name": format!("CBD Oil {}", uuid::Uuid::new_v4()), "parent_id": parent_id}))
+        .await;
+    res.assert_status(axum::http::StatusCode::CREATED);
+    let child = res.json::<serde_json::Value>();
+    assert_eq!(child["parent_id"], parent_id);
+    let child_id = child["id"].as_str().unwrap().to_string();
+
+    let res = server.get(&format!("/api/category/{}/children", parent_id)).await;
+    res.assert_status_ok();
+    let children = res.json::<Vec<serde_json::Value>>();
+    assert_eq!(children.len(), 1);
+    assert_eq!(children[0]["id"], child_id);
+
+    // A category can't be its own parent, nor sit under its own subcategory
+    server
+        .put(&format!("/api/category/{}", parent_id))
+        .add_header("Authorization", admin.clone())
+        .json(&json!({"parent_id": parent_id}))
+        .await
+        .assert_status_bad_request();
+    server
+        .put(&format!("/api/category/{}", parent_id))
+        .add_header("Authorization", admin.clone())
+        .json(&json!({"parent_id": child_id}))
+        .await
+        .assert_status_bad_request();
+
+    // Leaving a field out keeps it, while an explicit null clears it
+    let res = server
+        .put(&format!("/api/category/{}", child_id))
+        .add_header("Authorization", admin.clone())
+        .json(&json!({"description": "Drops and tinctures"}))
+        .await;
+    res.assert_status_ok();
+    assert_eq!(res.json::<serde_json::Value>()["parent_id"], parent_id);
+
+    let res = server
+        .put(&format!("/api/category/{}", child_id))
+        .add_header("Authorization", admin)
+        .json(&json!({"parent_id": null, "description": null}))
+        .await;
+    res.assert_status_ok();
+    let moved = res.json::<serde_json::Value>();
+    assert!(moved["parent_id"].is_null());
+    assert!(moved["description"].is_null());
+    let res = server.get(&format!("/api/category/{}/children", parent_id)).await;
+    assert!(res.json::<Vec<serde_json::Value>>().is_empty());
+
+    server
+        .get(&format!("/api/category/{}/children", uuid::Uuid::new_v4()))
+        .await
+        .assert_status_not_found();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn concurrent_moves_cannot_nest_categories_under_each_other() {
+    use hemp_backend::dtos::UpdateCategoryDto;
+    use hemp_backend::repository::CategoryRepository;
+    use hemp_backend::services::category_service::CategoryService;
+
+    let Some(state) = common::test_state_db().await else { return; };
+    let repo = CategoryRepository::new(state.db.clone());
+    let svc = CategoryService::new(repo.clone());
+
+    for _ in 0..10 {
+        let suffix = uuid::Uuid::new_v4();
+        let a = repo.create(&format!("Balms {}", suffix), None, None).await.unwrap();
+        let b = repo.create(&format!("Salves {}", suffix), None, None).await.unwrap();
+
+        let move_under = |id, parent_id| {
+            svc.update(id, UpdateCategoryDto { name: None, description: None, parent_id: Some(Some(parent_id)) })
+        };
+        let (a_moved, b_moved) = tokio::join!(move_under(a.id, b.id), move_under(b.id, a.id));
+
+        // Whichever move runs second sees the first and is refused
+        assert!(a_moved.is_ok() != b_moved.is_ok(), "{:?} / {:?}", a_moved, b_moved);
+        assert!(repo.ancestor_ids(a.id).await.unwrap().len() <= 2);
+        assert!(repo.ancestor_ids(b.id).await.unwrap().len() <= 2);
+        let a = repo.get(a.id).await.unwrap().unwrap();
+        let b = repo.get(b.id).await.unwrap().unwrap();
+        assert!(a.parent_id.is_none() || b.parent_id.is_none());
+    }
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn breadcrumb_runs_from_the_root_down() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let repo = hemp_backend::repository::CategoryRepository::new(state.db.clone());
+    let suffix = uuid::Uuid::new_v4();
+
+    let root = repo.create(&format!("Wellness {}", suffix), None, None).await.unwrap();
+    let middle = repo.create(&format!("Oils {}", suffix), None, Some(root.id)).await.unwrap();
+    let leaf = repo.create(&format!("Tinctures {}", suffix), None, Some(middle.id)).await.unwrap();
+
+    let res = server.get(&format!("/api/category/{}/breadcrumb", leaf.id)).await;
+    res.assert_status_ok();
+    let ids: Vec<String> = res
+        .json::<Vec<serde_json::Value>>()
+        .iter()
+        .map(|c| c["id"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(ids, vec![root.id.to_string(), middle.id.to_string(), leaf.id.to_string()]);
+
+    let res = server.get(&format!("/api/category/{}/breadcrumb", root.id)).await;
+    res.assert_status_ok();
+    assert_eq!(res.json::<Vec<serde_json::Value>>().len(), 1);
+
+    server
+        .get(&format!("/api/category/{}/breadcrumb", uuid::Uuid::new_v4()))
+        .await
+        .assert_status_not_found();
+
+    // A loop left by bad data ends at the depth cap instead of running forever
+    sqlx::query("UPDATE categories SET parent_id = $1 WHERE id = $2")
+        .bind(leaf.id)
+        .bind(root.id)
+        .execute(&state.db)
+        .await
+        .unwrap();
+    let res = server.get(&format!("/api/category/{}/breadcrumb", leaf.id)).await;
+    res.assert_status_ok();
+    let path = res.json::<Vec<serde_json::Value>>();
+    assert!(path.len() > 3 && path.len() <= 32, "{}", path.len());
+    assert_eq!(path.last().unwrap()["id"], leaf.id.to_string());
+    sqlx::query("UPDATE categories SET parent_id = NULL WHERE id = $1")
+        .bind(root.id)
+        .execute(&state.db)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn category_products_lists_assigned_products() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let admin = format!("Bearer {}", common::jwt_admin());
+
+    let res = server
+        .post("/api/category")
+        .add_header("Authorization", admin.clone())
+        .json(&json!({"name": format!("Tinctures {}", uuid::Uuid::new_v4())}))
+        .await;
+    res.assert_status(axum::http::StatusCode::CREATED);
+    let category_id = res.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+
+    let second = common::seed_product(&state.db, "Tincture B", "20.00", 5).await;
+    let first = common::seed_product(&state.db, "Tincture A", "18.00", 5).await;
+    common::seed_product(&state.db, "Tincture Unassigned", "15.00", 5).await;
+    for product_id in [second, first] {
+        server
+            .post(&format!("/api/category/{}/assign/{}", category_id, product_id))
+            .add_header("Authorization", admin.clone())
+            .await
+            .assert_status(axum::http::StatusCode::NO_CONTENT);
+    }
+
+    // A live hold comes off the available stock, as on the product listing
+    let user_id = common::seed_user(&state.db, "client").await;
+    let cart_id = common::seed_cart(&state.db, user_id).await;
+    sqlx::query(
+        "INSERT INTO stock_reservations (product_id, cart_id, quantity, expires_at) VALUES ($1, $2, 2, now() + interval '10 minutes')",
+    )
+    .bind(first)
+    .bind(cart_id)
+    .execute(&state.db)
+    .await
+    .unwrap();
+
+    let res = server.get(&format!("/api/category/{}/products", category_id)).await;
+    res.assert_status_ok();
+    let products = res.json::<Vec<serde_json::Value>>();
+    let ids: Vec<String> = products
+        .iter()
+        .map(|p| p["id"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(ids, vec![first.to_string(), second.to_string()]);
+    assert_eq!(products[0]["stock"], 5);
+    assert_eq!(products[0]["available_stock"], 3);
+    assert_eq!(products[1]["available_stock"], 5);
+
+    server
+        .get(&format!("/api/category/{}/products", uuid::Uuid::new_v4()))
+        .await
+        .assert_status_not_found();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn unassigned_product_leaves_category() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let admin = format!("Bearer {}", common::jwt_admin());
+
+    let res = server
+        .post("/api/category")
+        .add_header("Authorization", admin.clone())
+        .json(&json!({"name": format!("Topicals {}", uuid::Uuid::new_v4())}))
+        .await;
+    res.assert_status(axum::http::StatusCode::CREATED);
+    let category_id = res.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+    let product_id = common::seed_product(&state.db, "Topical Cream", "14.00", 5).await;
+    let link = format!("/api/category/{}/assign/{}", category_id, product_id);
+
+    server
+        .post(&link)
+        .add_header("Authorization", admin.clone())
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+    server
+        .delete(&link)
+        .add_header("Authorization", admin.clone())
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+
+    let res = server.get(&format!("/api/category/{}/products", category_id)).await;
+    res.assert_status_ok();
+    assert!(res.json::<Vec<serde_json::Value>>().is_empty());
+
+    // The link is already gone
+    server
+        .delete(&link)
+        .add_header("Authorization", admin)
+        .await
+        .assert_status_not_found();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn unpublished_products_hidden_from_public_listings() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let admin = format!("Bearer {}", common::jwt_admin());
+
+    let res = server
+        .post("/api/category")
+        .add_header("Authorization", admin.clone())
+        .json(&json!({"name": format!("Seasonal {}", uuid::Uuid::new_v4()), "description": null}))
+        .await;
+    let category_id = res.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+    let shown = common::seed_product(pool, "Year-round Oil", "10.00", 5).await;
+    let hidden = common::seed_product(pool, "Winter Balm", "12.00", 5).await;
+    for product_id in [shown, hidden] {
+        server
+            .post(&format!("/api/category/{}/assign/{}", category_id, product_id))
+            .add_header("Authorization", admin.clone())
+            .await
+            .assert_status(axum::http::StatusCode::NO_CONTENT);
+    }
+
+    let res = server
+        .put(&format!("/api/product/{}/unpublish", hidden))
+        .add_header("Authorization", admin.clone())
+        .await;
+    res.assert_status_ok();
+    assert_eq!(res.json::<serde_json::Value>()["is_published"], false);
+
+    let listed_ids = |res: axum_test::TestResponse| -> Vec<String> {
+        res.json::<Vec<serde_json::Value>>()
+            .iter()
+            .map(|p| p["id"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    // Anonymous shoppers only see the published product
+    let res = server.get("/api/product").add_query_param("category_id", &category_id).await;
+    assert_eq!(res.header("x-total-count"), "1");
+    assert_eq!(listed_ids(res), vec![shown.to_string()]);
+    let res = server.get(&format!("/api/category/{}/products", category_id)).await;
+    assert_eq!(listed_ids(res), vec![shown.to_string()]);
+
+    // Admins see both, and the product still resolves by id
+    let res = server
+        .get("/api/product")
+        .add_query_param("category_id", &category_id)
+        .add_header("Authorization", admin.clone())
+        .await;
+    assert_eq!(res.header("x-total-count"), "2");
+    assert!(listed_ids(res).contains(&hidden.to_string()));
+    server.get(&format!("/api/product/{}", hidden)).await.assert_status_ok();
+
+    // Publishing brings it back
+    server
+        .put(&format!("/api/product/{}/publish", hidden))
+        .add_header("Authorization", admin.clone())
+        .await
+        .assert_status_ok();
+    let res = server.get("/api/product").add_query_param("category_id", &category_id).await;
+    assert_eq!(res.header("x-total-count"), "2");
+
+    server
+        .put(&format!("/api/product/{}/unpublish", hidden))
+        .add_header("Authorization", format!("Bearer {}", common::jwt_user()))
+        .await
+        .assert_status_forbidden();
+    server
+        .put(&format!("/api/product/{}/publish", uuid::Uuid::new_v4()))
+        .add_header("Authorization", admin)
+        .await
+        .assert_status_not_found();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn category_with_products_is_kept_unless_reassigned() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let admin = format!("Bearer {}", common::jwt_admin());
+
+    let mut ids = Vec::new();
+    for name in ["Retiring", "Successor"] {
+        let res = server
+            .post("/api/category")
+            .add_header("Authorization", admin.clone())
+            .json(&json!({"name": format!("{} {}", name, uuid::Uuid::new_v4())}))
+            .await;
+        res.assert_status(axum::http::StatusCode::CREATED);
+        ids.push(res.json::<serde_json::Value>()["id"].as_str().unwrap().to_string());
+    }
+    let (retiring, successor) = (&ids[0], &ids[1]);
+    for name in ["Moved Tincture", "Moved Salve"] {
+        let product_id = common::seed_product(&state.db, name, "9.00", 5).await;
+        server
+            .post(&format!("/api/category/{}/assign/{}", retiring, product_id))
+            .add_header("Authorization", admin.clone())
+            .await
+            .assert_status(axum::http::StatusCode::NO_CONTENT);
+    }
+
+    let res = server
+        .delete(&format!("/api/category/{}", retiring))
+        .add_header("Authorization", admin.clone())
+        .await;
+    res.assert_status(axum::http::StatusCode::CONFLICT);
+    assert!(res.json::<serde_json::Value>()["details"].as_str().unwrap().contains("2 assigned"));
+    server.get(&format!("/api/category/{}", retiring)).await.assert_status_ok();
+
+    server
+        .delete(&format!("/api/category/{}", retiring))
+        .add_query_param("reassign_to", retiring)
+        .add_header("Authorization", admin.clone())
+        .await
+        .assert_status_bad_request();
+
+    server
+        .delete(&format!("/api/category/{}", retiring))
+        .add_query_param("reassign_to", successor)
+        .add_header("Authorization", admin)
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+    server.get(&format!("/api/category/{}", retiring)).await.assert_status_not_found();
+
+    let res = server.get(&format!("/api/category/{}/products", successor)).await;
+    res.assert_status_ok();
+    let mut names: Vec<String> = res
+        .json::<Vec<serde_json::Value>>()
+        .iter()
+        .map(|p| p["name"].as_str().unwrap().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["Moved Salve", "Moved Tincture"]);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn bulk_assignment_counts_only_new_links() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let admin = format!("Bearer {}", common::jwt_admin());
+
+    let res = server
+        .post("/api/category")
+        .add_header("Authorization", admin.clone())
+        .json(&json!({"name": format!("Bundles {}", uuid::Uuid::new_v4())}))
+        .await;
+    res.assert_status(axum::http::StatusCode::CREATED);
+    let category_id = res.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+
+    let mut product_ids = Vec::new();
+    for i in 0..5 {
+        product_ids.push(common::seed_product(&state.db, &format!("Bundle Item {}", i), "3.00", 5).await);
+    }
+    server
+        .post(&format!("/api/category/{}/assign/{}", category_id, product_ids[0]))
+        .add_header("Authorization", admin.clone())
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+
+    // A deleted product is skipped rather than linked
+    let deleted_id = common::seed_product(&state.db, "Bundle Item Deleted", "3.00", 5).await;
+    sqlx::query("UPDATE products SET deleted_at = now() WHERE id = $1")
+        .bind(deleted_id)
+        .execute(&state.db)
+        .await
+        .unwrap();
+    let mut requested_ids = product_ids.clone();
+    requested_ids.push(deleted_id);
+
+    let bulk = format!("/api/category/{}/assign-bulk", category_id);
+    server
+        .post(&bulk)
+        .add_header("Authorization", format!("Bearer {}", common::jwt_user()))
+        .json(&json!({"product_ids": product_ids}))
+        .await
+        .assert_status_forbidden();
+
+    let res = server
+        .post(&bulk)
+        .add_header("Authorization", admin.clone())
+        .json(&json!({"product_ids": requested_ids}))
+        .await;
+    res.assert_status_ok();
+    assert_eq!(res.json::<serde_json::Value>()["linked"], 4);
+
+    let deleted_links: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM product_categories WHERE product_id = $1")
+        .bind(deleted_id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap();
+    assert_eq!(deleted_links, 0);
+
+    let res = server.get(&format!("/api/category/{}/products", category_id)).await;
+    assert_eq!(res.json::<Vec<serde_json::Value>>().len(), 5);
+
+    server
+        .post(&format!("/api/category/{}/assign-bulk", uuid::Uuid::new_v4()))
+        .add_header("Authorization", admin)
+        .json(&json!({"product_ids": product_ids}))
+        .await
+        .assert_status_not_found();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn assigning_checks_the_category_and_product_exist() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let admin = format!("Bearer {}", common::jwt_admin());
+
+    let res = server
+        .post("/api/category")
+        .add_header("Authorization", admin.clone())
+        .json(&json!({"name": format!("Linked {}", uuid::Uuid::new_v4())}))
+        .await;
+    res.assert_status(axum::http::StatusCode::CREATED);
+    let category_id = res.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+    let product_id = common::seed_product(&state.db, "Linked Salve", "4.00", 5).await;
+
+    let res = server
+        .post(&format!("/api/category/{}/assign/{}", uuid::Uuid::new_v4(), product_id))
+        .add_header("Authorization", admin.clone())
+        .await;
+    res.assert_status_not_found();
+    assert!(res.json::<serde_json::Value>()["details"].as_str().unwrap().contains("Category"));
+
+    let res = server
+        .post(&format!("/api/category/{}/assign/{}", category_id, uuid::Uuid::new_v4()))
+        .add_header("Authorization", admin.clone())
+        .await;
+    res.assert_status_not_found();
+    assert!(res.json::<serde_json::Value>()["details"].as_str().unwrap().contains("Product"));
+
+    server
+        .post(&format!("/api/category/{}/assign/{}", category_id, product_id))
+        .add_header("Authorization", admin)
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+    let res = server.get(&format!("/api/category/{}/products", category_id)).await;
+    assert_eq!(res.json::<Vec<serde_json::Value>>()[0]["id"], product_id.to_string());
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn category_names_are_unique_ignoring_case() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+    let admin = format!("Bearer {}", common::jwt_admin());
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    let create = |name: String| {
+        server
+            .post("/api/category")
+            .add_header("Authorization", admin.clone())
+            .json(&json!({"name": name}))
+    };
+
+    let res = create(format!("Oils {}", suffix)).await;
+    res.assert_status(axum::http::StatusCode::CREATED);
+    let oils_id = res.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+
+    let res = create(format!("OILS {}", suffix.to_uppercase())).await;
+    res.assert_status_bad_request();
+    assert!(res.json::<serde_json::Value>()["details"].as_str().unwrap().contains("already exists"));
+
+    let res = create(format!("Tinctures {}", suffix)).await;
+    res.assert_status(axum::http::StatusCode::CREATED);
+    let tinctures_id = res.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+
+    // Renaming onto another category's name collides too, but recasing a category's own name doesn't
+    server
+        .put(&format!("/api/category/{}", tinctures_id))
+        .add_header("Authorization", admin.clone())
+        .json(&json!({"name": format!("oils {}", suffix)}))
+        .await
+        .assert_status_bad_request();
+    let res = server
+        .put(&format!("/api/category/{}", oils_id))
+        .add_header("Authorization", admin.clone())
+        .json(&json!({"name": format!("OILS {}", suffix)}))
+        .await;
+    res.assert_status_ok();
+    assert_eq!(res.json::<serde_json::Value>()["name"], format!("OILS {}", suffix));
+}
@@ -3,9 +3,9 @@ use std::sync::Arc;
 
 use axum::Router;
 use axum_test::TestServer;
-use sqlx::{postgres::PgPoolOptions, PgPool};
+use sqlx::{postgres::{PgConnectOptions, PgPoolOptions}, PgPool};
 
-use hemp_backend::{routes, state::AppState};
+use hemp_backend::{routes, services::mailer::NoopMailer, state::{AppState, AuthConfig, BodyLimits, CartLimits, CloudinaryConfig, HttpClientConfig, JwtConfig, InventoryConfig, LowStockWebhookConfig, OrderConfig, OrderWebhookConfig, PromotionConfig, ReservationConfig, StripeConfig}};
 use jsonwebtoken::{encode, EncodingKey, Header};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -21,21 +21,58 @@ pub async fn test_state_lazy() -> AppState {
     AppState {
         db: pool,
         jwt_secret: Arc::new("test_secret".to_string()),
-        cloudinary_cloud_name: Arc::new("cloud_name".to_string()),
-        cloudinary_api_key: Arc::new("cloud_key".to_string()),
-        cloudinary_api_secret: Arc::new("cloud_secret".to_string()),
+        jwt_config: Arc::new(JwtConfig::default()),
+        auth_config: Arc::new(AuthConfig::default()),
+        reservation_config: Arc::new(ReservationConfig::default()),
+        inventory_config: Arc::new(InventoryConfig::default()),
+        low_stock_webhook_config: Arc::new(LowStockWebhookConfig::default()),
+        order_webhook_config: Arc::new(OrderWebhookConfig::default()),
+        order_config: Arc::new(OrderConfig::default()),
+        cart_limits: Arc::new(CartLimits::default()),
+        promotion_config: Arc::new(PromotionConfig::default()),
+        body_limits: Arc::new(BodyLimits::default()),
+        http: HttpClientConfig::default().build_client(),
+        mailer: Arc::new(NoopMailer),
+        stripe_config: Arc::new(StripeConfig::new("sk_test_dummy")),
+        cloudinary_config: Arc::new(CloudinaryConfig::new("cloud_name", "cloud_key", "cloud_secret")),
     }
 }
 
 pub async fn test_state_db() -> Option<AppState> {
-    let db_url = match env::var("TEST_DATABASE_URL") {
-        Ok(v) => v,
-        Err(_) => return None,
-    };
+    let options: PgConnectOptions = env::var("TEST_DATABASE_URL").ok()?.parse().ok()?;
+    state_for_db(options).await
+}
+
+/// Like [`test_state_db`], but on a freshly created database no other test writes to, for
+/// tests that check store-wide totals. Pass the state to [`drop_private_db`] when done.
+pub async fn test_state_private_db() -> Option<AppState> {
+    let options: PgConnectOptions = env::var("TEST_DATABASE_URL").ok()?.parse().ok()?;
+    let name = format!("femite_test_{}", Uuid::new_v4().simple());
+
+    let admin = PgPoolOptions::new().max_connections(1).connect_with(options.clone()).await.ok()?;
+    sqlx::query(&format!("CREATE DATABASE {}", name)).execute(&admin).await.ok()?;
+    admin.close().await;
+
+    state_for_db(options.database(&name)).await
+}
+
+/// Closes the state's connections and drops the database made by [`test_state_private_db`].
+pub async fn drop_private_db(state: AppState) {
+    let name = state.db.connect_options().get_database().expect("private database has a name").to_string();
+    state.db.close().await;
+
+    let options: PgConnectOptions = env::var("TEST_DATABASE_URL").unwrap().parse().unwrap();
+    let admin = PgPoolOptions::new().max_connections(1).connect_with(options).await.unwrap();
+    sqlx::query(&format!("DROP DATABASE IF EXISTS {} WITH (FORCE)", name))
+        .execute(&admin)
+        .await
+        .unwrap();
+}
 
+async fn state_for_db(options: PgConnectOptions) -> Option<AppState> {
     let pool = PgPoolOptions::new()
         .max_connections(5)
-        .connect(&db_url)
+        .connect_with(options)
         .await
         .ok()?;
 
@@ -47,12 +84,29 @@ pub async fn test_state_db() -> Option<AppState> {
         }
     }
 
+    // Tokens are only honoured for live accounts
+    if let Err(e) = seed_fixture_users(&pool).await {
+        eprintln!("Warning: failed to seed fixture users for tests: {}", e);
+        return None;
+    }
+
     Some(AppState {
         db: pool,
         jwt_secret: Arc::new("test_secret".to_string()),
-        cloudinary_cloud_name: Arc::new("cloud_name".to_string()),
-        cloudinary_api_key: Arc::new("cloud_key".to_string()),
-        cloudinary_api_secret: Arc::new("cloud_secret".to_string()),
+        jwt_config: Arc::new(JwtConfig::default()),
+        auth_config: Arc::new(AuthConfig::default()),
+        reservation_config: Arc::new(ReservationConfig::default()),
+        inventory_config: Arc::new(InventoryConfig::default()),
+        low_stock_webhook_config: Arc::new(LowStockWebhookConfig::default()),
+        order_webhook_config: Arc::new(OrderWebhookConfig::default()),
+        order_config: Arc::new(OrderConfig::default()),
+        cart_limits: Arc::new(CartLimits::default()),
+        promotion_config: Arc::new(PromotionConfig::default()),
+        body_limits: Arc::new(BodyLimits::default()),
+        http: HttpClientConfig::default().build_client(),
+        mailer: Arc::new(NoopMailer),
+        stripe_config: Arc::new(StripeConfig::new("sk_test_dummy")),
+        cloudinary_config: Arc::new(CloudinaryConfig::new("cloud_name", "cloud_key", "cloud_secret")),
     })
 }
 
@@ -60,14 +114,40 @@ pub async fn app_with_state(state: AppState) -> Router {
     routes::build_route(state)
 }
 
+/// The account behind [`jwt_for`]'s tokens; [`test_state_db`] makes sure it exists.
+pub fn fixture_user_id(role: &str) -> Uuid {
+    match role {
+        "admin" => Uuid::from_u128(0xad),
+        _ => Uuid::from_u128(0x1),
+    }
+}
+
+async fn seed_fixture_users(pool: &PgPool) -> Result<(), sqlx::Error> {
+    for role in ["client", "admin"] {
+        sqlx::query(
+            r#"
+            INSERT INTO users (id, email, password_hash, role) VALUES ($1, $2, 'x', $3)
+            ON CONFLICT (id) DO UPDATE SET role = EXCLUDED.role, is_active = true, deleted_at = NULL
+            "#,
+        )
+        .bind(fixture_user_id(role))
+        .bind(format!("fixture-{}@example.com", role))
+        .bind(role)
+        .execute(pool)
+        .await?;
+    }
+    Ok(())
+}
+
 pub fn jwt_for(role: &str) -> String {
     #[derive(Debug, Serialize, Deserialize)]
-    struct Claims { sub: Uuid, email: String, role: String, exp: usize }
+    struct Claims { sub: Uuid, email: String, role: String, exp: usize, jti: Uuid }
     let claims = Claims {
-        sub: Uuid::new_v4(),
+        sub: fixture_user_id(role),
         email: format!("{}@example.com", role),
         role: role.to_string(),
         exp: 4102444800,
+        jti: Uuid::new_v4(),
     };
     encode(&Header::default(), &claims, &EncodingKey::from_secret(b"test_secret")).unwrap()
 }
@@ -81,3 +161,58 @@ pub async fn test_server_lazy() -> TestServer {
     TestServer::new(app).expect("failed to start test server")
 }
 
+
+pub fn jwt_for_user(user_id: Uuid, role: &str) -> String {
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Claims { sub: Uuid, email: String, role: String, exp: usize, jti: Uuid }
+    let claims = Claims {
+        sub: user_id,
+        email: format!("{}@example.com", user_id),
+        role: role.to_string(),
+        exp: 4102444800,
+        jti: Uuid::new_v4(),
+    };
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(b"test_secret")).unwrap()
+}
+
+pub async fn seed_user(pool: &PgPool, role: &str) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query("INSERT INTO users (id, email, password_hash, role) VALUES ($1, $2, 'x', $3)")
+        .bind(id)
+        .bind(format!("{}@example.com", id))
+        .bind(role)
+        .execute(pool)
+        .await
+        .expect("failed to seed user");
+    id
+}
+
+pub async fn seed_product(pool: &PgPool, name: &str, price: &str, stock: i32) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query("INSERT INTO products (id, name, price, stock) VALUES ($1, $2, $3::numeric, $4)")
+        .bind(id)
+        .bind(name)
+        .bind(price)
+        .bind(stock)
+        .execute(pool)
+        .await
+        .expect("failed to seed product");
+    id
+}
+
+pub async fn seed_cart(pool: &PgPool, user_id: Uuid) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query("INSERT INTO carts (id, user_id) VALUES ($1, $2)")
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .expect("failed to seed cart");
+    id
+}
+
+pub async fn test_server_db() -> Option<(TestServer, AppState)> {
+    let state = test_state_db().await?;
+    let app = app_with_state(state.clone()).await;
+    Some((TestServer::new(app).expect("failed to start test server"), state))
+}
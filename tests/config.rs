@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use hemp_backend::model::promotion::PromotionStacking;
+use hemp_backend::state::{AuthConfig, CartLimits, CloudinaryConfig, DbPoolConfig, EnvConfig, HttpClientConfig, InventoryConfig, JwtConfig, LowStockWebhookConfig, MailConfig, MetricsConfig, OrderConfig, OrderWebhookConfig, PromotionConfig, ReservationConfig, StripeConfig};
+use rust_decimal::Decimal;
+
+fn pool_config(vars: &[(&str, &str)]) -> Result<DbPoolConfig, String> {
+    let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+    DbPoolConfig::from_lookup(|key| vars.get(key).cloned())
+}
+
+fn reservation_config(vars: &[(&str, &str)]) -> Result<ReservationConfig, String> {
+    let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+    ReservationConfig::from_lookup(|key| vars.get(key).cloned())
+}
+
+#[test]
+fn pool_config_defaults_when_unset() {
+    assert_eq!(pool_config(&[]).unwrap(), DbPoolConfig::default());
+}
+
+#[test]
+fn pool_config_reads_overrides() {
+    let config = pool_config(&[
+        ("DB_MAX_CONNECTIONS", "40"),
+        ("DB_MIN_CONNECTIONS", "5"),
+        ("DB_ACQUIRE_TIMEOUT_SECS", "3"),
+    ])
+    .unwrap();
+
+    assert_eq!(config.max_connections, 40);
+    assert_eq!(config.min_connections, 5);
+    assert_eq!(config.acquire_timeout, Duration::from_secs(3));
+}
+
+#[test]
+fn pool_config_rejects_bad_values() {
+    for vars in [
+        [("DB_MAX_CONNECTIONS", "0")],
+        [("DB_MAX_CONNECTIONS", "ten")],
+        [("DB_MIN_CONNECTIONS", "-1")],
+        [("DB_ACQUIRE_TIMEOUT_SECS", "0")],
+        [("DB_MIN_CONNECTIONS", "11")],
+    ] {
+        let err = pool_config(&vars).unwrap_err();
+        assert!(err.contains(vars[0].0), "error for {:?} should name the variable: {}", vars, err);
+    }
+}
+
+#[test]
+fn reservation_config_reads_overrides_and_checks_them() {
+    assert_eq!(reservation_config(&[]).unwrap(), ReservationConfig::default());
+
+    let config = reservation_config(&[("RESERVATION_DEFAULT_MINUTES", "15"), ("RESERVATION_MAX_MINUTES", "120")]).unwrap();
+    assert_eq!(config.default_minutes, 15);
+    assert_eq!(config.max_minutes, 120);
+
+    for vars in [
+        &[("RESERVATION_DEFAULT_MINUTES", "0")][..],
+        &[("RESERVATION_MAX_MINUTES", "soon")][..],
+        &[("RESERVATION_DEFAULT_MINUTES", "90"), ("RESERVATION_MAX_MINUTES", "60")][..],
+    ] {
+        let err = reservation_config(vars).unwrap_err();
+        assert!(err.contains(vars[0].0), "error for {:?} should name the variable: {}", vars, err);
+    }
+}
+
+#[test]
+fn http_client_config_reads_overrides_and_checks_them() {
+    let vars: HashMap<&str, &str> = HashMap::from([("HTTP_CONNECT_TIMEOUT_SECS", "2"), ("HTTP_TIMEOUT_SECS", "20")]);
+    let config = HttpClientConfig::from_lookup(|key| vars.get(key).map(|v| v.to_string())).unwrap();
+    assert_eq!(config.connect_timeout, Duration::from_secs(2));
+    assert_eq!(config.timeout, Duration::from_secs(20));
+
+    assert_eq!(HttpClientConfig::from_lookup(|_| None).unwrap(), HttpClientConfig::default());
+    let err = HttpClientConfig::from_lookup(|key| (key == "HTTP_TIMEOUT_SECS").then(|| "0".to_string())).unwrap_err();
+    assert!(err.contains("HTTP_TIMEOUT_SECS"));
+}
+
+#[test]
+fn stripe_config_requires_a_secret_key() {
+    assert!(StripeConfig::from_lookup(|_| None).unwrap_err().contains("STRIPE_SECRET_KEY"));
+
+    let config = StripeConfig::from_lookup(|key| (key == "STRIPE_SECRET_KEY").then(|| "sk_test_1".to_string())).unwrap();
+    assert_eq!(config.secret_key, "sk_test_1");
+    assert_eq!(config.api_base, StripeConfig::DEFAULT_API_BASE);
+
+    // The key stays out of debug output
+    assert!(!format!("{:?}", config).contains("sk_test_1"));
+}
+
+#[test]
+fn cloudinary_config_requires_credentials_and_reads_the_api_base() {
+    let vars: HashMap<&str, &str> = [
+        ("CLOUDINARY_CLOUD_NAME", "demo"),
+        ("CLOUDINARY_API_KEY", "key_1"),
+        ("CLOUDINARY_API_SECRET", "secret_1"),
+    ]
+    .into_iter()
+    .collect();
+
+    let config = CloudinaryConfig::from_lookup(|key| vars.get(key).map(|v| v.to_string())).unwrap();
+    assert_eq!(config.cloud_name, "demo");
+    assert_eq!(config.api_base, CloudinaryConfig::DEFAULT_API_BASE);
+
+    // The secret stays out of debug output
+    assert!(!format!("{:?}", config).contains("secret_1"));
+
+    let config = CloudinaryConfig::from_lookup(|key| match key {
+        "CLOUDINARY_API_BASE" => Some("http://127.0.0.1:9000".to_string()),
+        _ => vars.get(key).map(|v| v.to_string()),
+    })
+    .unwrap();
+    assert_eq!(config.api_base, "http://127.0.0.1:9000");
+
+    let err = CloudinaryConfig::from_lookup(|key| match key {
+        "CLOUDINARY_API_SECRET" => None,
+        _ => vars.get(key).map(|v| v.to_string()),
+    })
+    .unwrap_err();
+    assert!(err.contains("CLOUDINARY_API_SECRET"), "{}", err);
+}
+
+#[test]
+fn inventory_config_reads_the_default_low_stock_threshold() {
+    assert_eq!(InventoryConfig::from_lookup(|_| None).unwrap().default_low_stock_threshold, None);
+
+    let config = InventoryConfig::from_lookup(|key| (key == "DEFAULT_LOW_STOCK_THRESHOLD").then(|| "10".to_string())).unwrap();
+    assert_eq!(config.default_low_stock_threshold, Some(10));
+
+    for raw in ["-1", "few"] {
+        let err = InventoryConfig::from_lookup(|_| Some(raw.to_string())).unwrap_err();
+        assert!(err.contains("DEFAULT_LOW_STOCK_THRESHOLD"), "{}", err);
+    }
+}
+
+#[test]
+fn low_stock_webhook_config_reads_the_url_and_checks_the_debounce() {
+    assert_eq!(LowStockWebhookConfig::from_lookup(|_| None).unwrap(), LowStockWebhookConfig::default());
+
+    let config = LowStockWebhookConfig::from_lookup(|key| match key {
+        "LOW_STOCK_WEBHOOK_URL" => Some("https://example.com/stock".to_string()),
+        "LOW_STOCK_WEBHOOK_DEBOUNCE_SECONDS" => Some("0".to_string()),
+        _ => None,
+    })
+    .unwrap();
+    assert_eq!(config.url.as_deref(), Some("https://example.com/stock"));
+    assert_eq!(config.debounce_seconds, 0);
+
+    for raw in ["-5", "hourly"] {
+        let err = LowStockWebhookConfig::from_lookup(|key| {
+            (key == "LOW_STOCK_WEBHOOK_DEBOUNCE_SECONDS").then(|| raw.to_string())
+        })
+        .unwrap_err();
+        assert!(err.contains("LOW_STOCK_WEBHOOK_DEBOUNCE_SECONDS"), "{}", err);
+    }
+}
+
+#[test]
+fn order_webhook_config_needs_a_secret_once_a_url_is_set() {
+    assert_eq!(OrderWebhookConfig::from_lookup(|_| None).unwrap().url, None);
+
+    let err = OrderWebhookConfig::from_lookup(|key| (key == "ORDER_WEBHOOK_URL").then(|| "https://example.com/hook".to_string()))
+        .unwrap_err();
+    assert!(err.contains("ORDER_WEBHOOK_SECRET"), "{}", err);
+
+    let config = OrderWebhookConfig::from_lookup(|key| match key {
+        "ORDER_WEBHOOK_URL" => Some("https://example.com/hook".to_string()),
+        "ORDER_WEBHOOK_SECRET" => Some("whsec_123".to_string()),
+        _ => None,
+    })
+    .unwrap();
+    assert_eq!(config.url.as_deref(), Some("https://example.com/hook"));
+    assert!(!format!("{:?}", config).contains("whsec_123"));
+}
+
+#[test]
+fn order_config_reads_overrides_and_checks_them() {
+    assert_eq!(OrderConfig::from_lookup(|_| None).unwrap(), OrderConfig::default());
+
+    let vars: HashMap<&str, &str> =
+        HashMap::from([("TAX_RATE", "8.25"), ("SHIPPING_FEE", "4.99"), ("FREE_SHIPPING_THRESHOLD", "50")]);
+    let pricing = OrderConfig::from_lookup(|key| vars.get(key).map(|v| v.to_string())).unwrap().pricing;
+    assert_eq!(pricing.tax_rate_percent, Decimal::new(825, 2));
+    assert_eq!(pricing.shipping_fee, Decimal::new(499, 2));
+    assert_eq!(pricing.free_shipping_threshold, Some(Decimal::new(50, 0)));
+
+    assert!(OrderConfig::default().lock_prices);
+    assert!(!OrderConfig::from_lookup(|key| (key == "LOCK_PRICE_AT_ORDER").then(|| "FALSE".to_string())).unwrap().lock_prices);
+
+    for (key, raw) in [
+        ("TAX_RATE", "8%"),
+        ("SHIPPING_FEE", "-1"),
+        ("FREE_SHIPPING_THRESHOLD", "fifty"),
+        ("LOCK_PRICE_AT_ORDER", "no"),
+    ] {
+        let err = OrderConfig::from_lookup(|k| (k == key).then(|| raw.to_string())).unwrap_err();
+        assert!(err.contains(key), "error for {}={} should name the variable: {}", key, raw, err);
+    }
+}
+
+#[test]
+fn cart_limits_read_overrides_and_check_them() {
+    assert_eq!(CartLimits::from_lookup(|_| None).unwrap(), CartLimits::default());
+
+    let vars: HashMap<&str, &str> = HashMap::from([("CART_MAX_ITEM_QUANTITY", "20"), ("CART_MAX_DISTINCT_ITEMS", "5")]);
+    let limits = CartLimits::from_lookup(|key| vars.get(key).map(|v| v.to_string())).unwrap();
+    assert_eq!(limits, CartLimits { max_item_quantity: 20, max_distinct_items: 5 });
+
+    for (key, raw) in [("CART_MAX_ITEM_QUANTITY", "0"), ("CART_MAX_DISTINCT_ITEMS", "lots")] {
+        let err = CartLimits::from_lookup(|k| (k == key).then(|| raw.to_string())).unwrap_err();
+        assert!(err.contains(key), "error for {}={} should name the variable: {}", key, raw, err);
+    }
+}
+
+#[test]
+fn auth_config_reads_email_verification_and_checks_it() {
+    assert!(!AuthConfig::from_lookup(|_| None).unwrap().require_email_verification);
+    assert!(AuthConfig::from_lookup(|_| Some("1".to_string())).unwrap().require_email_verification);
+    assert!(AuthConfig::from_lookup(|_| Some("TRUE".to_string())).unwrap().require_email_verification);
+
+    let err = AuthConfig::from_lookup(|_| Some("yes please".to_string())).unwrap_err();
+    assert!(err.contains("REQUIRE_EMAIL_VERIFICATION"), "{}", err);
+
+    // Without a mail provider nobody could receive a verification link
+    let err = AuthConfig::from_lookup(|key| (key == "REQUIRE_EMAIL_VERIFICATION").then(|| "true".to_string())).unwrap_err();
+    assert!(err.contains("MAIL_API_KEY"), "{}", err);
+}
+
+#[test]
+fn mail_config_is_off_without_a_key_and_needs_a_sender_and_app_url_with_one() {
+    let config = MailConfig::from_lookup(|_| None).unwrap();
+    assert_eq!(config.api_key, None);
+    assert_eq!(config.api_base, MailConfig::DEFAULT_API_BASE);
+
+    for missing in ["MAIL_FROM", "APP_URL"] {
+        let err = MailConfig::from_lookup(|key| match key {
+            "MAIL_API_KEY" => Some("re_123".to_string()),
+            "MAIL_FROM" => Some("Shop <shop@example.com>".to_string()),
+            "APP_URL" => Some("https://shop.example.com".to_string()),
+            _ => None,
+        }
+        .filter(|_| key != missing))
+        .unwrap_err();
+        assert!(err.contains(missing), "{}", err);
+    }
+
+    let config = MailConfig::from_lookup(|key| match key {
+        "MAIL_API_KEY" => Some("re_123".to_string()),
+        "MAIL_API_BASE" => Some("http://127.0.0.1:9999".to_string()),
+        "MAIL_FROM" => Some("Shop <shop@example.com>".to_string()),
+        "APP_URL" => Some("https://shop.example.com/".to_string()),
+        _ => None,
+    })
+    .unwrap();
+    assert_eq!(config.api_key.as_deref(), Some("re_123"));
+    assert_eq!(config.api_base, "http://127.0.0.1:9999");
+    assert_eq!(config.app_url, "https://shop.example.com");
+    assert!(!format!("{:?}", config).contains("re_123"));
+}
+
+#[test]
+fn metrics_config_reads_the_switch_and_checks_it() {
+    assert!(!MetricsConfig::from_lookup(|_| None).unwrap().enabled);
+    assert!(MetricsConfig::from_lookup(|_| Some("True".to_string())).unwrap().enabled);
+    assert!(!MetricsConfig::from_lookup(|_| Some("0".to_string())).unwrap().enabled);
+
+    let err = MetricsConfig::from_lookup(|_| Some("ture".to_string())).unwrap_err();
+    assert!(err.contains("METRICS_ENABLED"), "{}", err);
+}
+
+#[test]
+fn promotion_config_reads_the_stacking_mode() {
+    assert_eq!(PromotionConfig::from_lookup(|_| None).unwrap().stacking, PromotionStacking::BestOnly);
+    assert_eq!(PromotionConfig::from_lookup(|_| Some("stack".to_string())).unwrap().stacking, PromotionStacking::Stack);
+
+    let err = PromotionConfig::from_lookup(|_| Some("everything".to_string())).unwrap_err();
+    assert!(err.contains("PROMOTION_STACKING"), "{}", err);
+}
+
+#[test]
+fn jwt_config_reads_overrides_and_checks_them() {
+    let config = JwtConfig::from_lookup(|_| None).unwrap();
+    assert_eq!(config.expiry_seconds, JwtConfig::default().expiry_seconds);
+    assert_eq!(config.algorithm, jsonwebtoken::Algorithm::HS256);
+
+    let vars: HashMap<&str, &str> =
+        HashMap::from([("JWT_EXPIRY_SECONDS", "900"), ("JWT_ALGORITHM", "HS512"), ("JWT_ISSUER", "hemp-backend")]);
+    let config = JwtConfig::from_lookup(|key| vars.get(key).map(|v| v.to_string())).unwrap();
+    assert_eq!(config.expiry_seconds, 900);
+    assert_eq!(config.algorithm, jsonwebtoken::Algorithm::HS512);
+    assert_eq!(config.issuer.as_deref(), Some("hemp-backend"));
+
+    // A bad value is reported instead of panicking or quietly falling back to the default
+    for (key, raw) in [("JWT_ALGORITHM", "RS256"), ("JWT_EXPIRY_SECONDS", "0"), ("JWT_EXPIRY_SECONDS", "1h")] {
+        let err = JwtConfig::from_lookup(|k| (k == key).then(|| raw.to_string())).unwrap_err();
+        assert!(err.contains(key), "error for {}={} should name the variable: {}", key, raw, err);
+    }
+}
@@ -0,0 +1,161 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use hemp_backend::model::coupon::Coupon;
+use hemp_backend::services::coupon_service::{check_usable, coupon_discount};
+use rust_decimal::Decimal;
+use serde_json::json;
+use uuid::Uuid;
+
+fn coupon(percent_off: Option<i64>, amount_off: Option<i64>) -> Coupon {
+    Coupon {
+        id: Uuid::new_v4(),
+        code: "SAVE".to_string(),
+        percent_off: percent_off.map(|p| Decimal::new(p, 0)),
+        amount_off: amount_off.map(|a| Decimal::new(a, 0)),
+        expires_at: None,
+        usage_limit: None,
+        times_used: 0,
+        created_at: Utc::now(),
+    }
+}
+
+#[test]
+fn coupon_discount_is_percent_or_fixed_and_capped() {
+    assert_eq!(coupon_discount(&coupon(Some(10), None), Decimal::new(4999, 2)), Decimal::new(500, 2));
+    assert_eq!(coupon_discount(&coupon(None, Some(5)), Decimal::new(4999, 2)), Decimal::new(5, 0));
+    // A fixed amount never takes the order below zero
+    assert_eq!(coupon_discount(&coupon(None, Some(20)), Decimal::new(1500, 2)), Decimal::new(1500, 2));
+}
+
+#[test]
+fn expired_or_used_up_coupons_are_not_usable() {
+    let now = Utc::now();
+
+    let mut c = coupon(Some(10), None);
+    assert!(check_usable(&c, now).is_ok());
+
+    c.expires_at = Some(now - Duration::minutes(1));
+    assert!(check_usable(&c, now).is_err());
+
+    let mut c = coupon(Some(10), None);
+    c.usage_limit = Some(2);
+    c.times_used = 1;
+    assert!(check_usable(&c, now).is_ok());
+    c.times_used = 2;
+    assert!(check_usable(&c, now).is_err());
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn coupon_admin_routes_require_admin() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+    let res = server
+        .post("/api/coupon")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_user()))
+        .json(&json!({"code": "SAVE10", "percent_off": "10"}))
+        .await;
+    res.assert_status(axum::http::StatusCode::FORBIDDEN);
+}
+
+async fn checkout(server: &axum_test::TestServer, pool: &sqlx::PgPool, coupon_code: &str) -> (Uuid, axum_test::TestResponse) {
+    let user_id = common::seed_user(pool, "client").await;
+    let product_id = common::seed_product(pool, "Coupon Tincture", "40.00", 10).await;
+    let cart_id = common::seed_cart(pool, user_id).await;
+    sqlx::query("INSERT INTO cart_items (id, cart_id, product_id, quantity) VALUES ($1, $2, $3, 1)")
+        .bind(Uuid::new_v4())
+        .bind(cart_id)
+        .bind(product_id)
+        .execute(pool)
+        .await
+        .unwrap();
+
+    let res = server
+        .post("/api/order")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_for_user(user_id, "client")))
+        .json(&json!({"coupon_code": coupon_code}))
+        .await;
+    (user_id, res)
+}
+
+async fn create_coupon(server: &axum_test::TestServer, body: serde_json::Value) -> serde_json::Value {
+    let res = server
+        .post("/api/coupon")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_admin()))
+        .json(&body)
+        .await;
+    res.assert_status(axum::http::StatusCode::CREATED);
+    res.json::<serde_json::Value>()
+}
+
+async fn times_used(pool: &sqlx::PgPool, code: &str) -> i32 {
+    sqlx::query_scalar("SELECT times_used FROM coupons WHERE code = $1")
+        .bind(code)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+fn unique_code(prefix: &str) -> String {
+    format!("{}{}", prefix, &Uuid::new_v4().simple().to_string()[..8]).to_uppercase()
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn valid_coupon_is_applied_to_the_order() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let code = unique_code("save");
+    create_coupon(&server, json!({"code": code.to_lowercase(), "amount_off": "7.50", "usage_limit": 5})).await;
+
+    let (_, res) = checkout(&server, &state.db, &code.to_lowercase()).await;
+    res.assert_status(axum::http::StatusCode::CREATED);
+    let order = res.json::<serde_json::Value>();
+
+    assert_eq!(order["coupon_code"], code);
+    assert_eq!(order["coupon_discount"], json!(7.5));
+    assert_eq!(order["subtotal"], json!(40.0));
+    assert_eq!(order["total"], json!(32.5));
+    assert_eq!(times_used(&state.db, &code).await, 1);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn expired_coupon_is_rejected() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let code = unique_code("old");
+    create_coupon(&server, json!({"code": code, "percent_off": "10"})).await;
+    sqlx::query("UPDATE coupons SET expires_at = now() - interval '1 day' WHERE code = $1")
+        .bind(&code)
+        .execute(&state.db)
+        .await
+        .unwrap();
+
+    let (user_id, res) = checkout(&server, &state.db, &code).await;
+    res.assert_status_bad_request();
+    assert!(res.json::<serde_json::Value>()["details"].as_str().unwrap().contains("expired"));
+
+    let orders: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orders WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap();
+    assert_eq!(orders, 0);
+    assert_eq!(times_used(&state.db, &code).await, 0);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn coupon_over_its_usage_limit_is_rejected() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let code = unique_code("once");
+    create_coupon(&server, json!({"code": code, "percent_off": "25", "usage_limit": 1})).await;
+
+    let (_, first) = checkout(&server, &state.db, &code).await;
+    first.assert_status(axum::http::StatusCode::CREATED);
+    assert_eq!(first.json::<serde_json::Value>()["coupon_discount"], json!(10.0));
+
+    let (_, second) = checkout(&server, &state.db, &code).await;
+    second.assert_status_bad_request();
+    assert!(second.json::<serde_json::Value>()["details"].as_str().unwrap().contains("usage limit"));
+    assert_eq!(times_used(&state.db, &code).await, 1);
+}
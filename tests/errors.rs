@@ -0,0 +1,59 @@
+use axum::{http::StatusCode, response::IntoResponse};
+use hemp_backend::errors::AppError;
+use serde_json::Value;
+
+async fn render(err: AppError) -> (StatusCode, Value) {
+    let response = err.into_response();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn error_body_carries_machine_readable_code() {
+    let cases = vec![
+        (AppError::Database(sqlx::Error::RowNotFound), StatusCode::INTERNAL_SERVER_ERROR, "database_error"),
+        (AppError::ImageUpload("boom".into()), StatusCode::BAD_REQUEST, "image_upload_failed"),
+        (AppError::FileTooLarge { max_size: 1, actual_size: 2 }, StatusCode::PAYLOAD_TOO_LARGE, "file_too_large"),
+        (
+            AppError::InvalidFileType { expected: "image/png".into(), actual: "text/plain".into() },
+            StatusCode::BAD_REQUEST,
+            "invalid_file_type",
+        ),
+        (AppError::Validation("bad".into()), StatusCode::BAD_REQUEST, "validation_error"),
+        (
+            AppError::InvalidFields([("name".to_string(), vec!["required".to_string()])].into()),
+            StatusCode::BAD_REQUEST,
+            "validation_error",
+        ),
+        (AppError::NotFound("missing".into()), StatusCode::NOT_FOUND, "not_found"),
+        (AppError::InsufficientStock("oil".into()), StatusCode::CONFLICT, "insufficient_stock"),
+        (AppError::Conflict("shipped".into()), StatusCode::CONFLICT, "conflict"),
+        (AppError::Unauthorized, StatusCode::UNAUTHORIZED, "unauthorized"),
+        (AppError::Forbidden("nope".into()), StatusCode::FORBIDDEN, "forbidden"),
+        (AppError::Internal("oops".into()), StatusCode::INTERNAL_SERVER_ERROR, "internal_error"),
+    ];
+
+    for (err, expected_status, expected_code) in cases {
+        let (status, body) = render(err).await;
+        assert_eq!(status, expected_status);
+        assert_eq!(body["code"], expected_code);
+        assert!(body["error"].is_string());
+        assert!(body["details"].is_string());
+    }
+}
+
+#[tokio::test]
+async fn server_errors_do_not_leak_internal_detail() {
+    let sql_error = sqlx::Error::Protocol(
+        "relation \"stock_reservations\" does not exist at SELECT quantity FROM stock_reservations".into(),
+    );
+
+    for err in [AppError::Database(sql_error), AppError::Internal("stripe key sk_test_123 rejected".into())] {
+        let (_, body) = render(err).await;
+        let text = body.to_string();
+        assert!(!text.contains("SELECT"), "leaked SQL: {}", text);
+        assert!(!text.contains("stock_reservations"), "leaked table name: {}", text);
+        assert!(!text.contains("sk_test_123"), "leaked internal message: {}", text);
+    }
+}
@@ -1,10 +1,15 @@
 mod common;
 
+use std::{collections::HashMap, sync::Arc};
+
 use axum_test::TestServer;
+use hemp_backend::state::CloudinaryConfig;
+use serde_json::json;
 
 #[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
 async fn image_upload_requires_admin() {
-    let server = common::test_server_lazy().await;
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
 
     // Without admin token expect 401
     server.post("/api/image/upload").await.assert_status_unauthorized();
@@ -17,3 +22,82 @@ async fn image_upload_requires_admin() {
     assert!(res.status_code().as_u16() != 401 && res.status_code().as_u16() != 403);
 }
 
+
+/// Serves Cloudinary's destroy endpoint for `cloud_name`, checking each request's signature
+/// against `cloud_secret`. `hemp_products/abc123` exists; every other id is "not found".
+/// Sends each public id it is asked to delete down the channel.
+async fn spawn_fake_cloudinary() -> (CloudinaryConfig, tokio::sync::mpsc::UnboundedReceiver<String>) {
+    use axum::{http::StatusCode, response::IntoResponse, routing::post, Router};
+    use sha1::{Digest, Sha1};
+
+    let (deleted_tx, deleted) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let app = Router::new().route(
+        "/v1_1/cloud_name/image/destroy",
+        post(move |axum::Form(params): axum::Form<HashMap<String, String>>| async move {
+            let to_sign = format!("public_id={}&timestamp={}cloud_secret", params["public_id"], params["timestamp"]);
+            if params["api_key"] != "cloud_key" || params["signature"] != hex::encode(Sha1::digest(to_sign.as_bytes())) {
+                return (StatusCode::UNAUTHORIZED, "Invalid Signature").into_response();
+            }
+
+            deleted_tx.send(params["public_id"].clone()).unwrap();
+            let result = if params["public_id"] == "hemp_products/abc123" { "ok" } else { "not found" };
+            axum::Json(json!({ "result": result })).into_response()
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let mut config = CloudinaryConfig::new("cloud_name", "cloud_key", "cloud_secret");
+    config.api_base = format!("http://{}", addr);
+    (config, deleted)
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn image_delete_requires_admin() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+
+    server
+        .delete("/api/image")
+        .add_query_param("public_id", "hemp_products/abc123")
+        .await
+        .assert_status_unauthorized();
+
+    server
+        .delete("/api/image")
+        .add_query_param("public_id", "hemp_products/abc123")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_user()))
+        .await
+        .assert_status_forbidden();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn image_delete_sends_a_signed_destroy_to_cloudinary() {
+    let Some(mut state) = common::test_state_db().await else { return; };
+    let (cloudinary, mut deleted) = spawn_fake_cloudinary().await;
+    state.cloudinary_config = Arc::new(cloudinary);
+    let server = TestServer::new(common::app_with_state(state).await).unwrap();
+
+    // A full image URL is cut down to its public id
+    server
+        .delete("/api/image")
+        .add_query_param(
+            "public_id",
+            "https://res.cloudinary.com/cloud_name/image/upload/v1/hemp_products/abc123.jpg",
+        )
+        .add_header("Authorization", format!("Bearer {}", common::jwt_admin()))
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+    assert_eq!(deleted.recv().await.unwrap(), "hemp_products/abc123");
+
+    // Cloudinary answers 200 for unknown ids too, but with a "not found" result
+    let res = server
+        .delete("/api/image")
+        .add_query_param("public_id", "hemp_products/missing")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_admin()))
+        .await;
+    res.assert_status_bad_request();
+    assert_eq!(deleted.recv().await.unwrap(), "hemp_products/missing");
+}
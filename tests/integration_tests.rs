@@ -1,7 +1,8 @@
 use hemp_backend::{
     dtos::{NewProductDto, ProductResponse, UpdateProductDto, SignupDto, LoginDto, Claims},
     routes::build_route,
-    state::AppState,
+    services::mailer::NoopMailer,
+    state::{AppState, AuthConfig, BodyLimits, CartLimits, CloudinaryConfig, HttpClientConfig, JwtConfig, InventoryConfig, LowStockWebhookConfig, OrderConfig, OrderWebhookConfig, PromotionConfig, ReservationConfig, StripeConfig},
 };
 use axum::{
     body::Body,
@@ -29,19 +30,44 @@ async fn setup_test_db() -> PgPool {
     
     // Run migrations
     sqlx::migrate!("./migrations").run(&pool).await.expect("Failed to run migrations");
+
+    // The account behind create_admin_token; tokens are only honoured for live accounts
+    sqlx::query(
+        r#"
+        INSERT INTO users (id, email, password_hash, role) VALUES ($1, 'admin@test.com', 'x', 'admin')
+        ON CONFLICT (id) DO UPDATE SET role = 'admin', is_active = true, deleted_at = NULL
+        "#,
+    )
+    .bind(ADMIN_ID)
+    .execute(&pool)
+    .await
+    .expect("Failed to seed admin user");
     
     pool
 }
 
+const ADMIN_ID: Uuid = Uuid::from_u128(0xad_1);
+
 async fn setup_test_app() -> Router {
     let pool = setup_test_db().await;
     
     let state = AppState {
         db: pool.clone(),
         jwt_secret: Arc::new("test_secret".to_string()),
-        cloudinary_cloud_name: Arc::new("test_cloud".to_string()),
-        cloudinary_api_key: Arc::new("test_key".to_string()),
-        cloudinary_api_secret: Arc::new("test_secret".to_string()),
+        jwt_config: Arc::new(JwtConfig::default()),
+        auth_config: Arc::new(AuthConfig::default()),
+        reservation_config: Arc::new(ReservationConfig::default()),
+        inventory_config: Arc::new(InventoryConfig::default()),
+        low_stock_webhook_config: Arc::new(LowStockWebhookConfig::default()),
+        order_webhook_config: Arc::new(OrderWebhookConfig::default()),
+        order_config: Arc::new(OrderConfig::default()),
+        cart_limits: Arc::new(CartLimits::default()),
+        promotion_config: Arc::new(PromotionConfig::default()),
+        body_limits: Arc::new(BodyLimits::default()),
+        http: HttpClientConfig::default().build_client(),
+        mailer: Arc::new(NoopMailer),
+        stripe_config: Arc::new(StripeConfig::new("sk_test_dummy")),
+        cloudinary_config: Arc::new(CloudinaryConfig::new("test_cloud", "test_key", "test_secret")),
     };
     
     build_route(state)
@@ -57,7 +83,7 @@ async fn cleanup_test_data(pool: &PgPool) {
     sqlx::query("DELETE FROM product_categories").execute(pool).await.unwrap();
     sqlx::query("DELETE FROM products").execute(pool).await.unwrap();
     sqlx::query("DELETE FROM categories").execute(pool).await.unwrap();
-    sqlx::query("DELETE FROM users").execute(pool).await.unwrap();
+    sqlx::query("DELETE FROM users WHERE id <> $1").bind(ADMIN_ID).execute(pool).await.unwrap();
 }
 
 fn create_admin_token() -> String {
@@ -67,10 +93,13 @@ fn create_admin_token() -> String {
         .as_secs() + 3600; // 1 hour from now
     
     let claims = Claims {
-        sub: Uuid::new_v4(),
+        sub: ADMIN_ID,
         email: "admin@test.com".to_string(),
         role: "admin".to_string(),
         exp: exp as usize,
+        jti: Uuid::new_v4(),
+        iss: None,
+        aud: None,
     };
     
     encode(
@@ -84,11 +113,13 @@ fn create_admin_token() -> String {
 fn create_test_product_dto() -> NewProductDto {
     NewProductDto {
         name: "Test Hemp Oil".to_string(),
+        sku: None,
         description: Some("High quality CBD oil".to_string()),
         price: Decimal::new(2999, 2), // $29.99
         stock: 100,
         image_url: Some("https://example.com/image.jpg".to_string()),
         low_stock_threshold: Some(10),
+        reorder_target: None,
         track_inventory: Some(true),
     }
 }
@@ -241,6 +272,348 @@ async fn test_list_products_success() {
     assert!(products.len() >= 3);
 }
 
+async fn get_product_page(app: Router, query: &str) -> (i64, Vec<ProductResponse>) {
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(format!("/api/product{}", query))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let total = response
+        .headers()
+        .get("x-total-count")
+        .expect("missing X-Total-Count header")
+        .to_str()
+        .unwrap()
+        .parse::<i64>()
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let products: Vec<ProductResponse> = serde_json::from_slice(&body).unwrap();
+
+    (total, products)
+}
+
+async fn create_products(app: &Router, count: usize) {
+    for i in 0..count {
+        let mut product = create_test_product_dto();
+        product.name = format!("Paged Product {}", i);
+        let request_body = serde_json::to_string(&product).unwrap();
+
+        app.clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/product")
+                    .header("content-type", "application/json")
+                    .header("authorization", format!("Bearer {}", create_admin_token()))
+                    .body(Body::from(request_body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn test_list_products_default_page() {
+    let app = setup_test_app().await;
+    create_products(&app, 3).await;
+
+    let (total, products) = get_product_page(app, "").await;
+
+    assert!(total >= 3);
+    assert_eq!(products.len() as i64, total.min(50));
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn test_list_products_custom_page() {
+    let app = setup_test_app().await;
+    create_products(&app, 3).await;
+
+    let (total, first_page) = get_product_page(app.clone(), "?limit=2&offset=0").await;
+    let (_, second_page) = get_product_page(app.clone(), "?limit=2&offset=1").await;
+
+    assert!(total >= 3);
+    assert_eq!(first_page.len(), 2);
+    assert_eq!(second_page.len(), 2);
+
+    // Limits above the cap are clamped
+    let (_, capped) = get_product_page(app, "?limit=1000").await;
+    assert!(capped.len() <= 100);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn test_list_products_offset_out_of_range() {
+    let app = setup_test_app().await;
+    create_products(&app, 1).await;
+
+    let (total, _) = get_product_page(app.clone(), "").await;
+    let (page_total, products) = get_product_page(app, &format!("?offset={}", total + 10)).await;
+
+    assert_eq!(page_total, total);
+    assert!(products.is_empty());
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn test_list_products_page_headers() {
+    let app = setup_test_app().await;
+    // Only this test's products fall in the window, so the count can't move under parallel tests
+    let base = unique_price_base();
+    let cents = |n: i64| base + Decimal::new(n, 2);
+    create_priced_products(&app, &[cents(1), cents(2), cents(3)]).await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(format!("/api/product?min_price={}&max_price={}&limit=1000&offset=1", base, cents(3)))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let headers = response.headers();
+    assert_eq!(headers["x-total-count"], "3");
+    // The limit reported is the one applied after clamping
+    assert_eq!(headers["x-page-limit"], "100");
+    assert_eq!(headers["x-page-offset"], "1");
+
+    // The body stays a bare array
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let products: Vec<ProductResponse> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(products.len(), 2);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn test_list_products_envelope() {
+    let app = setup_test_app().await;
+    create_products(&app, 3).await;
+
+    let (total, bare) = get_product_page(app.clone(), "?limit=2").await;
+    assert_eq!(bare.len(), 2);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/api/product?limit=2&offset=1&envelope=true")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["x-total-count"], total.to_string());
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(page["data"].as_array().unwrap().len(), 2);
+    assert_eq!(page["meta"]["total"], total);
+    assert_eq!(page["meta"]["limit"], 2);
+    assert_eq!(page["meta"]["offset"], 1);
+}
+
+async fn create_priced_products(app: &Router, prices: &[Decimal]) {
+    for price in prices {
+        let mut product = create_test_product_dto();
+        product.name = format!("Priced Product {}", price);
+        product.price = *price;
+        let request_body = serde_json::to_string(&product).unwrap();
+
+        app.clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/product")
+                    .header("content-type", "application/json")
+                    .header("authorization", format!("Bearer {}", create_admin_token()))
+                    .body(Body::from(request_body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+}
+
+/// A whole-dollar price, picked at random far above what other tests use, whose next few cents
+/// only the calling test prices products in; the database outlives each run.
+fn unique_price_base() -> Decimal {
+    let dollars = 1_000_000 + (Uuid::new_v4().as_u128() % 90_000_000) as i64;
+    Decimal::new(dollars * 100, 2)
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn test_list_products_price_range() {
+    let app = setup_test_app().await;
+    let base = unique_price_base();
+    let cents = |n: i64| base + Decimal::new(n, 2);
+    create_priced_products(&app, &[cents(1), cents(5), cents(10)]).await;
+
+    let (total, products) = get_product_page(app, &format!("?min_price={}&max_price={}", base, cents(6))).await;
+
+    assert_eq!(total, 2);
+    assert!(products.iter().all(|p| p.price >= base && p.price <= cents(6)));
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn test_list_products_single_price_bound() {
+    let app = setup_test_app().await;
+    create_priced_products(&app, &[Decimal::new(800001, 2), Decimal::new(800501, 2)]).await;
+
+    let (_, above) = get_product_page(app.clone(), "?min_price=8000.00&limit=100").await;
+    assert!(above.len() >= 2);
+    assert!(above.iter().all(|p| p.price >= Decimal::new(800000, 2)));
+
+    let (_, below) = get_product_page(app, "?max_price=8000.00&limit=100").await;
+    assert!(below.iter().all(|p| p.price <= Decimal::new(800000, 2)));
+}
+
+async fn seed_product_created_at(pool: &PgPool, created_at: &str) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query("INSERT INTO products (id, name, price, stock, created_at) VALUES ($1, $2, 10.00, 1, $3::timestamptz)")
+        .bind(id)
+        .bind(format!("Dated Product {}", created_at))
+        .bind(created_at)
+        .execute(pool)
+        .await
+        .unwrap();
+    id
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn test_list_products_created_range() {
+    let pool = setup_test_db().await;
+    let app = setup_test_app().await;
+    let just_before = seed_product_created_at(&pool, "2001-02-28T23:59:59Z").await;
+    let at_start = seed_product_created_at(&pool, "2001-03-01T00:00:00Z").await;
+    let inside = seed_product_created_at(&pool, "2001-03-15T12:00:00Z").await;
+    let at_end = seed_product_created_at(&pool, "2001-04-01T00:00:00Z").await;
+
+    let (_, products) = get_product_page(
+        app.clone(),
+        "?created_after=2001-03-01T00:00:00Z&created_before=2001-04-01T00:00:00Z&limit=100",
+    )
+    .await;
+    let ids: Vec<Uuid> = products.iter().map(|p| p.id).collect();
+
+    // created_after is inclusive, created_before exclusive
+    assert!(ids.contains(&at_start));
+    assert!(ids.contains(&inside));
+    assert!(!ids.contains(&just_before));
+    assert!(!ids.contains(&at_end));
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/api/product?created_after=2001-04-01T00:00:00Z&created_before=2001-03-01T00:00:00Z")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+async fn send_product_json(app: &Router, method: &str, uri: &str, body: String) -> StatusCode {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .header("authorization", format!("Bearer {}", create_admin_token()))
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn test_create_product_duplicate_sku() {
+    let app = setup_test_app().await;
+    let sku = format!("SKU-{}", Uuid::new_v4().simple());
+
+    let mut product = create_test_product_dto();
+    product.sku = Some(sku.clone());
+    let body = serde_json::to_string(&product).unwrap();
+
+    assert_eq!(send_product_json(&app, "POST", "/api/product", body.clone()).await, StatusCode::CREATED);
+    assert_eq!(send_product_json(&app, "POST", "/api/product", body).await, StatusCode::BAD_REQUEST);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(format!("/api/product/by-sku/{}", sku))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let found: ProductResponse = serde_json::from_slice(&body).unwrap();
+    assert_eq!(found.sku.as_deref(), Some(sku.as_str()));
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn test_update_product_duplicate_sku() {
+    let app = setup_test_app().await;
+    let taken_sku = format!("SKU-{}", Uuid::new_v4().simple());
+
+    let mut first = create_test_product_dto();
+    first.sku = Some(taken_sku.clone());
+    let status = send_product_json(&app, "POST", "/api/product", serde_json::to_string(&first).unwrap()).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/product")
+                .header("content-type", "application/json")
+                .header("authorization", format!("Bearer {}", create_admin_token()))
+                .body(Body::from(serde_json::to_string(&create_test_product_dto()).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let second: ProductResponse = serde_json::from_slice(&body).unwrap();
+
+    let status = send_product_json(
+        &app,
+        "PUT",
+        &format!("/api/product/{}", second.id),
+        json!({ "sku": taken_sku }).to_string(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
 #[tokio::test]
 #[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
 async fn test_update_product_success() {
@@ -270,11 +643,13 @@ async fn test_update_product_success() {
     // Update the product
     let update_dto = UpdateProductDto {
         name: Some("Updated Hemp Oil".to_string()),
-        description: Some("Updated description".to_string()),
+        sku: None,
+        description: Some(Some("Updated description".to_string())),
         price: Some(Decimal::new(3999, 2)), // $39.99
         stock: Some(50),
-        image_url: Some("https://example.com/new_image.jpg".to_string()),
-        low_stock_threshold: Some(5),
+        image_url: Some(Some("https://example.com/new_image.jpg".to_string())),
+        low_stock_threshold: Some(Some(5)),
+        reorder_target: None,
         track_inventory: Some(false),
     };
     
@@ -303,6 +678,127 @@ async fn test_update_product_success() {
     assert_eq!(updated_product.stock, 50);
 }
 
+/// Sends an admin JSON request and returns the status with the decoded body.
+async fn send_product_json_body(app: &Router, method: &str, uri: &str, body: String) -> (StatusCode, Value) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .header("authorization", format!("Bearer {}", create_admin_token()))
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn test_partial_update_leaves_other_fields_intact() {
+    let app = setup_test_app().await;
+    let sku = format!("SKU-{}", Uuid::new_v4().simple());
+
+    let mut product = create_test_product_dto();
+    product.sku = Some(sku.clone());
+    let (status, created) = send_product_json_body(&app, "POST", "/api/product", serde_json::to_string(&product).unwrap()).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let created: ProductResponse = serde_json::from_value(created).unwrap();
+    let uri = format!("/api/product/{}", created.id);
+
+    let (status, updated) = send_product_json_body(&app, "PUT", &uri, json!({ "price": 12.5 }).to_string()).await;
+    assert_eq!(status, StatusCode::OK);
+    let updated: ProductResponse = serde_json::from_value(updated).unwrap();
+    assert_eq!(updated.price, Decimal::new(1250, 2));
+    assert_eq!(updated.name, created.name);
+    assert_eq!(updated.sku.as_deref(), Some(sku.as_str()));
+    assert_eq!(updated.description, created.description);
+    assert_eq!(updated.stock, created.stock);
+    assert_eq!(updated.image_url, created.image_url);
+    assert_eq!(updated.low_stock_threshold, created.low_stock_threshold);
+    assert_eq!(updated.track_inventory, created.track_inventory);
+    assert!(updated.updated_at.is_some());
+
+    // A second partial update keeps the first one
+    let (status, updated) = send_product_json_body(&app, "PUT", &uri, json!({ "stock": 7, "track_inventory": false }).to_string()).await;
+    assert_eq!(status, StatusCode::OK);
+    let updated: ProductResponse = serde_json::from_value(updated).unwrap();
+    assert_eq!(updated.stock, 7);
+    assert!(!updated.track_inventory);
+    assert_eq!(updated.price, Decimal::new(1250, 2));
+    assert_eq!(updated.description, created.description);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn test_update_clears_nullable_fields_sent_as_null() {
+    let app = setup_test_app().await;
+
+    let (status, created) = send_product_json_body(
+        &app,
+        "POST",
+        "/api/product",
+        serde_json::to_string(&create_test_product_dto()).unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let created: ProductResponse = serde_json::from_value(created).unwrap();
+    let uri = format!("/api/product/{}", created.id);
+
+    // null clears, a value sets, and a missing field is kept
+    let (status, updated) = send_product_json_body(
+        &app,
+        "PUT",
+        &uri,
+        json!({ "image_url": null, "low_stock_threshold": null, "description": "Now with a new label" }).to_string(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let updated: ProductResponse = serde_json::from_value(updated).unwrap();
+    assert_eq!(updated.image_url, None);
+    assert_eq!(updated.low_stock_threshold, None);
+    assert_eq!(updated.description.as_deref(), Some("Now with a new label"));
+    assert_eq!(updated.name, created.name);
+    assert_eq!(updated.price, created.price);
+
+    // Omitting them again leaves them cleared, and they can be set back
+    let (status, updated) = send_product_json_body(&app, "PUT", &uri, json!({ "stock": 3 }).to_string()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["image_url"], Value::Null);
+    assert_eq!(updated["description"], "Now with a new label");
+
+    let (status, updated) = send_product_json_body(
+        &app,
+        "PUT",
+        &uri,
+        json!({ "image_url": "https://example.com/back.jpg", "low_stock_threshold": 2 }).to_string(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["image_url"], "https://example.com/back.jpg");
+    assert_eq!(updated["low_stock_threshold"], 2);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn test_update_missing_product_not_found() {
+    let app = setup_test_app().await;
+
+    let (status, _) = send_product_json_body(
+        &app,
+        "PUT",
+        &format!("/api/product/{}", Uuid::new_v4()),
+        json!({ "name": "Nobody" }).to_string(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
 #[tokio::test]
 #[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
 async fn test_delete_product_success() {
@@ -360,6 +856,71 @@ async fn test_delete_product_success() {
     assert_eq!(get_response.status(), StatusCode::NOT_FOUND);
 }
 
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn test_duplicate_product() {
+    let app = setup_test_app().await;
+
+    let mut source = create_test_product_dto();
+    source.sku = Some(format!("SKU-{}", Uuid::new_v4().simple()));
+    source.reorder_target = Some(40);
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/product")
+                .header("content-type", "application/json")
+                .header("authorization", format!("Bearer {}", create_admin_token()))
+                .body(Body::from(serde_json::to_string(&source).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let source: ProductResponse = serde_json::from_slice(&body).unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/product/{}/duplicate", source.id))
+                .header("authorization", format!("Bearer {}", create_admin_token()))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let copy: ProductResponse = serde_json::from_slice(&body).unwrap();
+
+    assert_ne!(copy.id, source.id);
+    assert_eq!(copy.name, format!("{} (copy)", source.name));
+    assert_eq!(copy.description, source.description);
+    assert_eq!(copy.price, source.price);
+    assert_eq!(copy.image_url, source.image_url);
+    assert_eq!(copy.low_stock_threshold, source.low_stock_threshold);
+    assert_eq!(copy.reorder_target, Some(40));
+    assert_eq!(copy.stock, 0);
+    assert_eq!(copy.sku, None);
+
+    let status = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/product/{}/duplicate", Uuid::new_v4()))
+                .header("authorization", format!("Bearer {}", create_admin_token()))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+        .status();
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
 #[tokio::test]
 #[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
 async fn test_health_endpoint() {
@@ -0,0 +1,60 @@
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use hemp_backend::logging::{subscriber, LogFormat};
+use tracing_subscriber::EnvFilter;
+
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn log_in_request(format: LogFormat) -> String {
+    let captured = Captured::default();
+    let writer = captured.clone();
+    let subscriber = subscriber(format, EnvFilter::new("info"), move || writer.clone());
+
+    tracing::subscriber::with_default(subscriber, || {
+        let span = tracing::info_span!("request", request_id = "req-123");
+        let _entered = span.enter();
+        tracing::info!(order_id = 7, "order placed");
+    });
+
+    let output = captured.0.lock().unwrap().clone();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn pretty_logs_are_plain_text() {
+    let output = log_in_request(LogFormat::Pretty);
+    assert!(output.contains("order placed"));
+    assert!(output.contains("request_id"));
+    assert!(serde_json::from_str::<serde_json::Value>(output.trim()).is_err());
+}
+
+#[test]
+fn json_logs_carry_timestamp_and_request_id() {
+    let output = log_in_request(LogFormat::Json);
+    let line: serde_json::Value = serde_json::from_str(output.trim()).expect("one JSON object per line");
+
+    assert!(line["timestamp"].is_string());
+    assert_eq!(line["level"], "INFO");
+    assert_eq!(line["fields"]["message"], "order placed");
+    assert_eq!(line["span"]["request_id"], "req-123");
+}
+
+#[test]
+fn log_format_parses_known_values_only() {
+    assert_eq!("json".parse::<LogFormat>().unwrap(), LogFormat::Json);
+    assert_eq!("Pretty".parse::<LogFormat>().unwrap(), LogFormat::Pretty);
+    assert!("xml".parse::<LogFormat>().is_err());
+}
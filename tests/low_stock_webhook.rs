@@ -0,0 +1,115 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::{routing::post, Json, Router};
+use axum_test::TestServer;
+use hemp_backend::state::{AppState, InventoryConfig, LowStockWebhookConfig};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+
+/// Serves a webhook receiver on a free local port, forwarding every JSON body it gets.
+async fn spawn_receiver() -> (String, mpsc::UnboundedReceiver<Value>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let app = Router::new().route(
+        "/hook",
+        post(move |Json(body): Json<Value>| {
+            let tx = tx.clone();
+            async move {
+                let _ = tx.send(body);
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{}/hook", addr), rx)
+}
+
+/// A test server whose low-stock alerts go to `url`.
+async fn server_posting_to(url: &str, inventory_config: InventoryConfig) -> (TestServer, AppState) {
+    let mut state = common::test_state_db().await.expect("test database unavailable");
+    state.inventory_config = std::sync::Arc::new(inventory_config);
+    state.low_stock_webhook_config = std::sync::Arc::new(LowStockWebhookConfig {
+        url: Some(url.to_string()),
+        ..LowStockWebhookConfig::default()
+    });
+    let server = TestServer::new(common::app_with_state(state.clone()).await).unwrap();
+    (server, state)
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn stock_adjustment_below_threshold_posts_one_alert() {
+    let (url, mut received) = spawn_receiver().await;
+    let (server, state) = server_posting_to(&url, InventoryConfig::default()).await;
+    let product_id = common::seed_product(&state.db, "Webhook Balm", "12.00", 5).await;
+    sqlx::query("UPDATE products SET track_inventory = true, low_stock_threshold = 3 WHERE id = $1")
+        .bind(product_id)
+        .execute(&state.db)
+        .await
+        .unwrap();
+    let stock_url = format!("/api/inventory/products/{}/stock", product_id);
+    let admin = format!("Bearer {}", common::jwt_admin());
+
+    // Still above the threshold: nothing is sent
+    server
+        .put(&stock_url)
+        .add_header("Authorization", admin.clone())
+        .json(&json!({"quantity": -1, "mode": "adjust"}))
+        .await
+        .assert_status_ok();
+
+    server
+        .put(&stock_url)
+        .add_header("Authorization", admin.clone())
+        .json(&json!({"quantity": -2, "mode": "adjust"}))
+        .await
+        .assert_status_ok();
+
+    let payload = tokio::time::timeout(Duration::from_secs(5), received.recv())
+        .await
+        .expect("no low stock webhook received")
+        .unwrap();
+    assert_eq!(payload["event"], "low_stock");
+    assert_eq!(payload["alert"]["product_id"], product_id.to_string());
+    assert_eq!(payload["alert"]["product_name"], "Webhook Balm");
+    assert_eq!(payload["alert"]["current_stock"], 2);
+    assert_eq!(payload["alert"]["threshold"], 3);
+    assert_eq!(payload["alert"]["is_critical"], false);
+
+    // A further dip inside the debounce window isn't reported again
+    server
+        .put(&stock_url)
+        .add_header("Authorization", admin)
+        .json(&json!({"quantity": -1, "mode": "adjust"}))
+        .await
+        .assert_status_ok();
+    assert!(tokio::time::timeout(Duration::from_secs(1), received.recv()).await.is_err());
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn products_without_a_threshold_are_held_to_the_default() {
+    let (url, mut received) = spawn_receiver().await;
+    let (server, state) = server_posting_to(&url, InventoryConfig { default_low_stock_threshold: Some(4) }).await;
+    let product_id = common::seed_product(&state.db, "Default Balm", "12.00", 6).await;
+    sqlx::query("UPDATE products SET low_stock_threshold = NULL WHERE id = $1")
+        .bind(product_id)
+        .execute(&state.db)
+        .await
+        .unwrap();
+    server
+        .put(&format!("/api/inventory/products/{}/stock", product_id))
+        .add_header("Authorization", format!("Bearer {}", common::jwt_admin()))
+        .json(&json!({"quantity": -2, "mode": "adjust"}))
+        .await
+        .assert_status_ok();
+
+    let payload = tokio::time::timeout(Duration::from_secs(5), received.recv())
+        .await
+        .expect("no low stock webhook received for the default threshold")
+        .unwrap();
+    assert_eq!(payload["alert"]["product_id"], product_id.to_string());
+    assert_eq!(payload["alert"]["threshold"], 4);
+}
@@ -0,0 +1,66 @@
+use std::sync::{Arc, Mutex};
+
+use axum::{http::HeaderMap, routing::post, Json, Router};
+use hemp_backend::services::mailer;
+use hemp_backend::state::MailConfig;
+use serde_json::{json, Value};
+
+/// Serves a fake email API that records the authorization header and body of every email.
+async fn spawn_mail_api() -> (String, Arc<Mutex<Vec<(String, Value)>>>) {
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    let seen = sent.clone();
+    let app = Router::new().route(
+        "/emails",
+        post(move |headers: HeaderMap, Json(body): Json<Value>| {
+            let seen = seen.clone();
+            async move {
+                let auth = headers["authorization"].to_str().unwrap().to_string();
+                seen.lock().unwrap().push((auth, body));
+                Json(json!({"id": "email_123"}))
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{}", addr), sent)
+}
+
+fn config(api_base: String) -> MailConfig {
+    MailConfig {
+        api_key: Some("re_test_key".to_string()),
+        api_base,
+        from: "Shop <shop@example.com>".to_string(),
+        app_url: "https://shop.example.com".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn account_emails_carry_links_to_the_app() {
+    let (base, sent) = spawn_mail_api().await;
+    let mailer = mailer::from_config(reqwest::Client::new(), &config(base));
+
+    mailer.send_email_verification("buyer@example.com", "verifytoken").await;
+    mailer.send_password_reset("buyer@example.com", "resettoken").await;
+
+    let sent = sent.lock().unwrap();
+    assert_eq!(sent.len(), 2);
+    for (auth, body) in sent.iter() {
+        assert_eq!(auth, "Bearer re_test_key");
+        assert_eq!(body["from"], "Shop <shop@example.com>");
+        assert_eq!(body["to"], json!(["buyer@example.com"]));
+    }
+    assert!(sent[0].1["text"].as_str().unwrap().contains("https://shop.example.com/verify-email?token=verifytoken"));
+    assert!(sent[1].1["text"].as_str().unwrap().contains("https://shop.example.com/reset-password?token=resettoken"));
+}
+
+#[tokio::test]
+async fn no_api_key_means_no_mail_is_sent() {
+    let (base, sent) = spawn_mail_api().await;
+    let mailer = mailer::from_config(reqwest::Client::new(), &MailConfig { api_key: None, ..config(base) });
+
+    mailer.send_password_reset("buyer@example.com", "resettoken").await;
+
+    assert!(sent.lock().unwrap().is_empty());
+    assert!(!format!("{:?}", mailer).contains("re_test_key"));
+}
@@ -0,0 +1,29 @@
+mod common;
+
+use axum_test::TestServer;
+use hemp_backend::{middleware::metrics, routes};
+
+#[tokio::test]
+async fn metrics_count_requests_by_route() {
+    let state = common::test_state_lazy().await;
+    let app = common::app_with_state(state.clone())
+        .await
+        .merge(routes::metrics::build_route(metrics::install_recorder()).with_state(state))
+        .layer(axum::middleware::from_fn(metrics::track_metrics));
+    let server = TestServer::new(app).unwrap();
+
+    server.get("/health").await.assert_status_ok();
+
+    let res = server.get("/metrics").await;
+    res.assert_status_ok();
+    let body = res.text();
+    assert!(
+        body.lines().any(|line| line.starts_with("http_requests_total{")
+            && line.contains("path=\"/health\"")
+            && line.contains("status=\"200\"")),
+        "no request counter for /health in:\n{}",
+        body
+    );
+    assert!(body.contains("http_request_duration_seconds_bucket"));
+    assert!(body.contains("db_pool_connections"));
+}
@@ -0,0 +1,123 @@
+use hemp_backend::openapi::ApiDoc;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use utoipa::OpenApi;
+
+fn openapi_json() -> serde_json::Value {
+    serde_json::to_value(ApiDoc::openapi()).expect("OpenAPI document should serialize")
+}
+
+#[test]
+fn bearer_auth_is_an_http_bearer_scheme() {
+    let doc = openapi_json();
+    let scheme = &doc["components"]["securitySchemes"]["bearer_auth"];
+
+    // Swagger UI then prefixes the pasted token with `Bearer `, as `AuthUser` expects
+    assert_eq!(scheme["type"], "http");
+    assert_eq!(scheme["scheme"], "bearer");
+    assert_eq!(scheme["bearerFormat"], "JWT");
+}
+
+const METHODS: [&str; 5] = ["get", "post", "put", "patch", "delete"];
+
+/// The string literal at the start of `s` (after whitespace), without its quotes.
+fn leading_literal(s: &str) -> Option<&str> {
+    let s = s.trim_start().strip_prefix('"')?;
+    s.split('"').next()
+}
+
+/// The text of a call's arguments, from just after its opening parenthesis to the matching one.
+fn call_arguments(s: &str) -> &str {
+    let mut depth = 1;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return &s[..i];
+                }
+            }
+            _ => {}
+        }
+    }
+    s
+}
+
+/// The HTTP methods a method router such as `get(a).post(b)` registers.
+fn methods_in(expr: &str) -> Vec<&'static str> {
+    METHODS
+        .into_iter()
+        .filter(|method| {
+            expr.match_indices(&format!("{}(", method)).any(|(i, _)| {
+                !expr[..i].ends_with(|c: char| c.is_alphanumeric() || c == '_')
+            })
+        })
+        .collect()
+}
+
+/// Every `(method, path)` registered by the routers under `src/routes`, read from their
+/// source so that a newly added route shows up here without being listed by hand.
+fn router_operations() -> BTreeSet<(String, String)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/routes");
+    let mod_rs = fs::read_to_string(dir.join("mod.rs")).unwrap();
+    let mut operations = BTreeSet::new();
+
+    for entry in fs::read_dir(&dir).unwrap() {
+        let file = entry.unwrap().path();
+        let module = file.file_stem().unwrap().to_str().unwrap().to_string();
+        if module == "mod" {
+            continue;
+        }
+
+        // Modules nested in `routes::build_route` sit under `/api/<prefix>`; the rest are
+        // mounted at the root
+        let prefix = mod_rs
+            .split(".nest(")
+            .skip(1)
+            .map(call_arguments)
+            .find(|args| args.contains(&format!("{}::build_route", module)))
+            .and_then(leading_literal)
+            .map(|nest| format!("/api{}", nest))
+            .unwrap_or_default();
+
+        let source = fs::read_to_string(&file).unwrap();
+        for route in source.split(".route(").skip(1).map(call_arguments) {
+            let path = leading_literal(route).expect("route path should be a string literal");
+            let full = format!("{}{}", prefix, path);
+            let full = if full.len() > 1 { full.trim_end_matches('/').to_string() } else { full };
+            for method in methods_in(route) {
+                operations.insert((method.to_string(), full.clone()));
+            }
+        }
+    }
+
+    operations
+}
+
+fn documented_operations() -> BTreeSet<(String, String)> {
+    let doc = openapi_json();
+    let mut operations = BTreeSet::new();
+    for (path, item) in doc["paths"].as_object().expect("paths should be an object") {
+        for method in METHODS {
+            if item.get(method).is_some() {
+                operations.insert((method.to_string(), path.clone()));
+            }
+        }
+    }
+    operations
+}
+
+#[test]
+fn every_route_is_documented() {
+    let routed = router_operations();
+    let documented = documented_operations();
+    assert!(routed.len() > 50, "route parsing found only {:?}", routed);
+
+    let undocumented: Vec<_> = routed.difference(&documented).collect();
+    assert!(undocumented.is_empty(), "routes missing from ApiDoc: {:?}", undocumented);
+
+    let stale: Vec<_> = documented.difference(&routed).collect();
+    assert!(stale.is_empty(), "documented operations with no route: {:?}", stale);
+}
@@ -1,6 +1,16 @@
+mod common;
+
+use chrono::Utc;
 use hemp_backend::model::order::OrderStatus;
+use hemp_backend::model::promotion::{Promotion, PromotionStacking};
+use hemp_backend::repository::OrderRepository;
+use hemp_backend::services::order_service::{OrderPricing, OrderService};
+use hemp_backend::services::promotion_service::apply_promotions;
+use hemp_backend::state::OrderConfig;
+use rust_decimal::Decimal;
 use hemp_backend::dtos::order::{CreateOrderRequest, CreateOrderResponse, OrderDetailsResponse};
 use serde_json;
+use std::str::FromStr;
 use uuid::Uuid;
 
 /// Unit tests for order-related functionality
@@ -19,16 +29,39 @@ fn test_order_status_string_conversion() {
     assert_eq!(OrderStatus::Refunded.to_string(), "refunded");
 }
 
+#[test]
+fn order_status_parses_every_known_status_and_nothing_else() {
+    for status in [
+        OrderStatus::Cart,
+        OrderStatus::PendingPayment,
+        OrderStatus::PaymentProcessing,
+        OrderStatus::Paid,
+        OrderStatus::Processing,
+        OrderStatus::Shipped,
+        OrderStatus::Delivered,
+        OrderStatus::Cancelled,
+        OrderStatus::Refunded,
+    ] {
+        assert_eq!(status.to_string().parse::<OrderStatus>(), Ok(status));
+        assert_eq!(serde_json::to_value(status).unwrap(), status.to_string());
+    }
+
+    assert!("pending".parse::<OrderStatus>().is_err());
+    assert!("Paid".parse::<OrderStatus>().is_err());
+    assert!(OrderStatus::try_from(String::new()).is_err());
+}
+
 #[test]
 fn test_create_order_request_serialization() {
     let request = CreateOrderRequest {
         notes: Some("Test order with special instructions".to_string()),
+        coupon_code: None,
     };
     
     let json = serde_json::to_string(&request).unwrap();
     assert!(json.contains("Test order with special instructions"));
     
-    let empty_request = CreateOrderRequest { notes: None };
+    let empty_request = CreateOrderRequest { notes: None, coupon_code: None };
     let empty_json = serde_json::to_string(&empty_request).unwrap();
     assert!(empty_json.contains("null") || !empty_json.contains("notes"));
 }
@@ -41,3 +74,1496 @@ fn test_uuid_generation() {
     assert_ne!(order_id, user_id);
     assert_eq!(order_id.to_string().len(), 36); // Standard UUID string length
 }
+
+fn promotion(name: &str, percent: i64, min_order_total: i64) -> Promotion {
+    Promotion {
+        id: Uuid::new_v4(),
+        name: name.to_string(),
+        description: None,
+        discount_percent: Decimal::new(percent, 0),
+        min_order_total: Decimal::new(min_order_total, 0),
+        starts_at: Utc::now(),
+        ends_at: None,
+        active: true,
+        created_at: Utc::now(),
+    }
+}
+
+#[test]
+fn test_promotion_applies_over_threshold() {
+    let promotions = vec![promotion("15% off over $100", 15, 100)];
+
+    let outcome = apply_promotions(Decimal::new(12500, 2), &promotions, PromotionStacking::BestOnly);
+
+    assert_eq!(outcome.applied.len(), 1);
+    assert_eq!(outcome.applied[0].name, "15% off over $100");
+    assert_eq!(outcome.discount, Decimal::new(1875, 2));
+}
+
+#[test]
+fn test_promotion_skipped_under_threshold() {
+    let promotions = vec![promotion("15% off over $100", 15, 100)];
+
+    let outcome = apply_promotions(Decimal::new(9999, 2), &promotions, PromotionStacking::BestOnly);
+
+    assert!(outcome.applied.is_empty());
+    assert_eq!(outcome.discount, Decimal::ZERO);
+}
+
+#[test]
+fn test_promotion_stacking_policy() {
+    let promotions = vec![
+        promotion("10% off everything", 10, 0),
+        promotion("15% off over $100", 15, 100),
+    ];
+    let subtotal = Decimal::new(20000, 2);
+
+    let best = apply_promotions(subtotal, &promotions, PromotionStacking::BestOnly);
+    assert_eq!(best.applied.len(), 1);
+    assert_eq!(best.discount, Decimal::new(3000, 2));
+
+    let stacked = apply_promotions(subtotal, &promotions, PromotionStacking::Stack);
+    assert_eq!(stacked.applied.len(), 2);
+    assert_eq!(stacked.discount, Decimal::new(5000, 2));
+}
+
+#[test]
+fn promotion_stacking_parses_its_setting_names() {
+    assert_eq!("best".parse::<PromotionStacking>(), Ok(PromotionStacking::BestOnly));
+    assert_eq!("stack".parse::<PromotionStacking>(), Ok(PromotionStacking::Stack));
+    assert!("all".parse::<PromotionStacking>().is_err());
+    assert_eq!(PromotionStacking::default(), PromotionStacking::BestOnly);
+}
+
+#[test]
+fn order_totals_add_tax_and_flat_shipping() {
+    let pricing = OrderPricing {
+        tax_rate_percent: Decimal::new(825, 2),
+        shipping_fee: Decimal::new(499, 2),
+        free_shipping_threshold: None,
+    };
+
+    let totals = pricing.totals(Decimal::new(4000, 2), Decimal::ZERO);
+    assert_eq!(totals.tax, Decimal::new(330, 2));
+    assert_eq!(totals.shipping, Decimal::new(499, 2));
+    assert_eq!(totals.total, Decimal::new(4829, 2));
+
+    // Tax is on the discounted subtotal and rounded to the cent
+    let totals = pricing.totals(Decimal::new(12999, 2), Decimal::new(1950, 2));
+    assert_eq!(totals.subtotal, Decimal::new(12999, 2));
+    assert_eq!(totals.tax, Decimal::new(912, 2));
+    assert_eq!(totals.total, Decimal::new(12999 - 1950 + 912 + 499, 2));
+}
+
+#[test]
+fn order_totals_ship_free_from_threshold() {
+    let pricing = OrderPricing {
+        tax_rate_percent: Decimal::new(10, 0),
+        shipping_fee: Decimal::new(599, 2),
+        free_shipping_threshold: Some(Decimal::new(50, 0)),
+    };
+
+    let below = pricing.totals(Decimal::new(4999, 2), Decimal::ZERO);
+    assert_eq!(below.shipping, Decimal::new(599, 2));
+    assert_eq!(below.total, Decimal::new(4999 + 500 + 599, 2));
+
+    let at = pricing.totals(Decimal::new(5000, 2), Decimal::ZERO);
+    assert_eq!(at.shipping, Decimal::ZERO);
+    assert_eq!(at.total, Decimal::new(5500, 2));
+
+    // A promotion that drops the order under the threshold brings shipping back
+    let discounted = pricing.totals(Decimal::new(5500, 2), Decimal::new(825, 2));
+    assert_eq!(discounted.shipping, Decimal::new(599, 2));
+}
+
+#[test]
+fn order_totals_default_to_no_tax_or_shipping() {
+    let totals = OrderPricing::default().totals(Decimal::new(2500, 2), Decimal::new(250, 2));
+    assert_eq!(totals.tax, Decimal::ZERO);
+    assert_eq!(totals.shipping, Decimal::ZERO);
+    assert_eq!(totals.total, Decimal::new(2250, 2));
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn deleted_product_hidden_from_listing_but_kept_in_order_history() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let admin = format!("Bearer {}", common::jwt_admin());
+
+    let user_id = common::seed_user(pool, "client").await;
+    let product_id = common::seed_product(pool, "Discontinued Tincture", "15.00", 5).await;
+    let order_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO orders (id, user_id, total, status) VALUES ($1, $2, 15.00, 'paid')")
+        .bind(order_id)
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO order_items (id, order_id, product_id, quantity, price) VALUES ($1, $2, $3, 1, 15.00)")
+        .bind(Uuid::new_v4())
+        .bind(order_id)
+        .bind(product_id)
+        .execute(pool)
+        .await
+        .unwrap();
+
+    server
+        .delete(&format!("/api/product/{}", product_id))
+        .add_header("Authorization", admin.clone())
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+
+    server
+        .get(&format!("/api/product/{}", product_id))
+        .await
+        .assert_status_not_found();
+    let listed = server
+        .get("/api/product")
+        .add_query_param("limit", 100)
+        .await
+        .json::<Vec<serde_json::Value>>();
+    assert!(listed.iter().all(|p| p["id"] != product_id.to_string()));
+
+    let res = server
+        .get(&format!("/api/order/{}", order_id))
+        .add_header("Authorization", format!("Bearer {}", common::jwt_for_user(user_id, "user")))
+        .await;
+    res.assert_status_ok();
+    let order = res.json::<serde_json::Value>();
+    assert_eq!(order["items"][0]["product_name"], "Discontinued Tincture");
+
+    // Restoring brings it back into the catalog
+    server
+        .post(&format!("/api/product/{}/restore", product_id))
+        .add_header("Authorization", admin)
+        .await
+        .assert_status_ok();
+    server
+        .get(&format!("/api/product/{}", product_id))
+        .await
+        .assert_status_ok();
+}
+
+async fn seed_order(pool: &sqlx::PgPool, user_id: Uuid, product_id: Uuid, quantity: i32, status: &str) -> Uuid {
+    let order_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO orders (id, user_id, total, status) VALUES ($1, $2, 20.00, $3)")
+        .bind(order_id)
+        .bind(user_id)
+        .bind(status)
+        .execute(pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO order_items (id, order_id, product_id, quantity, price) VALUES ($1, $2, $3, $4, 10.00)")
+        .bind(Uuid::new_v4())
+        .bind(order_id)
+        .bind(product_id)
+        .bind(quantity)
+        .execute(pool)
+        .await
+        .unwrap();
+    order_id
+}
+
+/// Holds `quantity` of the product for the order's payment window, as a payment intent does.
+async fn hold_for_order(pool: &sqlx::PgPool, order_id: Uuid, cart_id: Uuid, product_id: Uuid, quantity: i32) {
+    use hemp_backend::repository::StockRepository;
+
+    let mut tx = pool.begin().await.unwrap();
+    StockRepository::new(pool.clone())
+        .reserve_for_order_in(&mut tx, order_id, cart_id, &[(product_id, quantity)], 30)
+        .await
+        .unwrap()
+        .expect("stock should be reservable");
+    tx.commit().await.unwrap();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn user_can_cancel_order_awaiting_payment() {
+    use hemp_backend::repository::StockRepository;
+
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let user_id = common::seed_user(pool, "client").await;
+    let product_id = common::seed_product(pool, "Cancelled Balm", "10.00", 5).await;
+    let cart_id = common::seed_cart(pool, user_id).await;
+    let order_id = seed_order(pool, user_id, product_id, 2, "payment_processing").await;
+
+    let stock_repo = StockRepository::new(pool.clone());
+    hold_for_order(pool, order_id, cart_id, product_id, 2).await;
+    assert_eq!(stock_repo.get_available_stock(product_id).await.unwrap(), Some(3));
+
+    let res = server
+        .post(&format!("/api/order/{}/cancel", order_id))
+        .add_header("Authorization", format!("Bearer {}", common::jwt_for_user(user_id, "client")))
+        .await;
+    res.assert_status_ok();
+    assert_eq!(res.json::<serde_json::Value>()["status"], "cancelled");
+
+    // The held units are available again
+    assert_eq!(stock_repo.get_available_stock(product_id).await.unwrap(), Some(5));
+
+    // Cancelling twice is a conflict
+    server
+        .post(&format!("/api/order/{}/cancel", order_id))
+        .add_header("Authorization", format!("Bearer {}", common::jwt_for_user(user_id, "client")))
+        .await
+        .assert_status(axum::http::StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn shipped_or_foreign_orders_cannot_be_cancelled() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let user_id = common::seed_user(pool, "client").await;
+    let product_id = common::seed_product(pool, "Shipped Oil", "10.00", 5).await;
+    let token = format!("Bearer {}", common::jwt_for_user(user_id, "client"));
+
+    for status in ["shipped", "delivered"] {
+        let order_id = seed_order(pool, user_id, product_id, 1, status).await;
+        server
+            .post(&format!("/api/order/{}/cancel", order_id))
+            .add_header("Authorization", token.clone())
+            .await
+            .assert_status(axum::http::StatusCode::CONFLICT);
+    }
+
+    let other_user = common::seed_user(pool, "client").await;
+    let order_id = seed_order(pool, other_user, product_id, 1, "pending_payment").await;
+    server
+        .post(&format!("/api/order/{}/cancel", order_id))
+        .add_header("Authorization", token.clone())
+        .await
+        .assert_status_forbidden();
+
+    server
+        .post(&format!("/api/order/{}/cancel", Uuid::new_v4()))
+        .add_header("Authorization", token)
+        .await
+        .assert_status_not_found();
+}
+
+#[test]
+fn only_paid_orders_return_stock() {
+    use hemp_backend::services::order_service::returns_stock;
+
+    assert!(returns_stock(OrderStatus::Paid, OrderStatus::Cancelled));
+    assert!(returns_stock(OrderStatus::Processing, OrderStatus::Refunded));
+    assert!(!returns_stock(OrderStatus::PendingPayment, OrderStatus::Cancelled));
+    assert!(!returns_stock(OrderStatus::Cancelled, OrderStatus::Refunded));
+    assert!(!returns_stock(OrderStatus::Paid, OrderStatus::Shipped));
+}
+
+#[test]
+fn leaving_the_payment_window_releases_reservations() {
+    use hemp_backend::services::order_service::releases_reservations;
+
+    assert!(releases_reservations(OrderStatus::PendingPayment, OrderStatus::Cancelled));
+    assert!(releases_reservations(OrderStatus::PaymentProcessing, OrderStatus::Cancelled));
+    assert!(!releases_reservations(OrderStatus::PaymentProcessing, OrderStatus::PendingPayment));
+    assert!(!releases_reservations(OrderStatus::Paid, OrderStatus::Cancelled));
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn admin_cancelling_an_unpaid_order_releases_its_reservations() {
+    use hemp_backend::repository::StockRepository;
+
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let admin = format!("Bearer {}", common::jwt_admin());
+    let user_id = common::seed_user(pool, "client").await;
+    let product_id = common::seed_product(pool, "Held Tincture", "10.00", 5).await;
+    let cart_id = common::seed_cart(pool, user_id).await;
+    let single = seed_order(pool, user_id, product_id, 2, "payment_processing").await;
+    let bulk = seed_order(pool, user_id, product_id, 1, "pending_payment").await;
+
+    let stock_repo = StockRepository::new(pool.clone());
+    for (order_id, quantity) in [(single, 2), (bulk, 1)] {
+        hold_for_order(pool, order_id, cart_id, product_id, quantity).await;
+    }
+    assert_eq!(stock_repo.get_available_stock(product_id).await.unwrap(), Some(2));
+
+    server
+        .put(&format!("/api/order/{}/status", single))
+        .add_header("Authorization", admin.clone())
+        .json(&serde_json::json!({"status": "cancelled"}))
+        .await
+        .assert_status_ok();
+    assert_eq!(stock_repo.get_available_stock(product_id).await.unwrap(), Some(4));
+
+    server
+        .post("/api/order/bulk-status")
+        .add_header("Authorization", admin)
+        .json(&serde_json::json!({"order_ids": [bulk], "status": "cancelled"}))
+        .await
+        .assert_status_ok();
+    assert_eq!(stock_repo.get_available_stock(product_id).await.unwrap(), Some(5));
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn refunding_a_paid_order_restocks_exactly_once() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let admin = format!("Bearer {}", common::jwt_admin());
+    let user_id = common::seed_user(pool, "client").await;
+    // Stock already reflects the 2 units sold when the order was paid
+    let product_id = common::seed_product(pool, "Restocked Oil", "10.00", 3).await;
+    let order_id = seed_order(pool, user_id, product_id, 2, "paid").await;
+    let stock = || async {
+        sqlx::query_scalar::<_, i32>("SELECT stock FROM products WHERE id = $1")
+            .bind(product_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    };
+
+    // Cancelling would keep the customer's money, so a paid order has to be refunded
+    let res = server
+        .put(&format!("/api/order/{}/status", order_id))
+        .add_header("Authorization", admin.clone())
+        .json(&serde_json::json!({"status": "cancelled"}))
+        .await;
+    res.assert_status_bad_request();
+    assert!(res.json::<serde_json::Value>()["details"].as_str().unwrap().contains("refund"));
+    assert_eq!(stock().await, 3);
+
+    server
+        .put(&format!("/api/order/{}/status", order_id))
+        .add_header("Authorization", admin.clone())
+        .json(&serde_json::json!({"status": "refunded"}))
+        .await
+        .assert_status_ok();
+    assert_eq!(stock().await, 5);
+
+    let logged: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM inventory_logs WHERE reference_id = $1 AND change_type = 'stock_in'",
+    )
+    .bind(order_id)
+    .fetch_one(pool)
+    .await
+    .unwrap();
+    assert_eq!(logged, 1);
+
+    // Even if the order is pushed through a second refunding transition, stock comes back only once
+    sqlx::query("UPDATE orders SET status = 'paid' WHERE id = $1")
+        .bind(order_id)
+        .execute(pool)
+        .await
+        .unwrap();
+    server
+        .put(&format!("/api/order/{}/status", order_id))
+        .add_header("Authorization", admin)
+        .json(&serde_json::json!({"status": "refunded"}))
+        .await
+        .assert_status_ok();
+    assert_eq!(stock().await, 5);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn status_update_conflicts_with_a_payment_landing_meanwhile() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let user_id = common::seed_user(pool, "client").await;
+    let product_id = common::seed_product(pool, "Contested Oil", "10.00", 3).await;
+    let order_id = seed_order(pool, user_id, product_id, 2, "pending_payment").await;
+
+    // Hold the order while the cancel is checked, then let a payment commit first
+    let mut tx = pool.begin().await.unwrap();
+    sqlx::query("SELECT id FROM orders WHERE id = $1 FOR UPDATE")
+        .bind(order_id)
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+    let cancel = server
+        .put(&format!("/api/order/{}/status", order_id))
+        .add_header("Authorization", format!("Bearer {}", common::jwt_admin()))
+        .json(&serde_json::json!({"status": "cancelled"}));
+    let pay = async {
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+        sqlx::query("UPDATE orders SET status = 'paid' WHERE id = $1")
+            .bind(order_id)
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+    };
+    let (res, _) = tokio::join!(cancel, pay);
+    res.assert_status(axum::http::StatusCode::CONFLICT);
+
+    let status: String = sqlx::query_scalar("SELECT status FROM orders WHERE id = $1")
+        .bind(order_id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "paid");
+}
+
+#[test]
+fn test_order_status_transitions() {
+    use OrderStatus::*;
+
+    assert!(Paid.can_transition_to(&Processing));
+    assert!(Processing.can_transition_to(&Shipped));
+    assert!(Shipped.can_transition_to(&Delivered));
+    assert!(PendingPayment.can_transition_to(&Cancelled));
+    assert!(PaymentProcessing.can_transition_to(&Cancelled));
+    // Paid orders are refunded, not cancelled
+    assert!(!Paid.can_transition_to(&Cancelled));
+    assert!(!Shipped.can_transition_to(&Cancelled));
+    assert!(!Delivered.can_transition_to(&Cancelled));
+    assert!(Shipped.can_transition_to(&Refunded));
+    assert!(!Delivered.can_transition_to(&Cart));
+    assert!(!Cancelled.can_transition_to(&Paid));
+    assert!(!Paid.can_transition_to(&Delivered));
+    // Only a payment takes the stock that makes an order paid
+    assert!(!PendingPayment.can_transition_to(&Paid));
+    assert!(!PaymentProcessing.can_transition_to(&Paid));
+    // Only a payment attempt opens or closes the payment window
+    assert!(!PendingPayment.can_transition_to(&PaymentProcessing));
+    assert!(!PaymentProcessing.can_transition_to(&PendingPayment));
+    assert!("shipped".parse::<OrderStatus>().is_ok());
+    assert!("shiped".parse::<OrderStatus>().is_err());
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn unknown_order_status_is_rejected() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+
+    // Parsed before the order is loaded
+    server
+        .put(&format!("/api/order/{}/status", Uuid::new_v4()))
+        .add_header("Authorization", format!("Bearer {}", common::jwt_admin()))
+        .json(&serde_json::json!({"status": "teleported"}))
+        .await
+        .assert_status_bad_request();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn tracking_details_are_checked_before_the_order_is_loaded() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+    let admin = format!("Bearer {}", common::jwt_admin());
+
+    for body in [
+        serde_json::json!({"status": "processing", "tracking_number": "1Z999"}),
+        serde_json::json!({"status": "shipped", "carrier": "UPS"}),
+        serde_json::json!({"status": "shipped", "tracking_number": "1".repeat(101)}),
+    ] {
+        server
+            .put(&format!("/api/order/{}/status", Uuid::new_v4()))
+            .add_header("Authorization", admin.clone())
+            .json(&body)
+            .await
+            .assert_status_bad_request();
+    }
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn updating_status_of_missing_order_is_not_found() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+
+    let res = server
+        .put(&format!("/api/order/{}/status", Uuid::new_v4()))
+        .add_header("Authorization", format!("Bearer {}", common::jwt_admin()))
+        .json(&serde_json::json!({"status": "shipped"}))
+        .await;
+    res.assert_status_not_found();
+    assert_eq!(res.json::<serde_json::Value>()["code"], "not_found");
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn order_status_follows_lifecycle() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let admin = format!("Bearer {}", common::jwt_admin());
+    let user_id = common::seed_user(pool, "client").await;
+    let product_id = common::seed_product(pool, "Lifecycle Oil", "10.00", 5).await;
+
+    let order_id = seed_order(pool, user_id, product_id, 1, "paid").await;
+    let res = server
+        .put(&format!("/api/order/{}/status", order_id))
+        .add_header("Authorization", admin.clone())
+        .json(&serde_json::json!({"status": "processing"}))
+        .await;
+    res.assert_status_ok();
+    assert_eq!(res.json::<serde_json::Value>()["status"], "processing");
+
+    let delivered = seed_order(pool, user_id, product_id, 1, "delivered").await;
+    for status in ["cart", "cancelled"] {
+        server
+            .put(&format!("/api/order/{}/status", delivered))
+            .add_header("Authorization", admin.clone())
+            .json(&serde_json::json!({"status": status}))
+            .await
+            .assert_status_bad_request();
+    }
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn bulk_status_update_skips_orders_that_cannot_move() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let admin = format!("Bearer {}", common::jwt_admin());
+    let user_id = common::seed_user(pool, "client").await;
+    let product_id = common::seed_product(pool, "Batch Oil", "10.00", 5).await;
+
+    let first = seed_order(pool, user_id, product_id, 1, "processing").await;
+    let second = seed_order(pool, user_id, product_id, 1, "processing").await;
+    let delivered = seed_order(pool, user_id, product_id, 1, "delivered").await;
+    let missing = Uuid::new_v4();
+    let status_of = |order_id: Uuid| async move {
+        sqlx::query_scalar::<_, String>("SELECT status FROM orders WHERE id = $1")
+            .bind(order_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    };
+
+    // All or nothing: one order can't be shipped, so none are
+    server
+        .post("/api/order/bulk-status")
+        .add_header("Authorization", admin.clone())
+        .json(&serde_json::json!({"order_ids": [first, delivered], "status": "shipped", "all_or_nothing": true}))
+        .await
+        .assert_status(axum::http::StatusCode::CONFLICT);
+    assert_eq!(status_of(first).await, "processing");
+
+    let res = server
+        .post("/api/order/bulk-status")
+        .add_header("Authorization", admin.clone())
+        .json(&serde_json::json!({"order_ids": [first, delivered, second, missing], "status": "shipped"}))
+        .await;
+    res.assert_status_ok();
+    let body = res.json::<serde_json::Value>();
+    assert_eq!(body["updated"], 2);
+    assert_eq!(body["failed"], 2);
+    let results = body["results"].as_array().unwrap();
+    let outcome: Vec<(String, bool)> = results
+        .iter()
+        .map(|r| (r["order_id"].as_str().unwrap().to_string(), r["updated"].as_bool().unwrap()))
+        .collect();
+    assert_eq!(outcome, vec![
+        (first.to_string(), true),
+        (delivered.to_string(), false),
+        (second.to_string(), true),
+        (missing.to_string(), false),
+    ]);
+    assert!(results[1]["error"].as_str().unwrap().contains("delivered"));
+
+    assert_eq!(status_of(first).await, "shipped");
+    assert_eq!(status_of(second).await, "shipped");
+    assert_eq!(status_of(delivered).await, "delivered");
+
+    server
+        .post("/api/order/bulk-status")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_for_user(user_id, "client")))
+        .json(&serde_json::json!({"order_ids": [first], "status": "delivered"}))
+        .await
+        .assert_status_forbidden();
+    server
+        .post("/api/order/bulk-status")
+        .add_header("Authorization", admin)
+        .json(&serde_json::json!({"order_ids": [], "status": "shipped"}))
+        .await
+        .assert_status_bad_request();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn shipping_records_tracking_details() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let admin = format!("Bearer {}", common::jwt_admin());
+    let user_id = common::seed_user(pool, "client").await;
+    let product_id = common::seed_product(pool, "Tracked Oil", "10.00", 5).await;
+    let order_id = seed_order(pool, user_id, product_id, 1, "processing").await;
+
+    let res = server
+        .put(&format!("/api/order/{}/status", order_id))
+        .add_header("Authorization", admin.clone())
+        .json(&serde_json::json!({"status": "shipped", "tracking_number": " 1Z999AA10123456784 ", "carrier": "UPS"}))
+        .await;
+    res.assert_status_ok();
+    let body = res.json::<serde_json::Value>();
+    assert_eq!(body["tracking_number"], "1Z999AA10123456784");
+    assert_eq!(body["carrier"], "UPS");
+
+    let details = server
+        .get(&format!("/api/order/{}", order_id))
+        .add_header("Authorization", format!("Bearer {}", common::jwt_for_user(user_id, "client")))
+        .await
+        .json::<serde_json::Value>();
+    assert_eq!(details["tracking_number"], "1Z999AA10123456784");
+    assert_eq!(details["carrier"], "UPS");
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn tracking_is_rejected_unless_shipping() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let admin = format!("Bearer {}", common::jwt_admin());
+    let user_id = common::seed_user(pool, "client").await;
+    let product_id = common::seed_product(pool, "Untracked Oil", "10.00", 5).await;
+
+    // Tracking sent with some other status
+    let processing = seed_order(pool, user_id, product_id, 1, "processing").await;
+    let res = server
+        .put(&format!("/api/order/{}/status", processing))
+        .add_header("Authorization", admin.clone())
+        .json(&serde_json::json!({"status": "delivered", "tracking_number": "1Z999"}))
+        .await;
+    res.assert_status_bad_request();
+
+    // An order that can't ship yet
+    let paid = seed_order(pool, user_id, product_id, 1, "paid").await;
+    server
+        .put(&format!("/api/order/{}/status", paid))
+        .add_header("Authorization", admin.clone())
+        .json(&serde_json::json!({"status": "shipped", "tracking_number": "1Z999"}))
+        .await
+        .assert_status_bad_request();
+
+    let tracked: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orders WHERE id = ANY($1) AND tracking_number IS NOT NULL")
+        .bind(vec![processing, paid])
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    assert_eq!(tracked, 0);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn order_notes_are_saved() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let user_id = common::seed_user(pool, "client").await;
+    let product_id = common::seed_product(pool, "Noted Oil", "10.00", 5).await;
+    let cart_id = common::seed_cart(pool, user_id).await;
+    sqlx::query("INSERT INTO cart_items (id, cart_id, product_id, quantity) VALUES ($1, $2, $3, 1)")
+        .bind(Uuid::new_v4())
+        .bind(cart_id)
+        .bind(product_id)
+        .execute(pool)
+        .await
+        .unwrap();
+    let token = format!("Bearer {}", common::jwt_for_user(user_id, "client"));
+
+    let res = server
+        .post("/api/order")
+        .add_header("Authorization", token.clone())
+        .json(&serde_json::json!({"notes": "Leave at the back door"}))
+        .await;
+    res.assert_status(axum::http::StatusCode::CREATED);
+    let order_id = res.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+
+    let res = server
+        .get(&format!("/api/order/{}", order_id))
+        .add_header("Authorization", token)
+        .await;
+    res.assert_status_ok();
+    assert_eq!(res.json::<serde_json::Value>()["notes"], "Leave at the back door");
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn orders_are_charged_the_configured_tax_and_shipping() {
+    let mut state = common::test_state_db().await.expect("test database unavailable");
+    state.order_config = std::sync::Arc::new(OrderConfig {
+        pricing: OrderPricing {
+            tax_rate_percent: Decimal::new(10, 0),
+            shipping_fee: Decimal::new(499, 2),
+            free_shipping_threshold: Some(Decimal::new(1000, 0)),
+        },
+        ..Default::default()
+    });
+    let server = axum_test::TestServer::new(common::app_with_state(state.clone()).await).unwrap();
+    let pool = &state.db;
+    let user_id = common::seed_user(pool, "client").await;
+    let product_id = common::seed_product(pool, "Taxed Oil", "20.00", 5).await;
+    let cart_id = common::seed_cart(pool, user_id).await;
+    sqlx::query("INSERT INTO cart_items (id, cart_id, product_id, quantity) VALUES ($1, $2, $3, 2)")
+        .bind(Uuid::new_v4())
+        .bind(cart_id)
+        .bind(product_id)
+        .execute(pool)
+        .await
+        .unwrap();
+
+    let res = server
+        .post("/api/order")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_for_user(user_id, "client")))
+        .json(&serde_json::json!({}))
+        .await;
+    res.assert_status(axum::http::StatusCode::CREATED);
+    let body = res.json::<serde_json::Value>();
+    let amount = |key: &str| Decimal::from_str(&body[key].to_string()).unwrap();
+    let discounted = amount("subtotal") - amount("discount");
+    assert_eq!(amount("subtotal"), Decimal::new(40, 0));
+    assert_eq!(amount("tax"), (discounted / Decimal::TEN).round_dp(2));
+    assert_eq!(amount("shipping"), Decimal::new(499, 2));
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn order_details_list_the_promotions_applied() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    // Only orders this large qualify, so other tests' orders don't pick it up
+    let promotion_id: Uuid = sqlx::query_scalar(
+        "INSERT INTO promotions (name, discount_percent, min_order_total) VALUES ('Bulk Deal', 99, 100000) RETURNING id",
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap();
+    let user_id = common::seed_user(pool, "client").await;
+    let product_id = common::seed_product(pool, "Bulk Oil", "60000.00", 5).await;
+    let cart_id = common::seed_cart(pool, user_id).await;
+    sqlx::query("INSERT INTO cart_items (id, cart_id, product_id, quantity) VALUES ($1, $2, $3, 2)")
+        .bind(Uuid::new_v4())
+        .bind(cart_id)
+        .bind(product_id)
+        .execute(pool)
+        .await
+        .unwrap();
+    let token = format!("Bearer {}", common::jwt_for_user(user_id, "client"));
+
+    let res = server.post("/api/order").add_header("Authorization", token.clone()).json(&serde_json::json!({})).await;
+    res.assert_status(axum::http::StatusCode::CREATED);
+    let order_id = res.json::<serde_json::Value>()["id"].as_str().unwrap().to_string();
+    sqlx::query("UPDATE promotions SET active = false WHERE id = $1")
+        .bind(promotion_id)
+        .execute(pool)
+        .await
+        .unwrap();
+
+    let res = server.get(&format!("/api/order/{}", order_id)).add_header("Authorization", token).await;
+    res.assert_status_ok();
+    let applied = res.json::<serde_json::Value>()["applied_promotions"].clone();
+    assert_eq!(applied.as_array().unwrap().len(), 1, "{}", applied);
+    assert_eq!(applied[0]["promotion_id"], promotion_id.to_string());
+    assert_eq!(applied[0]["name"], "Bulk Deal");
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn order_listing_pages_and_filters_by_status() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let user_id = common::seed_user(pool, "client").await;
+    let product_id = common::seed_product(pool, "Paged Oil", "10.00", 5).await;
+    let token = format!("Bearer {}", common::jwt_for_user(user_id, "client"));
+
+    for status in ["paid", "paid", "pending_payment"] {
+        seed_order(pool, user_id, product_id, 1, status).await;
+    }
+
+    let res = server
+        .get("/api/order/my")
+        .add_query_param("limit", 2)
+        .add_header("Authorization", token.clone())
+        .await;
+    res.assert_status_ok();
+    assert_eq!(res.header("x-total-count"), "3");
+    assert_eq!(res.json::<Vec<serde_json::Value>>().len(), 2);
+
+    let res = server
+        .get("/api/order/my")
+        .add_query_param("limit", 2)
+        .add_query_param("offset", 2)
+        .add_header("Authorization", token.clone())
+        .await;
+    assert_eq!(res.json::<Vec<serde_json::Value>>().len(), 1);
+
+    let res = server
+        .get("/api/order/my")
+        .add_query_param("limit", 2)
+        .add_query_param("envelope", true)
+        .add_header("Authorization", token.clone())
+        .await;
+    res.assert_status_ok();
+    let page = res.json::<serde_json::Value>();
+    assert_eq!(page["data"].as_array().unwrap().len(), 2);
+    assert_eq!(page["meta"], serde_json::json!({"total": 3, "limit": 2, "offset": 0}));
+
+    let res = server
+        .get("/api/order/my")
+        .add_query_param("status", "paid")
+        .add_header("Authorization", token.clone())
+        .await;
+    res.assert_status_ok();
+    assert_eq!(res.header("x-total-count"), "2");
+    assert!(res.json::<Vec<serde_json::Value>>().iter().all(|o| o["status"] == "paid"));
+
+    let res = server
+        .get("/api/order/all")
+        .add_query_param("status", "paid")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_admin()))
+        .await;
+    res.assert_status_ok();
+    assert!(res.json::<Vec<serde_json::Value>>().iter().all(|o| o["status"] == "paid"));
+
+    server
+        .get("/api/order/my")
+        .add_query_param("status", "lost_in_mail")
+        .add_header("Authorization", token)
+        .await
+        .assert_status_bad_request();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn paying_order_consumes_cart_reservation() {
+    use hemp_backend::repository::StockRepository;
+
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let user_id = common::seed_user(pool, "client").await;
+    let product_id = common::seed_product(pool, "Reserved Tincture", "10.00", 10).await;
+    let cart_id = common::seed_cart(pool, user_id).await;
+
+    let stock_repo = StockRepository::new(pool.clone());
+    stock_repo
+        .create_reservation(product_id, cart_id, 2, 30, None)
+        .await
+        .unwrap()
+        .expect("stock should be reservable");
+    assert_eq!(stock_repo.get_available_stock(product_id).await.unwrap(), Some(8));
+
+    let order_id = seed_order(pool, user_id, product_id, 2, "pending_payment").await;
+    let res = server
+        .post(&format!("/api/order/{}/pay", order_id))
+        .add_header("Authorization", format!("Bearer {}", common::jwt_for_user(user_id, "client")))
+        .await;
+    res.assert_status_ok();
+    assert_eq!(res.json::<serde_json::Value>()["status"], "paid");
+
+    // The sale takes the place of the hold rather than counting against stock a second time
+    assert_eq!(stock_repo.get_available_stock(product_id).await.unwrap(), Some(8));
+    let reservations: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM stock_reservations WHERE product_id = $1")
+        .bind(product_id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    assert_eq!(reservations, 0);
+
+    let sold: i32 = sqlx::query_scalar(
+        "SELECT quantity_change FROM inventory_logs WHERE reference_id = $1 AND change_type = 'sold'",
+    )
+    .bind(order_id)
+    .fetch_one(pool)
+    .await
+    .unwrap();
+    assert_eq!(sold, -2);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn paying_for_untracked_products_leaves_their_stock_alone() {
+    let pool = common::test_state_db().await.expect("test database unavailable").db;
+    let user_id = common::seed_user(&pool, "client").await;
+    let product_id = common::seed_product(&pool, "Made To Order Soap", "10.00", 1).await;
+    sqlx::query("UPDATE products SET track_inventory = false WHERE id = $1")
+        .bind(product_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    common::seed_cart(&pool, user_id).await;
+    let order_id = seed_order(&pool, user_id, product_id, 3, "pending_payment").await;
+
+    let svc = OrderService::new(OrderRepository::new(pool.clone()));
+    svc.pay_order(user_id, order_id).await.expect("untracked products are always available");
+
+    let stock: i32 = sqlx::query_scalar("SELECT stock FROM products WHERE id = $1")
+        .bind(product_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(stock, 1);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn concurrent_payments_take_stock_once() {
+    let pool = common::test_state_db().await.expect("test database unavailable").db;
+    let user_id = common::seed_user(&pool, "client").await;
+    let product_id = common::seed_product(&pool, "Contended Salve", "10.00", 10).await;
+    common::seed_cart(&pool, user_id).await;
+    let order_id = seed_order(&pool, user_id, product_id, 2, "pending_payment").await;
+
+    let svc = OrderService::new(OrderRepository::new(pool.clone()));
+    let (first, second) = tokio::join!(svc.pay_order(user_id, order_id), svc.pay_order(user_id, order_id));
+    let paid = [first, second].into_iter().filter(Result::is_ok).count();
+    assert_eq!(paid, 1);
+
+    let stock: i32 = sqlx::query_scalar("SELECT stock FROM products WHERE id = $1")
+        .bind(product_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(stock, 8);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn payment_is_refused_when_other_carts_hold_the_stock() {
+    use hemp_backend::repository::StockRepository;
+
+    let pool = common::test_state_db().await.expect("test database unavailable").db;
+    let user_id = common::seed_user(&pool, "client").await;
+    let product_id = common::seed_product(&pool, "Scarce Salve", "10.00", 2).await;
+    common::seed_cart(&pool, user_id).await;
+    let other_cart = common::seed_cart(&pool, common::seed_user(&pool, "client").await).await;
+    StockRepository::new(pool.clone())
+        .create_reservation(product_id, other_cart, 1, 30, None)
+        .await
+        .unwrap()
+        .expect("stock should be reservable");
+    let order_id = seed_order(&pool, user_id, product_id, 2, "pending_payment").await;
+
+    let svc = OrderService::new(OrderRepository::new(pool.clone()));
+    let err = svc.pay_order(user_id, order_id).await.unwrap_err();
+    assert_eq!(err.code(), "insufficient_stock");
+    assert!(err.to_string().contains(&product_id.to_string()), "{}", err);
+
+    let (stock, status): (i32, String) = sqlx::query_as(
+        "SELECT p.stock, o.status FROM products p, orders o WHERE p.id = $1 AND o.id = $2",
+    )
+    .bind(product_id)
+    .bind(order_id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!((stock, status.as_str()), (2, "pending_payment"));
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn locked_prices_are_charged_after_a_price_change() {
+    let pool = common::test_state_db().await.expect("test database unavailable").db;
+    let user_id = common::seed_user(&pool, "client").await;
+    let product_id = common::seed_product(&pool, "Locked Price Salve", "10.00", 10).await;
+    common::seed_cart(&pool, user_id).await;
+    let order_id = seed_order(&pool, user_id, product_id, 2, "pending_payment").await;
+    sqlx::query("UPDATE products SET price = 12.00 WHERE id = $1")
+        .bind(product_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let svc = OrderService::new(OrderRepository::new(pool.clone())).with_price_lock(true);
+    let order = svc.pay_order(user_id, order_id).await.unwrap();
+    assert_eq!(order.status, OrderStatus::Paid);
+    assert_eq!(order.total, Decimal::new(2000, 2));
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn unlocked_prices_refuse_payment_after_a_price_change() {
+    let pool = common::test_state_db().await.expect("test database unavailable").db;
+    let user_id = common::seed_user(&pool, "client").await;
+    let product_id = common::seed_product(&pool, "Repriced Salve", "10.00", 10).await;
+    common::seed_cart(&pool, user_id).await;
+    let order_id = seed_order(&pool, user_id, product_id, 2, "pending_payment").await;
+    sqlx::query("UPDATE products SET price = 12.00 WHERE id = $1")
+        .bind(product_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let svc = OrderService::new(OrderRepository::new(pool.clone())).with_price_lock(false);
+    let err = svc.pay_order(user_id, order_id).await.unwrap_err();
+    assert_eq!(err.code(), "conflict");
+    assert!(err.to_string().contains("Repriced Salve"), "{}", err);
+
+    let status: String = sqlx::query_scalar("SELECT status FROM orders WHERE id = $1")
+        .bind(order_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "pending_payment");
+
+    // Unchanged prices still go through
+    sqlx::query("UPDATE products SET price = 10.00 WHERE id = $1")
+        .bind(product_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert!(svc.pay_order(user_id, order_id).await.is_ok());
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn paying_and_shipping_are_recorded_in_the_status_history() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let user_id = common::seed_user(pool, "client").await;
+    let admin_id = common::seed_user(pool, "admin").await;
+    let user = format!("Bearer {}", common::jwt_for_user(user_id, "client"));
+    let admin = format!("Bearer {}", common::jwt_for_user(admin_id, "admin"));
+    let product_id = common::seed_product(pool, "Timeline Balm", "10.00", 10).await;
+    common::seed_cart(pool, user_id).await;
+    let order_id = seed_order(pool, user_id, product_id, 1, "pending_payment").await;
+    let history_url = format!("/api/order/{}/history", order_id);
+
+    server
+        .post(&format!("/api/order/{}/pay", order_id))
+        .add_header("Authorization", user.clone())
+        .await
+        .assert_status_ok();
+    for status in ["processing", "shipped"] {
+        server
+            .put(&format!("/api/order/{}/status", order_id))
+            .add_header("Authorization", admin.clone())
+            .json(&serde_json::json!({"status": status}))
+            .await
+            .assert_status_ok();
+    }
+
+    let res = server.get(&history_url).add_header("Authorization", user).await;
+    res.assert_status_ok();
+    let history = res.json::<Vec<serde_json::Value>>();
+    let steps: Vec<(&str, &str)> = history
+        .iter()
+        .map(|h| (h["from_status"].as_str().unwrap(), h["to_status"].as_str().unwrap()))
+        .collect();
+    assert_eq!(steps, vec![("pending_payment", "paid"), ("paid", "processing"), ("processing", "shipped")]);
+    assert_eq!(history[0]["changed_by"], user_id.to_string());
+    assert_eq!(history[2]["changed_by"], admin_id.to_string());
+
+    // Admins can read any order's history; other customers can't
+    server.get(&history_url).add_header("Authorization", admin).await.assert_status_ok();
+    server
+        .get(&history_url)
+        .add_header("Authorization", format!("Bearer {}", common::jwt_user()))
+        .await
+        .assert_status_forbidden();
+}
+
+async fn fill_cart(pool: &sqlx::PgPool, cart_id: Uuid, product_id: Uuid) {
+    sqlx::query("INSERT INTO cart_items (id, cart_id, product_id, quantity) VALUES ($1, $2, $3, 1)")
+        .bind(Uuid::new_v4())
+        .bind(cart_id)
+        .bind(product_id)
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+async fn count_orders(pool: &sqlx::PgPool, user_id: Uuid) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM orders WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn same_idempotency_key_creates_one_order() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let user_id = common::seed_user(pool, "client").await;
+    let product_id = common::seed_product(pool, "Retried Balm", "12.00", 10).await;
+    let cart_id = common::seed_cart(pool, user_id).await;
+    let token = format!("Bearer {}", common::jwt_for_user(user_id, "client"));
+
+    fill_cart(pool, cart_id, product_id).await;
+    let first = server
+        .post("/api/order")
+        .add_header("Authorization", token.clone())
+        .add_header("Idempotency-Key", "checkout-1")
+        .json(&serde_json::json!({}))
+        .await;
+    first.assert_status(axum::http::StatusCode::CREATED);
+    let first = first.json::<serde_json::Value>();
+
+    // The cart was emptied by the first request; refill it so a second order could be placed
+    fill_cart(pool, cart_id, product_id).await;
+    let retry = server
+        .post("/api/order")
+        .add_header("Authorization", token)
+        .add_header("Idempotency-Key", "checkout-1")
+        .json(&serde_json::json!({}))
+        .await;
+    retry.assert_status_ok();
+    let retry = retry.json::<serde_json::Value>();
+
+    assert_eq!(retry["id"], first["id"]);
+    assert_eq!(retry["total"], first["total"]);
+    assert_eq!(retry["items_count"], 1);
+    assert_eq!(count_orders(pool, user_id).await, 1);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn simultaneous_checkouts_of_one_cart_create_one_order() {
+    let pool = common::test_state_db().await.expect("test database unavailable").db;
+    let user_id = common::seed_user(&pool, "client").await;
+    let product_id = common::seed_product(&pool, "Contested Balm", "12.00", 10).await;
+    let cart_id = common::seed_cart(&pool, user_id).await;
+    fill_cart(&pool, cart_id, product_id).await;
+
+    let svc = OrderService::new(OrderRepository::new(pool.clone()));
+    let request = CreateOrderRequest { notes: None, coupon_code: None };
+    let (first, second) = tokio::join!(
+        svc.create_order_from_cart(user_id, &request),
+        svc.create_order_from_cart(user_id, &request),
+    );
+
+    // One checkout places the order; the other waits for the cart and finds it empty
+    let (placed, refused): (Vec<_>, Vec<_>) = [first, second].into_iter().partition(|r| r.is_ok());
+    assert_eq!(placed.len(), 1);
+    let err = refused.into_iter().next().unwrap().unwrap_err();
+    assert!(err.to_string().contains("Cart is empty"), "{}", err);
+
+    assert_eq!(count_orders(&pool, user_id).await, 1);
+    let items: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM order_items oi JOIN orders o ON o.id = oi.order_id WHERE o.user_id = $1")
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(items, 1);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn simultaneous_retries_with_one_idempotency_key_replay_the_order() {
+    let pool = common::test_state_db().await.expect("test database unavailable").db;
+    let user_id = common::seed_user(&pool, "client").await;
+    let product_id = common::seed_product(&pool, "Double Clicked Balm", "12.00", 10).await;
+    let cart_id = common::seed_cart(&pool, user_id).await;
+    fill_cart(&pool, cart_id, product_id).await;
+
+    let svc = OrderService::new(OrderRepository::new(pool.clone()));
+    let request = CreateOrderRequest { notes: None, coupon_code: None };
+    let (first, second) = tokio::join!(
+        svc.create_order_idempotent(user_id, Some("checkout-race"), &request),
+        svc.create_order_idempotent(user_id, Some("checkout-race"), &request),
+    );
+    let (first, first_replayed) = first.unwrap();
+    let (second, second_replayed) = second.unwrap();
+
+    assert_eq!(first.id, second.id);
+    assert!(first_replayed != second_replayed);
+    assert_eq!(count_orders(&pool, user_id).await, 1);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn different_idempotency_keys_create_separate_orders() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let user_id = common::seed_user(pool, "client").await;
+    let product_id = common::seed_product(pool, "Twice Bought Oil", "8.00", 10).await;
+    let cart_id = common::seed_cart(pool, user_id).await;
+    let token = format!("Bearer {}", common::jwt_for_user(user_id, "client"));
+
+    let mut ids = Vec::new();
+    for key in ["checkout-a", "checkout-b"] {
+        fill_cart(pool, cart_id, product_id).await;
+        let res = server
+            .post("/api/order")
+            .add_header("Authorization", token.clone())
+            .add_header("Idempotency-Key", key)
+            .json(&serde_json::json!({}))
+            .await;
+        res.assert_status(axum::http::StatusCode::CREATED);
+        ids.push(res.json::<serde_json::Value>()["id"].clone());
+    }
+
+    assert_ne!(ids[0], ids[1]);
+    assert_eq!(count_orders(pool, user_id).await, 2);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn admin_lists_orders_with_their_items() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let user_id = common::seed_user(pool, "client").await;
+    let oil = common::seed_product(pool, "Listed Oil", "10.00", 10).await;
+    let balm = common::seed_product(pool, "Listed Balm", "10.00", 10).await;
+
+    let first = seed_order(pool, user_id, oil, 2, "paid").await;
+    sqlx::query("INSERT INTO order_items (id, order_id, product_id, quantity, price) VALUES ($1, $2, $3, 1, 10.00)")
+        .bind(Uuid::new_v4())
+        .bind(first)
+        .bind(balm)
+        .execute(pool)
+        .await
+        .unwrap();
+    let second = seed_order(pool, user_id, balm, 3, "paid").await;
+
+    let res = server
+        .get("/api/order/all/with-items?limit=100")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_admin()))
+        .await;
+    res.assert_status_ok();
+    let orders = res.json::<Vec<serde_json::Value>>();
+    let find = |id: Uuid| {
+        orders
+            .iter()
+            .find(|o| o["order"]["id"] == id.to_string())
+            .unwrap_or_else(|| panic!("order {} missing from listing", id))
+    };
+
+    let listed = find(first);
+    assert_eq!(listed["order"]["user_id"], user_id.to_string());
+    let mut products: Vec<(String, i64)> = listed["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|i| {
+            assert_eq!(i["order_id"], first.to_string());
+            (i["product_id"].as_str().unwrap().to_string(), i["quantity"].as_i64().unwrap())
+        })
+        .collect();
+    products.sort();
+    let mut expected = vec![(oil.to_string(), 2), (balm.to_string(), 1)];
+    expected.sort();
+    assert_eq!(products, expected);
+
+    let listed = find(second);
+    let items = listed["items"].as_array().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["product_id"], balm.to_string());
+    assert_eq!(items[0]["quantity"], 3);
+
+    server
+        .get("/api/order/all/with-items")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_for_user(user_id, "client")))
+        .await
+        .assert_status_forbidden();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn admin_order_listing_filters_by_creation_range() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let user_id = common::seed_user(pool, "client").await;
+    let product_id = common::seed_product(pool, "Dated Oil", "10.00", 10).await;
+
+    let mut seeded = Vec::new();
+    for created_at in ["2001-05-31T23:59:59Z", "2001-06-01T00:00:00Z", "2001-06-20T08:30:00Z", "2001-07-01T00:00:00Z"] {
+        let order_id = seed_order(pool, user_id, product_id, 1, "paid").await;
+        sqlx::query("UPDATE orders SET created_at = $1::timestamptz WHERE id = $2")
+            .bind(created_at)
+            .bind(order_id)
+            .execute(pool)
+            .await
+            .unwrap();
+        seeded.push(order_id.to_string());
+    }
+    let admin = format!("Bearer {}", common::jwt_admin());
+
+    let res = server
+        .get("/api/order/all?created_after=2001-06-01T00:00:00Z&created_before=2001-07-01T00:00:00Z&limit=100")
+        .add_header("Authorization", admin.clone())
+        .await;
+    res.assert_status_ok();
+    let ids: Vec<String> = res
+        .json::<Vec<serde_json::Value>>()
+        .iter()
+        .map(|o| o["id"].as_str().unwrap().to_string())
+        .collect();
+
+    // created_after is inclusive, created_before exclusive
+    assert!(!ids.contains(&seeded[0]));
+    assert!(ids.contains(&seeded[1]));
+    assert!(ids.contains(&seeded[2]));
+    assert!(!ids.contains(&seeded[3]));
+
+    server
+        .get("/api/order/all?created_after=2001-07-01T00:00:00Z&created_before=2001-06-01T00:00:00Z")
+        .add_header("Authorization", admin)
+        .await
+        .assert_status_bad_request();
+}
+
+#[test]
+fn invoice_number_uses_order_date_and_id() {
+    use hemp_backend::services::order_service::invoice_number;
+
+    let order_id = Uuid::parse_str("1a2b3c4d-0000-4000-8000-000000000000").unwrap();
+    let placed_at = "2025-09-12T18:30:00Z".parse().unwrap();
+    assert_eq!(invoice_number(order_id, placed_at), "INV-20250912-1A2B3C4D");
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn invoice_lists_items_totals_and_payment_status() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let user_id = common::seed_user(pool, "client").await;
+    let product_id = common::seed_product(pool, "Invoiced Oil", "10.00", 5).await;
+    let order_id = seed_order(pool, user_id, product_id, 2, "paid").await;
+    sqlx::query(
+        "INSERT INTO payments (id, order_id, stripe_payment_intent_id, amount, currency, status) \
+         VALUES ($1, $2, $3, 20.00, 'usd', 'succeeded')",
+    )
+    .bind(Uuid::new_v4())
+    .bind(order_id)
+    .bind(format!("pi_{}", Uuid::new_v4().simple()))
+    .execute(pool)
+    .await
+    .unwrap();
+    let token = format!("Bearer {}", common::jwt_for_user(user_id, "client"));
+
+    let res = server
+        .get(&format!("/api/order/{}/invoice", order_id))
+        .add_header("Authorization", token.clone())
+        .await;
+    res.assert_status_ok();
+    let invoice = res.json::<serde_json::Value>();
+    assert_eq!(invoice["order_id"], order_id.to_string());
+    assert!(invoice["invoice_number"].as_str().unwrap().starts_with("INV-"));
+    assert_eq!(invoice["customer_id"], user_id.to_string());
+    assert_eq!(invoice["customer_email"], format!("{}@example.com", user_id));
+    assert_eq!(invoice["items"][0]["product_name"], "Invoiced Oil");
+    assert_eq!(invoice["items"][0]["quantity"], 2);
+    assert_eq!(invoice["items"][0]["subtotal"], serde_json::json!(20.0));
+    assert_eq!(invoice["total"], serde_json::json!(20.0));
+    assert_eq!(invoice["order_status"], "paid");
+    assert_eq!(invoice["payment_status"], "succeeded");
+
+    let res = server
+        .get(&format!("/api/order/{}/invoice?format=pdf", order_id))
+        .add_header("Authorization", token.clone())
+        .await;
+    res.assert_status_ok();
+    assert_eq!(res.header("content-type"), "application/pdf");
+    assert!(res.as_bytes().starts_with(b"%PDF-"));
+
+    server
+        .get(&format!("/api/order/{}/invoice?format=docx", order_id))
+        .add_header("Authorization", token)
+        .await
+        .assert_status_bad_request();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn invoice_is_only_visible_to_owner_and_admin() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let owner = common::seed_user(pool, "client").await;
+    let stranger = common::seed_user(pool, "client").await;
+    let product_id = common::seed_product(pool, "Private Balm", "10.00", 5).await;
+    let order_id = seed_order(pool, owner, product_id, 1, "pending_payment").await;
+    let url = format!("/api/order/{}/invoice", order_id);
+
+    server
+        .get(&url)
+        .add_header("Authorization", format!("Bearer {}", common::jwt_for_user(stranger, "client")))
+        .await
+        .assert_status_forbidden();
+
+    let res = server
+        .get(&url)
+        .add_header("Authorization", format!("Bearer {}", common::jwt_admin()))
+        .await;
+    res.assert_status_ok();
+    assert_eq!(res.json::<serde_json::Value>()["payment_status"], serde_json::Value::Null);
+
+    server
+        .get(&format!("/api/order/{}/invoice", Uuid::new_v4()))
+        .add_header("Authorization", format!("Bearer {}", common::jwt_for_user(owner, "client")))
+        .await
+        .assert_status_not_found();
+}
+
+/// Serves a fake fulfillment endpoint that forwards the headers and body of every POST it gets.
+async fn spawn_webhook_receiver() -> (String, tokio::sync::mpsc::UnboundedReceiver<(axum::http::HeaderMap, String)>) {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let app = axum::Router::new().route(
+        "/orders",
+        axum::routing::post(move |headers: axum::http::HeaderMap, body: String| {
+            let tx = tx.clone();
+            async move {
+                let _ = tx.send((headers, body));
+                axum::http::StatusCode::NO_CONTENT
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{}/orders", addr), rx)
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn order_events_are_posted_with_a_signature() {
+    use hemp_backend::state::OrderWebhookConfig;
+    use hmac::{Hmac, Mac};
+
+    let (url, mut events) = spawn_webhook_receiver().await;
+    let mut state = common::test_state_db().await.expect("test database unavailable");
+    state.order_webhook_config = std::sync::Arc::new(OrderWebhookConfig {
+        url: Some(url),
+        secret: "whsec_test".to_string(),
+    });
+    let pool = state.db.clone();
+    let server = axum_test::TestServer::new(common::app_with_state(state).await).unwrap();
+    let user_id = common::seed_user(&pool, "client").await;
+    let product_id = common::seed_product(&pool, "Shipped Oil", "12.00", 10).await;
+    let cart_id = common::seed_cart(&pool, user_id).await;
+    let token = format!("Bearer {}", common::jwt_for_user(user_id, "client"));
+
+    fill_cart(&pool, cart_id, product_id).await;
+    let res = server
+        .post("/api/order")
+        .add_header("Authorization", token.clone())
+        .json(&serde_json::json!({}))
+        .await;
+    res.assert_status(axum::http::StatusCode::CREATED);
+    let created = res.json::<serde_json::Value>();
+    server
+        .post(&format!("/api/order/{}/pay", created["id"].as_str().unwrap()))
+        .add_header("Authorization", token)
+        .await
+        .assert_status_ok();
+
+    // Each event is sent from its own task, so they may arrive in either order
+    let mut received = Vec::new();
+    for _ in 0..2 {
+        let event = tokio::time::timeout(std::time::Duration::from_secs(5), events.recv())
+            .await
+            .expect("webhook was not sent")
+            .unwrap();
+        received.push(event);
+    }
+    let mut names: Vec<_> = received.iter().map(|(headers, _)| headers["x-order-webhook-event"].clone()).collect();
+    names.sort();
+    assert_eq!(names, vec!["order.created", "order.paid"]);
+
+    let (headers, body) = received
+        .into_iter()
+        .find(|(headers, _)| headers["x-order-webhook-event"] == "order.paid")
+        .unwrap();
+    let mut mac = Hmac::<sha2::Sha256>::new_from_slice(b"whsec_test").unwrap();
+    mac.update(body.as_bytes());
+    let expected = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+    assert_eq!(headers["x-order-webhook-signature"], expected.as_str());
+
+    let payload: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(payload["event"], "order.paid");
+    assert_eq!(payload["order_id"], created["id"]);
+    assert_eq!(payload["user_id"], user_id.to_string());
+    assert_eq!(payload["status"], "paid");
+    assert_eq!(payload["total"], created["total"]);
+    assert_eq!(payload["items"].as_array().unwrap().len(), 1);
+    assert_eq!(payload["items"][0]["product_id"], product_id.to_string());
+    assert_eq!(payload["items"][0]["quantity"], 1);
+}
@@ -5,10 +5,9 @@ use serde_json::json;
 use uuid::Uuid;
 
 #[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
 async fn payment_routes_require_auth_and_exist() {
-    // Ensure Stripe secret exists for request construction in handler
-    std::env::set_var("STRIPE_SECRET_KEY", "sk_test_dummy");
-    let server = common::test_server_lazy().await;
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
 
     // Create intent requires auth; without auth, 401
     server
@@ -17,13 +16,14 @@ async fn payment_routes_require_auth_and_exist() {
         .await
         .assert_status_unauthorized();
 
-    // With user auth, endpoint exists; DB may cause 400/500 but not 404/401/403
+    // With user auth the endpoint is reached and looks the order up
     let res = server
         .post("/api/payment/create-payment-intent")
         .add_header("Authorization", format!("Bearer {}", common::jwt_user()))
         .json(&json!({"amount":"10.00","currency":"usd","order_id":Uuid::nil()}))
         .await;
-    assert!(res.status_code().as_u16() != 401 && res.status_code().as_u16() != 403 && res.status_code().as_u16() != 404);
+    res.assert_status_not_found();
+    assert_eq!(res.json::<serde_json::Value>()["details"], "Not found: Order not found");
 
     // Webhook endpoint exists (no auth), responds with 200/400
     let res = server
@@ -35,3 +35,964 @@ async fn payment_routes_require_auth_and_exist() {
     assert!(code == 200 || code == 400 || code == 500);
 }
 
+
+#[tokio::test]
+async fn payment_service_takes_its_stripe_key_from_state() {
+    use hemp_backend::repository::{OrderRepository, PaymentRepository};
+    use hemp_backend::services::payment_service::PaymentService;
+
+    // Nothing is read from the environment once the key is in AppState
+    std::env::remove_var("STRIPE_SECRET_KEY");
+    let state = common::test_state_lazy().await;
+    let svc = PaymentService::new(
+        PaymentRepository::new(state.db.clone()),
+        OrderRepository::new(state.db.clone()),
+        state.http.clone(),
+        &state.stripe_config,
+    );
+    assert!(svc.create_payment_intent(Uuid::new_v4(), hemp_backend::model::payment::CreatePaymentIntentRequest {
+        amount: None,
+        currency: "xyz".to_string(),
+        order_id: Uuid::new_v4(),
+    }).await.is_err());
+}
+
+#[test]
+fn currencies_are_normalized_against_the_allowlist() {
+    use hemp_backend::services::payment_service::normalize_currency;
+
+    assert_eq!(normalize_currency("USD").unwrap(), "usd");
+    assert_eq!(normalize_currency(" eur ").unwrap(), "eur");
+    assert!(normalize_currency("US Dollars").is_err());
+    assert!(normalize_currency("").is_err());
+    assert!(normalize_currency("xyz").is_err());
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn unsupported_currency_is_rejected_before_stripe() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+
+    let res = server
+        .post("/api/payment/create-payment-intent")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_user()))
+        .json(&json!({"amount":"10.00","currency":"US Dollars","order_id":Uuid::new_v4()}))
+        .await;
+    res.assert_status_bad_request();
+    let body = res.json::<serde_json::Value>();
+    assert_eq!(body["code"], "validation_error");
+    assert!(body["details"].as_str().unwrap().contains("US Dollars"));
+}
+
+async fn seed_pending_order(pool: &sqlx::PgPool, stock: i32, quantity: i32) -> (Uuid, Uuid, Uuid) {
+    let user_id = common::seed_user(pool, "client").await;
+    let product_id = common::seed_product(pool, "Reserved Oil", "10.00", stock).await;
+    let cart_id = common::seed_cart(pool, user_id).await;
+
+    let order_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO orders (id, user_id, total, status) VALUES ($1, $2, 20.00, 'pending_payment')")
+        .bind(order_id)
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO order_items (id, order_id, product_id, quantity, price) VALUES ($1, $2, $3, $4, 10.00)")
+        .bind(Uuid::new_v4())
+        .bind(order_id)
+        .bind(product_id)
+        .bind(quantity)
+        .execute(pool)
+        .await
+        .unwrap();
+
+    (order_id, product_id, cart_id)
+}
+
+async fn reserve_and_create_payment(pool: &sqlx::PgPool, order_id: Uuid, product_id: Uuid, cart_id: Uuid, quantity: i32) -> String {
+    use hemp_backend::repository::{PaymentRepository, StockRepository};
+
+    let mut tx = pool.begin().await.unwrap();
+    let reserved = StockRepository::new(pool.clone())
+        .reserve_for_order_in(&mut tx, order_id, cart_id, &[(product_id, quantity)], 30)
+        .await
+        .unwrap();
+    assert!(reserved.is_some());
+    tx.commit().await.unwrap();
+
+    let intent_id = format!("pi_test_{}", Uuid::new_v4().simple());
+    PaymentRepository::new(pool.clone())
+        .create(order_id, intent_id.clone(), "20.00".parse().unwrap(), "usd".to_string())
+        .await
+        .unwrap();
+    // Where create_payment_intent leaves the order
+    sqlx::query("UPDATE orders SET status = 'payment_processing' WHERE id = $1")
+        .bind(order_id)
+        .execute(pool)
+        .await
+        .unwrap();
+    intent_id
+}
+
+async fn order_owner(pool: &sqlx::PgPool, order_id: Uuid) -> Uuid {
+    sqlx::query_scalar("SELECT user_id FROM orders WHERE id = $1")
+        .bind(order_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+async fn stock_and_status(pool: &sqlx::PgPool, order_id: Uuid, product_id: Uuid) -> (i32, String) {
+    let stock: i32 = sqlx::query_scalar("SELECT stock FROM products WHERE id = $1")
+        .bind(product_id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    let status: String = sqlx::query_scalar("SELECT status FROM orders WHERE id = $1")
+        .bind(order_id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    (stock, status)
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn payment_failure_releases_order_reservations() {
+    use hemp_backend::repository::{OrderRepository, PaymentRepository};
+    use hemp_backend::services::payment_service::PaymentService;
+
+    let Some(state) = common::test_state_db().await else { return; };
+    let pool = state.db.clone();
+
+    let (order_id, product_id, cart_id) = seed_pending_order(&pool, 5, 2).await;
+    let intent_id = reserve_and_create_payment(&pool, order_id, product_id, cart_id, 2).await;
+
+    let svc = PaymentService::new(PaymentRepository::new(pool.clone()), OrderRepository::new(pool.clone()), state.http.clone(), &state.stripe_config);
+    svc.handle_payment_failed(&intent_id).await.unwrap();
+
+    let held: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM stock_reservations WHERE order_id = $1")
+        .bind(order_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(held, 0);
+
+    let stock: i32 = sqlx::query_scalar("SELECT stock FROM products WHERE id = $1")
+        .bind(product_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(stock, 5);
+
+    let status: String = sqlx::query_scalar("SELECT status FROM orders WHERE id = $1")
+        .bind(order_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "pending_payment");
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn payment_success_converts_order_reservations_to_sales() {
+    use hemp_backend::repository::{OrderRepository, PaymentRepository};
+    use hemp_backend::services::payment_service::PaymentService;
+
+    let Some(state) = common::test_state_db().await else { return; };
+    let pool = state.db.clone();
+
+    let (order_id, product_id, cart_id) = seed_pending_order(&pool, 5, 2).await;
+    let intent_id = reserve_and_create_payment(&pool, order_id, product_id, cart_id, 2).await;
+
+    let svc = PaymentService::new(PaymentRepository::new(pool.clone()), OrderRepository::new(pool.clone()), state.http.clone(), &state.stripe_config);
+    svc.handle_payment_succeeded(&intent_id).await.unwrap();
+
+    let held: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM stock_reservations WHERE order_id = $1")
+        .bind(order_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(held, 0);
+
+    let stock: i32 = sqlx::query_scalar("SELECT stock FROM products WHERE id = $1")
+        .bind(product_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(stock, 3);
+
+    let status: String = sqlx::query_scalar("SELECT status FROM orders WHERE id = $1")
+        .bind(order_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "paid");
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn repeated_payment_success_takes_stock_once() {
+    use hemp_backend::repository::{OrderRepository, PaymentRepository};
+    use hemp_backend::services::payment_service::PaymentService;
+
+    let Some(state) = common::test_state_db().await else { return; };
+    let pool = state.db.clone();
+
+    let (order_id, product_id, cart_id) = seed_pending_order(&pool, 5, 2).await;
+    let intent_id = reserve_and_create_payment(&pool, order_id, product_id, cart_id, 2).await;
+
+    let svc = PaymentService::new(PaymentRepository::new(pool.clone()), OrderRepository::new(pool.clone()), state.http.clone(), &state.stripe_config);
+    svc.handle_payment_succeeded(&intent_id).await.unwrap();
+    svc.handle_payment_succeeded(&intent_id).await.unwrap();
+    assert_eq!(stock_and_status(&pool, order_id, product_id).await, (3, "paid".to_string()));
+
+    // A failure arriving after the success doesn't reopen the order
+    svc.handle_payment_failed(&intent_id).await.unwrap();
+    assert_eq!(stock_and_status(&pool, order_id, product_id).await, (3, "paid".to_string()));
+
+    let paid_steps: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM order_status_history WHERE order_id = $1 AND to_status = 'paid'")
+        .bind(order_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(paid_steps, 1);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn payment_success_after_cancellation_refunds_and_leaves_the_order_cancelled() {
+    use axum::{routing::post, Router};
+    use hemp_backend::repository::{OrderRepository, PaymentRepository};
+    use hemp_backend::services::order_service::OrderService;
+    use hemp_backend::services::payment_service::PaymentService;
+    use hemp_backend::state::StripeConfig;
+
+    // Fake Stripe that reports the intent each refund was for
+    let (refunds_tx, mut refunds) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let app = Router::new().route(
+        "/v1/refunds",
+        post(move |axum::Form(params): axum::Form<std::collections::HashMap<String, String>>| async move {
+            refunds_tx.send(params["payment_intent"].clone()).unwrap();
+            axum::Json(json!({"id": "re_123"}))
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    let stripe = StripeConfig { secret_key: "sk_test_fake".to_string(), api_base: format!("http://{}", addr) };
+
+    let Some(state) = common::test_state_db().await else { return; };
+    let pool = state.db.clone();
+
+    let (order_id, product_id, cart_id) = seed_pending_order(&pool, 5, 2).await;
+    let intent_id = reserve_and_create_payment(&pool, order_id, product_id, cart_id, 2).await;
+    let user_id: Uuid = sqlx::query_scalar("SELECT user_id FROM orders WHERE id = $1")
+        .bind(order_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+    OrderService::new(OrderRepository::new(pool.clone()))
+        .cancel_order(user_id, order_id)
+        .await
+        .unwrap();
+
+    let svc = PaymentService::new(PaymentRepository::new(pool.clone()), OrderRepository::new(pool.clone()), state.http.clone(), &stripe);
+    svc.handle_payment_succeeded(&intent_id).await.unwrap();
+    assert_eq!(stock_and_status(&pool, order_id, product_id).await, (5, "cancelled".to_string()));
+
+    assert_eq!(refunds.try_recv().unwrap(), intent_id);
+    let payment = PaymentRepository::new(pool.clone()).get_by_stripe_payment_intent_id(&intent_id).await.unwrap().unwrap();
+    assert_eq!(payment.status, "canceled");
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn admins_list_payments_by_status() {
+    use hemp_backend::repository::PaymentRepository;
+
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = state.db.clone();
+    let payments = PaymentRepository::new(pool.clone());
+
+    let mut succeeded = Vec::new();
+    for status in ["succeeded", "succeeded", "failed"] {
+        let (order_id, _, _) = seed_pending_order(&pool, 5, 1).await;
+        let intent_id = format!("pi_test_{}", Uuid::new_v4().simple());
+        let payment = payments
+            .create(order_id, intent_id.clone(), "10.00".parse().unwrap(), "usd".to_string())
+            .await
+            .unwrap();
+        payments
+            .update_status_by_stripe_id(&intent_id, status.to_string(), None)
+            .await
+            .unwrap();
+        if status == "succeeded" {
+            succeeded.push(payment.id);
+        }
+    }
+
+    let admin = format!("Bearer {}", common::jwt_admin());
+    let res = server
+        .get("/api/payment")
+        .add_query_param("status", "succeeded")
+        .add_header("Authorization", admin.clone())
+        .await;
+    res.assert_status_ok();
+    let total: i64 = res.header("x-total-count").to_str().unwrap().parse().unwrap();
+    let page = res.json::<Vec<serde_json::Value>>();
+    assert!(total >= 2);
+    assert!(page.iter().all(|p| p["status"] == "succeeded"));
+    for id in &succeeded {
+        assert!(page.iter().any(|p| p["id"] == json!(id)));
+    }
+
+    let res = server
+        .get("/api/payment")
+        .add_query_param("status", "succeeded")
+        .add_query_param("limit", 1)
+        .add_header("Authorization", admin.clone())
+        .await;
+    res.assert_status_ok();
+    assert_eq!(res.json::<Vec<serde_json::Value>>().len(), 1);
+    assert_eq!(res.header("x-total-count").to_str().unwrap(), total.to_string());
+
+    server
+        .get("/api/payment")
+        .add_query_param("status", "settled")
+        .add_header("Authorization", admin)
+        .await
+        .assert_status_bad_request();
+
+    server
+        .get("/api/payment")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_user()))
+        .await
+        .assert_status_forbidden();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn payment_amount_below_order_total_is_rejected() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = state.db.clone();
+    let (order_id, product_id, _) = seed_pending_order(&pool, 5, 2).await;
+    let owner = order_owner(&pool, order_id).await;
+
+    let res = server
+        .post("/api/payment/create-payment-intent")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_for_user(owner, "user")))
+        .json(&json!({"amount":"1.00","currency":"usd","order_id":order_id}))
+        .await;
+    res.assert_status_bad_request();
+    assert!(res.json::<serde_json::Value>()["details"].as_str().unwrap().contains("20.00"));
+
+    // Nothing was held or recorded for the rejected attempt
+    let held: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM stock_reservations WHERE product_id = $1")
+        .bind(product_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(held, 0);
+    let payments: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM payments WHERE order_id = $1")
+        .bind(order_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(payments, 0);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn paying_for_another_users_order_is_not_found() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = state.db.clone();
+    let (order_id, product_id, _) = seed_pending_order(&pool, 5, 2).await;
+    let stranger = common::seed_user(&pool, "client").await;
+
+    let res = server
+        .post("/api/payment/create-payment-intent")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_for_user(stranger, "user")))
+        .json(&json!({"currency":"usd","order_id":order_id}))
+        .await;
+    res.assert_status_not_found();
+
+    // The owner's stock stays free and the order still awaits payment
+    let held: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM stock_reservations WHERE order_id = $1")
+        .bind(order_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(held, 0);
+    assert_eq!(stock_and_status(&pool, order_id, product_id).await, (5, "pending_payment".to_string()));
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn admins_look_up_payments_by_intent_id() {
+    use hemp_backend::repository::PaymentRepository;
+
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let (order_id, _, _) = seed_pending_order(&state.db, 5, 1).await;
+    let intent_id = format!("pi_test_{}", Uuid::new_v4().simple());
+    let payment = PaymentRepository::new(state.db.clone())
+        .create(order_id, intent_id.clone(), "10.00".parse().unwrap(), "usd".to_string())
+        .await
+        .unwrap();
+
+    let admin = format!("Bearer {}", common::jwt_admin());
+    let res = server
+        .get(&format!("/api/payment/intent/{}", intent_id))
+        .add_header("Authorization", admin.clone())
+        .await;
+    res.assert_status_ok();
+    let body = res.json::<serde_json::Value>();
+    assert_eq!(body["id"], json!(payment.id));
+    assert_eq!(body["order_id"], json!(order_id));
+
+    server
+        .get("/api/payment/intent/pi_test_unknown")
+        .add_header("Authorization", admin)
+        .await
+        .assert_status_not_found();
+
+    server
+        .get(&format!("/api/payment/intent/{}", intent_id))
+        .add_header("Authorization", format!("Bearer {}", common::jwt_user()))
+        .await
+        .assert_status_forbidden();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn refunds_use_the_injected_http_client_and_stripe_key() {
+    use axum::{http::HeaderMap, routing::post, Router};
+    use hemp_backend::repository::{OrderRepository, PaymentRepository};
+    use hemp_backend::services::payment_service::PaymentService;
+    use hemp_backend::state::StripeConfig;
+
+    // Fake Stripe that only accepts requests made with the client and key injected below
+    let app = Router::new().route(
+        "/v1/refunds",
+        post(|headers: HeaderMap| async move {
+            if headers.get("x-shared-client").is_some() && headers["authorization"] == "Bearer sk_test_injected" {
+                (axum::http::StatusCode::OK, axum::Json(json!({"id": "re_123"})))
+            } else {
+                (axum::http::StatusCode::BAD_REQUEST, axum::Json(json!({"error": "unexpected client"})))
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let Some(state) = common::test_state_db().await else { return; };
+    let pool = state.db.clone();
+    let (order_id, _, _) = seed_pending_order(&pool, 5, 1).await;
+    sqlx::query("UPDATE orders SET status = 'delivered' WHERE id = $1")
+        .bind(order_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let payment = PaymentRepository::new(pool.clone())
+        .create(order_id, format!("pi_test_{}", Uuid::new_v4().simple()), "20.00".parse().unwrap(), "usd".to_string())
+        .await
+        .unwrap();
+
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert("x-shared-client", "yes".parse().unwrap());
+    let http = reqwest::Client::builder().default_headers(headers).build().unwrap();
+
+    let stripe = StripeConfig { secret_key: "sk_test_injected".to_string(), api_base: format!("http://{}", addr) };
+
+    let svc = PaymentService::new(PaymentRepository::new(pool.clone()), OrderRepository::new(pool.clone()), http, &stripe);
+    svc.refund_payment(payment.id).await.unwrap();
+
+    let status: String = sqlx::query_scalar("SELECT status FROM orders WHERE id = $1")
+        .bind(order_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "refunded");
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn refunds_require_an_admin() {
+    use hemp_backend::repository::PaymentRepository;
+
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let (order_id, _, _) = seed_pending_order(&state.db, 5, 1).await;
+    sqlx::query("UPDATE orders SET status = 'paid' WHERE id = $1")
+        .bind(order_id)
+        .execute(&state.db)
+        .await
+        .unwrap();
+    let payment = PaymentRepository::new(state.db.clone())
+        .create(order_id, format!("pi_test_{}", Uuid::new_v4().simple()), "20.00".parse().unwrap(), "usd".to_string())
+        .await
+        .unwrap();
+
+    server
+        .post(&format!("/api/payment/{}/refund", payment.id))
+        .await
+        .assert_status_unauthorized();
+    server
+        .post(&format!("/api/payment/{}/refund", payment.id))
+        .add_header("Authorization", format!("Bearer {}", common::jwt_user()))
+        .await
+        .assert_status_forbidden();
+
+    let status: String = sqlx::query_scalar("SELECT status FROM orders WHERE id = $1")
+        .bind(order_id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap();
+    assert_eq!(status, "paid");
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn orders_that_were_not_paid_for_cannot_be_refunded() {
+    use hemp_backend::repository::{OrderRepository, PaymentRepository};
+    use hemp_backend::services::payment_service::{PaymentError, PaymentService};
+    use hemp_backend::state::StripeConfig;
+
+    let Some(state) = common::test_state_db().await else { return; };
+    let pool = state.db.clone();
+    let (order_id, _, _) = seed_pending_order(&pool, 5, 1).await;
+    let payment = PaymentRepository::new(pool.clone())
+        .create(order_id, format!("pi_test_{}", Uuid::new_v4().simple()), "20.00".parse().unwrap(), "usd".to_string())
+        .await
+        .unwrap();
+
+    // Nothing listens here, so reaching Stripe would fail with a different error
+    let stripe = StripeConfig { secret_key: "sk_test_unused".to_string(), api_base: "http://127.0.0.1:1".to_string() };
+    let svc = PaymentService::new(PaymentRepository::new(pool.clone()), OrderRepository::new(pool.clone()), state.http.clone(), &stripe);
+    let err = svc.refund_payment(payment.id).await.unwrap_err();
+    assert!(matches!(err, PaymentError::InvalidOrderStatus(ref status) if status == "pending_payment"), "{:?}", err);
+
+    let status: String = sqlx::query_scalar("SELECT status FROM orders WHERE id = $1")
+        .bind(order_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "pending_payment");
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn failing_to_record_a_payment_releases_the_order_reservations() {
+    use axum::{routing::post, Router};
+    use hemp_backend::model::payment::CreatePaymentIntentRequest;
+    use hemp_backend::repository::{OrderRepository, PaymentRepository};
+    use hemp_backend::services::payment_service::PaymentService;
+    use hemp_backend::state::StripeConfig;
+
+    let Some(state) = common::test_state_db().await else { return; };
+    let pool = state.db.clone();
+    let (order_id, product_id, _) = seed_pending_order(&pool, 5, 2).await;
+
+    // An intent id that is already stored, so recording the payment fails on the unique key
+    let (other_order, _, _) = seed_pending_order(&pool, 5, 1).await;
+    let taken = format!("pi_test_{}", Uuid::new_v4().simple());
+    PaymentRepository::new(pool.clone())
+        .create(other_order, taken.clone(), "20.00".parse().unwrap(), "usd".to_string())
+        .await
+        .unwrap();
+
+    let app = Router::new().route(
+        "/v1/payment_intents",
+        post(move || async move { axum::Json(json!({"id": taken, "client_secret": "secret_123"})) }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let stripe = StripeConfig { secret_key: "sk_test_fake".to_string(), api_base: format!("http://{}", addr) };
+    let svc = PaymentService::new(PaymentRepository::new(pool.clone()), OrderRepository::new(pool.clone()), state.http.clone(), &stripe);
+    let request = CreatePaymentIntentRequest { amount: None, currency: "usd".to_string(), order_id };
+    assert!(svc.create_payment_intent(order_owner(&pool, order_id).await, request).await.is_err());
+
+    let held: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM stock_reservations WHERE order_id = $1")
+        .bind(order_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(held, 0);
+    assert_eq!(stock_and_status(&pool, order_id, product_id).await, (5, "pending_payment".to_string()));
+}
+
+/// A stand-in for Stripe that answers every payment intent with a fresh id after `delay`.
+async fn spawn_fake_stripe(delay: std::time::Duration) -> hemp_backend::state::StripeConfig {
+    use axum::{routing::post, Router};
+
+    let app = Router::new().route(
+        "/v1/payment_intents",
+        post(move || async move {
+            tokio::time::sleep(delay).await;
+            axum::Json(json!({"id": format!("pi_test_{}", Uuid::new_v4().simple()), "client_secret": "secret_123"}))
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    hemp_backend::state::StripeConfig { secret_key: "sk_test_fake".to_string(), api_base: format!("http://{}", addr) }
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn concurrent_payment_intents_hold_the_stock_once() {
+    use hemp_backend::model::payment::CreatePaymentIntentRequest;
+    use hemp_backend::repository::{OrderRepository, PaymentRepository};
+    use hemp_backend::services::payment_service::{PaymentError, PaymentService};
+
+    let Some(state) = common::test_state_db().await else { return; };
+    let pool = state.db.clone();
+    let (order_id, product_id, _) = seed_pending_order(&pool, 5, 2).await;
+
+    let stripe = spawn_fake_stripe(std::time::Duration::ZERO).await;
+    let svc = PaymentService::new(PaymentRepository::new(pool.clone()), OrderRepository::new(pool.clone()), state.http.clone(), &stripe);
+    let owner = order_owner(&pool, order_id).await;
+    let request = || CreatePaymentIntentRequest { amount: None, currency: "usd".to_string(), order_id };
+    let (first, second) = tokio::join!(svc.create_payment_intent(owner, request()), svc.create_payment_intent(owner, request()));
+
+    assert!(first.is_ok() != second.is_ok(), "{:?} / {:?}", first, second);
+    let refused = first.err().or(second.err()).unwrap();
+    assert!(matches!(refused, PaymentError::InvalidOrderStatus(ref status) if status == "payment_processing"), "{:?}", refused);
+
+    let held: i64 = sqlx::query_scalar("SELECT COALESCE(SUM(quantity), 0) FROM stock_reservations WHERE order_id = $1")
+        .bind(order_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(held, 2);
+    assert_eq!(stock_and_status(&pool, order_id, product_id).await, (5, "payment_processing".to_string()));
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn cancelling_while_the_intent_is_created_keeps_the_order_cancelled() {
+    use hemp_backend::model::payment::CreatePaymentIntentRequest;
+    use hemp_backend::repository::{OrderRepository, PaymentRepository};
+    use hemp_backend::services::order_service::OrderService;
+    use hemp_backend::services::payment_service::PaymentService;
+
+    let Some(state) = common::test_state_db().await else { return; };
+    let pool = state.db.clone();
+    let (order_id, product_id, _) = seed_pending_order(&pool, 5, 2).await;
+    let user_id = order_owner(&pool, order_id).await;
+
+    let stripe = spawn_fake_stripe(std::time::Duration::from_millis(300)).await;
+    let svc = PaymentService::new(PaymentRepository::new(pool.clone()), OrderRepository::new(pool.clone()), state.http.clone(), &stripe);
+    let request = CreatePaymentIntentRequest { amount: None, currency: "usd".to_string(), order_id };
+    let cancel = async {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        OrderService::new(OrderRepository::new(pool.clone())).cancel_order(user_id, order_id).await
+    };
+    let (intent, cancelled) = tokio::join!(svc.create_payment_intent(user_id, request), cancel);
+    assert!(intent.is_ok(), "{:?}", intent.err());
+    assert!(cancelled.is_ok(), "{:?}", cancelled.err());
+
+    assert_eq!(stock_and_status(&pool, order_id, product_id).await, (5, "cancelled".to_string()));
+    let held: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM stock_reservations WHERE order_id = $1")
+        .bind(order_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(held, 0);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn paying_one_order_keeps_the_holds_of_the_users_other_orders() {
+    use hemp_backend::repository::{OrderRepository, PaymentRepository};
+    use hemp_backend::services::payment_service::PaymentService;
+
+    let Some(state) = common::test_state_db().await else { return; };
+    let pool = state.db.clone();
+    let (first_order, product_id, cart_id) = seed_pending_order(&pool, 5, 2).await;
+
+    // A second order of the same user for the same product, awaiting payment too
+    let second_order = Uuid::new_v4();
+    sqlx::query("INSERT INTO orders (id, user_id, total, status) SELECT $1, user_id, 20.00, 'pending_payment' FROM orders WHERE id = $2")
+        .bind(second_order)
+        .bind(first_order)
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO order_items (id, order_id, product_id, quantity, price) VALUES ($1, $2, $3, 2, 10.00)")
+        .bind(Uuid::new_v4())
+        .bind(second_order)
+        .bind(product_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let first_intent = reserve_and_create_payment(&pool, first_order, product_id, cart_id, 2).await;
+    reserve_and_create_payment(&pool, second_order, product_id, cart_id, 2).await;
+
+    let svc = PaymentService::new(PaymentRepository::new(pool.clone()), OrderRepository::new(pool.clone()), state.http.clone(), &state.stripe_config);
+    svc.handle_payment_succeeded(&first_intent).await.unwrap();
+    assert_eq!(stock_and_status(&pool, first_order, product_id).await, (3, "paid".to_string()));
+
+    let held: i64 = sqlx::query_scalar("SELECT COALESCE(SUM(quantity), 0) FROM stock_reservations WHERE order_id = $1")
+        .bind(second_order)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(held, 2);
+}
+
+/// A stand-in for Stripe's refunds endpoint that reports the intent of each refund it accepts.
+/// While `accepting` is false every refund is declined with a 400.
+async fn spawn_fake_stripe_refunds(
+    accepting: std::sync::Arc<std::sync::atomic::AtomicBool>,
+) -> (hemp_backend::state::StripeConfig, tokio::sync::mpsc::UnboundedReceiver<String>) {
+    use axum::{http::StatusCode, response::IntoResponse, routing::post, Router};
+    use std::sync::atomic::Ordering;
+
+    let (refunds_tx, refunds) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let app = Router::new().route(
+        "/v1/refunds",
+        post(move |axum::Form(params): axum::Form<std::collections::HashMap<String, String>>| async move {
+            if !accepting.load(Ordering::SeqCst) {
+                return (StatusCode::BAD_REQUEST, "refund declined").into_response();
+            }
+            refunds_tx.send(params["payment_intent"].clone()).unwrap();
+            axum::Json(json!({"id": "re_123"})).into_response()
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let stripe = hemp_backend::state::StripeConfig { secret_key: "sk_test_fake".to_string(), api_base: format!("http://{}", addr) };
+    (stripe, refunds)
+}
+
+async fn payment_status(pool: &sqlx::PgPool, intent_id: &str) -> String {
+    sqlx::query_scalar("SELECT status FROM payments WHERE stripe_payment_intent_id = $1")
+        .bind(intent_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn payment_success_after_an_earlier_failure_marks_the_order_paid() {
+    use hemp_backend::repository::{OrderRepository, PaymentRepository};
+    use hemp_backend::services::payment_service::PaymentService;
+
+    let Some(state) = common::test_state_db().await else { return; };
+    let pool = state.db.clone();
+
+    let (order_id, product_id, cart_id) = seed_pending_order(&pool, 5, 2).await;
+    let intent_id = reserve_and_create_payment(&pool, order_id, product_id, cart_id, 2).await;
+
+    // The first attempt fails, then the customer retries the same intent and it goes through
+    let svc = PaymentService::new(PaymentRepository::new(pool.clone()), OrderRepository::new(pool.clone()), state.http.clone(), &state.stripe_config);
+    svc.handle_payment_failed(&intent_id).await.unwrap();
+    assert_eq!(stock_and_status(&pool, order_id, product_id).await, (5, "pending_payment".to_string()));
+
+    svc.handle_payment_succeeded(&intent_id).await.unwrap();
+    assert_eq!(stock_and_status(&pool, order_id, product_id).await, (3, "paid".to_string()));
+    assert_eq!(payment_status(&pool, &intent_id).await, "succeeded");
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn second_successful_payment_for_a_paid_order_is_refunded() {
+    use hemp_backend::repository::{OrderRepository, PaymentRepository};
+    use hemp_backend::services::payment_service::PaymentService;
+    use std::sync::{atomic::AtomicBool, Arc};
+
+    let (stripe, mut refunds) = spawn_fake_stripe_refunds(Arc::new(AtomicBool::new(true))).await;
+    let Some(state) = common::test_state_db().await else { return; };
+    let pool = state.db.clone();
+
+    let (order_id, product_id, cart_id) = seed_pending_order(&pool, 5, 2).await;
+    let first = reserve_and_create_payment(&pool, order_id, product_id, cart_id, 2).await;
+    let second = format!("pi_test_{}", Uuid::new_v4().simple());
+    PaymentRepository::new(pool.clone())
+        .create(order_id, second.clone(), "20.00".parse().unwrap(), "usd".to_string())
+        .await
+        .unwrap();
+
+    let svc = PaymentService::new(PaymentRepository::new(pool.clone()), OrderRepository::new(pool.clone()), state.http.clone(), &stripe);
+    svc.handle_payment_succeeded(&first).await.unwrap();
+    svc.handle_payment_succeeded(&second).await.unwrap();
+
+    // The order keeps the first payment and its stock is taken once
+    assert_eq!(stock_and_status(&pool, order_id, product_id).await, (3, "paid".to_string()));
+    assert_eq!(refunds.try_recv().unwrap(), second);
+    assert!(refunds.try_recv().is_err());
+    assert_eq!(payment_status(&pool, &first).await, "succeeded");
+    assert_eq!(payment_status(&pool, &second).await, "canceled");
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn payment_success_without_the_stock_is_refunded_and_reported() {
+    use hemp_backend::repository::{OrderRepository, PaymentRepository};
+    use hemp_backend::services::payment_service::{PaymentError, PaymentService};
+    use std::sync::{atomic::AtomicBool, Arc};
+
+    let (stripe, mut refunds) = spawn_fake_stripe_refunds(Arc::new(AtomicBool::new(true))).await;
+    let Some(state) = common::test_state_db().await else { return; };
+    let pool = state.db.clone();
+
+    let (order_id, product_id, cart_id) = seed_pending_order(&pool, 5, 2).await;
+    let intent_id = reserve_and_create_payment(&pool, order_id, product_id, cart_id, 2).await;
+    // An admin correction leaves less stock than the order needs
+    sqlx::query("UPDATE products SET stock = 1 WHERE id = $1")
+        .bind(product_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let svc = PaymentService::new(PaymentRepository::new(pool.clone()), OrderRepository::new(pool.clone()), state.http.clone(), &stripe);
+    let result = svc.handle_payment_succeeded(&intent_id).await;
+    assert!(matches!(result, Err(PaymentError::InsufficientStock)), "{:?}", result);
+
+    assert_eq!(refunds.try_recv().unwrap(), intent_id);
+    assert_eq!(payment_status(&pool, &intent_id).await, "canceled");
+    assert_eq!(stock_and_status(&pool, order_id, product_id).await, (1, "pending_payment".to_string()));
+    let held: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM stock_reservations WHERE order_id = $1")
+        .bind(order_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(held, 0);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn failed_webhooks_are_reported_to_stripe_and_processed_on_redelivery() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let accepting = Arc::new(AtomicBool::new(false));
+    let (stripe, mut refunds) = spawn_fake_stripe_refunds(accepting.clone()).await;
+    let Some(mut state) = common::test_state_db().await else { return; };
+    state.stripe_config = Arc::new(stripe);
+    let server = TestServer::new(common::app_with_state(state.clone()).await).unwrap();
+    let pool = state.db.clone();
+
+    // A payment that succeeds for an order that was cancelled, while Stripe declines refunds
+    let (order_id, product_id, cart_id) = seed_pending_order(&pool, 5, 2).await;
+    let intent_id = reserve_and_create_payment(&pool, order_id, product_id, cart_id, 2).await;
+    sqlx::query("UPDATE orders SET status = 'cancelled' WHERE id = $1")
+        .bind(order_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let event_id = format!("evt_test_{}", Uuid::new_v4().simple());
+    let event = json!({
+        "id": event_id,
+        "type": "payment_intent.succeeded",
+        "data": {"object": {"id": intent_id}},
+    });
+    server.post("/api/payment/webhook").json(&event).await.assert_status(axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(payment_status(&pool, &intent_id).await, "pending");
+    let error: Option<String> = sqlx::query_scalar("SELECT error_message FROM payment_webhooks WHERE stripe_event_id = $1")
+        .bind(&event_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(error.is_some());
+
+    // Stripe delivers the event again once refunds go through
+    accepting.store(true, Ordering::SeqCst);
+    server.post("/api/payment/webhook").json(&event).await.assert_status_ok();
+    assert_eq!(refunds.try_recv().unwrap(), intent_id);
+    assert_eq!(payment_status(&pool, &intent_id).await, "canceled");
+
+    // A further redelivery of the handled event is only acknowledged
+    server.post("/api/payment/webhook").json(&event).await.assert_status_ok();
+    assert!(refunds.try_recv().is_err());
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn paying_after_reserving_the_cart_uses_the_cart_hold() {
+    use std::sync::Arc;
+
+    let Some(mut state) = common::test_state_db().await else { return; };
+    state.stripe_config = Arc::new(spawn_fake_stripe(std::time::Duration::ZERO).await);
+    let server = TestServer::new(common::app_with_state(state.clone()).await).unwrap();
+    let pool = state.db.clone();
+
+    // Stock exactly covers the order
+    let user_id = common::seed_user(&pool, "client").await;
+    let product_id = common::seed_product(&pool, "Last Two Oils", "10.00", 2).await;
+    let user = format!("Bearer {}", common::jwt_for_user(user_id, "client"));
+
+    server
+        .post("/api/cart/add")
+        .add_header("Authorization", user.clone())
+        .json(&json!({"product_id": product_id, "quantity": 2}))
+        .await
+        .assert_status_success();
+    server
+        .post("/api/inventory/reservations/cart")
+        .add_header("Authorization", user.clone())
+        .json(&json!({}))
+        .await
+        .assert_status_success();
+    let res = server
+        .post("/api/order")
+        .add_header("Authorization", user.clone())
+        .json(&json!({}))
+        .await;
+    res.assert_status_success();
+    let order_id: Uuid = res.json::<serde_json::Value>()["id"].as_str().unwrap().parse().unwrap();
+
+    server
+        .post("/api/payment/create-payment-intent")
+        .add_header("Authorization", user)
+        .json(&json!({"currency":"usd","order_id":order_id}))
+        .await
+        .assert_status_ok();
+
+    // The cart's hold became the order's rather than being counted next to it
+    let holds: Vec<(Option<Uuid>, i32)> = sqlx::query_as("SELECT order_id, quantity FROM stock_reservations WHERE product_id = $1")
+        .bind(product_id)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(holds, vec![(Some(order_id), 2)]);
+    assert_eq!(stock_and_status(&pool, order_id, product_id).await, (2, "payment_processing".to_string()));
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn refunding_a_paid_order_returns_its_stock_with_the_status_change() {
+    use hemp_backend::repository::{OrderRepository, PaymentRepository};
+    use hemp_backend::services::payment_service::PaymentService;
+    use std::sync::{atomic::AtomicBool, Arc};
+
+    let (stripe, mut refunds) = spawn_fake_stripe_refunds(Arc::new(AtomicBool::new(true))).await;
+    let Some(state) = common::test_state_db().await else { return; };
+    let pool = state.db.clone();
+
+    let (order_id, product_id, cart_id) = seed_pending_order(&pool, 5, 2).await;
+    let intent_id = reserve_and_create_payment(&pool, order_id, product_id, cart_id, 2).await;
+    let svc = PaymentService::new(PaymentRepository::new(pool.clone()), OrderRepository::new(pool.clone()), state.http.clone(), &stripe);
+    svc.handle_payment_succeeded(&intent_id).await.unwrap();
+    assert_eq!(stock_and_status(&pool, order_id, product_id).await, (3, "paid".to_string()));
+
+    let payment = PaymentRepository::new(pool.clone()).get_by_stripe_payment_intent_id(&intent_id).await.unwrap().unwrap();
+    svc.refund_payment(payment.id).await.unwrap();
+    assert_eq!(refunds.try_recv().unwrap(), intent_id);
+    assert_eq!(stock_and_status(&pool, order_id, product_id).await, (5, "refunded".to_string()));
+    let restocked: bool = sqlx::query_scalar("SELECT restocked_at IS NOT NULL FROM orders WHERE id = $1")
+        .bind(order_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(restocked);
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use axum_test::multipart::{MultipartForm, Part};
+use hemp_backend::services::product_service::parse_product_csv;
+use serde_json::Value;
+use uuid::Uuid;
+
+fn csv_form(csv: String) -> MultipartForm {
+    MultipartForm::new().add_part(
+        "file",
+        Part::bytes(csv.into_bytes()).file_name("products.csv").mime_type("text/csv"),
+    )
+}
+
+#[test]
+fn parse_reports_malformed_rows_by_line() {
+    let csv = "name,price,stock,sku\n\
+               Hemp Oil,19.99,10,\n\
+               Broken Price,abc,5,\n\
+               ,4.00,1,\n\
+               Negative Stock,4.00,-2,\n";
+    let rows = parse_product_csv(csv.as_bytes()).unwrap();
+
+    let outcome: Vec<(u64, bool)> = rows.iter().map(|(row, dto)| (row.line, dto.is_some())).collect();
+    assert_eq!(outcome, vec![(2, true), (3, false), (4, false), (5, false)]);
+    assert!(rows[1].0.error.as_deref().unwrap().contains("price"));
+    assert!(rows[2].0.error.as_deref().unwrap().contains("name"));
+    assert!(rows[3].0.error.as_deref().unwrap().contains("stock"));
+    assert_eq!(rows[0].1.as_ref().unwrap().sku, None);
+}
+
+#[test]
+fn parse_requires_name_price_and_stock_columns() {
+    assert!(parse_product_csv(b"name,price\nOil,1.00\n").is_err());
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn import_requires_admin() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+    server
+        .post("/api/product/import")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_user()))
+        .multipart(csv_form("name,price,stock\nOil,1.00,1\n".into()))
+        .await
+        .assert_status_forbidden();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn clean_file_imports_every_row() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+    let batch = Uuid::new_v4().simple().to_string();
+    let csv = format!(
+        "name,description,price,stock,sku,image_url\n\
+         Import Oil {batch},Cold pressed,19.99,10,IMP-{batch}-1,https://example.com/oil.jpg\n\
+         Import Balm {batch},,7.50,0,IMP-{batch}-2,\n"
+    );
+
+    let res = server
+        .post("/api/product/import")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_admin()))
+        .multipart(csv_form(csv))
+        .await;
+    res.assert_status_ok();
+    let report = res.json::<Value>();
+    assert_eq!(report["imported"], 2);
+    assert_eq!(report["failed"], 0);
+
+    let id = report["rows"][1]["product_id"].as_str().unwrap();
+    let res = server.get(&format!("/api/product/{}", id)).await;
+    res.assert_status_ok();
+    let product = res.json::<Value>();
+    assert_eq!(product["name"], format!("Import Balm {batch}"));
+    assert_eq!(product["sku"], format!("IMP-{batch}-2"));
+    assert_eq!(product["price"], serde_json::json!(7.5));
+    assert_eq!(product["description"], Value::Null);
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn bad_rows_are_reported_and_good_rows_imported() {
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let batch = Uuid::new_v4().simple().to_string();
+    let csv = format!(
+        "name,price,stock,sku\n\
+         Good Oil {batch},10.00,3,GOOD-{batch}\n\
+         Bad Price {batch},ten,3,\n\
+         Bad Stock {batch},10.00,lots,\n\
+         Good Balm {batch},5.00,1,\n"
+    );
+
+    let res = server
+        .post("/api/product/import")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_admin()))
+        .multipart(csv_form(csv))
+        .await;
+    res.assert_status_ok();
+    let report = res.json::<Value>();
+    assert_eq!(report["imported"], 2);
+    assert_eq!(report["failed"], 2);
+
+    let failed: Vec<(u64, &str)> = report["rows"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|row| !row["error"].is_null())
+        .map(|row| (row["line"].as_u64().unwrap(), row["name"].as_str().unwrap()))
+        .collect();
+    assert_eq!(failed, vec![(3, format!("Bad Price {batch}").as_str()), (4, format!("Bad Stock {batch}").as_str())]);
+
+    let imported: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM products WHERE name LIKE $1")
+        .bind(format!("% {batch}"))
+        .fetch_one(&state.db)
+        .await
+        .unwrap();
+    assert_eq!(imported, 2);
+
+    // Importing the same SKU again is reported rather than failing the whole file
+    let res = server
+        .post("/api/product/import")
+        .add_header("Authorization", format!("Bearer {}", common::jwt_admin()))
+        .multipart(csv_form(format!("name,price,stock,sku\nAgain {batch},1.00,1,GOOD-{batch}\n")))
+        .await;
+    res.assert_status_ok();
+    let report = res.json::<Value>();
+    assert_eq!(report["imported"], 0);
+    assert!(report["rows"][0]["error"].as_str().unwrap().contains("already in use"));
+}
@@ -0,0 +1,31 @@
+mod common;
+
+use axum_test::TestServer;
+use hemp_backend::middleware::request_id::request_id;
+
+async fn server() -> TestServer {
+    let state = common::test_state_lazy().await;
+    let app = common::app_with_state(state).await.layer(axum::middleware::from_fn(request_id));
+    TestServer::new(app).expect("failed to start test server")
+}
+
+#[tokio::test]
+async fn supplied_request_id_is_echoed() {
+    let server = server().await;
+
+    let res = server.get("/health").add_header("X-Request-Id", "trace-abc-123").await;
+    res.assert_status_ok();
+    assert_eq!(res.header("x-request-id"), "trace-abc-123");
+}
+
+#[tokio::test]
+async fn request_id_is_generated_when_missing_or_malformed() {
+    let server = server().await;
+
+    let res = server.get("/health").await;
+    let generated = res.header("x-request-id");
+    assert!(uuid::Uuid::parse_str(generated.to_str().unwrap()).is_ok());
+
+    let res = server.get("/health").add_header("X-Request-Id", "a".repeat(500)).await;
+    assert!(uuid::Uuid::parse_str(res.header("x-request-id").to_str().unwrap()).is_ok());
+}
@@ -12,6 +12,7 @@ struct Claims {
     email: String,
     role: String,
     exp: usize,
+    jti: Uuid,
 }
 
 fn jwt(user_id: Uuid, role: &str) -> String {
@@ -20,6 +21,7 @@ fn jwt(user_id: Uuid, role: &str) -> String {
         email: "user@example.com".to_string(),
         role: role.to_string(),
         exp: 4102444800, // year 2100
+        jti: Uuid::new_v4(),
     };
     encode(&Header::default(), &claims, &EncodingKey::from_secret(b"test_secret")).unwrap()
 }
@@ -35,10 +37,24 @@ async fn open_product_routes() {
 }
 
 #[tokio::test]
-async fn create_product_requires_admin() {
+async fn product_list_rejects_inverted_price_range() {
     let server = common::test_server_lazy().await;
 
-    let token = jwt(Uuid::new_v4(), "user");
+    // Rejected before any query runs, so no database is needed
+    server
+        .get("/api/product")
+        .add_query_param("min_price", "50")
+        .add_query_param("max_price", "10")
+        .await
+        .assert_status_bad_request();
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn create_product_requires_admin() {
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
+
+    let token = jwt(common::fixture_user_id("client"), "client");
     server
         .post("/api/product")
         .add_header("Authorization", format!("Bearer {}", token))
@@ -52,16 +68,16 @@ async fn create_product_requires_admin() {
             "track_inventory": true
         }))
         .await
-        .assert_status_unauthorized();
+        .assert_status_forbidden();
 }
 
 #[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
 async fn admin_protected_inventory_endpoints_check() {
-    let server = common::test_server_lazy().await;
+    let (server, _state) = common::test_server_db().await.expect("test database unavailable");
 
-    let token = jwt(Uuid::new_v4(), "admin");
+    let token = common::jwt_admin();
 
-    // Will likely fail with 500/404 due to missing DB; but should not be 401/403
     let res = server
         .get("/api/inventory/alerts")
         .add_header("Authorization", format!("Bearer {}", token))
@@ -69,3 +85,112 @@ async fn admin_protected_inventory_endpoints_check() {
     assert!(res.status_code().as_u16() != 401 && res.status_code().as_u16() != 403);
 }
 
+
+#[test]
+fn page_defaults_and_clamps_limit() {
+    use hemp_backend::routes::pagination::{page, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+
+    assert_eq!(page(None, None), (DEFAULT_PAGE_SIZE, 0));
+    assert_eq!(page(Some(1_000_000), Some(20)), (MAX_PAGE_SIZE, 20));
+    assert_eq!(page(Some(0), Some(-5)), (1, 0));
+}
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn oversized_limit_is_clamped() {
+    use hemp_backend::routes::pagination::MAX_PAGE_SIZE;
+
+    let (server, state) = common::test_server_db().await.expect("test database unavailable");
+    let pool = &state.db;
+    let category_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO categories (id, name) VALUES ($1, $2)")
+        .bind(category_id)
+        .bind(format!("Bulk {}", category_id))
+        .execute(pool)
+        .await
+        .unwrap();
+    for i in 0..=MAX_PAGE_SIZE {
+        let product_id = common::seed_product(pool, &format!("Bulk Oil {}", i), "1.00", 1).await;
+        sqlx::query("INSERT INTO product_categories (product_id, category_id) VALUES ($1, $2)")
+            .bind(product_id)
+            .bind(category_id)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    let res = server
+        .get("/api/product")
+        .add_query_param("category_id", category_id)
+        .add_query_param("limit", 1_000_000)
+        .await;
+    res.assert_status_ok();
+    assert_eq!(res.header("x-total-count"), (MAX_PAGE_SIZE + 1).to_string().as_str());
+    assert_eq!(res.json::<Vec<serde_json::Value>>().len(), MAX_PAGE_SIZE as usize);
+
+    let res = server
+        .get(&format!("/api/category/{}/products", category_id))
+        .add_query_param("limit", 1_000_000)
+        .await;
+    res.assert_status_ok();
+    assert_eq!(res.json::<Vec<serde_json::Value>>().len(), MAX_PAGE_SIZE as usize);
+}
+
+#[tokio::test]
+async fn oversized_bodies_are_rejected_with_413() {
+    let mut state = common::test_state_lazy().await;
+    state.body_limits = std::sync::Arc::new(hemp_backend::state::BodyLimits {
+        max_request_bytes: 1024,
+        max_upload_bytes: 8 * 1024,
+    });
+    let server = TestServer::new(common::app_with_state(state).await).unwrap();
+
+    let res = server
+        .post("/api/auth/login")
+        .json(&json!({"email": "big@example.com", "password": "x".repeat(2048)}))
+        .await;
+    res.assert_status(axum::http::StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(res.json::<serde_json::Value>()["code"], "file_too_large");
+
+    // Uploads may exceed the JSON limit, up to their own
+    let upload = |size: usize| {
+        server
+            .post("/api/image/upload")
+            .add_header("Authorization", format!("Bearer {}", common::jwt_admin()))
+            .add_header("Content-Type", "multipart/form-data; boundary=limit")
+            .bytes(vec![b'a'; size].into())
+    };
+    assert_ne!(upload(4 * 1024).await.status_code().as_u16(), 413);
+    upload(16 * 1024).await.assert_status(axum::http::StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn unsupported_methods_get_405_with_allow() {
+    let server = common::test_server_lazy().await;
+
+    let res = server.method(axum::http::Method::PATCH, "/api/product").await;
+    res.assert_status(axum::http::StatusCode::METHOD_NOT_ALLOWED);
+    let allow = res.header("allow");
+    let allowed: Vec<&str> = allow.to_str().unwrap().split(',').map(str::trim).collect();
+    for method in ["GET", "HEAD", "POST"] {
+        assert!(allowed.contains(&method), "{} missing from Allow: {:?}", method, allowed);
+    }
+    assert_eq!(res.json::<serde_json::Value>()["code"], "method_not_allowed");
+
+    // HEAD is answered wherever GET is
+    server
+        .method(axum::http::Method::HEAD, "/health")
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn unknown_paths_get_a_json_404() {
+    let server = common::test_server_lazy().await;
+
+    let res = server.get("/api/no-such-thing").await;
+    res.assert_status_not_found();
+    let body = res.json::<serde_json::Value>();
+    assert_eq!(body["code"], "not_found");
+    assert_eq!(body["details"], "Not found: No route for GET /api/no-such-thing");
+}
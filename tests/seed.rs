@@ -0,0 +1,44 @@
+mod common;
+
+use hemp_backend::seed::{seed, SeedConfig};
+
+#[tokio::test]
+#[ignore = "Requires TEST_DATABASE_URL and Postgres running"]
+async fn seeding_inserts_sample_data_once() {
+    let state = common::test_state_db().await.expect("test database unavailable");
+    let label = format!("Seed {}", uuid::Uuid::new_v4());
+    let config = SeedConfig {
+        label: label.clone(),
+        categories: 2,
+        products: 6,
+        demo_email: format!("{}@example.com", uuid::Uuid::new_v4()),
+        ..Default::default()
+    };
+
+    let summary = seed(&state.db, &config).await.unwrap();
+    assert!(!summary.skipped);
+    assert_eq!(summary.categories, 2);
+    assert_eq!(summary.products, 6);
+
+    let products: Vec<(i32, i64)> = sqlx::query_as(
+        "SELECT p.stock, (SELECT COUNT(*) FROM product_categories pc WHERE pc.product_id = p.id)
+         FROM products p WHERE p.name LIKE $1",
+    )
+    .bind(format!("{} Product %", label))
+    .fetch_all(&state.db)
+    .await
+    .unwrap();
+    assert_eq!(products.len(), 6);
+    assert!(products.iter().all(|(_, categories)| *categories == 1));
+    assert!(products.iter().any(|(stock, _)| *stock == 0));
+
+    // A second run finds the demo user and leaves everything alone
+    let again = seed(&state.db, &config).await.unwrap();
+    assert!(again.skipped);
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM products WHERE name LIKE $1")
+        .bind(format!("{} Product %", label))
+        .fetch_one(&state.db)
+        .await
+        .unwrap();
+    assert_eq!(count, 6);
+}
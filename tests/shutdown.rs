@@ -0,0 +1,26 @@
+#![cfg(unix)]
+
+use std::time::Duration;
+
+use hemp_backend::shutdown::shutdown_signal;
+
+#[tokio::test]
+async fn server_stops_on_sigterm() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let app = axum::Router::new().route("/", axum::routing::get(|| async { "OK" }));
+    // Installs the handlers now, so the signal below can't kill the test process
+    let signal = shutdown_signal();
+    let server = tokio::spawn(async move { axum::serve(listener, app).with_graceful_shutdown(signal).await });
+
+    let status = std::process::Command::new("kill")
+        .args(["-TERM", &std::process::id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .expect("server did not shut down after SIGTERM")
+        .unwrap()
+        .unwrap();
+}
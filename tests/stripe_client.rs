@@ -0,0 +1,89 @@
+use std::sync::{Arc, Mutex};
+
+use axum::{http::{HeaderMap, StatusCode}, routing::post, Json, Router};
+use hemp_backend::services::stripe_client::StripeClient;
+use serde_json::{json, Value};
+
+/// Serves a fake Stripe that answers with `statuses` in turn (repeating the last one),
+/// recording the headers of every request it gets.
+async fn spawn_stripe(statuses: Vec<u16>) -> (String, Arc<Mutex<Vec<HeaderMap>>>) {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let seen = requests.clone();
+    let app = Router::new().route(
+        "/v1/payment_intents",
+        post(move |headers: HeaderMap| {
+            let seen = seen.clone();
+            let statuses = statuses.clone();
+            async move {
+                let mut seen = seen.lock().unwrap();
+                seen.push(headers);
+                let status = statuses[(seen.len() - 1).min(statuses.len() - 1)];
+                (
+                    StatusCode::from_u16(status).unwrap(),
+                    Json(json!({"id": "pi_123", "client_secret": "pi_123_secret"})),
+                )
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{}", addr), requests)
+}
+
+fn client(api_base: String) -> StripeClient {
+    StripeClient::new(reqwest::Client::new(), "sk_test_dummy".to_string(), api_base)
+}
+
+#[tokio::test]
+async fn server_errors_are_retried_with_the_same_idempotency_key() {
+    let (base, requests) = spawn_stripe(vec![503, 200]).await;
+
+    let body: Value = client(base)
+        .post_form("/v1/payment_intents", &[("amount", "1000".to_string())], "intent-key")
+        .await
+        .expect("retry should succeed");
+    assert_eq!(body["id"], "pi_123");
+
+    let keys: Vec<_> = requests.lock().unwrap().iter().map(|h| h["idempotency-key"].clone()).collect();
+    assert_eq!(keys, vec!["intent-key", "intent-key"]);
+}
+
+#[tokio::test]
+async fn client_errors_are_not_retried() {
+    let (base, requests) = spawn_stripe(vec![400]).await;
+
+    let result = client(base)
+        .post_form("/v1/payment_intents", &[("amount", "1000".to_string())], "intent-key")
+        .await;
+    assert!(result.is_err());
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn retries_give_up_after_three_attempts() {
+    let (base, requests) = spawn_stripe(vec![503]).await;
+
+    let result = client(base)
+        .post_form("/v1/payment_intents", &[("amount", "1000".to_string())], "intent-key")
+        .await;
+    assert!(result.is_err());
+    assert_eq!(requests.lock().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn requests_go_through_the_injected_client() {
+    let (base, requests) = spawn_stripe(vec![200]).await;
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert("x-shared-client", "yes".parse().unwrap());
+    let http = reqwest::Client::builder().default_headers(headers).build().unwrap();
+
+    StripeClient::new(http, "sk_test_dummy".to_string(), base)
+        .post_form("/v1/payment_intents", &[("amount", "1000".to_string())], "intent-key")
+        .await
+        .unwrap();
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0]["x-shared-client"], "yes");
+    assert_eq!(requests[0]["authorization"], "Bearer sk_test_dummy");
+}
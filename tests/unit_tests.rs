@@ -1,7 +1,7 @@
 use hemp_backend::{
     dtos::{NewProductDto, UpdateProductDto},
-    model::product::Product,
-    repository::ProductRepository,
+    model::product::ProductWithAvailableStock,
+    repository::{ProductFilter, ProductRepository},
     services::product_service::ProductService,
 };
 use rust_decimal::Decimal;
@@ -32,11 +32,13 @@ async fn test_product_repository_create() {
     
     let result = repo.create(
         "Test Product",
+        None,
         Some("Test description"),
         Decimal::new(1999, 2), // $19.99
         50,
         Some("https://example.com/image.jpg"),
         Some(10),
+        None,
         true,
     ).await;
     
@@ -61,11 +63,13 @@ async fn test_product_repository_get() {
     // Create a product first
     let created = repo.create(
         "Get Test Product",
+        None,
         Some("Get test description"),
         Decimal::new(2499, 2),
         25,
         None,
         None,
+        None,
         true,
     ).await.unwrap();
     
@@ -90,11 +94,13 @@ async fn test_product_repository_update() {
     // Create a product first
     let created = repo.create(
         "Update Test Product",
+        None,
         Some("Original description"),
         Decimal::new(1500, 2),
         100,
         None,
         Some(20),
+        None,
         true,
     ).await.unwrap();
     
@@ -102,11 +108,13 @@ async fn test_product_repository_update() {
     let result = repo.update(
         created.id,
         Some("Updated Test Product"),
-        Some("Updated description"),
+        None,
+        Some(Some("Updated description")),
         Some(Decimal::new(1750, 2)),
         Some(75),
-        Some("https://example.com/updated_image.jpg"),
-        Some(15),
+        Some(Some("https://example.com/updated_image.jpg")),
+        Some(Some(15)),
+        None,
         Some(false),
     ).await;
     
@@ -133,11 +141,13 @@ async fn test_product_service_create() {
     
     let dto = NewProductDto {
         name: "Service Test Product".to_string(),
+        sku: None,
         description: Some("Service test description".to_string()),
         price: Decimal::new(3500, 2), // $35.00
         stock: 200,
         image_url: Some("https://example.com/service_image.jpg".to_string()),
         low_stock_threshold: Some(25),
+        reorder_target: None,
         track_inventory: Some(true),
     };
     
@@ -167,11 +177,13 @@ async fn test_product_service_list() {
     for i in 0..5 {
         let dto = NewProductDto {
             name: format!("List Test Product {}", i),
+            sku: None,
             description: Some(format!("List test description {}", i)),
             price: Decimal::new(1000 + ((i as i64) * 100), 2),
             stock: 10 + i as i32,
             image_url: None,
             low_stock_threshold: Some(5),
+            reorder_target: None,
             track_inventory: Some(true),
         };
         
@@ -179,7 +191,7 @@ async fn test_product_service_list() {
     }
     
     // List products
-    let result = service.list(10, 0).await;
+    let result = service.list(&ProductFilter::default(), 10, 0).await;
     assert!(result.is_ok());
     
     let products = result.unwrap();
@@ -187,9 +199,9 @@ async fn test_product_service_list() {
     assert!(products.len() >= 5);
     
     // Check that our test products are there
-    let test_products: Vec<&Product> = products
+    let test_products: Vec<&ProductWithAvailableStock> = products
         .iter()
-        .filter(|p| p.name.starts_with("List Test Product"))
+        .filter(|p| p.product.name.starts_with("List Test Product"))
         .collect();
     assert_eq!(test_products.len(), 5);
 }
@@ -206,3 +218,15 @@ async fn test_decimal_precision() {
     let price3 = Decimal::new(50, 2); // $0.50
     assert_eq!(price3.to_string(), "0.50");
 }
+
+#[test]
+fn test_update_dto_tells_null_from_missing() {
+    let dto: UpdateProductDto = serde_json::from_str(
+        r#"{"description": null, "image_url": "https://example.com/new.jpg"}"#,
+    ).unwrap();
+
+    assert_eq!(dto.description, Some(None));
+    assert_eq!(dto.image_url, Some(Some("https://example.com/new.jpg".to_string())));
+    assert_eq!(dto.sku, None);
+    assert_eq!(dto.low_stock_threshold, None);
+}